@@ -0,0 +1,113 @@
+// セレクタのマッチングのベンチマーク。
+// 生成した大きなページとスタイルシートで、要素ごとにすべてのルールを調べる場合と、
+// 索引のバケットにあるルールだけを調べる場合の時間を比べる
+//
+//   cargo bench -p saba_core --bench selector_matching
+
+use std::{cell::RefCell, hint::black_box, rc::Rc, time::Instant};
+
+use saba_core::renderer::{
+    css::{
+        cascade::CascadedStyle, cssom::CssParser, media::Viewport, rule_index::RuleIndex,
+        token::CssTokenizer,
+    },
+    dom::node::{Element, Node},
+    html::{parser::HtmlParser, token::HtmlTokenizer},
+};
+
+const RULES: usize = 4000;
+const ELEMENTS: usize = 2000;
+const TAGS: [&str; 4] = ["div", "p", "span", "li"];
+
+fn generate_stylesheet() -> String {
+    let mut css = String::new();
+    for i in 0..RULES {
+        let selector = match i % 4 {
+            0 => format!(".class{i}"),
+            1 => format!("#id{i}"),
+            2 => format!(".class{i}, .other{i}"),
+            // タイプセレクタのルールは、多くの要素にマッチするので少なめにする
+            _ if i % 200 == 3 => TAGS[i / 200 % TAGS.len()].to_string(),
+            _ => format!(".unused{i}"),
+        };
+        css.push_str(&format!(
+            "{selector} {{ color: #{:06x}; margin: {}px; }}\n",
+            i * 37 % 0xffffff,
+            i % 16
+        ));
+    }
+    css
+}
+
+fn generate_page() -> String {
+    let mut html = String::from("<html><head></head><body>");
+    for i in 0..ELEMENTS {
+        let tag = TAGS[i % TAGS.len()];
+        html.push_str(&format!(
+            r#"<{tag} id="id{}" class="class{} shared">text</{tag}>"#,
+            i * 4 % RULES + 1,
+            i * 4 % RULES,
+        ));
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn collect_elements(node: Option<Rc<RefCell<Node>>>, elements: &mut Vec<Element>) {
+    let Some(node) = node else {
+        return;
+    };
+    if let Some(element) = node.borrow().get_element() {
+        elements.push(element);
+    }
+    collect_elements(node.borrow().first_child(), elements);
+    collect_elements(node.borrow().next_sibling(), elements);
+}
+
+fn main() {
+    let sheet = CssParser::new(CssTokenizer::new(generate_stylesheet())).parse_stylesheet();
+    let window = HtmlParser::new(HtmlTokenizer::new(generate_page())).construct_tree();
+    let mut elements = Vec::new();
+    collect_elements(Some(window.borrow().document()), &mut elements);
+
+    let start = Instant::now();
+    let index = RuleIndex::new(&[sheet], &Viewport::default());
+    let build = start.elapsed();
+
+    let start = Instant::now();
+    for element in &elements {
+        black_box(CascadedStyle::from_rules(
+            element,
+            index.rules(),
+            index.inline_source_order(),
+        ));
+    }
+    let all_rules = start.elapsed();
+
+    let start = Instant::now();
+    for element in &elements {
+        black_box(CascadedStyle::from_index(element, &index));
+    }
+    let indexed = start.elapsed();
+
+    // どちらの方法でも、カスケードの結果は同じになる
+    for element in elements.iter().step_by(97) {
+        assert_eq!(
+            CascadedStyle::from_rules(element, index.rules(), index.inline_source_order()),
+            CascadedStyle::from_index(element, &index)
+        );
+    }
+
+    println!(
+        "{} elements x {} rules (index built in {:?})",
+        elements.len(),
+        index.rules().len(),
+        build
+    );
+    println!("all rules:    {all_rules:?}");
+    println!("rule buckets: {indexed:?}");
+    println!(
+        "speedup:      {:.1}x",
+        all_rules.as_secs_f64() / indexed.as_secs_f64()
+    );
+}
@@ -1,8 +1,10 @@
-use alloc::string::String;
+use alloc::{string::String, vec, vec::Vec};
 
 use crate::renderer::layout::{
-    computed_style::ComputedStyle,
+    computed_style::{BorderStyle, Color, ComputedStyle},
+    font::Font,
     layout_object::{LayoutPoint, LayoutRect},
+    text::TextDecorationStyle,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +16,137 @@ pub enum DisplayItem {
     Text {
         text: String,
         style: ComputedStyle,
+        font: Font,
         layout_point: LayoutPoint,
     },
+    // 1つの辺の border。layout_rect はボーダーボックスの縁に沿った帯
+    Border {
+        border_style: BorderStyle,
+        color: Color,
+        layout_rect: LayoutRect,
+    },
+    // テキストの下線、上線、取り消し線。layout_rect は線を引く帯で、高さが線の太さになる
+    Decoration {
+        decoration_style: TextDecorationStyle,
+        color: Color,
+        layout_rect: LayoutRect,
+    },
+}
+
+// border の帯を、塗りつぶす矩形に分ける。
+// dashed は太さの3倍の長さの線、dotted は太さと同じ大きさの点を、同じ長さの間隔をあけて並べる
+pub fn border_segments(border_style: BorderStyle, layout_rect: LayoutRect) -> Vec<LayoutRect> {
+    let LayoutRect { point, size } = layout_rect;
+    let horizontal = size.width >= size.height;
+    let (length, thickness) = if horizontal {
+        (size.width, size.height)
+    } else {
+        (size.height, size.width)
+    };
+
+    let dash = match border_style {
+        BorderStyle::None | BorderStyle::Hidden => return Vec::new(),
+        BorderStyle::Solid => return vec![layout_rect],
+        BorderStyle::Dashed => thickness * 3,
+        BorderStyle::Dotted => thickness,
+    };
+    if dash <= 0 {
+        return Vec::new();
+    }
+
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    while offset < length {
+        let segment = dash.min(length - offset);
+        segments.push(if horizontal {
+            LayoutRect::new(point.x + offset, point.y, segment, thickness)
+        } else {
+            LayoutRect::new(point.x, point.y + offset, thickness, segment)
+        });
+        offset += dash * 2;
+    }
+    segments
+}
+
+// テキストの装飾の帯を、塗りつぶす矩形に分ける。
+// double は太さの2倍下にもう1本引き、wavy は太さの2倍の長さの線を上下に交互にずらして並べる
+pub fn decoration_segments(
+    decoration_style: TextDecorationStyle,
+    layout_rect: LayoutRect,
+) -> Vec<LayoutRect> {
+    let LayoutRect { point, size } = layout_rect;
+    let thickness = size.height;
+
+    match decoration_style {
+        TextDecorationStyle::Solid => vec![layout_rect],
+        TextDecorationStyle::Double => vec![
+            layout_rect,
+            LayoutRect::new(point.x, point.y + thickness * 2, size.width, thickness),
+        ],
+        TextDecorationStyle::Dotted => border_segments(BorderStyle::Dotted, layout_rect),
+        TextDecorationStyle::Dashed => border_segments(BorderStyle::Dashed, layout_rect),
+        TextDecorationStyle::Wavy => {
+            let wave = thickness * 2;
+            if wave <= 0 {
+                return Vec::new();
+            }
+
+            let mut segments = Vec::new();
+            let mut offset = 0;
+            while offset < size.width {
+                let y = if (offset / wave) % 2 == 0 {
+                    point.y
+                } else {
+                    point.y + thickness
+                };
+                segments.push(LayoutRect::new(
+                    point.x + offset,
+                    y,
+                    wave.min(size.width - offset),
+                    thickness,
+                ));
+                offset += wave;
+            }
+            segments
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_border_segments() {
+        let rect = LayoutRect::new(10, 20, 16, 2);
+        assert_eq!(vec![rect], border_segments(BorderStyle::Solid, rect));
+        assert_eq!(
+            vec![LayoutRect::new(10, 20, 6, 2), LayoutRect::new(22, 20, 4, 2)],
+            border_segments(BorderStyle::Dashed, rect)
+        );
+
+        let rect = LayoutRect::new(0, 0, 3, 10);
+        assert_eq!(
+            vec![LayoutRect::new(0, 0, 3, 3), LayoutRect::new(0, 6, 3, 3)],
+            border_segments(BorderStyle::Dotted, rect)
+        );
+        assert!(border_segments(BorderStyle::None, rect).is_empty());
+    }
+
+    #[test]
+    fn test_decoration_segments() {
+        let rect = LayoutRect::new(0, 10, 10, 2);
+        assert_eq!(
+            vec![rect, LayoutRect::new(0, 14, 10, 2)],
+            decoration_segments(TextDecorationStyle::Double, rect)
+        );
+        assert_eq!(
+            vec![
+                LayoutRect::new(0, 10, 4, 2),
+                LayoutRect::new(4, 12, 4, 2),
+                LayoutRect::new(8, 10, 2, 2),
+            ],
+            decoration_segments(TextDecorationStyle::Wavy, rect)
+        );
+    }
 }
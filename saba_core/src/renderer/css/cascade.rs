@@ -0,0 +1,423 @@
+use alloc::{string::ToString, vec, vec::Vec};
+
+use crate::renderer::{
+    css::{
+        cssom::{ComponentValue, Declaration, Selector, StyleSheet},
+        media::Viewport,
+        rule_index::{IndexedRule, RuleIndex},
+        shorthand::expand_declaration,
+    },
+    dom::node::Element,
+};
+
+// 優先度の低いオリジンから順に並べる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum CascadeOrigin {
+    UserAgent,
+    User,
+    #[default]
+    Author,
+}
+
+// https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+// すべてのプロパティに指定できるキーワード
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssWideKeyword {
+    Initial,
+    Inherit,
+    Unset,
+    Revert,
+}
+
+impl CssWideKeyword {
+    pub fn parse(value: &[ComponentValue]) -> Option<Self> {
+        let [ComponentValue::Ident(keyword)] = value else {
+            return None;
+        };
+
+        match keyword.to_ascii_lowercase().as_str() {
+            "initial" => Some(Self::Initial),
+            "inherit" => Some(Self::Inherit),
+            "unset" => Some(Self::Unset),
+            "revert" => Some(Self::Revert),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Specificity {
+    // (ID セレクタの数, クラスセレクタの数, タイプセレクタの数)
+    ids: u32,
+    classes: u32,
+    types: u32,
+}
+
+impl Specificity {
+    pub fn new(ids: u32, classes: u32, types: u32) -> Self {
+        Self {
+            ids,
+            classes,
+            types,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedDeclaration {
+    pub declaration: Declaration,
+    // style 属性に書かれた宣言の場合は None
+    pub selector: Option<Selector>,
+    pub origin: CascadeOrigin,
+    pub specificity: Specificity,
+    pub source_order: usize,
+}
+
+impl MatchedDeclaration {
+    // https://www.w3.org/TR/css-cascade-4/#cascade-origin
+    // !important がついた宣言は優先順位が反転する
+    fn origin_rank(&self) -> u8 {
+        match (self.declaration.important, self.origin) {
+            (false, CascadeOrigin::UserAgent) => 0,
+            (false, CascadeOrigin::User) => 1,
+            (false, CascadeOrigin::Author) => 2,
+            (true, CascadeOrigin::Author) => 3,
+            (true, CascadeOrigin::User) => 4,
+            (true, CascadeOrigin::UserAgent) => 5,
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.selector.is_none()
+    }
+
+    // 同じオリジンと重要度の中では、style 属性の宣言がどのセレクタよりも優先される
+    fn cascade_key(&self) -> (u8, bool, Specificity, usize) {
+        (
+            self.origin_rank(),
+            self.is_inline(),
+            self.specificity,
+            self.source_order,
+        )
+    }
+}
+
+// ある要素にマッチした宣言を、優先度の低いものから高いものの順に保持する
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CascadedStyle {
+    declarations: Vec<MatchedDeclaration>,
+}
+
+impl CascadedStyle {
+    pub fn new(element: &Element, stylesheets: &[StyleSheet], viewport: &Viewport) -> Self {
+        Self::from_index(element, &RuleIndex::new(stylesheets, viewport))
+    }
+
+    // 索引から、要素にマッチしうるルールだけを取り出して調べる
+    pub fn from_index(element: &Element, index: &RuleIndex) -> Self {
+        Self::from_rules(
+            element,
+            index.candidates(element),
+            index.inline_source_order(),
+        )
+    }
+
+    // 与えられたルールのうち、要素にマッチするものの宣言と style 属性の宣言をカスケードする
+    pub fn from_rules<'a>(
+        element: &Element,
+        rules: impl IntoIterator<Item = &'a IndexedRule>,
+        inline_source_order: usize,
+    ) -> Self {
+        let mut declarations = Vec::new();
+
+        for rule in rules {
+            let Some(selector) = rule.matched_selector(element) else {
+                continue;
+            };
+
+            for (i, declaration) in rule.declarations.iter().enumerate() {
+                declarations.push(MatchedDeclaration {
+                    declaration: declaration.clone(),
+                    selector: Some(selector.clone()),
+                    origin: rule.origin,
+                    specificity: selector.specificity(),
+                    source_order: rule.source_order + i,
+                });
+            }
+        }
+
+        let mut source_order = inline_source_order;
+        for declaration in element
+            .inline_style()
+            .into_iter()
+            .flat_map(expand_declaration)
+        {
+            declarations.push(MatchedDeclaration {
+                declaration,
+                selector: None,
+                origin: CascadeOrigin::Author,
+                specificity: Specificity::default(),
+                source_order,
+            });
+            source_order += 1;
+        }
+
+        declarations.sort_by_key(|d| d.cascade_key());
+
+        Self { declarations }
+    }
+
+    pub fn declarations(&self) -> &[MatchedDeclaration] {
+        &self.declarations
+    }
+
+    // 後ろにあるものほど優先度が高いので、順番に適用すれば勝者の値が残る。
+    // revert は、前のオリジンまででカスケードした値に置き換える
+    pub fn sorted_declarations(&self) -> Vec<Declaration> {
+        let mut sorted: Vec<Declaration> = Vec::new();
+
+        for (i, matched) in self.declarations.iter().enumerate() {
+            let mut declaration = matched.declaration.clone();
+
+            if CssWideKeyword::parse(&declaration.value) == Some(CssWideKeyword::Revert) {
+                // 自身より前にある、優先度の低いオリジンの宣言のうち最後のものが勝者になる。
+                // そのような宣言がなければ unset と同じになる
+                declaration.value = (0..i)
+                    .rev()
+                    .find(|&j| {
+                        let d = &self.declarations[j];
+                        d.origin < matched.origin && d.declaration.property == declaration.property
+                    })
+                    .map_or_else(
+                        || vec![ComponentValue::Ident("unset".to_string())],
+                        |j| sorted[j].value.clone(),
+                    );
+            }
+
+            sorted.push(declaration);
+        }
+
+        sorted
+    }
+
+    pub fn winning_declaration(&self, property: &str) -> Option<&MatchedDeclaration> {
+        self.declarations
+            .iter()
+            .rev()
+            .find(|d| d.declaration.property == property)
+    }
+
+    // プロパティに対する宣言を、勝者を先頭にして優先度の高い順に返す。
+    // 2番目以降は上書きされた宣言で、devtools で「なぜこの値になったか」を表示するために使う
+    pub fn explain(&self, property: &str) -> Vec<&MatchedDeclaration> {
+        self.declarations
+            .iter()
+            .rev()
+            .filter(|d| d.declaration.property == property)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec,
+    };
+
+    use crate::renderer::{
+        css::{
+            cssom::{CssParser, serialize_value},
+            token::CssTokenizer,
+        },
+        html::attribute::Attribute,
+    };
+
+    use super::*;
+
+    fn stylesheet(css: &str, origin: CascadeOrigin) -> StyleSheet {
+        let t = CssTokenizer::new(css.to_string());
+        let mut sheet = CssParser::new(t).parse_stylesheet();
+        sheet.set_origin(origin);
+        sheet
+    }
+
+    fn winning_value(cascaded: &CascadedStyle, property: &str) -> String {
+        let declaration = &cascaded
+            .winning_declaration(property)
+            .expect("declaration should exist")
+            .declaration;
+        serialize_value(&declaration.value)
+    }
+
+    #[test]
+    fn test_specificity_beats_source_order() {
+        let element = Element::new("p", vec![Attribute::nv("class", "x")]);
+
+        let sheet = stylesheet(
+            "p { color: red; } .x { color: blue; }",
+            CascadeOrigin::Author,
+        );
+        let cascaded = CascadedStyle::new(&element, &[sheet], &Viewport::default());
+        assert_eq!("blue", winning_value(&cascaded, "color"));
+
+        let sheet = stylesheet(
+            ".x { color: blue; } p { color: red; }",
+            CascadeOrigin::Author,
+        );
+        let cascaded = CascadedStyle::new(&element, &[sheet], &Viewport::default());
+        assert_eq!("blue", winning_value(&cascaded, "color"));
+    }
+
+    #[test]
+    fn test_source_order() {
+        let element = Element::new("p", Vec::new());
+        let sheet = stylesheet(
+            "p { color: red; } p { color: blue; }",
+            CascadeOrigin::Author,
+        );
+        let cascaded = CascadedStyle::new(&element, &[sheet], &Viewport::default());
+        assert_eq!("blue", winning_value(&cascaded, "color"));
+    }
+
+    #[test]
+    fn test_origin() {
+        let element = Element::new("p", vec![Attribute::nv("id", "main")]);
+        let ua = stylesheet("#main { color: red; }", CascadeOrigin::UserAgent);
+        let user = stylesheet("#main { color: green; }", CascadeOrigin::User);
+        let author = stylesheet("p { color: blue; }", CascadeOrigin::Author);
+        let cascaded = CascadedStyle::new(&element, &[author, user, ua], &Viewport::default());
+        assert_eq!("blue", winning_value(&cascaded, "color"));
+    }
+
+    #[test]
+    fn test_important() {
+        let element = Element::new("p", vec![Attribute::nv("id", "main")]);
+        let ua = stylesheet("p { color: red !important; }", CascadeOrigin::UserAgent);
+        let user = stylesheet("p { color: green !important; }", CascadeOrigin::User);
+        let author = stylesheet(
+            "#main { color: blue !important; } p { color: black; }",
+            CascadeOrigin::Author,
+        );
+
+        let cascaded = CascadedStyle::new(&element, &[author.clone(), user], &Viewport::default());
+        assert_eq!("green", winning_value(&cascaded, "color"));
+
+        let cascaded = CascadedStyle::new(&element, &[author.clone(), ua], &Viewport::default());
+        assert_eq!("red", winning_value(&cascaded, "color"));
+
+        let cascaded = CascadedStyle::new(&element, &[author], &Viewport::default());
+        assert_eq!("blue", winning_value(&cascaded, "color"));
+    }
+
+    #[test]
+    fn test_selector_list_specificity() {
+        let element = Element::new("p", vec![Attribute::nv("class", "x")]);
+        let sheet = stylesheet(
+            "p, .x { color: red; } p { color: blue; }",
+            CascadeOrigin::Author,
+        );
+        let cascaded = CascadedStyle::new(&element, &[sheet], &Viewport::default());
+        assert_eq!("red", winning_value(&cascaded, "color"));
+    }
+
+    #[test]
+    fn test_complex_selector() {
+        let sheets = [stylesheet(
+            "div p { color: red; } p.x { color: blue; }",
+            CascadeOrigin::Author,
+        )];
+
+        // 結合子や複合セレクタの一部の単純セレクタだけでは、マッチしない
+        let elements = [
+            Element::new("div", Vec::new()),
+            Element::new("p", vec![Attribute::nv("class", "y")]),
+            Element::new("span", vec![Attribute::nv("class", "x")]),
+        ];
+        for element in elements {
+            let cascaded = CascadedStyle::new(&element, &sheets, &Viewport::default());
+            assert!(cascaded.explain("color").is_empty());
+        }
+    }
+
+    #[test]
+    fn test_inline_style() {
+        let element = Element::new(
+            "p",
+            vec![
+                Attribute::nv("id", "main"),
+                Attribute::nv("style", "color: red; background-color: white"),
+            ],
+        );
+        let user = stylesheet(
+            "p { background-color: black !important; }",
+            CascadeOrigin::User,
+        );
+        let author = stylesheet(
+            "#main { color: blue; background-color: blue; } p { color: green !important; }",
+            CascadeOrigin::Author,
+        );
+        let cascaded = CascadedStyle::new(&element, &[user, author], &Viewport::default());
+
+        // style 属性は ID セレクタより優先されるが、!important つきの宣言には負ける
+        assert_eq!("green", winning_value(&cascaded, "color"));
+        assert_eq!("black", winning_value(&cascaded, "background-color"));
+        assert!(cascaded.explain("background-color")[1].is_inline());
+
+        let element = Element::new("p", vec![Attribute::nv("style", "color: red")]);
+        let author = stylesheet("p { color: blue; }", CascadeOrigin::Author);
+        let cascaded = CascadedStyle::new(&element, &[author], &Viewport::default());
+        assert_eq!("red", winning_value(&cascaded, "color"));
+    }
+
+    #[test]
+    fn test_explain() {
+        let element = Element::new("p", vec![Attribute::nv("class", "x")]);
+        let sheet = stylesheet(
+            ".x { color: blue; } p { color: red; background-color: white; }",
+            CascadeOrigin::Author,
+        );
+        let cascaded = CascadedStyle::new(&element, &[sheet], &Viewport::default());
+
+        let explained = cascaded.explain("color");
+        assert_eq!(2, explained.len());
+        assert_eq!(
+            Some(Selector::ClassSelector("x".to_string())),
+            explained[0].selector
+        );
+        assert_eq!(Specificity::new(0, 1, 0), explained[0].specificity);
+        assert_eq!(
+            Some(Selector::TypeSelector("p".to_string())),
+            explained[1].selector
+        );
+    }
+
+    #[test]
+    fn test_revert() {
+        let element = Element::new("p", Vec::new());
+        let ua = stylesheet(
+            "p { display: block; color: revert; }",
+            CascadeOrigin::UserAgent,
+        );
+        let user = stylesheet("p { display: inline; }", CascadeOrigin::User);
+        let author = stylesheet(
+            "p { display: revert; color: revert; width: revert !important; }",
+            CascadeOrigin::Author,
+        );
+        let cascaded = CascadedStyle::new(&element, &[ua, user, author], &Viewport::default());
+
+        let value = |property: &str| {
+            cascaded
+                .sorted_declarations()
+                .into_iter()
+                .rev()
+                .find(|d| d.property == property)
+                .map(|d| serialize_value(&d.value))
+        };
+        // 作成者オリジンの revert はユーザーオリジンの値に戻る
+        assert_eq!(Some("inline".to_string()), value("display"));
+        // 戻る先のオリジンに宣言がなければ unset になる
+        assert_eq!(Some("unset".to_string()), value("color"));
+        assert_eq!(Some("unset".to_string()), value("width"));
+    }
+}
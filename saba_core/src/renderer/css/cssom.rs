@@ -1,11 +1,27 @@
-use core::iter::Peekable;
+use core::{
+    fmt::{Display, Formatter},
+    iter::Peekable,
+    mem,
+};
 
 use alloc::{
+    format,
     string::{String, ToString},
     vec::Vec,
 };
 
-use crate::renderer::css::token::{CssToken, CssTokenizer};
+use crate::{
+    error::Error,
+    renderer::{
+        css::{
+            cascade::{CascadeOrigin, Specificity},
+            media::{MediaQueryList, Viewport},
+            object_model::CssStyleDeclaration,
+            token::{CssToken, CssTokenizer},
+        },
+        dom::node::Element,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct CssParser {
@@ -19,12 +35,31 @@ impl CssParser {
 
     pub fn parse_stylesheet(&mut self) -> StyleSheet {
         let mut sheet = StyleSheet::default();
-        let rules = self.consume_list_of_rules();
+        let rules = self.consume_list_of_rules(false);
         sheet.set_rules(rules);
         sheet
     }
 
-    fn consume_list_of_rules(&mut self) -> Vec<QualifiedRule> {
+    // style 属性の値のような、波括弧に囲まれていない宣言のリストをパースする
+    pub fn parse_declaration_list(&mut self) -> Vec<Declaration> {
+        self.consume_list_of_declarations()
+    }
+
+    // https://www.w3.org/TR/css-syntax-3/#parse-rule
+    // ちょうど1つのルールだけが書かれた文字列をパースする
+    pub fn parse_rule(&mut self) -> Result<CssRule, Error> {
+        let mut rules = self.consume_list_of_rules(false);
+        if rules.len() != 1 || self.t.peek().is_some() {
+            return Err(Error::UnexpectedInput(format!(
+                "expected exactly one rule but got {}",
+                rules.len()
+            )));
+        }
+        Ok(rules.remove(0))
+    }
+
+    // nested が true のときは @media などのブロックの中身をパースしていて、`}` で終わる
+    fn consume_list_of_rules(&mut self, nested: bool) -> Vec<CssRule> {
         let mut rules = Vec::new();
 
         loop {
@@ -32,16 +67,27 @@ impl CssParser {
                 return rules;
             };
             match *token {
+                CssToken::CloseCurly if nested => {
+                    assert_eq!(self.t.next(), Some(CssToken::CloseCurly));
+                    return rules;
+                }
                 // AtKeyword トークンが出てきた場合、ほかの CSS をインポートする
                 // @import、メディアクエリを表す @media などのルールが始まることを表す
                 CssToken::AtKeyword(_) => {
-                    let _rule = self.consume_qualified_rule();
-                    // しかし、本書のブラウザでは @ から始まるルールはサポートしないので、無視する
+                    if let Some(rule) = self.consume_at_rule() {
+                        // @import はほかのすべてのルールより前に書かれている場合だけ有効
+                        if matches!(rule, CssRule::Import(_))
+                            && rules.iter().any(|r| !matches!(r, CssRule::Import(_)))
+                        {
+                            continue;
+                        }
+                        rules.push(rule);
+                    }
                 }
                 _ => {
                     let rule = self.consume_qualified_rule();
                     match rule {
-                        Some(r) => rules.push(r),
+                        Some(r) => rules.push(CssRule::Style(r)),
                         None => return rules,
                     }
                 }
@@ -49,18 +95,74 @@ impl CssParser {
         }
     }
 
+    fn consume_at_rule(&mut self) -> Option<CssRule> {
+        let Some(CssToken::AtKeyword(name)) = self.t.next() else {
+            return None;
+        };
+
+        let mut prelude = Vec::new();
+        loop {
+            match self.t.next()? {
+                CssToken::SemiColon => break,
+                CssToken::OpenCurly if name == "media" => {
+                    let media = MediaQueryList::parse(&prelude);
+                    let rules = self.consume_list_of_rules(true);
+                    return Some(CssRule::Media(MediaRule { media, rules }));
+                }
+                CssToken::OpenCurly => {
+                    // 本書のブラウザでサポートしないブロックつきの @ ルールは読み飛ばす
+                    self.skip_block();
+                    return None;
+                }
+                token => prelude.push(token),
+            }
+        }
+
+        match name.as_str() {
+            "import" => match prelude.first()? {
+                CssToken::StringToken(href) | CssToken::Url(href) => {
+                    Some(CssRule::Import(ImportRule { href: href.clone() }))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn skip_block(&mut self) {
+        // `{` を読んだ直後から、対応する `}` までを読み飛ばす
+        let mut depth = 1;
+        while depth > 0 {
+            match self.t.next() {
+                Some(CssToken::OpenCurly) => depth += 1,
+                Some(CssToken::CloseCurly) => depth -= 1,
+                Some(_) => {}
+                None => return,
+            }
+        }
+    }
+
     fn consume_qualified_rule(&mut self) -> Option<QualifiedRule> {
         let mut rule = QualifiedRule::default();
+        // セレクタリストのうち、いま読んでいるセレクタを構成する単純セレクタ
+        let mut compound = Vec::new();
 
         loop {
             match self.t.peek()? {
                 CssToken::OpenCurly => {
                     assert_eq!(self.t.next(), Some(CssToken::OpenCurly));
+                    rule.selectors.extend(complex_selector(compound));
                     rule.set_declarations(self.consume_list_of_declarations());
                     return Some(rule);
                 }
+                CssToken::Delim(',') => {
+                    // `h1, h2` のようなセレクタリストの区切り
+                    assert_eq!(self.t.next(), Some(CssToken::Delim(',')));
+                    rule.selectors
+                        .extend(complex_selector(mem::take(&mut compound)));
+                }
                 _ => {
-                    rule.set_selector(self.consume_selector());
+                    compound.push(self.consume_selector());
                 }
             }
         }
@@ -68,41 +170,79 @@ impl CssParser {
 
     fn consume_selector(&mut self) -> Selector {
         let Some(token) = self.t.next() else {
-            panic!("should have a token but got None");
+            return Selector::UnknownSelector;
         };
 
         match token {
             CssToken::HashToken(v) => Selector::IdSelector(v[1..].to_string()),
-            CssToken::Delim(delim) => {
-                if delim == '.' {
-                    return Selector::ClassSelector(self.consume_ident());
-                }
-                panic!("Parse error: {token:?} is an unexpected token.")
-            }
+            CssToken::Delim('.') => match self.consume_ident() {
+                Some(class_name) => Selector::ClassSelector(class_name),
+                None => Selector::UnknownSelector,
+            },
+            CssToken::Delim('*') => Selector::UniversalSelector,
+            CssToken::Delim('[') => self.consume_attribute_selector(),
             CssToken::Ident(ident) => {
                 // a:hover のようなセレクタはタイプセレクタとして扱うため、
                 // もしコロン（:）が出てきた場合は宣言ブロックの開始直前までトークンを進める
                 if self.t.peek() == Some(&CssToken::Colon) {
-                    while self.t.peek() != Some(&CssToken::OpenCurly) {
-                        self.t.next();
-                    }
+                    self.skip_to_block();
                 }
                 Selector::TypeSelector(ident.to_string())
             }
             CssToken::AtKeyword(_) => {
                 // @ から始まるルールを無視するために、宣言ブロックの開始直前までトークンを進める
-                while self.t.peek() != Some(&CssToken::OpenCurly) {
-                    self.t.next();
-                }
-                Selector::UnknownSelector
-            }
-            _ => {
-                self.t.next();
+                self.skip_to_block();
                 Selector::UnknownSelector
             }
+            // `>` や `+` などの結合子と、本書のブラウザで扱わないトークンはマッチしないセレクタにする
+            _ => Selector::UnknownSelector,
+        }
+    }
+
+    fn skip_to_block(&mut self) {
+        while !matches!(self.t.peek(), Some(CssToken::OpenCurly) | None) {
+            self.t.next();
+        }
+    }
+
+    // https://www.w3.org/TR/selectors-4/#attribute-representation
+    // `[name]` と `[name=value]` の属性セレクタ。`[` の後ろから `]` までを読む
+    fn consume_attribute_selector(&mut self) -> Selector {
+        let Some(name) = self.consume_ident() else {
+            self.skip_attribute_selector();
+            return Selector::UnknownSelector;
+        };
+        let value = match self.t.next() {
+            Some(CssToken::Delim(']')) => return Selector::AttributeSelector(name, None),
+            Some(CssToken::Delim('=')) => match self.t.next() {
+                Some(CssToken::Ident(value) | CssToken::StringToken(value)) => Some(value),
+                _ => None,
+            },
+            _ => None,
+        };
+        // サポートしていない形の属性セレクタは、`]` まで読み飛ばしてマッチしないセレクタにする
+        let closed = self.skip_attribute_selector();
+        match value {
+            Some(value) if closed => Selector::AttributeSelector(name, Some(value)),
+            _ => Selector::UnknownSelector,
         }
     }
 
+    // 属性セレクタの `]` までを読み飛ばし、すぐに `]` で閉じていたかどうかを返す
+    fn skip_attribute_selector(&mut self) -> bool {
+        let closed = self.t.peek() == Some(&CssToken::Delim(']'));
+        while !matches!(
+            self.t.peek(),
+            Some(CssToken::Delim(']') | CssToken::OpenCurly) | None
+        ) {
+            self.t.next();
+        }
+        if self.t.peek() == Some(&CssToken::Delim(']')) {
+            self.t.next();
+        }
+        closed
+    }
+
     fn consume_list_of_declarations(&mut self) -> Vec<Declaration> {
         let mut declarations = Vec::new();
 
@@ -136,28 +276,52 @@ impl CssParser {
         self.t.peek()?;
 
         let mut declaration = Declaration::default();
-        declaration.set_property(self.consume_ident());
+        declaration.set_property(self.consume_ident()?);
 
         match self.t.next()? {
             CssToken::Colon => {}
             _ => return None,
         }
 
-        declaration.set_value(self.consume_component_value());
+        // 値は `;` か `}` までのトークン列。括弧の中の `;` は値の一部として扱う
+        let mut value = Vec::new();
+        let mut depth = 0;
+        while let Some(token) = self.t.peek() {
+            match token {
+                CssToken::CloseCurly => break,
+                CssToken::SemiColon if depth == 0 => break,
+                CssToken::Function(_) | CssToken::OpenParenthesis => depth += 1,
+                CssToken::CloseParenthesis if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            value.push(self.consume_component_value());
+        }
+
+        // 値の末尾が `!important` の場合は重要度つきの宣言として扱う
+        if let [.., CssToken::Delim('!'), CssToken::Ident(ident)] = value.as_slice()
+            && ident.eq_ignore_ascii_case("important")
+        {
+            value.truncate(value.len() - 2);
+            declaration.set_important(true);
+        }
+
+        // カスタムプロパティ以外は、値が空の宣言を無効とする
+        if value.is_empty() && !is_custom_property(&declaration.property) {
+            return None;
+        }
+        declaration.set_value(value);
 
         Some(declaration)
     }
 
-    fn consume_ident(&mut self) -> String {
-        let Some(token) = self.t.next() else {
-            panic!("should have a token but got None");
-        };
-
-        match token {
-            CssToken::Ident(ref ident) => ident.to_string(),
-            _ => {
-                panic!("Parse erroe {token:?} is an unexpected token.")
-            }
+    // 次のトークンが識別子のときだけ読み進める
+    fn consume_ident(&mut self) -> Option<String> {
+        match self.t.peek()? {
+            CssToken::Ident(_) => match self.t.next() {
+                Some(CssToken::Ident(ident)) => Some(ident),
+                _ => None,
+            },
+            _ => None,
         }
     }
 
@@ -168,40 +332,151 @@ impl CssParser {
     }
 }
 
+// `,` で区切られたひとつのセレクタを、単純セレクタの並びからつくる。
+// 本書のブラウザは `p.x` のような複合セレクタと `div p` のような結合子をサポートしない。
+// トークナイザが空白を読み捨てるので両者を区別できず、どちらもどの要素にもマッチしないセレクタにする
+fn complex_selector(mut selectors: Vec<Selector>) -> Option<Selector> {
+    match selectors.len() {
+        0 => None,
+        1 => selectors.pop(),
+        _ => Some(Selector::UnknownSelector),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct StyleSheet {
-    pub rules: Vec<QualifiedRule>,
+    pub rules: Vec<CssRule>,
+    pub origin: CascadeOrigin,
 }
 
 impl StyleSheet {
-    pub fn set_rules(&mut self, rules: Vec<QualifiedRule>) {
+    pub fn set_rules(&mut self, rules: Vec<CssRule>) {
         self.rules = rules;
     }
+
+    // ビューポートにマッチする @media の中のルールも含めて、スタイルルールを出現順に返す
+    pub fn style_rules(&self, viewport: &Viewport) -> Vec<&QualifiedRule> {
+        let mut style_rules = Vec::new();
+        collect_style_rules(&self.rules, viewport, &mut style_rules);
+        style_rules
+    }
+
+    pub fn import_rules(&self) -> impl Iterator<Item = &ImportRule> {
+        self.rules.iter().filter_map(|rule| match rule {
+            CssRule::Import(rule) => Some(rule),
+            _ => None,
+        })
+    }
+
+    pub fn set_origin(&mut self, origin: CascadeOrigin) {
+        self.origin = origin;
+    }
+}
+
+fn collect_style_rules<'a>(
+    rules: &'a [CssRule],
+    viewport: &Viewport,
+    style_rules: &mut Vec<&'a QualifiedRule>,
+) {
+    for rule in rules {
+        match rule {
+            CssRule::Style(rule) => style_rules.push(rule),
+            CssRule::Media(rule) => {
+                if rule.media.evaluate(viewport) {
+                    collect_style_rules(&rule.rules, viewport, style_rules);
+                }
+            }
+            CssRule::Import(_) => {}
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct QualifiedRule {
-    pub selector: Selector,
-    pub declarations: Vec<Declaration>,
+pub enum CssRule {
+    Style(QualifiedRule),
+    Import(ImportRule),
+    Media(MediaRule),
 }
 
-impl Default for QualifiedRule {
-    fn default() -> Self {
-        Self {
-            selector: Selector::TypeSelector("".to_string()),
-            declarations: Vec::new(),
+impl CssRule {
+    // https://www.w3.org/TR/cssom-1/#serialize-a-css-rule
+    pub fn css_text(&self) -> String {
+        match self {
+            CssRule::Style(rule) => {
+                // マッチしないセレクタはパースし直しても同じ意味になるので書き出さない
+                let selectors: Vec<String> = rule
+                    .selectors
+                    .iter()
+                    .filter(|s| **s != Selector::UnknownSelector)
+                    .map(|s| s.to_string())
+                    .collect();
+                let declarations = rule.style().css_text();
+                if declarations.is_empty() {
+                    format!("{} {{ }}", selectors.join(", "))
+                } else {
+                    format!("{} {{ {} }}", selectors.join(", "), declarations)
+                }
+            }
+            CssRule::Import(rule) => format!("@import {};", CssToken::Url(rule.href.clone())),
+            CssRule::Media(rule) => {
+                let mut s = format!("@media {} {{", rule.media);
+                for rule in &rule.rules {
+                    s.push_str("\n  ");
+                    s.push_str(&rule.css_text());
+                }
+                s.push_str("\n}");
+                s
+            }
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRule {
+    pub media: MediaQueryList,
+    pub rules: Vec<CssRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRule {
+    pub href: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QualifiedRule {
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<Declaration>,
+}
+
 impl QualifiedRule {
-    pub fn set_selector(&mut self, selector: Selector) {
-        self.selector = selector;
+    pub fn set_selectors(&mut self, selectors: Vec<Selector>) {
+        self.selectors = selectors;
+    }
+
+    pub fn add_selector(&mut self, selector: Selector) {
+        self.selectors.push(selector);
+    }
+
+    // セレクタリストのうち、要素にマッチするもっとも詳細度の高いセレクタを返す
+    pub fn matched_selector(&self, element: &Element) -> Option<&Selector> {
+        self.selectors
+            .iter()
+            .filter(|s| s.matches(element))
+            .max_by_key(|s| s.specificity())
     }
 
     pub fn set_declarations(&mut self, declarations: Vec<Declaration>) {
         self.declarations = declarations;
     }
+
+    // 宣言ブロックを CSSOM の宣言として返す。変更したものは set_style で書き戻す
+    pub fn style(&self) -> CssStyleDeclaration {
+        CssStyleDeclaration::new(self.declarations.clone())
+    }
+
+    pub fn set_style(&mut self, style: &CssStyleDeclaration) {
+        self.declarations = style.declarations().to_vec();
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -209,40 +484,128 @@ pub enum Selector {
     TypeSelector(String),
     ClassSelector(String),
     IdSelector(String),
+    // `[name]` は属性を持つ要素に、`[name=value]` は属性の値が value と等しい要素にマッチする
+    AttributeSelector(String, Option<String>),
+    // すべての要素にマッチする `*`
+    UniversalSelector,
     UnknownSelector,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Declaration {
-    pub property: String,
-    pub value: ComponentValue,
+impl Selector {
+    pub fn specificity(&self) -> Specificity {
+        match self {
+            Selector::IdSelector(_) => Specificity::new(1, 0, 0),
+            Selector::ClassSelector(_) | Selector::AttributeSelector(..) => {
+                Specificity::new(0, 1, 0)
+            }
+            Selector::TypeSelector(_) => Specificity::new(0, 0, 1),
+            Selector::UniversalSelector | Selector::UnknownSelector => Specificity::default(),
+        }
+    }
+
+    pub fn matches(&self, element: &Element) -> bool {
+        match self {
+            Selector::TypeSelector(type_name) => element.kind().to_string() == *type_name,
+            // class 属性は空白で区切られたクラスのリストで、そのどれかと一致すればマッチする
+            Selector::ClassSelector(class_name) => element.attributes().iter().any(|a| {
+                a.name() == "class"
+                    && a.value()
+                        .split_ascii_whitespace()
+                        .any(|class| class == class_name)
+            }),
+            Selector::IdSelector(id_name) => element
+                .attributes()
+                .iter()
+                .any(|a| a.name() == "id" && a.value() == *id_name),
+            Selector::AttributeSelector(name, value) => element.attributes().iter().any(|a| {
+                a.name() == *name && value.as_ref().is_none_or(|value| a.value() == *value)
+            }),
+            Selector::UniversalSelector => true,
+            Selector::UnknownSelector => false,
+        }
+    }
 }
 
-impl Default for Declaration {
-    fn default() -> Self {
-        Self {
-            property: String::new(),
-            value: ComponentValue::Ident(String::new()),
+// https://www.w3.org/TR/cssom-1/#serialize-a-simple-selector
+impl Display for Selector {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            Selector::TypeSelector(type_name) => write!(f, "{type_name}"),
+            Selector::ClassSelector(class_name) => write!(f, ".{class_name}"),
+            Selector::IdSelector(id_name) => write!(f, "#{id_name}"),
+            Selector::AttributeSelector(name, None) => write!(f, "[{name}]"),
+            Selector::AttributeSelector(name, Some(value)) => write!(f, "[{name}=\"{value}\"]"),
+            Selector::UniversalSelector => write!(f, "*"),
+            Selector::UnknownSelector => Ok(()),
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Declaration {
+    pub property: String,
+    pub value: Vec<ComponentValue>,
+    pub important: bool,
+}
+
 impl Declaration {
     pub fn set_property(&mut self, property: String) {
         self.property = property;
     }
 
-    pub fn set_value(&mut self, value: ComponentValue) {
+    pub fn set_value(&mut self, value: Vec<ComponentValue>) {
         self.value = value;
     }
+
+    pub fn set_important(&mut self, important: bool) {
+        self.important = important;
+    }
+}
+
+impl Display for Declaration {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}: {}", self.property, serialize_value(&self.value))?;
+        if self.important {
+            write!(f, " !important")?;
+        }
+        Ok(())
+    }
 }
 
 pub type ComponentValue = CssToken;
 
+// `--` から始まるプロパティはカスタムプロパティ
+// https://www.w3.org/TR/css-variables-1/#defining-variables
+pub fn is_custom_property(property: &str) -> bool {
+    property.starts_with("--")
+}
+
+// 宣言の値のトークン列を文字列に戻す
+pub fn serialize_value(value: &[ComponentValue]) -> String {
+    let mut s = String::new();
+    let mut prev: Option<&ComponentValue> = None;
+    for token in value {
+        let needs_space = !matches!(
+            (prev, token),
+            (None, _)
+                | (Some(CssToken::Function(_) | CssToken::OpenParenthesis), _)
+                | (_, CssToken::CloseParenthesis | CssToken::Delim(','))
+        );
+        if needs_space {
+            s.push(' ');
+        }
+        s.push_str(&token.to_string());
+        prev = Some(token);
+    }
+    s
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
 
+    use crate::renderer::html::attribute::Attribute;
+
     use super::*;
 
     #[test]
@@ -261,13 +624,13 @@ mod tests {
         let cssom = CssParser::new(t).parse_stylesheet();
 
         let mut rule = QualifiedRule::default();
-        rule.set_selector(Selector::TypeSelector("p".to_string()));
+        rule.add_selector(Selector::TypeSelector("p".to_string()));
         let mut declaration = Declaration::default();
         declaration.set_property("color".to_string());
-        declaration.set_value(ComponentValue::Ident("red".to_string()));
+        declaration.set_value(vec![ComponentValue::Ident("red".to_string())]);
         rule.set_declarations(vec![declaration]);
 
-        let expected = [rule];
+        let expected = [CssRule::Style(rule)];
         assert_eq!(cssom.rules.len(), expected.len());
         for (r, e) in cssom.rules.iter().zip(expected.iter()) {
             assert_eq!(r, e);
@@ -281,13 +644,13 @@ mod tests {
         let cssom = CssParser::new(t).parse_stylesheet();
 
         let mut rule = QualifiedRule::default();
-        rule.set_selector(Selector::IdSelector("id".to_string()));
+        rule.add_selector(Selector::IdSelector("id".to_string()));
         let mut declaration = Declaration::default();
         declaration.set_property("color".to_string());
-        declaration.set_value(ComponentValue::Ident("red".to_string()));
+        declaration.set_value(vec![ComponentValue::Ident("red".to_string())]);
         rule.set_declarations(vec![declaration]);
 
-        let expected = [rule];
+        let expected = [CssRule::Style(rule)];
         assert_eq!(cssom.rules.len(), expected.len());
         for (r, e) in cssom.rules.iter().zip(expected.iter()) {
             assert_eq!(r, e);
@@ -301,13 +664,13 @@ mod tests {
         let cssom = CssParser::new(t).parse_stylesheet();
 
         let mut rule = QualifiedRule::default();
-        rule.set_selector(Selector::ClassSelector("class".to_string()));
+        rule.add_selector(Selector::ClassSelector("class".to_string()));
         let mut declaration = Declaration::default();
         declaration.set_property("color".to_string());
-        declaration.set_value(ComponentValue::Ident("red".to_string()));
+        declaration.set_value(vec![ComponentValue::Ident("red".to_string())]);
         rule.set_declarations(vec![declaration]);
 
-        let expected = [rule];
+        let expected = [CssRule::Style(rule)];
         assert_eq!(cssom.rules.len(), expected.len());
         for (r, e) in cssom.rules.iter().zip(expected.iter()) {
             assert_eq!(r, e);
@@ -321,26 +684,254 @@ mod tests {
         let cssom = CssParser::new(t).parse_stylesheet();
 
         let mut rule1 = QualifiedRule::default();
-        rule1.set_selector(Selector::TypeSelector("p".to_string()));
+        rule1.add_selector(Selector::TypeSelector("p".to_string()));
         let mut declaration = Declaration::default();
         declaration.set_property("content".to_string());
-        declaration.set_value(ComponentValue::StringToken("Hey".to_string()));
+        declaration.set_value(vec![ComponentValue::StringToken("Hey".to_string())]);
         rule1.set_declarations(vec![declaration]);
 
         let mut rule2 = QualifiedRule::default();
-        rule2.set_selector(Selector::TypeSelector("h1".to_string()));
+        rule2.add_selector(Selector::TypeSelector("h1".to_string()));
         let mut declaration2 = Declaration::default();
         declaration2.set_property("font-size".to_string());
-        declaration2.set_value(ComponentValue::Number(40.0));
+        declaration2.set_value(vec![ComponentValue::Number(40.0)]);
         let mut declaration3 = Declaration::default();
         declaration3.set_property("color".to_string());
-        declaration3.set_value(ComponentValue::Ident("blue".to_string()));
+        declaration3.set_value(vec![ComponentValue::Ident("blue".to_string())]);
         rule2.set_declarations(vec![declaration2, declaration3]);
 
-        let expected = [rule1, rule2];
+        let expected = [CssRule::Style(rule1), CssRule::Style(rule2)];
         assert_eq!(cssom.rules.len(), expected.len());
         for (r, e) in cssom.rules.iter().zip(expected.iter()) {
             assert_eq!(r, e);
         }
     }
+
+    #[test]
+    fn test_important() {
+        let style = "p { color: red !important; background-color: blue; }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let mut rule = QualifiedRule::default();
+        rule.add_selector(Selector::TypeSelector("p".to_string()));
+        let mut declaration1 = Declaration::default();
+        declaration1.set_property("color".to_string());
+        declaration1.set_value(vec![ComponentValue::Ident("red".to_string())]);
+        declaration1.set_important(true);
+        let mut declaration2 = Declaration::default();
+        declaration2.set_property("background-color".to_string());
+        declaration2.set_value(vec![ComponentValue::Ident("blue".to_string())]);
+        rule.set_declarations(vec![declaration1, declaration2]);
+
+        assert_eq!(cssom.rules, vec![CssRule::Style(rule)]);
+        assert_eq!(cssom.origin, CascadeOrigin::Author);
+    }
+
+    #[test]
+    fn test_specificity() {
+        assert!(
+            Selector::IdSelector("id".to_string()).specificity()
+                > Selector::ClassSelector("class".to_string()).specificity()
+        );
+        assert!(
+            Selector::ClassSelector("class".to_string()).specificity()
+                > Selector::TypeSelector("p".to_string()).specificity()
+        );
+    }
+
+    #[test]
+    fn test_selector_list() {
+        let style = "h1, .title, #main { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let mut rule = QualifiedRule::default();
+        rule.set_selectors(vec![
+            Selector::TypeSelector("h1".to_string()),
+            Selector::ClassSelector("title".to_string()),
+            Selector::IdSelector("main".to_string()),
+        ]);
+        let mut declaration = Declaration::default();
+        declaration.set_property("color".to_string());
+        declaration.set_value(vec![ComponentValue::Ident("red".to_string())]);
+        rule.set_declarations(vec![declaration]);
+
+        assert_eq!(cssom.rules, vec![CssRule::Style(rule)]);
+    }
+
+    #[test]
+    fn test_complex_selector() {
+        let style = "div p, p.x, .x, p .x { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let CssRule::Style(rule) = &cssom.rules[0] else {
+            panic!("should be a style rule");
+        };
+        // 複数の単純セレクタからなるセレクタは、ひとつのマッチしないセレクタになる
+        assert_eq!(
+            vec![
+                Selector::UnknownSelector,
+                Selector::UnknownSelector,
+                Selector::ClassSelector("x".to_string()),
+                Selector::UnknownSelector,
+            ],
+            rule.selectors
+        );
+
+        let div = Element::new("div", Vec::new());
+        let p = Element::new("p", Vec::new());
+        assert_eq!(None, rule.matched_selector(&div));
+        assert_eq!(None, rule.matched_selector(&p));
+    }
+
+    #[test]
+    fn test_attribute_selector() {
+        let style = "[hidden], [dir=rtl], [lang=\"ar\"], [a~=b] { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let CssRule::Style(rule) = &cssom.rules[0] else {
+            panic!("should be a style rule");
+        };
+        assert_eq!(
+            vec![
+                Selector::AttributeSelector("hidden".to_string(), None),
+                Selector::AttributeSelector("dir".to_string(), Some("rtl".to_string())),
+                Selector::AttributeSelector("lang".to_string(), Some("ar".to_string())),
+                Selector::UnknownSelector,
+            ],
+            rule.selectors
+        );
+        assert_eq!("[dir=\"rtl\"]", rule.selectors[1].to_string());
+        assert_eq!(
+            Selector::ClassSelector("a".to_string()).specificity(),
+            rule.selectors[1].specificity()
+        );
+
+        let element = Element::new(
+            "p",
+            vec![Attribute::nv("dir", "rtl"), Attribute::nv("hidden", "")],
+        );
+        assert!(rule.selectors[0].matches(&element));
+        assert!(rule.selectors[1].matches(&element));
+        assert!(!rule.selectors[2].matches(&element));
+    }
+
+    #[test]
+    fn test_at_rules() {
+        let style = r#"
+            @import "a.css";
+            @import url(b.css);
+            @font-face { font-family: foo; }
+            p { color: red; }
+            @import "ignored.css";
+        "#
+        .to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let mut rule = QualifiedRule::default();
+        rule.add_selector(Selector::TypeSelector("p".to_string()));
+        let mut declaration = Declaration::default();
+        declaration.set_property("color".to_string());
+        declaration.set_value(vec![ComponentValue::Ident("red".to_string())]);
+        rule.set_declarations(vec![declaration]);
+
+        let expected = vec![
+            CssRule::Import(ImportRule {
+                href: "a.css".to_string(),
+            }),
+            CssRule::Import(ImportRule {
+                href: "b.css".to_string(),
+            }),
+            CssRule::Style(rule),
+        ];
+        assert_eq!(cssom.rules, expected);
+    }
+
+    #[test]
+    fn test_declaration_list() {
+        let style = "color: red; background-color: #ff0000 !important".to_string();
+        let t = CssTokenizer::new(style);
+        let declarations = CssParser::new(t).parse_declaration_list();
+
+        let mut declaration1 = Declaration::default();
+        declaration1.set_property("color".to_string());
+        declaration1.set_value(vec![ComponentValue::Ident("red".to_string())]);
+        let mut declaration2 = Declaration::default();
+        declaration2.set_property("background-color".to_string());
+        declaration2.set_value(vec![ComponentValue::HashToken("#ff0000".to_string())]);
+        declaration2.set_important(true);
+
+        assert_eq!(declarations, vec![declaration1, declaration2]);
+        assert_eq!("color: red", declarations[0].to_string());
+        assert_eq!(
+            "background-color: #ff0000 !important",
+            declarations[1].to_string()
+        );
+    }
+
+    #[test]
+    fn test_media_rule() {
+        let style = r#"
+            p { color: red; }
+            @media (max-width: 600px) {
+              p { color: blue; }
+              @media print { p { color: black; } }
+            }
+            h1 { color: green; }
+        "#
+        .to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        assert_eq!(cssom.rules.len(), 3);
+        let CssRule::Media(media) = &cssom.rules[1] else {
+            panic!("second rule should be @media");
+        };
+        assert_eq!(media.rules.len(), 2);
+        assert!(matches!(media.rules[1], CssRule::Media(_)));
+
+        let selectors = |viewport: &Viewport| {
+            cssom
+                .style_rules(viewport)
+                .iter()
+                .map(|r| r.declarations[0].value[0].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                ComponentValue::Ident("red".to_string()),
+                ComponentValue::Ident("blue".to_string()),
+                ComponentValue::Ident("green".to_string()),
+            ],
+            selectors(&Viewport::new(320, 480))
+        );
+        assert_eq!(
+            vec![
+                ComponentValue::Ident("red".to_string()),
+                ComponentValue::Ident("green".to_string()),
+            ],
+            selectors(&Viewport::new(1024, 768))
+        );
+    }
+
+    #[test]
+    fn test_multi_token_value() {
+        let style =
+            "--gap: 1px 2px; color: var(--main, #fff) !important; --empty:; width:".to_string();
+        let t = CssTokenizer::new(style);
+        let declarations = CssParser::new(t).parse_declaration_list();
+
+        assert_eq!(3, declarations.len());
+        assert_eq!("--gap: 1px 2px", declarations[0].to_string());
+        assert_eq!(
+            "color: var(--main, #fff) !important",
+            declarations[1].to_string()
+        );
+        assert!(declarations[1].important);
+        assert!(declarations[2].value.is_empty());
+    }
 }
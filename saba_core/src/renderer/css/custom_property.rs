@@ -0,0 +1,265 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::renderer::css::{
+    cssom::{ComponentValue, Declaration, is_custom_property},
+    token::CssToken,
+};
+
+// https://www.w3.org/TR/css-variables-1/
+// 要素ごとに、計算済みのカスタムプロパティの名前と値を保持する
+pub type CustomProperties = BTreeMap<String, Vec<ComponentValue>>;
+
+// カスケード順に並んだ宣言からカスタムプロパティの計算値を求める。
+// カスタムプロパティは常に継承されるので、親の計算値に要素自身の宣言を重ねる
+pub fn compute_custom_properties(
+    declarations: &[Declaration],
+    inherited: &CustomProperties,
+) -> CustomProperties {
+    let mut specified = BTreeMap::new();
+    for declaration in declarations {
+        if is_custom_property(&declaration.property) {
+            // 後ろにある宣言ほど優先度が高い
+            specified.insert(declaration.property.clone(), declaration.value.clone());
+        }
+    }
+
+    let mut resolver = Resolver {
+        specified: &specified,
+        inherited,
+        computed: BTreeMap::new(),
+        resolving: Vec::new(),
+        cyclic: BTreeSet::new(),
+    };
+    for name in specified.keys() {
+        resolver.resolve(name);
+    }
+
+    let mut custom_properties = inherited.clone();
+    for (name, value) in resolver.computed {
+        match value {
+            Some(value) => custom_properties.insert(name, value),
+            // 無効になったカスタムプロパティは初期値（guaranteed-invalid value）になる
+            None => custom_properties.remove(&name),
+        };
+    }
+    custom_properties
+}
+
+// 値の中の var() を置き換える。参照先がなくフォールバックもない場合は、
+// 宣言が computed-value time で無効になるので None を返す
+pub fn substitute_var(
+    value: &[ComponentValue],
+    custom_properties: &CustomProperties,
+) -> Option<Vec<ComponentValue>> {
+    substitute(value, &mut |name| custom_properties.get(name).cloned())
+}
+
+pub fn contains_var(value: &[ComponentValue]) -> bool {
+    value
+        .iter()
+        .any(|token| matches!(token, CssToken::Function(name) if name.eq_ignore_ascii_case("var")))
+}
+
+struct Resolver<'a> {
+    specified: &'a BTreeMap<String, Vec<ComponentValue>>,
+    inherited: &'a CustomProperties,
+    // None は computed-value time で無効になったことを表す
+    computed: BTreeMap<String, Option<Vec<ComponentValue>>>,
+    // 循環参照を検出するために、解決中のプロパティを積んでおく
+    resolving: Vec<String>,
+    cyclic: BTreeSet<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, name: &str) -> Option<Vec<ComponentValue>> {
+        if let Some(value) = self.computed.get(name) {
+            return value.clone();
+        }
+
+        let Some(specified) = self.specified.get(name) else {
+            return self.inherited.get(name).cloned();
+        };
+
+        if let Some(pos) = self.resolving.iter().position(|n| n == name) {
+            // 循環に含まれるプロパティは、フォールバックの有無にかかわらずすべて無効になる
+            for n in &self.resolving[pos..] {
+                self.cyclic.insert(n.clone());
+            }
+            return None;
+        }
+
+        self.resolving.push(name.to_string());
+        let value = match specified.as_slice() {
+            [CssToken::Ident(keyword)] if keyword.eq_ignore_ascii_case("initial") => None,
+            [CssToken::Ident(keyword)]
+                if keyword.eq_ignore_ascii_case("inherit")
+                    || keyword.eq_ignore_ascii_case("unset") =>
+            {
+                self.inherited.get(name).cloned()
+            }
+            _ => {
+                let specified = specified.clone();
+                substitute(&specified, &mut |n| self.resolve(n))
+            }
+        };
+        self.resolving.pop();
+
+        let value = if self.cyclic.contains(name) {
+            None
+        } else {
+            value
+        };
+        self.computed.insert(name.to_string(), value.clone());
+        value
+    }
+}
+
+fn substitute(
+    value: &[ComponentValue],
+    lookup: &mut dyn FnMut(&str) -> Option<Vec<ComponentValue>>,
+) -> Option<Vec<ComponentValue>> {
+    let mut substituted = Vec::new();
+    let mut i = 0;
+
+    while i < value.len() {
+        let token = &value[i];
+        i += 1;
+
+        let CssToken::Function(function) = token else {
+            substituted.push(token.clone());
+            continue;
+        };
+        if !function.eq_ignore_ascii_case("var") {
+            substituted.push(token.clone());
+            continue;
+        }
+
+        // var( から対応する閉じ括弧までを引数として取り出す
+        let start = i;
+        let mut depth = 0;
+        while i < value.len() {
+            match value[i] {
+                CssToken::Function(_) | CssToken::OpenParenthesis => depth += 1,
+                CssToken::CloseParenthesis if depth == 0 => break,
+                CssToken::CloseParenthesis => depth -= 1,
+                _ => {}
+            }
+            i += 1;
+        }
+        let arguments = &value[start..i];
+        // 閉じ括弧を読み飛ばす。閉じられていない var() も末尾までを引数として扱う
+        i += 1;
+
+        let (name, fallback) = match arguments {
+            [CssToken::Ident(name)] => (name, None),
+            [CssToken::Ident(name), CssToken::Delim(','), fallback @ ..] => (name, Some(fallback)),
+            _ => return None,
+        };
+        if !is_custom_property(name) {
+            return None;
+        }
+
+        match (lookup(name), fallback) {
+            (Some(v), _) => substituted.extend(v),
+            (None, Some(fallback)) => substituted.extend(substitute(fallback, lookup)?),
+            (None, None) => return None,
+        }
+    }
+
+    Some(substituted)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::renderer::css::{
+        cssom::{CssParser, serialize_value},
+        token::CssTokenizer,
+    };
+
+    use super::*;
+
+    fn declarations(css: &str) -> Vec<Declaration> {
+        CssParser::new(CssTokenizer::new(css.to_string())).parse_declaration_list()
+    }
+
+    fn value(custom_properties: &CustomProperties, name: &str) -> Option<String> {
+        custom_properties.get(name).map(|v| serialize_value(v))
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let parent = compute_custom_properties(
+            &declarations("--main: red; --gap: 1px 2px"),
+            &CustomProperties::new(),
+        );
+        let child = compute_custom_properties(&declarations("--main: blue"), &parent);
+
+        assert_eq!(Some("blue".to_string()), value(&child, "--main"));
+        assert_eq!(Some("1px 2px".to_string()), value(&child, "--gap"));
+        assert_eq!(Some("red".to_string()), value(&parent, "--main"));
+    }
+
+    #[test]
+    fn test_var_reference() {
+        let parent =
+            compute_custom_properties(&declarations("--base: green"), &CustomProperties::new());
+        let child = compute_custom_properties(
+            &declarations(
+                "--accent: var(--base); --border: 1px solid var(--missing, var(--accent))",
+            ),
+            &parent,
+        );
+
+        assert_eq!(Some("green".to_string()), value(&child, "--accent"));
+        assert_eq!(
+            Some("1px solid green".to_string()),
+            value(&child, "--border")
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let parent = compute_custom_properties(&declarations("--a: red"), &CustomProperties::new());
+        let child = compute_custom_properties(
+            &declarations("--a: var(--b, blue); --b: var(--a, green); --c: var(--a, black)"),
+            &parent,
+        );
+
+        // 循環している --a と --b は、フォールバックがあっても無効になる
+        assert_eq!(None, value(&child, "--a"));
+        assert_eq!(None, value(&child, "--b"));
+        assert_eq!(Some("black".to_string()), value(&child, "--c"));
+    }
+
+    #[test]
+    fn test_substitute_var() {
+        let custom_properties = compute_custom_properties(
+            &declarations("--color: blue; --empty: initial"),
+            &CustomProperties::new(),
+        );
+
+        let value = declarations("color: var(--color)").remove(0).value;
+        assert!(contains_var(&value));
+        assert_eq!(
+            Some(vec![CssToken::Ident("blue".to_string())]),
+            substitute_var(&value, &custom_properties)
+        );
+
+        let value = declarations("color: var(--empty)").remove(0).value;
+        assert_eq!(None, substitute_var(&value, &custom_properties));
+
+        let value = declarations("color: var(--unknown, #ff0000)")
+            .remove(0)
+            .value;
+        assert_eq!(
+            Some(vec![CssToken::HashToken("#ff0000".to_string())]),
+            substitute_var(&value, &custom_properties)
+        );
+    }
+}
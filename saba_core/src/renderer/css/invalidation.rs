@@ -0,0 +1,114 @@
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
+
+use crate::renderer::css::cssom::{CssRule, Selector, StyleSheet};
+
+// スタイルシートのセレクタが参照している id とクラス、属性の名前の集合。
+// 要素の属性が変わったとき、ここに含まれない id やクラス、属性だけが変わったのであれば、
+// どのセレクタのマッチ結果も変わらないので、スタイルを計算し直さなくてよい
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RuleFeatures {
+    ids: BTreeSet<String>,
+    classes: BTreeSet<String>,
+    attributes: BTreeSet<String>,
+}
+
+impl RuleFeatures {
+    // @media の中のルールは、ビューポートにマッチするかどうかにかかわらず含める
+    pub fn new(stylesheets: &[StyleSheet]) -> Self {
+        let mut features = Self::default();
+        for sheet in stylesheets {
+            features.add_rules(&sheet.rules);
+        }
+        features
+    }
+
+    fn add_rules(&mut self, rules: &[CssRule]) {
+        for rule in rules {
+            match rule {
+                CssRule::Style(rule) => {
+                    for selector in &rule.selectors {
+                        self.add_selector(selector);
+                    }
+                }
+                CssRule::Media(rule) => self.add_rules(&rule.rules),
+                CssRule::Import(_) => {}
+            }
+        }
+    }
+
+    fn add_selector(&mut self, selector: &Selector) {
+        match selector {
+            Selector::IdSelector(id) => {
+                self.ids.insert(id.clone());
+            }
+            Selector::ClassSelector(class) => {
+                self.classes.insert(class.clone());
+            }
+            Selector::AttributeSelector(name, _) => {
+                self.attributes.insert(name.clone());
+            }
+            Selector::TypeSelector(_) | Selector::UniversalSelector | Selector::UnknownSelector => {
+            }
+        }
+    }
+
+    // 属性の値が old から new に変わったときに、要素のスタイルを計算し直す必要があるかどうか。
+    // class、id、style と、属性セレクタが参照している属性だけがスタイルに影響する
+    pub fn affects_style(&self, name: &str, old: Option<&str>, new: Option<&str>) -> bool {
+        if old == new {
+            return false;
+        }
+
+        match name {
+            "style" => true,
+            "id" => [old, new]
+                .into_iter()
+                .flatten()
+                .any(|id| self.ids.contains(id)),
+            "class" => {
+                let old: BTreeSet<&str> = old.unwrap_or("").split_ascii_whitespace().collect();
+                let new: BTreeSet<&str> = new.unwrap_or("").split_ascii_whitespace().collect();
+                old.symmetric_difference(&new)
+                    .any(|class| self.classes.contains(*class))
+            }
+            name => self.attributes.contains(name),
+        }
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.ids.iter().map(|id| id.as_str()).collect()
+    }
+
+    pub fn classes(&self) -> Vec<&str> {
+        self.classes.iter().map(|class| class.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use crate::renderer::css::{cssom::CssParser, token::CssTokenizer};
+
+    use super::*;
+
+    #[test]
+    fn test_rule_features() {
+        let css = "#main { color: red; } .a, p { color: blue; } @media print { .b { color: green; } } [dir=rtl] { color: red; }";
+        let sheet = CssParser::new(CssTokenizer::new(css.to_string())).parse_stylesheet();
+        let features = RuleFeatures::new(&[sheet]);
+
+        assert_eq!(vec!["main"], features.ids());
+        assert_eq!(vec!["a", "b"], features.classes());
+
+        assert!(features.affects_style("class", Some("x"), Some("x a")));
+        assert!(features.affects_style("class", Some("b"), None));
+        assert!(!features.affects_style("class", Some("x a"), Some("a y")));
+        assert!(features.affects_style("id", None, Some("main")));
+        assert!(!features.affects_style("id", Some("other"), Some("another")));
+        assert!(features.affects_style("style", None, Some("color: red")));
+        assert!(!features.affects_style("title", None, Some("hello")));
+        assert!(features.affects_style("dir", None, Some("rtl")));
+        assert!(!features.affects_style("style", Some("x"), Some("x")));
+    }
+}
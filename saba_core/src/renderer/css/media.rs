@@ -0,0 +1,440 @@
+use core::fmt::{Display, Formatter};
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    constants::{CONTENT_AREA_HRIGHT, CONTENT_AREA_WIDTH},
+    renderer::css::token::CssToken,
+};
+
+// メディアクエリを評価するときの、ページを表示している環境
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub width: i64,
+    pub height: i64,
+    pub color_scheme: ColorScheme,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            width: CONTENT_AREA_WIDTH,
+            height: CONTENT_AREA_HRIGHT,
+            color_scheme: ColorScheme::Light,
+        }
+    }
+}
+
+impl Viewport {
+    pub fn new(width: i64, height: i64) -> Self {
+        Self {
+            width,
+            height,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.color_scheme = color_scheme;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+impl Display for ColorScheme {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            ColorScheme::Light => write!(f, "light"),
+            ColorScheme::Dark => write!(f, "dark"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+impl Display for Orientation {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            Orientation::Portrait => write!(f, "portrait"),
+            Orientation::Landscape => write!(f, "landscape"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    All,
+    Screen,
+    Print,
+    // 知らないメディアタイプはどの環境にもマッチしない
+    Unknown,
+}
+
+impl Display for MediaType {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            MediaType::All => write!(f, "all"),
+            MediaType::Screen => write!(f, "screen"),
+            MediaType::Print => write!(f, "print"),
+            // 元の名前は保持していないが、パースし直しても同じ意味になる
+            MediaType::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaFeature {
+    Width(f64),
+    MinWidth(f64),
+    MaxWidth(f64),
+    Height(f64),
+    MinHeight(f64),
+    MaxHeight(f64),
+    Orientation(Orientation),
+    PrefersColorScheme(ColorScheme),
+    // 知らない、もしくは値が不正なメディア特性はマッチしない
+    Unknown,
+}
+
+// 長さは px に変換したものを書き出す
+impl Display for MediaFeature {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            MediaFeature::Width(w) => write!(f, "(width: {w}px)"),
+            MediaFeature::MinWidth(w) => write!(f, "(min-width: {w}px)"),
+            MediaFeature::MaxWidth(w) => write!(f, "(max-width: {w}px)"),
+            MediaFeature::Height(h) => write!(f, "(height: {h}px)"),
+            MediaFeature::MinHeight(h) => write!(f, "(min-height: {h}px)"),
+            MediaFeature::MaxHeight(h) => write!(f, "(max-height: {h}px)"),
+            MediaFeature::Orientation(o) => write!(f, "(orientation: {o})"),
+            MediaFeature::PrefersColorScheme(scheme) => {
+                write!(f, "(prefers-color-scheme: {scheme})")
+            }
+            MediaFeature::Unknown => write!(f, "(unknown)"),
+        }
+    }
+}
+
+impl MediaFeature {
+    fn evaluate(&self, viewport: &Viewport) -> bool {
+        let width = viewport.width as f64;
+        let height = viewport.height as f64;
+
+        match *self {
+            MediaFeature::Width(w) => width == w,
+            MediaFeature::MinWidth(w) => width >= w,
+            MediaFeature::MaxWidth(w) => width <= w,
+            MediaFeature::Height(h) => height == h,
+            MediaFeature::MinHeight(h) => height >= h,
+            MediaFeature::MaxHeight(h) => height <= h,
+            MediaFeature::Orientation(o) => {
+                let orientation = if height >= width {
+                    Orientation::Portrait
+                } else {
+                    Orientation::Landscape
+                };
+                orientation == o
+            }
+            MediaFeature::PrefersColorScheme(scheme) => viewport.color_scheme == scheme,
+            MediaFeature::Unknown => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    pub negated: bool,
+    pub media_type: MediaType,
+    pub features: Vec<MediaFeature>,
+}
+
+impl MediaQuery {
+    fn evaluate(&self, viewport: &Viewport) -> bool {
+        // 本書のブラウザは画面に描画するので、メディアタイプは常に screen として扱う
+        let type_matched = matches!(self.media_type, MediaType::All | MediaType::Screen);
+        let matched = type_matched && self.features.iter().all(|f| f.evaluate(viewport));
+        matched != self.negated
+    }
+}
+
+// https://www.w3.org/TR/cssom-1/#serialize-a-media-query
+impl Display for MediaQuery {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        if self.negated {
+            write!(f, "not ")?;
+        }
+        // not がなく、メディア特性がある場合は all を省略する
+        let mut parts = Vec::new();
+        if self.negated || self.media_type != MediaType::All || self.features.is_empty() {
+            parts.push(self.media_type.to_string());
+        }
+        parts.extend(self.features.iter().map(|feature| feature.to_string()));
+        write!(f, "{}", parts.join(" and "))
+    }
+}
+
+// カンマで区切られたメディアクエリのリスト。ひとつでもマッチすればリスト全体がマッチする
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MediaQueryList {
+    pub queries: Vec<MediaQuery>,
+}
+
+impl MediaQueryList {
+    pub fn evaluate(&self, viewport: &Viewport) -> bool {
+        // 空のリストは `all` と同じ意味になる
+        if self.queries.is_empty() {
+            return true;
+        }
+        self.queries.iter().any(|q| q.evaluate(viewport))
+    }
+
+    // https://www.w3.org/TR/mediaqueries-4/#mq-syntax
+    pub fn parse(tokens: &[CssToken]) -> Self {
+        let queries = tokens
+            .split(|t| *t == CssToken::Delim(','))
+            .map(parse_media_query)
+            .collect();
+        Self { queries }
+    }
+}
+
+// https://www.w3.org/TR/cssom-1/#serialize-a-media-query-list
+impl Display for MediaQueryList {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let queries: Vec<String> = self.queries.iter().map(|q| q.to_string()).collect();
+        write!(f, "{}", queries.join(", "))
+    }
+}
+
+fn parse_media_query(tokens: &[CssToken]) -> MediaQuery {
+    // 構文が不正なクエリは `not all` として扱う
+    let invalid = MediaQuery {
+        negated: true,
+        media_type: MediaType::All,
+        features: Vec::new(),
+    };
+
+    let mut query = MediaQuery {
+        negated: false,
+        media_type: MediaType::All,
+        features: Vec::new(),
+    };
+
+    let mut i = 0;
+    let mut expect_feature_only = false;
+    if let Some(CssToken::Ident(ident)) = tokens.first() {
+        match ident.to_ascii_lowercase().as_str() {
+            "not" => {
+                query.negated = true;
+                i += 1;
+            }
+            "only" => i += 1,
+            _ => {}
+        }
+    }
+
+    if let Some(CssToken::Ident(ident)) = tokens.get(i) {
+        query.media_type = match ident.to_ascii_lowercase().as_str() {
+            "all" => MediaType::All,
+            "screen" => MediaType::Screen,
+            "print" => MediaType::Print,
+            _ => MediaType::Unknown,
+        };
+        i += 1;
+    } else {
+        // メディアタイプが省略されている場合は `all` になり、not を前につけることはできない
+        if query.negated {
+            return invalid;
+        }
+        expect_feature_only = true;
+    }
+
+    let mut first = true;
+    while i < tokens.len() {
+        if !(first && expect_feature_only) {
+            match &tokens[i] {
+                CssToken::Ident(ident) if ident.eq_ignore_ascii_case("and") => i += 1,
+                _ => return invalid,
+            }
+        }
+        first = false;
+
+        if tokens.get(i) != Some(&CssToken::OpenParenthesis) {
+            return invalid;
+        }
+        let Some(close) = tokens[i..]
+            .iter()
+            .position(|t| *t == CssToken::CloseParenthesis)
+        else {
+            return invalid;
+        };
+        query
+            .features
+            .push(parse_media_feature(&tokens[i + 1..i + close]));
+        i += close + 1;
+    }
+
+    query
+}
+
+fn parse_media_feature(tokens: &[CssToken]) -> MediaFeature {
+    let [CssToken::Ident(name), CssToken::Colon, value] = tokens else {
+        return MediaFeature::Unknown;
+    };
+
+    match name.to_ascii_lowercase().as_str() {
+        "width" => length(value).map_or(MediaFeature::Unknown, MediaFeature::Width),
+        "min-width" => length(value).map_or(MediaFeature::Unknown, MediaFeature::MinWidth),
+        "max-width" => length(value).map_or(MediaFeature::Unknown, MediaFeature::MaxWidth),
+        "height" => length(value).map_or(MediaFeature::Unknown, MediaFeature::Height),
+        "min-height" => length(value).map_or(MediaFeature::Unknown, MediaFeature::MinHeight),
+        "max-height" => length(value).map_or(MediaFeature::Unknown, MediaFeature::MaxHeight),
+        "orientation" => match value {
+            CssToken::Ident(v) if v == "portrait" => {
+                MediaFeature::Orientation(Orientation::Portrait)
+            }
+            CssToken::Ident(v) if v == "landscape" => {
+                MediaFeature::Orientation(Orientation::Landscape)
+            }
+            _ => MediaFeature::Unknown,
+        },
+        "prefers-color-scheme" => match value {
+            CssToken::Ident(v) if v == "light" => {
+                MediaFeature::PrefersColorScheme(ColorScheme::Light)
+            }
+            CssToken::Ident(v) if v == "dark" => {
+                MediaFeature::PrefersColorScheme(ColorScheme::Dark)
+            }
+            _ => MediaFeature::Unknown,
+        },
+        _ => MediaFeature::Unknown,
+    }
+}
+
+fn length(token: &CssToken) -> Option<f64> {
+    match token {
+        CssToken::Number(n) if *n == 0.0 => Some(0.0),
+        CssToken::Dimension(n, unit) => match unit.to_ascii_lowercase().as_str() {
+            "px" => Some(*n),
+            // メディアクエリの em は、初期値のフォントサイズ（16px）を基準にする
+            "em" | "rem" => Some(*n * 16.0),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::renderer::css::token::CssTokenizer;
+
+    use super::*;
+
+    fn parse(media: &str) -> MediaQueryList {
+        let tokens: Vec<CssToken> = CssTokenizer::new(media.to_string()).collect();
+        MediaQueryList::parse(&tokens)
+    }
+
+    #[test]
+    fn test_parse() {
+        let list = parse("screen and (min-width: 600px), not print");
+        assert_eq!(
+            vec![
+                MediaQuery {
+                    negated: false,
+                    media_type: MediaType::Screen,
+                    features: vec![MediaFeature::MinWidth(600.0)],
+                },
+                MediaQuery {
+                    negated: true,
+                    media_type: MediaType::Print,
+                    features: Vec::new(),
+                },
+            ],
+            list.queries
+        );
+
+        let list = parse("(min-width: 40em) and (orientation: landscape)");
+        assert_eq!(
+            vec![MediaQuery {
+                negated: false,
+                media_type: MediaType::All,
+                features: vec![
+                    MediaFeature::MinWidth(640.0),
+                    MediaFeature::Orientation(Orientation::Landscape)
+                ],
+            }],
+            list.queries
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let serialize = |media: &str| parse(media).to_string();
+        assert_eq!(
+            "screen and (min-width: 600px), not print",
+            serialize("screen and (min-width: 600px), not print")
+        );
+        assert_eq!(
+            "(min-width: 640px) and (orientation: landscape)",
+            serialize("all and (min-width: 40em) and (orientation: landscape)")
+        );
+        assert_eq!("not all", serialize("screen or print"));
+        assert_eq!("all", serialize(""));
+    }
+
+    #[test]
+    fn test_evaluate_width() {
+        let narrow = Viewport::new(320, 480);
+        let wide = Viewport::new(1024, 768);
+
+        let list = parse("(max-width: 600px)");
+        assert!(list.evaluate(&narrow));
+        assert!(!list.evaluate(&wide));
+
+        let list = parse("screen and (min-width: 601px)");
+        assert!(!list.evaluate(&narrow));
+        assert!(list.evaluate(&wide));
+
+        let list = parse("(orientation: portrait)");
+        assert!(list.evaluate(&narrow));
+        assert!(!list.evaluate(&wide));
+    }
+
+    #[test]
+    fn test_evaluate_type_and_not() {
+        let viewport = Viewport::default();
+        assert!(parse("").evaluate(&viewport));
+        assert!(parse("all").evaluate(&viewport));
+        assert!(!parse("print").evaluate(&viewport));
+        assert!(parse("not print").evaluate(&viewport));
+        assert!(parse("print, screen").evaluate(&viewport));
+        assert!(!parse("tv").evaluate(&viewport));
+        assert!(!parse("screen and (unknown-feature: 1px)").evaluate(&viewport));
+        // 不正なクエリはマッチしない
+        assert!(!parse("screen or print").evaluate(&viewport));
+    }
+
+    #[test]
+    fn test_prefers_color_scheme() {
+        let mut viewport = Viewport::default();
+        let list = parse("(prefers-color-scheme: dark)");
+        assert!(!list.evaluate(&viewport));
+
+        viewport.set_color_scheme(ColorScheme::Dark);
+        assert!(list.evaluate(&viewport));
+    }
+}
@@ -1,2 +1,10 @@
+pub mod cascade;
 pub mod cssom;
+pub mod custom_property;
+pub mod invalidation;
+pub mod media;
+pub mod object_model;
+pub mod rule_index;
+pub mod shorthand;
 pub mod token;
+pub mod user_agent;
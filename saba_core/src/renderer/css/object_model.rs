@@ -0,0 +1,508 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    error::Error,
+    renderer::css::{
+        cssom::{
+            ComponentValue, CssParser, CssRule, Declaration, StyleSheet, is_custom_property,
+            serialize_value,
+        },
+        custom_property::contains_var,
+        shorthand::{
+            expand_declaration, expand_shorthand, is_shorthand, longhands, serialize_shorthand,
+            shorthands_of,
+        },
+        token::{CssToken, CssTokenizer},
+    },
+};
+
+// https://www.w3.org/TR/cssom-1/#the-cssstylesheet-interface
+// スクリプトや開発者ツールからスタイルシートを読み書きするための API
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CssStyleSheet {
+    sheet: StyleSheet,
+}
+
+impl CssStyleSheet {
+    pub fn new(sheet: StyleSheet) -> Self {
+        Self { sheet }
+    }
+
+    pub fn parse(css: &str) -> Self {
+        Self::new(CssParser::new(CssTokenizer::new(css.to_string())).parse_stylesheet())
+    }
+
+    pub fn style_sheet(&self) -> &StyleSheet {
+        &self.sheet
+    }
+
+    pub fn into_style_sheet(self) -> StyleSheet {
+        self.sheet
+    }
+
+    pub fn css_rules(&self) -> &[CssRule] {
+        &self.sheet.rules
+    }
+
+    pub fn css_rules_mut(&mut self) -> &mut [CssRule] {
+        &mut self.sheet.rules
+    }
+
+    // https://www.w3.org/TR/cssom-1/#insert-a-css-rule
+    // ルールを index の位置に挿入し、その位置を返す
+    pub fn insert_rule(&mut self, rule: &str, index: usize) -> Result<usize, Error> {
+        let rules = &mut self.sheet.rules;
+        if index > rules.len() {
+            return Err(Error::UnexpectedInput(format!(
+                "index {index} is larger than the number of rules {}",
+                rules.len()
+            )));
+        }
+
+        let rule = CssParser::new(CssTokenizer::new(rule.to_string())).parse_rule()?;
+
+        // @import はほかのすべてのルールより前にしか置けない
+        let misplaced = match rule {
+            CssRule::Import(_) => rules[..index]
+                .iter()
+                .any(|r| !matches!(r, CssRule::Import(_))),
+            _ => rules[index..]
+                .iter()
+                .any(|r| matches!(r, CssRule::Import(_))),
+        };
+        if misplaced {
+            return Err(Error::UnexpectedInput(
+                "@import must precede all other rules".to_string(),
+            ));
+        }
+
+        rules.insert(index, rule);
+        Ok(index)
+    }
+
+    // https://www.w3.org/TR/cssom-1/#remove-a-css-rule
+    pub fn delete_rule(&mut self, index: usize) -> Result<(), Error> {
+        if index >= self.sheet.rules.len() {
+            return Err(Error::UnexpectedInput(format!(
+                "index {index} is out of range"
+            )));
+        }
+        self.sheet.rules.remove(index);
+        Ok(())
+    }
+
+    pub fn css_text(&self) -> String {
+        self.sheet
+            .rules
+            .iter()
+            .map(|rule| rule.css_text())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// https://www.w3.org/TR/cssom-1/#the-cssstyledeclaration-interface
+// ショートハンドはロングハンドに展開して持ち、同じプロパティの宣言は1つだけにする。
+// ただし var() を含むショートハンドは、置き換えるまで展開できないのでそのまま持つ
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CssStyleDeclaration {
+    declarations: Vec<Declaration>,
+}
+
+impl CssStyleDeclaration {
+    pub fn new(declarations: Vec<Declaration>) -> Self {
+        let mut style = Self::default();
+        for declaration in declarations.into_iter().flat_map(expand_declaration) {
+            style.add_parsed(declaration);
+        }
+        style
+    }
+
+    // style 属性の値のような宣言のリストをパースする
+    pub fn parse(css_text: &str) -> Self {
+        Self::new(CssParser::new(CssTokenizer::new(css_text.to_string())).parse_declaration_list())
+    }
+
+    // 後の宣言が前の宣言を置き換える。ただし !important つきの宣言は、!important のない宣言では置き換えない
+    fn add_parsed(&mut self, declaration: Declaration) {
+        if let Some(i) = self.position(&declaration.property) {
+            if self.declarations[i].important && !declaration.important {
+                return;
+            }
+            self.declarations.remove(i);
+        }
+        self.declarations.push(declaration);
+    }
+
+    fn position(&self, property: &str) -> Option<usize> {
+        self.declarations
+            .iter()
+            .position(|d| d.property == property)
+    }
+
+    fn get(&self, property: &str) -> Option<&Declaration> {
+        self.declarations.iter().find(|d| d.property == property)
+    }
+
+    pub fn declarations(&self) -> &[Declaration] {
+        &self.declarations
+    }
+
+    pub fn length(&self) -> usize {
+        self.declarations.len()
+    }
+
+    pub fn item(&self, index: usize) -> Option<&str> {
+        self.declarations.get(index).map(|d| d.property.as_str())
+    }
+
+    // ショートハンドのロングハンドがすべてあり、重要度がそろっているときは、その宣言を返す
+    fn longhand_declarations(&self, shorthand: &str) -> Option<Vec<&Declaration>> {
+        let declarations = longhands(shorthand)?
+            .iter()
+            .map(|longhand| self.get(longhand))
+            .collect::<Option<Vec<_>>>()?;
+        let important = declarations.first()?.important;
+        declarations
+            .iter()
+            .all(|d| d.important == important)
+            .then_some(declarations)
+    }
+
+    // https://www.w3.org/TR/cssom-1/#dom-cssstyledeclaration-getpropertyvalue
+    // 宣言がないときや、ショートハンドで表せないときは空文字列を返す
+    pub fn get_property_value(&self, property: &str) -> String {
+        if let Some(declaration) = self.get(property) {
+            return serialize_value(&declaration.value);
+        }
+
+        self.longhand_declarations(property)
+            .and_then(|declarations| {
+                let values: Vec<&[ComponentValue]> =
+                    declarations.iter().map(|d| d.value.as_slice()).collect();
+                serialize_shorthand(property, &values)
+            })
+            .unwrap_or_default()
+    }
+
+    // https://www.w3.org/TR/cssom-1/#dom-cssstyledeclaration-getpropertypriority
+    pub fn get_property_priority(&self, property: &str) -> String {
+        let important = match self.get(property) {
+            Some(declaration) => declaration.important,
+            None => self
+                .longhand_declarations(property)
+                .is_some_and(|declarations| declarations[0].important),
+        };
+        if important {
+            "important".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    // https://www.w3.org/TR/cssom-1/#dom-cssstyledeclaration-setproperty
+    // 値が空のときは宣言を削除する。値や優先度が不正なときは何もしない
+    pub fn set_property(&mut self, property: &str, value: &str, priority: &str) {
+        let property = if is_custom_property(property) {
+            property.to_string()
+        } else {
+            property.to_ascii_lowercase()
+        };
+
+        let value: Vec<ComponentValue> = CssTokenizer::new(value.to_string()).collect();
+        if value.is_empty() {
+            self.remove_property(&property);
+            return;
+        }
+
+        let important = match priority {
+            "" => false,
+            p if p.eq_ignore_ascii_case("important") => true,
+            _ => return,
+        };
+
+        // 値は1つの宣言の値として完結していなければならず、!important を含めることもできない
+        let is_block_end = |t: &CssToken| {
+            matches!(
+                t,
+                CssToken::SemiColon | CssToken::OpenCurly | CssToken::CloseCurly
+            )
+        };
+        if value.iter().any(is_block_end) {
+            return;
+        }
+        if let [.., CssToken::Delim('!'), CssToken::Ident(ident)] = value.as_slice()
+            && ident.eq_ignore_ascii_case("important")
+        {
+            return;
+        }
+
+        let declarations = if contains_var(&value) || !is_shorthand(&property) {
+            vec![(property, value)]
+        } else {
+            let Some(expanded) = expand_shorthand(&property, &value) else {
+                return;
+            };
+            // 展開できたショートハンドは、置き換え前の宣言を残さない
+            self.declarations.retain(|d| d.property != property);
+            expanded
+        };
+
+        for (property, value) in declarations {
+            let declaration = Declaration {
+                property,
+                value,
+                important,
+            };
+            // すでに宣言があるときは、その位置のまま置き換える
+            match self.position(&declaration.property) {
+                Some(i) => self.declarations[i] = declaration,
+                None => self.declarations.push(declaration),
+            }
+        }
+    }
+
+    // https://www.w3.org/TR/cssom-1/#dom-cssstyledeclaration-removeproperty
+    // 削除する前の値を返す
+    pub fn remove_property(&mut self, property: &str) -> String {
+        let value = self.get_property_value(property);
+        let longhands = longhands(property).unwrap_or_default();
+        self.declarations
+            .retain(|d| d.property != property && !longhands.contains(&d.property));
+        value
+    }
+
+    // https://www.w3.org/TR/cssom-1/#serialize-a-css-declaration-block
+    // ロングハンドがそろっているショートハンドは、ショートハンドにまとめて書き出す
+    pub fn css_text(&self) -> String {
+        let mut blocks = Vec::new();
+        let mut serialized: Vec<&str> = Vec::new();
+
+        for declaration in &self.declarations {
+            if serialized.contains(&declaration.property.as_str()) {
+                continue;
+            }
+
+            let shorthand =
+                shorthands_of(&declaration.property)
+                    .into_iter()
+                    .find_map(|shorthand| {
+                        let declarations = self.longhand_declarations(&shorthand)?;
+                        let value = self.get_property_value(&shorthand);
+                        (!value.is_empty()).then_some((shorthand, value, declarations))
+                    });
+
+            match shorthand {
+                Some((shorthand, value, declarations)) => {
+                    let important = if declarations[0].important {
+                        " !important"
+                    } else {
+                        ""
+                    };
+                    blocks.push(format!("{shorthand}: {value}{important};"));
+                    serialized.extend(declarations.iter().map(|d| d.property.as_str()));
+                }
+                None => {
+                    blocks.push(format!("{declaration};"));
+                    serialized.push(&declaration.property);
+                }
+            }
+        }
+
+        blocks.join(" ")
+    }
+
+    pub fn set_css_text(&mut self, css_text: &str) {
+        *self = Self::parse(css_text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let css = r#"@import url("a.css");
+p { color: red; }
+h1, .title, #main { margin: 1px 2px; font-size: 40px !important; }
+* { border: 1px solid red; }
+@media screen and (min-width: 600px), print {
+  p { color: blue; }
+  @media (orientation: landscape) {
+  .b { padding: 0; }
+}
+}
+div { --gap: 1px 2px; width: var(--gap); }
+span { }"#;
+        let sheet = CssStyleSheet::parse(css);
+        assert_eq!(css, sheet.css_text());
+
+        // 書き出したものをパースし直すと、同じルールになる
+        let reparsed = CssStyleSheet::parse(&sheet.css_text());
+        assert_eq!(
+            sheet
+                .css_rules()
+                .iter()
+                .map(|r| r.css_text())
+                .collect::<Vec<_>>(),
+            reparsed
+                .css_rules()
+                .iter()
+                .map(|r| r.css_text())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_round_trip_string() {
+        let mut declaration = CssStyleDeclaration::parse("");
+        declaration.set_property("content", "\"a\\a b\"", "");
+        assert_eq!("content: \"a\\a b\";", declaration.css_text());
+
+        // 改行を含む文字列も、パースし直すと同じ値になる
+        let reparsed = CssStyleDeclaration::parse(&declaration.css_text());
+        assert_eq!(
+            declaration.get_property_value("content"),
+            reparsed.get_property_value("content")
+        );
+        assert_eq!(declaration.css_text(), reparsed.css_text());
+
+        // @import の URL も文字列としてエスケープする
+        let sheet = CssStyleSheet::parse(r#"@import url("a\"b.css");"#);
+        assert_eq!(r#"@import url("a\"b.css");"#, sheet.css_text());
+    }
+
+    #[test]
+    fn test_serialize_shorthands() {
+        let css_text = |css: &str| CssStyleDeclaration::parse(css).css_text();
+
+        assert_eq!("margin: 1px;", css_text("margin: 1px 1px 1px"));
+        assert_eq!(
+            "margin: 1px 2px 3px;",
+            css_text("margin-top: 1px; margin-right: 2px; margin-bottom: 3px; margin-left: 2px")
+        );
+        // ロングハンドがそろっていなければ、ロングハンドのまま書き出す
+        assert_eq!(
+            "margin-top: 1px; margin-right: 2px;",
+            css_text("margin-top: 1px; margin-right: 2px")
+        );
+        // 重要度がそろっていなければ、ショートハンドにまとめない
+        assert_eq!(
+            "padding-right: 0; padding-bottom: 0; padding-left: 0; padding-top: 1px !important;",
+            css_text("padding: 0; padding-top: 1px !important")
+        );
+        // 4つの辺がそろっていない border は、border-width などにまとめる
+        assert_eq!(
+            "border-width: 1px; border-style: solid; border-color: blue red red;",
+            css_text("border: 1px solid red; border-top-color: blue")
+        );
+        assert_eq!(
+            "border-top: thick dotted;",
+            css_text("border-top: dotted thick")
+        );
+        assert_eq!(
+            "font: italic bold 12px / 30px Georgia, serif;",
+            css_text("font: bold italic 12px/30px Georgia, serif")
+        );
+        assert_eq!(
+            "text-decoration: underline;",
+            css_text("text-decoration: underline")
+        );
+        assert_eq!("margin: inherit;", css_text("margin: inherit"));
+    }
+
+    #[test]
+    fn test_property_access() {
+        let mut style = CssStyleDeclaration::parse("color: red; margin: 1px 2px; color: blue");
+        assert_eq!(5, style.length());
+        assert_eq!(Some("margin-top"), style.item(0));
+        assert_eq!("blue", style.get_property_value("color"));
+        assert_eq!("2px", style.get_property_value("margin-left"));
+        assert_eq!("1px 2px", style.get_property_value("margin"));
+        assert_eq!("", style.get_property_value("padding"));
+
+        // 既存の宣言は、その位置のまま置き換える
+        style.set_property("margin-top", "5px", "important");
+        assert_eq!("important", style.get_property_priority("margin-top"));
+        assert_eq!("", style.get_property_priority("margin"));
+        assert_eq!("", style.get_property_value("margin"));
+        assert_eq!(
+            "margin-top: 5px !important; margin-right: 2px; margin-bottom: 1px; margin-left: 2px; color: blue;",
+            style.css_text()
+        );
+
+        style.set_property("margin", "3px", "");
+        assert_eq!("margin: 3px; color: blue;", style.css_text());
+
+        // 不正な値や優先度は無視する
+        style.set_property("margin", "red", "");
+        style.set_property("color", "green", "urgent");
+        style.set_property("color", "green; display: none", "");
+        style.set_property("color", "green !important", "");
+        assert_eq!("margin: 3px; color: blue;", style.css_text());
+
+        assert_eq!("3px", style.remove_property("margin"));
+        assert_eq!("color: blue;", style.css_text());
+        style.set_property("color", "", "");
+        assert_eq!(0, style.length());
+
+        // var() を含むショートハンドは展開しない
+        style.set_property("padding", "var(--x)", "");
+        assert_eq!("var(--x)", style.get_property_value("padding"));
+        assert_eq!("", style.get_property_value("padding-top"));
+        assert_eq!("padding: var(--x);", style.css_text());
+    }
+
+    #[test]
+    fn test_important_is_not_overridden_when_parsing() {
+        let style = CssStyleDeclaration::parse("color: red !important; color: blue");
+        assert_eq!("red", style.get_property_value("color"));
+        assert_eq!("important", style.get_property_priority("color"));
+    }
+
+    #[test]
+    fn test_insert_and_delete_rule() {
+        let mut sheet = CssStyleSheet::parse("p { color: red; }");
+
+        assert_eq!(Ok(0), sheet.insert_rule("h1 { color: blue; }", 0));
+        assert_eq!(
+            Ok(2),
+            sheet.insert_rule("@media print { p { color: black; } }", 2)
+        );
+        assert_eq!(
+            "h1 { color: blue; }\np { color: red; }\n@media print {\n  p { color: black; }\n}",
+            sheet.css_text()
+        );
+
+        assert!(sheet.insert_rule("div { color: red; }", 4).is_err());
+        assert!(sheet.insert_rule("div { color: red; } a { }", 0).is_err());
+        assert!(sheet.insert_rule("", 0).is_err());
+        // @import はほかのルールより後ろに置けない
+        assert!(sheet.insert_rule(r#"@import "a.css";"#, 1).is_err());
+        assert_eq!(Ok(0), sheet.insert_rule(r#"@import "a.css";"#, 0));
+        assert!(sheet.insert_rule("div { }", 0).is_err());
+
+        assert!(sheet.delete_rule(4).is_err());
+        assert_eq!(Ok(()), sheet.delete_rule(0));
+        assert_eq!(Ok(()), sheet.delete_rule(0));
+        assert_eq!(2, sheet.css_rules().len());
+
+        // ルールの宣言を書き換える
+        let CssRule::Style(rule) = &mut sheet.css_rules_mut()[0] else {
+            panic!("first rule should be a style rule");
+        };
+        let mut style = rule.style();
+        style.set_property("background-color", "white", "");
+        rule.set_style(&style);
+        assert_eq!(
+            "p { color: red; background-color: white; }",
+            sheet.css_rules()[0].css_text()
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::renderer::{
+    css::{
+        cascade::CascadeOrigin,
+        cssom::{Declaration, Selector, StyleSheet},
+        media::Viewport,
+        shorthand::expand_declaration,
+    },
+    dom::node::Element,
+};
+
+// カスケードに参加するスタイルルール。宣言はショートハンドを展開したもの
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedRule {
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<Declaration>,
+    pub origin: CascadeOrigin,
+    // このルールの最初の宣言の出現順
+    pub source_order: usize,
+}
+
+impl IndexedRule {
+    // セレクタリストのうち、要素にマッチするもっとも詳細度の高いセレクタを返す
+    pub fn matched_selector(&self, element: &Element) -> Option<&Selector> {
+        self.selectors
+            .iter()
+            .filter(|s| s.matches(element))
+            .max_by_key(|s| s.specificity())
+    }
+}
+
+// スタイルルールを、セレクタのもっとも右の部分の id、クラス、タイプ名ごとのバケットに分けた索引。
+// 要素のスタイルを計算するときは、要素の id、クラス、タイプ名のバケットと、
+// ユニバーサルセレクタのバケットにあるルールだけを調べればよい。
+// 本書のブラウザのセレクタは結合子を持たないので、祖先をたどってマッチを確かめる必要はない
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RuleIndex {
+    rules: Vec<IndexedRule>,
+    ids: BTreeMap<String, Vec<usize>>,
+    classes: BTreeMap<String, Vec<usize>>,
+    types: BTreeMap<String, Vec<usize>>,
+    universal: Vec<usize>,
+    // style 属性の宣言は、すべてのルールの宣言より後に出現したものとして扱う
+    inline_source_order: usize,
+}
+
+impl RuleIndex {
+    // ビューポートにマッチする @media の中のルールも含めて、スタイルシートの順に索引を作る
+    pub fn new(stylesheets: &[StyleSheet], viewport: &Viewport) -> Self {
+        let mut index = Self::default();
+        let mut source_order = 0;
+
+        for sheet in stylesheets {
+            for rule in sheet.style_rules(viewport) {
+                let declarations: Vec<Declaration> = rule
+                    .declarations
+                    .iter()
+                    .flat_map(|d| expand_declaration(d.clone()))
+                    .collect();
+                let count = declarations.len();
+                index.add_rule(IndexedRule {
+                    selectors: rule.selectors.clone(),
+                    declarations,
+                    origin: sheet.origin,
+                    source_order,
+                });
+                source_order += count;
+            }
+        }
+        index.inline_source_order = source_order;
+
+        index
+    }
+
+    fn add_rule(&mut self, rule: IndexedRule) {
+        let i = self.rules.len();
+
+        // セレクタリストの各セレクタを、それぞれのバケットに入れる。マッチしないセレクタは入れない
+        for selector in &rule.selectors {
+            let bucket = match selector {
+                Selector::IdSelector(id) => self.ids.entry(id.clone()).or_default(),
+                Selector::ClassSelector(class) => self.classes.entry(class.clone()).or_default(),
+                Selector::TypeSelector(type_name) => {
+                    self.types.entry(type_name.clone()).or_default()
+                }
+                // 属性セレクタは、すべての要素で試す
+                Selector::AttributeSelector(..) | Selector::UniversalSelector => {
+                    &mut self.universal
+                }
+                Selector::UnknownSelector => continue,
+            };
+            if bucket.last() != Some(&i) {
+                bucket.push(i);
+            }
+        }
+
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &[IndexedRule] {
+        &self.rules
+    }
+
+    pub fn inline_source_order(&self) -> usize {
+        self.inline_source_order
+    }
+
+    // 要素にマッチしうるルールを、出現順に返す。実際にマッチするかは呼び出し側で確かめる
+    pub fn candidates(&self, element: &Element) -> Vec<&IndexedRule> {
+        let mut indices = self.universal.clone();
+
+        let type_name = element.kind().to_string();
+        if let Some(bucket) = self.types.get(&type_name) {
+            indices.extend(bucket);
+        }
+        if let Some(id) = element.get_attribute("id")
+            && let Some(bucket) = self.ids.get(&id)
+        {
+            indices.extend(bucket);
+        }
+        if let Some(class) = element.get_attribute("class") {
+            for class in class.split_ascii_whitespace() {
+                if let Some(bucket) = self.classes.get(class) {
+                    indices.extend(bucket);
+                }
+            }
+        }
+
+        // セレクタリストの複数のセレクタが同じ要素にマッチしうる場合は、重複して集まる
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().map(|i| &self.rules[i]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::renderer::{
+        css::{cascade::CascadedStyle, cssom::CssParser, token::CssTokenizer},
+        html::attribute::Attribute,
+    };
+
+    use super::*;
+
+    fn index(css: &str) -> RuleIndex {
+        let sheet = CssParser::new(CssTokenizer::new(css.to_string())).parse_stylesheet();
+        RuleIndex::new(&[sheet], &Viewport::default())
+    }
+
+    #[test]
+    fn test_candidates() {
+        let index = index(
+            "p { color: red; } #main, .x { color: blue; } .y { margin: 0; } div { color: green; } * { color: black; }",
+        );
+        assert_eq!(5, index.rules().len());
+        // margin は4つのロングハンドに展開される
+        assert_eq!(8, index.inline_source_order());
+
+        let element = Element::new(
+            "p",
+            vec![Attribute::nv("id", "main"), Attribute::nv("class", "x z")],
+        );
+        let candidates = index.candidates(&element);
+        assert_eq!(
+            vec![0, 1, 7],
+            candidates
+                .iter()
+                .map(|rule| rule.source_order)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_same_result_as_all_rules() {
+        let index = index(
+            ".a { color: red; } p { color: blue; } .a, p { margin: 1px; } #b { color: green !important; }",
+        );
+        let elements = [
+            Element::new("p", vec![Attribute::nv("class", "a")]),
+            Element::new("div", vec![Attribute::nv("id", "b")]),
+            Element::new("span", vec![Attribute::nv("style", "color: red")]),
+        ];
+
+        for element in &elements {
+            assert_eq!(
+                CascadedStyle::from_rules(element, index.rules(), index.inline_source_order()),
+                CascadedStyle::from_index(element, &index)
+            );
+        }
+    }
+}
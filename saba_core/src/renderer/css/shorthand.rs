@@ -0,0 +1,997 @@
+use core::str::FromStr;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::renderer::{
+    css::{
+        cascade::CssWideKeyword,
+        cssom::{ComponentValue, Declaration, serialize_value},
+        custom_property::contains_var,
+    },
+    layout::{
+        computed_style::{BorderStyle, Color, Side, SideProperty, absolute_font_size},
+        flex::{FlexDirection, FlexWrap},
+        font::{FontStyle, FontWeight, parse_font_family},
+        grid::GridLine,
+        length::LengthPercentage,
+        text::{TextDecorationLine, TextDecorationStyle},
+    },
+};
+
+// https://www.w3.org/TR/css-cascade-4/#shorthand
+// ショートハンドプロパティが表すロングハンドプロパティの一覧を返す
+pub fn longhands(property: &str) -> Option<Vec<String>> {
+    let side_properties: &[SideProperty] = match property {
+        "margin" => &[SideProperty::Margin],
+        "padding" => &[SideProperty::Padding],
+        "border-width" => &[SideProperty::BorderWidth],
+        "border-style" => &[SideProperty::BorderStyle],
+        "border-color" => &[SideProperty::BorderColor],
+        "font" => {
+            return Some(
+                [
+                    "font-style",
+                    "font-weight",
+                    "font-size",
+                    "line-height",
+                    "font-family",
+                ]
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
+            );
+        }
+        "text-decoration" => {
+            return Some(
+                [
+                    "text-decoration-line",
+                    "text-decoration-style",
+                    "text-decoration-color",
+                ]
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
+            );
+        }
+        "flex" => {
+            return Some(
+                ["flex-grow", "flex-shrink", "flex-basis"]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            );
+        }
+        "flex-flow" => {
+            return Some(
+                ["flex-direction", "flex-wrap"]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            );
+        }
+        "gap" => {
+            return Some(
+                ["row-gap", "column-gap"]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            );
+        }
+        "grid-row" => {
+            return Some(
+                ["grid-row-start", "grid-row-end"]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            );
+        }
+        "grid-column" => {
+            return Some(
+                ["grid-column-start", "grid-column-end"]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            );
+        }
+        "grid-area" => {
+            return Some(
+                [
+                    "grid-row-start",
+                    "grid-column-start",
+                    "grid-row-end",
+                    "grid-column-end",
+                ]
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
+            );
+        }
+        "border" => &[
+            SideProperty::BorderWidth,
+            SideProperty::BorderStyle,
+            SideProperty::BorderColor,
+        ],
+        _ => {
+            let side = border_side(property)?;
+            return Some(
+                [
+                    SideProperty::BorderWidth,
+                    SideProperty::BorderStyle,
+                    SideProperty::BorderColor,
+                ]
+                .iter()
+                .map(|p| p.longhand(side))
+                .collect(),
+            );
+        }
+    };
+
+    Some(
+        side_properties
+            .iter()
+            .flat_map(|p| Side::ALL.iter().map(|side| p.longhand(*side)))
+            .collect(),
+    )
+}
+
+pub fn is_shorthand(property: &str) -> bool {
+    longhands(property).is_some()
+}
+
+// 本書のブラウザがサポートするショートハンドの一覧
+const SHORTHANDS: [&str; 18] = [
+    "border",
+    "border-top",
+    "border-right",
+    "border-bottom",
+    "border-left",
+    "border-width",
+    "border-style",
+    "border-color",
+    "margin",
+    "padding",
+    "font",
+    "text-decoration",
+    "flex",
+    "flex-flow",
+    "gap",
+    "grid-row",
+    "grid-column",
+    "grid-area",
+];
+
+// https://www.w3.org/TR/cssom-1/#concept-shorthands-preferred-order
+// ロングハンドを含むショートハンドを、含むロングハンドの多い順に返す
+pub fn shorthands_of(longhand: &str) -> Vec<String> {
+    let mut shorthands: Vec<(String, usize)> = SHORTHANDS
+        .iter()
+        .filter_map(|shorthand| {
+            let longhands = longhands(shorthand)?;
+            longhands
+                .iter()
+                .any(|l| l == longhand)
+                .then(|| (shorthand.to_string(), longhands.len()))
+        })
+        .collect();
+    shorthands.sort_by(|a, b| b.1.cmp(&a.1));
+    shorthands
+        .into_iter()
+        .map(|(shorthand, _)| shorthand)
+        .collect()
+}
+
+// https://www.w3.org/TR/cssom-1/#serialize-a-css-value
+// ロングハンドの値から、ショートハンドの値を組み立てる。values は longhands の順に並んでいること。
+// ショートハンドで表せないときは None を返す
+pub fn serialize_shorthand(property: &str, values: &[&[ComponentValue]]) -> Option<String> {
+    // CSS 全体のキーワードは、すべてのロングハンドが同じキーワードのときだけ表せる
+    let first = values.first()?;
+    if CssWideKeyword::parse(first).is_some() {
+        return values
+            .iter()
+            .all(|v| v == first)
+            .then(|| serialize_value(first));
+    }
+    if values.iter().any(|v| CssWideKeyword::parse(v).is_some()) {
+        return None;
+    }
+
+    let values: Vec<String> = values.iter().map(|v| serialize_value(v)).collect();
+    match property {
+        "font" => {
+            let [style, weight, size, line_height, family] = values.as_slice() else {
+                return None;
+            };
+            let mut parts = Vec::new();
+            if style != "normal" {
+                parts.push(style.clone());
+            }
+            if weight != "normal" && weight != "400" {
+                parts.push(weight.clone());
+            }
+            if line_height == "normal" {
+                parts.push(size.clone());
+            } else {
+                parts.push(format!("{size} / {line_height}"));
+            }
+            parts.push(family.clone());
+            Some(parts.join(" "))
+        }
+        "text-decoration" => {
+            let [line, style, color] = values.as_slice() else {
+                return None;
+            };
+            let mut parts = vec![line.clone()];
+            if style != "solid" {
+                parts.push(style.clone());
+            }
+            if color != "currentcolor" {
+                parts.push(color.clone());
+            }
+            Some(parts.join(" "))
+        }
+        "flex" => Some(values.join(" ")),
+        "flex-flow" => {
+            let [direction, wrap] = values.as_slice() else {
+                return None;
+            };
+            match (direction.as_str(), wrap.as_str()) {
+                (_, "nowrap") => Some(direction.clone()),
+                ("row", _) => Some(wrap.clone()),
+                _ => Some(values.join(" ")),
+            }
+        }
+        "gap" => {
+            let [row, column] = values.as_slice() else {
+                return None;
+            };
+            if row == column {
+                Some(row.clone())
+            } else {
+                Some(values.join(" "))
+            }
+        }
+        "grid-row" | "grid-column" | "grid-area" => Some(values.join(" / ")),
+        "margin" | "padding" | "border-width" | "border-style" | "border-color" => {
+            Some(serialize_sides(&values))
+        }
+        "border" => {
+            // 4つの辺がすべて同じときだけ border で表せる
+            let sides: Vec<&String> = values
+                .chunks(Side::ALL.len())
+                .map(|chunk| chunk.iter().all(|v| *v == chunk[0]).then_some(&chunk[0]))
+                .collect::<Option<_>>()?;
+            serialize_border(&sides)
+        }
+        _ => serialize_border(&values.iter().collect::<Vec<_>>()),
+    }
+}
+
+// 上、右、下、左の値を、同じ意味になるもっとも短い形で表す
+fn serialize_sides(values: &[String]) -> String {
+    let [top, right, bottom, left] = values else {
+        return values.join(" ");
+    };
+    if left != right {
+        return values.join(" ");
+    }
+    if top != bottom {
+        return format!("{top} {right} {bottom}");
+    }
+    if top != right {
+        return format!("{top} {right}");
+    }
+    top.clone()
+}
+
+// 幅、スタイル、色のうち、初期値でないものだけを書き出す
+fn serialize_border(values: &[&String]) -> Option<String> {
+    let [width, style, color] = values else {
+        return None;
+    };
+    let parts: Vec<&str> = [(width, "medium"), (style, "none"), (color, "currentcolor")]
+        .iter()
+        .filter(|(value, initial)| value.as_str() != *initial)
+        .map(|(value, _)| value.as_str())
+        .collect();
+    if parts.is_empty() {
+        return Some(style.to_string());
+    }
+    Some(parts.join(" "))
+}
+
+// ショートハンドの宣言をロングハンドの宣言に展開する。展開できない値の宣言は捨てる。
+// var() を含む値は置き換えたあとでないと展開できないので、そのまま残す
+pub fn expand_declaration(declaration: Declaration) -> Vec<Declaration> {
+    if !is_shorthand(&declaration.property) || contains_var(&declaration.value) {
+        return vec![declaration];
+    }
+
+    expand_shorthand(&declaration.property, &declaration.value)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(property, value)| Declaration {
+            property,
+            value,
+            important: declaration.important,
+        })
+        .collect()
+}
+
+// ショートハンドの値を、ロングハンドの名前と値の組に展開する。不正な値のときは None を返す
+pub fn expand_shorthand(
+    property: &str,
+    value: &[ComponentValue],
+) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    let longhands = longhands(property)?;
+
+    // inherit などのキーワードは、すべてのロングハンドに同じキーワードを指定したのと同じ
+    if CssWideKeyword::parse(value).is_some() {
+        return Some(
+            longhands
+                .into_iter()
+                .map(|longhand| (longhand, value.to_vec()))
+                .collect(),
+        );
+    }
+
+    let components = split_components(value);
+    match property {
+        "font" => expand_font(value, &components),
+        "text-decoration" => expand_text_decoration(&components),
+        "flex" => expand_flex(&components),
+        "flex-flow" => expand_flex_flow(&components),
+        "gap" => expand_gap(&components),
+        "grid-row" | "grid-column" | "grid-area" => expand_grid_lines(&longhands, value),
+        "margin" => expand_sides(SideProperty::Margin, &components),
+        "padding" => expand_sides(SideProperty::Padding, &components),
+        "border-width" => expand_sides(SideProperty::BorderWidth, &components),
+        "border-style" => expand_sides(SideProperty::BorderStyle, &components),
+        "border-color" => expand_sides(SideProperty::BorderColor, &components),
+        "border" => {
+            let values = expand_border(&components)?;
+            Some(
+                Side::ALL
+                    .iter()
+                    .flat_map(|side| {
+                        values
+                            .iter()
+                            .map(|(p, v)| (p.longhand(*side), v.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect(),
+            )
+        }
+        _ => {
+            let side = border_side(property)?;
+            let values = expand_border(&components)?;
+            Some(
+                values
+                    .into_iter()
+                    .map(|(p, v)| (p.longhand(side), v))
+                    .collect(),
+            )
+        }
+    }
+}
+
+// border-top などの、1つの辺の border ショートハンドのとき、その辺を返す
+fn border_side(property: &str) -> Option<Side> {
+    let side = property.strip_prefix("border-")?;
+    Side::ALL.into_iter().find(|s| s.name() == side)
+}
+
+// 1〜4個の値を、上、右、下、左の順に割り当てる
+fn expand_sides(
+    side_property: SideProperty,
+    components: &[&[ComponentValue]],
+) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    if !components.iter().all(|c| is_valid(side_property, c)) {
+        return None;
+    }
+
+    let (top, right, bottom, left) = match components {
+        [all] => (all, all, all, all),
+        [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+        [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+        [top, right, bottom, left] => (top, right, bottom, left),
+        _ => return None,
+    };
+
+    Some(
+        [
+            (Side::Top, top),
+            (Side::Right, right),
+            (Side::Bottom, bottom),
+            (Side::Left, left),
+        ]
+        .into_iter()
+        .map(|(side, value)| (side_property.longhand(side), value.to_vec()))
+        .collect(),
+    )
+}
+
+// 幅、スタイル、色を任意の順番で、それぞれ最大1つずつ指定できる。省略したものは初期値になる
+fn expand_border(
+    components: &[&[ComponentValue]],
+) -> Option<Vec<(SideProperty, Vec<ComponentValue>)>> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+
+    for component in components {
+        let slot = if is_valid(SideProperty::BorderWidth, component) {
+            &mut width
+        } else if is_valid(SideProperty::BorderStyle, component) {
+            &mut style
+        } else if is_valid(SideProperty::BorderColor, component) {
+            &mut color
+        } else {
+            return None;
+        };
+
+        if slot.is_some() {
+            return None;
+        }
+        *slot = Some(component.to_vec());
+    }
+    if components.is_empty() {
+        return None;
+    }
+
+    let initial = |keyword: &str| vec![ComponentValue::Ident(keyword.to_string())];
+    Some(vec![
+        (
+            SideProperty::BorderWidth,
+            width.unwrap_or_else(|| initial("medium")),
+        ),
+        (
+            SideProperty::BorderStyle,
+            style.unwrap_or_else(|| initial("none")),
+        ),
+        (
+            SideProperty::BorderColor,
+            color.unwrap_or_else(|| initial("currentcolor")),
+        ),
+    ])
+}
+
+// https://www.w3.org/TR/css-fonts-4/#font-prop
+// [ <font-style> || <font-weight> ]? <font-size> [ / <line-height> ]? <font-family> の形をとる。
+// font-variant は normal だけをサポートする
+fn expand_font(
+    value: &[ComponentValue],
+    components: &[&[ComponentValue]],
+) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    let normal = || vec![ComponentValue::Ident("normal".to_string())];
+    let mut style = None;
+    let mut weight = None;
+
+    let mut i = 0;
+    while let Some(component) = components.get(i) {
+        let keyword = match component {
+            [ComponentValue::Ident(keyword)] => Some(keyword.as_str()),
+            _ => None,
+        };
+
+        // normal はどのプロパティの値にもなりうるが、どれも初期値なので省略したのと同じ
+        if keyword.is_some_and(|k| k.eq_ignore_ascii_case("normal")) {
+        } else if style.is_none() && keyword.is_some_and(|k| FontStyle::from_str(k).is_ok()) {
+            style = Some(component.to_vec());
+        } else if weight.is_none() && FontWeight::parse(component).is_some() {
+            weight = Some(component.to_vec());
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    if i > 3 {
+        return None;
+    }
+
+    let size = components.get(i).filter(|c| is_font_size(c))?.to_vec();
+    i += 1;
+
+    let mut line_height = None;
+    if components.get(i) == Some(&&[ComponentValue::Delim('/')][..]) {
+        line_height = Some(
+            components
+                .get(i + 1)
+                .filter(|c| is_line_height(c))?
+                .to_vec(),
+        );
+        i += 2;
+    }
+
+    let offset = components[..i].iter().map(|c| c.len()).sum();
+    let family = &value[offset..];
+    parse_font_family(family)?;
+
+    Some(vec![
+        ("font-style".to_string(), style.unwrap_or_else(normal)),
+        ("font-weight".to_string(), weight.unwrap_or_else(normal)),
+        ("font-size".to_string(), size),
+        (
+            "line-height".to_string(),
+            line_height.unwrap_or_else(normal),
+        ),
+        ("font-family".to_string(), family.to_vec()),
+    ])
+}
+
+// https://www.w3.org/TR/css-text-decor-3/#text-decoration-property
+// 線の種類、スタイル、色を任意の順番で指定できる。省略したものは初期値になる
+fn expand_text_decoration(
+    components: &[&[ComponentValue]],
+) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    let mut line = Vec::new();
+    let mut style = None;
+    let mut color = None;
+
+    for component in components {
+        let keyword = match component {
+            [ComponentValue::Ident(keyword)] => Some(keyword.as_str()),
+            _ => None,
+        };
+
+        if TextDecorationLine::parse(component).is_some() {
+            line.extend_from_slice(component);
+        } else if style.is_none()
+            && keyword.is_some_and(|k| TextDecorationStyle::from_str(k).is_ok())
+        {
+            style = Some(component.to_vec());
+        } else if color.is_none()
+            && (keyword.is_some_and(|k| k.eq_ignore_ascii_case("currentcolor"))
+                || Color::from_component_values(component).is_ok())
+        {
+            color = Some(component.to_vec());
+        } else {
+            return None;
+        }
+    }
+    if components.is_empty() || (!line.is_empty() && TextDecorationLine::parse(&line).is_none()) {
+        return None;
+    }
+
+    let initial = |keyword: &str| vec![ComponentValue::Ident(keyword.to_string())];
+    if line.is_empty() {
+        line = initial("none");
+    }
+    Some(vec![
+        ("text-decoration-line".to_string(), line),
+        (
+            "text-decoration-style".to_string(),
+            style.unwrap_or_else(|| initial("solid")),
+        ),
+        (
+            "text-decoration-color".to_string(),
+            color.unwrap_or_else(|| initial("currentcolor")),
+        ),
+    ])
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#flex-property
+// none、または <flex-grow> <flex-shrink>? || <flex-basis> の形をとる。省略した flex-grow と
+// flex-shrink は 1、flex-basis は 0 になる。none は 0 0 auto と同じ
+fn expand_flex(components: &[&[ComponentValue]]) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    let keyword = |keyword: &str| vec![ComponentValue::Ident(keyword.to_string())];
+    let number = |n: f64| vec![ComponentValue::Number(n)];
+
+    let (grow, shrink, basis) = match components {
+        [[ComponentValue::Ident(none)]] if none.eq_ignore_ascii_case("none") => {
+            (number(0.0), number(1.0), keyword("auto"))
+        }
+        _ => {
+            let mut factors = Vec::new();
+            // flex-basis と、その前にある係数の数
+            let mut basis: Option<(Vec<ComponentValue>, usize)> = None;
+            for component in components {
+                match component {
+                    [ComponentValue::Number(n)] if *n >= 0.0 && factors.len() < 2 => {
+                        // 2つの係数の間には flex-basis を書けない
+                        if basis.as_ref().is_some_and(|(_, before)| *before > 0) {
+                            return None;
+                        }
+                        factors.push(component.to_vec());
+                    }
+                    _ if basis.is_none() && is_flex_basis(component) => {
+                        basis = Some((component.to_vec(), factors.len()));
+                    }
+                    _ => return None,
+                }
+            }
+            if components.is_empty() {
+                return None;
+            }
+
+            let mut factors = factors.into_iter();
+            (
+                factors.next().unwrap_or_else(|| number(1.0)),
+                factors.next().unwrap_or_else(|| number(1.0)),
+                basis.map_or_else(|| vec![ComponentValue::Percentage(0.0)], |(b, _)| b),
+            )
+        }
+    };
+    Some(vec![
+        ("flex-grow".to_string(), grow),
+        ("flex-shrink".to_string(), shrink),
+        ("flex-basis".to_string(), basis),
+    ])
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#flex-flow-property
+// flex-direction と flex-wrap を任意の順番で指定できる。省略したものは初期値になる
+fn expand_flex_flow(
+    components: &[&[ComponentValue]],
+) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    let mut direction = None;
+    let mut wrap = None;
+
+    for component in components {
+        let [ComponentValue::Ident(keyword)] = component else {
+            return None;
+        };
+        let slot = if FlexDirection::from_str(keyword).is_ok() {
+            &mut direction
+        } else if FlexWrap::from_str(keyword).is_ok() {
+            &mut wrap
+        } else {
+            return None;
+        };
+
+        if slot.is_some() {
+            return None;
+        }
+        *slot = Some(component.to_vec());
+    }
+    if components.is_empty() {
+        return None;
+    }
+
+    let initial = |keyword: &str| vec![ComponentValue::Ident(keyword.to_string())];
+    Some(vec![
+        (
+            "flex-direction".to_string(),
+            direction.unwrap_or_else(|| initial("row")),
+        ),
+        (
+            "flex-wrap".to_string(),
+            wrap.unwrap_or_else(|| initial("nowrap")),
+        ),
+    ])
+}
+
+// https://www.w3.org/TR/css-align-3/#gap-shorthand
+// row-gap と column-gap の順に指定する。1つだけのときは両方に同じ値を使う
+fn expand_gap(components: &[&[ComponentValue]]) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    if !components.iter().all(|c| is_gap(c)) {
+        return None;
+    }
+
+    let (row, column) = match components {
+        [all] => (all, all),
+        [row, column] => (row, column),
+        _ => return None,
+    };
+    Some(vec![
+        ("row-gap".to_string(), row.to_vec()),
+        ("column-gap".to_string(), column.to_vec()),
+    ])
+}
+
+// https://www.w3.org/TR/css-grid-1/#placement-shorthands
+// 線を / で区切って、longhands の順に指定する。省略した線は、対応する線が名前なら同じ名前、
+// それ以外は auto になる
+fn expand_grid_lines(
+    longhands: &[String],
+    value: &[ComponentValue],
+) -> Option<Vec<(String, Vec<ComponentValue>)>> {
+    let mut values: Vec<Vec<ComponentValue>> = value
+        .split(|t| *t == ComponentValue::Delim('/'))
+        .map(|line| line.to_vec())
+        .collect();
+    if values.len() > longhands.len() || values.iter().any(|v| GridLine::parse(v).is_none()) {
+        return None;
+    }
+
+    // grid-area では、列の始まりと行の終わりは行の始まりに、列の終わりは列の始まりに対応する
+    let half = longhands.len() / 2;
+    while values.len() < longhands.len() {
+        let i = values.len();
+        let source = i.saturating_sub(half);
+        let line = match GridLine::parse(&values[source]) {
+            Some(GridLine::Named(_)) => values[source].clone(),
+            _ => vec![ComponentValue::Ident("auto".to_string())],
+        };
+        values.push(line);
+    }
+    Some(longhands.iter().cloned().zip(values).collect())
+}
+
+fn is_flex_basis(value: &[ComponentValue]) -> bool {
+    if let [ComponentValue::Ident(keyword)] = value {
+        return keyword.eq_ignore_ascii_case("auto") || keyword.eq_ignore_ascii_case("content");
+    }
+    LengthPercentage::parse_non_negative(value).is_ok()
+}
+
+fn is_gap(value: &[ComponentValue]) -> bool {
+    if let [ComponentValue::Ident(keyword)] = value {
+        return keyword.eq_ignore_ascii_case("normal");
+    }
+    LengthPercentage::parse_non_negative(value).is_ok()
+}
+
+fn is_font_size(value: &[ComponentValue]) -> bool {
+    if let [ComponentValue::Ident(keyword)] = value {
+        let keyword = keyword.to_ascii_lowercase();
+        return keyword == "smaller"
+            || keyword == "larger"
+            || absolute_font_size(&keyword).is_some();
+    }
+    LengthPercentage::parse_non_negative(value).is_ok()
+}
+
+fn is_line_height(value: &[ComponentValue]) -> bool {
+    match value {
+        [ComponentValue::Ident(keyword)] => keyword.eq_ignore_ascii_case("normal"),
+        [ComponentValue::Number(n)] => *n >= 0.0,
+        _ => LengthPercentage::parse_non_negative(value).is_ok(),
+    }
+}
+
+fn is_valid(side_property: SideProperty, value: &[ComponentValue]) -> bool {
+    let keyword = match value {
+        [ComponentValue::Ident(keyword)] => Some(keyword.to_ascii_lowercase()),
+        _ => None,
+    };
+
+    match side_property {
+        SideProperty::Margin => {
+            keyword.as_deref() == Some("auto") || LengthPercentage::parse(value).is_ok()
+        }
+        SideProperty::Padding => LengthPercentage::parse_non_negative(value).is_ok(),
+        SideProperty::BorderWidth => {
+            matches!(keyword.as_deref(), Some("thin" | "medium" | "thick"))
+                || LengthPercentage::parse_non_negative(value).is_ok_and(|l| l.percent == 0.0)
+        }
+        SideProperty::BorderStyle => keyword.is_some_and(|k| BorderStyle::from_str(&k).is_ok()),
+        SideProperty::BorderColor => {
+            keyword.as_deref() == Some("currentcolor")
+                || Color::from_component_values(value).is_ok()
+        }
+    }
+}
+
+// 値を、空白で区切られた成分ごとに分ける。関数は閉じ括弧までを1つの成分にする
+pub fn split_components(value: &[ComponentValue]) -> Vec<&[ComponentValue]> {
+    let mut components = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+
+    for (i, token) in value.iter().enumerate() {
+        match token {
+            ComponentValue::Function(_) | ComponentValue::OpenParenthesis => depth += 1,
+            ComponentValue::CloseParenthesis => depth -= 1,
+            _ => {}
+        }
+        if depth <= 0 {
+            components.push(&value[start..=i]);
+            start = i + 1;
+            depth = 0;
+        }
+    }
+    if start < value.len() {
+        components.push(&value[start..]);
+    }
+
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::css::{
+        cssom::{CssParser, serialize_value},
+        token::CssTokenizer,
+    };
+
+    use super::*;
+
+    fn expand(css: &str) -> Vec<(String, String)> {
+        CssParser::new(CssTokenizer::new(css.to_string()))
+            .parse_declaration_list()
+            .into_iter()
+            .flat_map(expand_declaration)
+            .map(|d| (d.property, serialize_value(&d.value)))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(p, v)| (p.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_box_sides() {
+        assert_eq!(
+            pairs(&[
+                ("margin-top", "1px"),
+                ("margin-right", "auto"),
+                ("margin-bottom", "calc(1em + 2px)"),
+                ("margin-left", "auto"),
+            ]),
+            expand("margin: 1px auto calc(1em + 2px)")
+        );
+        assert_eq!(
+            pairs(&[
+                ("padding-top", "inherit"),
+                ("padding-right", "inherit"),
+                ("padding-bottom", "inherit"),
+                ("padding-left", "inherit"),
+            ]),
+            expand("padding: inherit")
+        );
+        // 負の padding は不正なので、宣言全体が捨てられる
+        assert_eq!(pairs(&[]), expand("padding: 1px -2px"));
+        assert_eq!(pairs(&[]), expand("margin: 1px 2px 3px 4px 5px"));
+    }
+
+    #[test]
+    fn test_font() {
+        assert_eq!(
+            pairs(&[
+                ("font-style", "italic"),
+                ("font-weight", "bold"),
+                ("font-size", "12px"),
+                ("line-height", "1.5"),
+                ("font-family", "\"Noto Sans\", sans-serif"),
+            ]),
+            expand(r#"font: bold italic 12px/1.5 "Noto Sans", sans-serif"#)
+        );
+        assert_eq!(
+            pairs(&[
+                ("font-style", "normal"),
+                ("font-weight", "normal"),
+                ("font-size", "large"),
+                ("line-height", "normal"),
+                ("font-family", "monospace"),
+            ]),
+            expand("font: normal large monospace")
+        );
+        // font-size と font-family は省略できない
+        assert_eq!(pairs(&[]), expand("font: bold serif"));
+        assert_eq!(pairs(&[]), expand("font: 12px"));
+    }
+
+    #[test]
+    fn test_text_decoration() {
+        assert_eq!(
+            pairs(&[
+                ("text-decoration-line", "underline overline"),
+                ("text-decoration-style", "wavy"),
+                ("text-decoration-color", "red"),
+            ]),
+            expand("text-decoration: red underline overline wavy")
+        );
+        assert_eq!(
+            pairs(&[
+                ("text-decoration-line", "none"),
+                ("text-decoration-style", "solid"),
+                ("text-decoration-color", "currentcolor"),
+            ]),
+            expand("text-decoration: none")
+        );
+        assert_eq!(pairs(&[]), expand("text-decoration: underline underline"));
+        assert_eq!(pairs(&[]), expand("text-decoration: solid dotted"));
+    }
+
+    #[test]
+    fn test_border() {
+        assert_eq!(
+            pairs(&[
+                ("border-top-width", "medium"),
+                ("border-top-style", "dashed"),
+                ("border-top-color", "rgb(0, 0, 255)"),
+            ]),
+            expand("border-top: rgb(0, 0, 255) dashed")
+        );
+        assert_eq!(12, expand("border: 2px solid").len());
+        assert_eq!(
+            ("border-left-color".to_string(), "currentcolor".to_string()),
+            expand("border: 2px solid")[11]
+        );
+        assert_eq!(pairs(&[]), expand("border: solid dotted"));
+
+        // var() を含む値は、置き換えたあとに展開する
+        assert_eq!(
+            pairs(&[("border", "var(--border)")]),
+            expand("border: var(--border)")
+        );
+    }
+
+    #[test]
+    fn test_flex() {
+        assert_eq!(
+            pairs(&[
+                ("flex-grow", "2"),
+                ("flex-shrink", "1"),
+                ("flex-basis", "0%"),
+            ]),
+            expand("flex: 2")
+        );
+        assert_eq!(
+            pairs(&[
+                ("flex-grow", "1"),
+                ("flex-shrink", "1"),
+                ("flex-basis", "auto"),
+            ]),
+            expand("flex: auto")
+        );
+        assert_eq!(
+            pairs(&[
+                ("flex-grow", "0"),
+                ("flex-shrink", "1"),
+                ("flex-basis", "auto"),
+            ]),
+            expand("flex: none")
+        );
+        assert_eq!(
+            pairs(&[
+                ("flex-grow", "1"),
+                ("flex-shrink", "3"),
+                ("flex-basis", "20px"),
+            ]),
+            expand("flex: 20px 1 3")
+        );
+        assert_eq!(pairs(&[]), expand("flex: 1 20px 3"));
+
+        assert_eq!(
+            pairs(&[("flex-direction", "column"), ("flex-wrap", "wrap")]),
+            expand("flex-flow: wrap column")
+        );
+        assert_eq!(
+            pairs(&[("row-gap", "10px"), ("column-gap", "10px")]),
+            expand("gap: 10px")
+        );
+        assert_eq!(pairs(&[]), expand("gap: 1px 2px 3px"));
+    }
+
+    #[test]
+    fn test_grid_lines() {
+        assert_eq!(
+            pairs(&[("grid-row-start", "1"), ("grid-row-end", "span 2")]),
+            expand("grid-row: 1 / span 2")
+        );
+        assert_eq!(
+            pairs(&[("grid-column-start", "2"), ("grid-column-end", "auto")]),
+            expand("grid-column: 2")
+        );
+        assert_eq!(
+            pairs(&[
+                ("grid-row-start", "main"),
+                ("grid-column-start", "main"),
+                ("grid-row-end", "main"),
+                ("grid-column-end", "main"),
+            ]),
+            expand("grid-area: main")
+        );
+        assert_eq!(
+            pairs(&[
+                ("grid-row-start", "1"),
+                ("grid-column-start", "nav"),
+                ("grid-row-end", "auto"),
+                ("grid-column-end", "nav"),
+            ]),
+            expand("grid-area: 1 / nav")
+        );
+        assert_eq!(pairs(&[]), expand("grid-row: 1 / 2 / 3"));
+        assert_eq!(pairs(&[]), expand("grid-column: span 0"));
+    }
+}
@@ -1,3 +1,5 @@
+use core::fmt::{Display, Formatter};
+
 use alloc::{string::String, vec::Vec};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -5,6 +7,8 @@ pub enum CssToken {
     HashToken(String),
     Delim(char),
     Number(f64),
+    Percentage(f64),
+    Dimension(f64, String),
     Colon,
     SemiColon,
     OpenParenthesis,
@@ -14,6 +18,51 @@ pub enum CssToken {
     Ident(String),
     StringToken(String),
     AtKeyword(String),
+    Url(String),
+    // `var(` のように、識別子の直後に `(` が続くもの。閉じ括弧は CloseParenthesis になる
+    Function(String),
+}
+
+impl Display for CssToken {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            CssToken::HashToken(s) => write!(f, "{s}"),
+            CssToken::Delim(c) => write!(f, "{c}"),
+            CssToken::Number(n) => write!(f, "{n}"),
+            CssToken::Percentage(n) => write!(f, "{n}%"),
+            CssToken::Dimension(n, unit) => write!(f, "{n}{unit}"),
+            CssToken::Colon => write!(f, ":"),
+            CssToken::SemiColon => write!(f, ";"),
+            CssToken::OpenParenthesis => write!(f, "("),
+            CssToken::CloseParenthesis => write!(f, ")"),
+            CssToken::OpenCurly => write!(f, "{{"),
+            CssToken::CloseCurly => write!(f, "}}"),
+            CssToken::Ident(s) => write!(f, "{s}"),
+            CssToken::StringToken(s) => serialize_string(f, s),
+            CssToken::AtKeyword(s) => write!(f, "@{s}"),
+            CssToken::Url(s) => {
+                write!(f, "url(")?;
+                serialize_string(f, s)?;
+                write!(f, ")")
+            }
+            CssToken::Function(s) => write!(f, "{s}("),
+        }
+    }
+}
+
+// https://drafts.csswg.org/cssom/#serialize-a-string
+// " と \ はバックスラッシュでエスケープし、制御文字は 16 進数のコードポイントと空白で書く
+fn serialize_string(f: &mut Formatter, s: &str) -> core::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '\0' => write!(f, "\u{FFFD}")?,
+            '\u{1}'..='\u{1f}' | '\u{7f}' => write!(f, "\\{:x} ", c as u32)?,
+            '"' | '\\' => write!(f, "\\{c}")?,
+            _ => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -31,17 +80,20 @@ impl CssTokenizer {
     }
 
     fn consume_string_token(&mut self) -> String {
+        // 現在の位置は開き引用符で、同じ引用符で文字列が終わる
+        let quote = self.input[self.pos];
         let mut s = String::new();
 
         loop {
-            if self.pos >= self.input.len() {
-                return s;
-            }
-
             self.pos += 1;
-            let c = self.input[self.pos];
+            let Some(&c) = self.input.get(self.pos) else {
+                return s;
+            };
             match c {
-                '"' | '\'' => break,
+                c if c == quote => break,
+                // 改行の前のバックスラッシュは、文字列を次の行に続ける
+                '\\' if self.input.get(self.pos + 1) == Some(&'\n') => self.pos += 1,
+                '\\' => s.extend(self.consume_escaped_code_point()),
                 _ => s.push(c),
             }
         }
@@ -49,6 +101,37 @@ impl CssTokenizer {
         s
     }
 
+    // https://www.w3.org/TR/css-syntax-3/#consume-escaped-code-point
+    // 現在の位置は \ で、最後に消費した文字の位置で終わる。
+    // 1 から 6 桁の 16 進数はコードポイントになり、直後の空白ひとつは区切りとして読み飛ばす
+    fn consume_escaped_code_point(&mut self) -> Option<char> {
+        self.pos += 1;
+        let &c = self.input.get(self.pos)?;
+        if !c.is_ascii_hexdigit() {
+            return Some(c);
+        }
+
+        let mut code_point = 0;
+        let mut digits = 0;
+        while digits < 6
+            && let Some(digit) = self.input.get(self.pos).and_then(|c| c.to_digit(16))
+        {
+            code_point = code_point * 16 + digit;
+            digits += 1;
+            self.pos += 1;
+        }
+        if !matches!(self.input.get(self.pos), Some(' ' | '\n' | '\t')) {
+            self.pos -= 1;
+        }
+
+        // NULL、サロゲート、範囲外のコードポイントは置換文字にする
+        Some(
+            char::from_u32(code_point)
+                .filter(|c| *c != '\0')
+                .unwrap_or('\u{FFFD}'),
+        )
+    }
+
     fn consume_numeric_token(&mut self) -> f64 {
         let mut num = 0f64;
         let mut floating = false;
@@ -84,13 +167,64 @@ impl CssTokenizer {
         num
     }
 
+    // 数値の直後に % や単位が続く場合は、まとめてひとつのトークンにする
+    fn consume_number(&mut self, sign: f64) -> CssToken {
+        let num = sign * self.consume_numeric_token();
+        match self.input.get(self.pos + 1) {
+            Some('%') => {
+                self.pos += 1;
+                CssToken::Percentage(num)
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.pos += 1;
+                CssToken::Dimension(num, self.consume_ident_token())
+            }
+            _ => CssToken::Number(num),
+        }
+    }
+
+    fn consume_url_token(&mut self) -> String {
+        // 現在の位置は `url(` の `(`
+        let mut s = String::new();
+        let mut quote = None;
+
+        loop {
+            self.pos += 1;
+            let Some(&c) = self.input.get(self.pos) else {
+                return s;
+            };
+            match c {
+                '"' | '\'' if quote.is_none() && s.is_empty() => quote = Some(c),
+                '"' | '\'' if quote == Some(c) => quote = None,
+                ')' if quote.is_none() => return s,
+                ' ' | '\n' | '\t' if quote.is_none() => {}
+                '\\' => s.extend(self.consume_escaped_code_point()),
+                _ => s.push(c),
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        // 現在の位置は `/*` の `/`
+        self.pos += 2;
+        while self.pos < self.input.len() {
+            if self.input[self.pos] == '*' && self.input.get(self.pos + 1) == Some(&'/') {
+                self.pos += 2;
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
     fn consume_ident_token(&mut self) -> String {
         let mut s = String::new();
         s.push(self.input[self.pos]);
 
         loop {
             self.pos += 1;
-            let c = self.input[self.pos];
+            let Some(&c) = self.input.get(self.pos) else {
+                break;
+            };
             match c {
                 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => {
                     s.push(c);
@@ -119,34 +253,65 @@ impl Iterator for CssTokenizer {
             let token = match c {
                 '(' => CssToken::OpenParenthesis,
                 ')' => CssToken::CloseParenthesis,
-                ',' | '.' => CssToken::Delim(c),
                 ':' => CssToken::Colon,
                 ';' => CssToken::SemiColon,
                 '{' => CssToken::OpenCurly,
                 '}' => CssToken::CloseCurly,
-                ' ' | '\n' => {
+                ' ' | '\n' | '\t' | '\r' => {
                     self.pos += 1;
                     continue;
                 }
+                '/' if self.input.get(self.pos + 1) == Some(&'*') => {
+                    self.skip_comment();
+                    continue;
+                }
                 '"' | '\'' => CssToken::StringToken(self.consume_string_token()),
-                '0'..='9' => CssToken::Number(self.consume_numeric_token()),
+                '0'..='9' => self.consume_number(1.0),
                 '#' => CssToken::HashToken(self.consume_ident_token()), // 本書では、常に #IDの形式のIDセレクタとして扱う。
-                '-' => CssToken::Ident(self.consume_ident_token()), // 本書では、負の数は取り扱わないため、ハイフンは識別子の一つとして扱う。
+                '-' => match self.input.get(self.pos + 1) {
+                    Some(c) if c.is_ascii_digit() => {
+                        self.pos += 1;
+                        self.consume_number(-1.0)
+                    }
+                    Some(c) if c.is_ascii_alphabetic() || *c == '-' || *c == '_' => {
+                        CssToken::Ident(self.consume_ident_token())
+                    }
+                    // calc() の中の引き算など
+                    _ => CssToken::Delim('-'),
+                },
                 '@' => {
-                    if self.input[self.pos + 1].is_ascii_alphabetic()
-                        && self.input[self.pos + 2].is_alphanumeric()
-                        && self.input[self.pos + 3].is_alphanumeric()
-                    {
+                    // https://www.w3.org/TR/css-syntax-3/#would-start-an-identifier
+                    let starts_ident = match self.input.get(self.pos + 1) {
+                        Some('-') => self
+                            .input
+                            .get(self.pos + 2)
+                            .is_some_and(|c| c.is_ascii_alphabetic() || *c == '-' || *c == '_'),
+                        Some(c) => c.is_ascii_alphabetic() || *c == '_',
+                        None => false,
+                    };
+                    if starts_ident {
                         self.pos += 1; // skip '@'
                         CssToken::AtKeyword(self.consume_ident_token())
                     } else {
                         CssToken::Delim('@')
                     }
                 }
-                'a'..='z' | 'A'..='Z' | '_' => CssToken::Ident(self.consume_ident_token()),
-                _ => {
-                    unimplemented!("char {c} is not supported yet")
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    let ident = self.consume_ident_token();
+                    if ident.eq_ignore_ascii_case("url")
+                        && self.input.get(self.pos + 1) == Some(&'(')
+                    {
+                        self.pos += 1;
+                        CssToken::Url(self.consume_url_token())
+                    } else if self.input.get(self.pos + 1) == Some(&'(') {
+                        self.pos += 1;
+                        CssToken::Function(ident)
+                    } else {
+                        CssToken::Ident(ident)
+                    }
                 }
+                // 本書のブラウザで扱わない文字は、パースを止めないように区切り文字として扱う
+                _ => CssToken::Delim(c),
             };
 
             self.pos += 1;
@@ -157,7 +322,7 @@ impl Iterator for CssTokenizer {
 
 #[cfg(test)]
 mod tests {
-    use alloc::string::ToString;
+    use alloc::{string::ToString, vec};
 
     use super::*;
 
@@ -255,4 +420,137 @@ mod tests {
         }
         assert!(t.next().is_none());
     }
+
+    #[test]
+    fn test_dimension_and_percentage() {
+        let style = "p { margin-top: 0.5em; width: 50%; height: 10px }".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("p".to_string()),
+            CssToken::OpenCurly,
+            CssToken::Ident("margin-top".to_string()),
+            CssToken::Colon,
+            CssToken::Dimension(0.5, "em".to_string()),
+            CssToken::SemiColon,
+            CssToken::Ident("width".to_string()),
+            CssToken::Colon,
+            CssToken::Percentage(50.0),
+            CssToken::SemiColon,
+            CssToken::Ident("height".to_string()),
+            CssToken::Colon,
+            CssToken::Dimension(10.0, "px".to_string()),
+            CssToken::CloseCurly,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
+    #[test]
+    fn test_comment_and_url() {
+        let style = "/* comment */ @import url(\"a.css\"); @import url( b.css );".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::AtKeyword("import".to_string()),
+            CssToken::Url("a.css".to_string()),
+            CssToken::SemiColon,
+            CssToken::AtKeyword("import".to_string()),
+            CssToken::Url("b.css".to_string()),
+            CssToken::SemiColon,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
+    #[test]
+    fn test_function() {
+        let style = "color: var(--main-color, rgb(0, 0, 0))".to_string();
+        let t = CssTokenizer::new(style);
+        let expected = vec![
+            CssToken::Ident("color".to_string()),
+            CssToken::Colon,
+            CssToken::Function("var".to_string()),
+            CssToken::Ident("--main-color".to_string()),
+            CssToken::Delim(','),
+            CssToken::Function("rgb".to_string()),
+            CssToken::Number(0.0),
+            CssToken::Delim(','),
+            CssToken::Number(0.0),
+            CssToken::Delim(','),
+            CssToken::Number(0.0),
+            CssToken::CloseParenthesis,
+            CssToken::CloseParenthesis,
+        ];
+        assert_eq!(expected, t.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_negative_number_and_minus() {
+        let style = "calc(100% - -2em) -webkit-box --x".to_string();
+        let t = CssTokenizer::new(style);
+        let expected = vec![
+            CssToken::Function("calc".to_string()),
+            CssToken::Percentage(100.0),
+            CssToken::Delim('-'),
+            CssToken::Dimension(-2.0, "em".to_string()),
+            CssToken::CloseParenthesis,
+            CssToken::Ident("-webkit-box".to_string()),
+            CssToken::Ident("--x".to_string()),
+        ];
+        assert_eq!(expected, t.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_at_keyword() {
+        let style = "@a @-webkit-x @1 @".to_string();
+        let t = CssTokenizer::new(style);
+        let expected = vec![
+            CssToken::AtKeyword("a".to_string()),
+            CssToken::AtKeyword("-webkit-x".to_string()),
+            CssToken::Delim('@'),
+            CssToken::Number(1.0),
+            CssToken::Delim('@'),
+        ];
+        assert_eq!(expected, t.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_escape() {
+        let style = r#""a\"b\\c\a d" 'it''s' "\41 B\
+C" url("x\29.png") url(a\ b.png)"#
+            .to_string();
+        let t = CssTokenizer::new(style);
+        let expected = vec![
+            CssToken::StringToken("a\"b\\c\nd".to_string()),
+            CssToken::StringToken("it".to_string()),
+            CssToken::StringToken("s".to_string()),
+            CssToken::StringToken("ABC".to_string()),
+            CssToken::Url("x).png".to_string()),
+            CssToken::Url("a b.png".to_string()),
+        ];
+        assert_eq!(expected, t.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_serialize_string() {
+        let tokens = vec![
+            CssToken::StringToken("line1\nline2\t\"quoted\" \\ '".to_string()),
+            CssToken::Url("a b\n.png".to_string()),
+        ];
+        let css = tokens
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(
+            r#""line1\a line2\9 \"quoted\" \\ '" url("a b\a .png")"#,
+            css
+        );
+
+        // 書き出したものをトークンに分割し直すと、同じトークンになる
+        assert_eq!(tokens, CssTokenizer::new(css).collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,173 @@
+use alloc::string::ToString;
+
+use crate::renderer::css::{
+    cascade::CascadeOrigin,
+    cssom::{CssParser, StyleSheet},
+    token::CssTokenizer,
+};
+
+// ブラウザが持つデフォルトのスタイルシート
+// https://html.spec.whatwg.org/multipage/rendering.html を参考に、サポートしている要素とプロパティのぶんだけ記述する
+static USER_AGENT_STYLE: &str = r#"
+html, body, address, article, aside, blockquote, div, dl, dt, dd, figcaption, figure,
+footer, header, hr, main, nav, ol, ul, li, p, pre, section, h1, h2, h3, h4, h5, h6 {
+  display: block;
+}
+
+head, style, script {
+  display: none;
+}
+
+body {
+  margin-top: 8px;
+  margin-right: 8px;
+  margin-bottom: 8px;
+  margin-left: 8px;
+}
+
+p, blockquote, figure, dl, ol, ul, pre {
+  margin-top: 1em;
+  margin-bottom: 1em;
+}
+
+blockquote, figure {
+  margin-left: 40px;
+  margin-right: 40px;
+}
+
+dd {
+  margin-left: 40px;
+}
+
+h1 {
+  font-size: xx-large;
+  margin-top: 0.67em;
+  margin-bottom: 0.67em;
+}
+
+h2 {
+  font-size: x-large;
+  margin-top: 0.83em;
+  margin-bottom: 0.83em;
+}
+
+h3 {
+  margin-top: 1em;
+  margin-bottom: 1em;
+}
+
+h4 {
+  margin-top: 1.33em;
+  margin-bottom: 1.33em;
+}
+
+h5 {
+  margin-top: 1.67em;
+  margin-bottom: 1.67em;
+}
+
+h6 {
+  margin-top: 2.33em;
+  margin-bottom: 2.33em;
+}
+
+hr {
+  margin-top: 0.5em;
+  margin-bottom: 0.5em;
+}
+
+pre {
+  white-space: pre;
+}
+
+a, u, ins {
+  text-decoration: underline;
+}
+
+s, del {
+  text-decoration: line-through;
+}
+
+b, strong {
+  font-weight: bolder;
+}
+
+em, i {
+  font-style: italic;
+}
+
+code, kbd, samp, pre {
+  font-family: monospace;
+}
+
+[dir=ltr] {
+  direction: ltr;
+  unicode-bidi: isolate;
+}
+
+[dir=rtl] {
+  direction: rtl;
+  unicode-bidi: isolate;
+}
+
+[dir=auto], bdi {
+  unicode-bidi: plaintext;
+}
+
+/* 複合セレクタの bdo[dir] を書けないので、!important で [dir] の規則より優先する */
+bdo {
+  unicode-bidi: isolate-override !important;
+}
+"#;
+
+pub fn user_agent_stylesheet() -> StyleSheet {
+    let t = CssTokenizer::new(USER_AGENT_STYLE.to_string());
+    let mut sheet = CssParser::new(t).parse_stylesheet();
+    sheet.set_origin(CascadeOrigin::UserAgent);
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use crate::renderer::{
+        css::{cascade::CascadedStyle, cssom::ComponentValue, media::Viewport},
+        dom::node::Element,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_user_agent_stylesheet() {
+        let sheet = user_agent_stylesheet();
+        assert_eq!(CascadeOrigin::UserAgent, sheet.origin);
+
+        let cascaded = CascadedStyle::new(
+            &Element::new("h1", Vec::new()),
+            core::slice::from_ref(&sheet),
+            &Viewport::default(),
+        );
+        let display = cascaded
+            .winning_declaration("display")
+            .expect("h1 should have display");
+        assert_eq!(
+            vec![ComponentValue::Ident("block".to_string())],
+            display.declaration.value
+        );
+        let font_size = cascaded
+            .winning_declaration("font-size")
+            .expect("h1 should have font-size");
+        assert_eq!(
+            vec![ComponentValue::Ident("xx-large".to_string())],
+            font_size.declaration.value
+        );
+
+        let cascaded = CascadedStyle::new(
+            &Element::new("span", Vec::new()),
+            &[sheet],
+            &Viewport::default(),
+        );
+        assert!(cascaded.winning_declaration("display").is_none());
+    }
+}
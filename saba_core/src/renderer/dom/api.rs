@@ -31,15 +31,106 @@ pub fn get_target_element_node(
     result1
 }
 
-pub fn get_style_content(root: Rc<RefCell<Node>>) -> String {
-    let Some(style_node) = get_target_element_node(Some(root), ElementKind::Style) else {
-        return "".to_string();
-    };
-    let Some(text_node) = style_node.borrow().first_child() else {
-        return "".to_string();
-    };
-    let NodeKind::Text(content) = &text_node.borrow().kind() else {
-        return "".to_string();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StyleSource {
+    // <style> 要素の中身
+    Inline(String),
+    // <link rel="stylesheet"> の href
+    External(String),
+}
+
+// 文書中のすべての <style> と <link rel="stylesheet"> を文書順に集める
+pub fn get_style_sources(root: Rc<RefCell<Node>>) -> Vec<StyleSource> {
+    let mut sources = Vec::new();
+    collect_style_sources(Some(root), &mut sources);
+    sources
+}
+
+fn collect_style_sources(node: Option<Rc<RefCell<Node>>>, sources: &mut Vec<StyleSource>) {
+    let Some(node) = node else {
+        return;
     };
-    content.clone()
+
+    if let NodeKind::Element(element) = node.borrow().kind() {
+        match element.kind() {
+            ElementKind::Style => {
+                let mut content = String::new();
+                let mut child = node.borrow().first_child();
+                while let Some(c) = child {
+                    if let NodeKind::Text(text) = c.borrow().kind() {
+                        content.push_str(&text);
+                    }
+                    child = c.borrow().next_sibling();
+                }
+                sources.push(StyleSource::Inline(content));
+            }
+            ElementKind::Link => {
+                let is_stylesheet = element.get_attribute("rel").is_some_and(|rel| {
+                    rel.split_ascii_whitespace()
+                        .any(|r| r.eq_ignore_ascii_case("stylesheet"))
+                });
+                if is_stylesheet && let Some(href) = element.get_attribute("href") {
+                    sources.push(StyleSource::External(href));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    collect_style_sources(node.borrow().first_child(), sources);
+    collect_style_sources(node.borrow().next_sibling(), sources);
+}
+
+// スタイルを計算し直したあとに、ノードとその子孫の印を消す。印のない部分木はたどらない
+pub fn clear_style_dirty(node: &Rc<RefCell<Node>>) {
+    if node.borrow().has_dirty_descendant() {
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            clear_style_dirty(&c);
+            child = c.borrow().next_sibling();
+        }
+    }
+    node.borrow_mut().clear_style_dirty();
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::renderer::html::{parser::HtmlParser, token::HtmlTokenizer};
+
+    use super::*;
+
+    #[test]
+    fn test_get_style_sources() {
+        let html = r#"
+            <html>
+            <head>
+              <style>p { color: red; }</style>
+              <link rel="stylesheet" href="a.css">
+              <link rel="icon" href="favicon.ico">
+              <style>h1 { color: blue; }</style>
+            </head>
+            <body>
+              <p>text</p>
+              <link rel="Alternate Stylesheet" href="b.css">
+              <style>a { color: green; }</style>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let dom = window.borrow().document();
+
+        assert_eq!(
+            vec![
+                StyleSource::Inline("p { color: red; }".to_string()),
+                StyleSource::External("a.css".to_string()),
+                StyleSource::Inline("h1 { color: blue; }".to_string()),
+                StyleSource::External("b.css".to_string()),
+                StyleSource::Inline("a { color: green; }".to_string()),
+            ],
+            get_style_sources(dom)
+        );
+    }
 }
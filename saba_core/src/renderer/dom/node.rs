@@ -10,7 +10,14 @@ use core::{
     str::FromStr,
 };
 
-use crate::renderer::html::attribute::Attribute;
+use crate::renderer::{
+    css::{
+        cssom::{CssParser, Declaration},
+        object_model::CssStyleDeclaration,
+        token::CssTokenizer,
+    },
+    html::attribute::Attribute,
+};
 
 #[derive(Debug, Clone)]
 pub struct Node {
@@ -21,6 +28,10 @@ pub struct Node {
     last_child: Weak<RefCell<Node>>,
     previous_sibling: Weak<RefCell<Node>>,
     next_sibling: Option<Rc<RefCell<Node>>>,
+    // このノードのスタイルを計算し直す必要があるかどうか
+    style_dirty: bool,
+    // 子孫のどれかのスタイルを計算し直す必要があるかどうか
+    child_style_dirty: bool,
 }
 
 impl Node {
@@ -33,6 +44,8 @@ impl Node {
             last_child: Weak::new(),
             previous_sibling: Weak::new(),
             next_sibling: None,
+            style_dirty: false,
+            child_style_dirty: false,
         }
     }
 
@@ -76,6 +89,33 @@ impl Node {
         self.next_sibling.as_ref().cloned()
     }
 
+    pub fn is_style_dirty(&self) -> bool {
+        self.style_dirty
+    }
+
+    pub fn has_dirty_descendant(&self) -> bool {
+        self.child_style_dirty
+    }
+
+    // スタイルを計算し直す必要があると印をつけ、祖先には子孫のスタイルが変わりうることを伝える
+    pub fn mark_style_dirty(node: &Rc<RefCell<Node>>) {
+        node.borrow_mut().style_dirty = true;
+
+        let mut ancestor = node.borrow().parent().upgrade();
+        while let Some(a) = ancestor {
+            if a.borrow().child_style_dirty {
+                break;
+            }
+            a.borrow_mut().child_style_dirty = true;
+            ancestor = a.borrow().parent().upgrade();
+        }
+    }
+
+    pub fn clear_style_dirty(&mut self) {
+        self.style_dirty = false;
+        self.child_style_dirty = false;
+    }
+
     pub fn set_window(&mut self, window: Weak<RefCell<Window>>) {
         self.window = window
     }
@@ -91,6 +131,13 @@ impl Node {
         }
     }
 
+    pub fn element_mut(&mut self) -> Option<&mut Element> {
+        match self.kind {
+            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Element(ref mut e) => Some(e),
+        }
+    }
+
     pub fn element_kind(&self) -> Option<ElementKind> {
         match self.kind {
             NodeKind::Document | NodeKind::Text(_) => None,
@@ -130,6 +177,12 @@ pub struct Window {
     document: Rc<RefCell<Node>>,
 }
 
+impl Default for Window {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Window {
     pub fn new() -> Self {
         let window = Self {
@@ -166,13 +219,6 @@ impl Element {
         self.kind
     }
 
-    pub fn is_block_element(&self) -> bool {
-        matches!(
-            self.kind,
-            ElementKind::Body | ElementKind::H1 | ElementKind::H2 | ElementKind::P
-        )
-    }
-
     pub fn attributes(&self) -> Vec<Attribute> {
         self.attributes.clone()
     }
@@ -185,6 +231,64 @@ impl Element {
         }
         None
     }
+
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self.attributes.iter_mut().find(|a| a.name() == name) {
+            Some(attr) => attr.set_value(value),
+            None => self.attributes.push(Attribute::new(name, value)),
+        }
+    }
+
+    pub fn remove_attribute(&mut self, name: &str) {
+        self.attributes.retain(|a| a.name() != name);
+    }
+
+    // style 属性に書かれた宣言のリスト
+    pub fn inline_style(&self) -> Vec<Declaration> {
+        let Some(style) = self.get_attribute("style") else {
+            return Vec::new();
+        };
+        CssParser::new(CssTokenizer::new(style)).parse_declaration_list()
+    }
+
+    pub fn inline_style_property(&self, property: &str) -> Option<String> {
+        let value = self.style().get_property_value(property);
+        (!value.is_empty()).then_some(value)
+    }
+
+    // 値は CSSOM の setProperty と同じように検証し、不正な値のときは何もしない
+    pub fn set_inline_style_property(&mut self, property: &str, value: &str, important: bool) {
+        let priority = if important { "important" } else { "" };
+        self.update_style(|style| style.set_property(property, value, priority));
+    }
+
+    pub fn remove_inline_style_property(&mut self, property: &str) {
+        self.update_style(|style| {
+            style.remove_property(property);
+        });
+    }
+
+    // style 属性を CSSOM の宣言として返す
+    pub fn style(&self) -> CssStyleDeclaration {
+        CssStyleDeclaration::parse(&self.get_attribute("style").unwrap_or_default())
+    }
+
+    // 宣言を変更し、変わったときだけ style 属性に書き戻す
+    fn update_style(&mut self, update: impl FnOnce(&mut CssStyleDeclaration)) {
+        let original = self.style();
+        let mut style = original.clone();
+        update(&mut style);
+        if style == original {
+            return;
+        }
+
+        let css_text = style.css_text();
+        if css_text.is_empty() {
+            self.remove_attribute("style");
+        } else {
+            self.set_attribute("style", &css_text);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -193,12 +297,67 @@ pub enum ElementKind {
     Head,
     Style,
     Script,
+    Link,
     Body,
+    Address,
+    Article,
+    Aside,
+    Blockquote,
+    Div,
+    Dl,
+    Dt,
+    Dd,
+    Figcaption,
+    Figure,
+    Footer,
+    Header,
+    Hr,
+    Main,
+    Nav,
+    Ol,
     P,
+    Pre,
+    Section,
+    Ul,
+    Li,
     H1,
     H2,
+    H3,
+    H4,
+    H5,
+    H6,
     A,
+    Abbr,
+    B,
+    Bdi,
+    Bdo,
+    Br,
+    Cite,
+    Code,
+    Del,
+    Em,
+    I,
+    Img,
+    Ins,
+    Kbd,
+    Mark,
+    Q,
+    S,
+    Samp,
+    Small,
     Span,
+    Strong,
+    Sub,
+    Sup,
+    U,
+    Var,
+}
+
+impl ElementKind {
+    // 子ノードを持たない要素。開始タグだけで閉じたものとして扱う
+    pub fn is_void(&self) -> bool {
+        matches!(self, Self::Link | Self::Br | Self::Hr | Self::Img)
+    }
 }
 
 impl FromStr for ElementKind {
@@ -210,12 +369,60 @@ impl FromStr for ElementKind {
             "head" => Ok(ElementKind::Head),
             "style" => Ok(ElementKind::Style),
             "script" => Ok(ElementKind::Script),
+            "link" => Ok(ElementKind::Link),
             "body" => Ok(ElementKind::Body),
+            "address" => Ok(ElementKind::Address),
+            "article" => Ok(ElementKind::Article),
+            "aside" => Ok(ElementKind::Aside),
+            "blockquote" => Ok(ElementKind::Blockquote),
+            "div" => Ok(ElementKind::Div),
+            "dl" => Ok(ElementKind::Dl),
+            "dt" => Ok(ElementKind::Dt),
+            "dd" => Ok(ElementKind::Dd),
+            "figcaption" => Ok(ElementKind::Figcaption),
+            "figure" => Ok(ElementKind::Figure),
+            "footer" => Ok(ElementKind::Footer),
+            "header" => Ok(ElementKind::Header),
+            "hr" => Ok(ElementKind::Hr),
+            "main" => Ok(ElementKind::Main),
+            "nav" => Ok(ElementKind::Nav),
+            "ol" => Ok(ElementKind::Ol),
             "p" => Ok(ElementKind::P),
+            "pre" => Ok(ElementKind::Pre),
+            "section" => Ok(ElementKind::Section),
+            "ul" => Ok(ElementKind::Ul),
+            "li" => Ok(ElementKind::Li),
             "h1" => Ok(ElementKind::H1),
             "h2" => Ok(ElementKind::H2),
+            "h3" => Ok(ElementKind::H3),
+            "h4" => Ok(ElementKind::H4),
+            "h5" => Ok(ElementKind::H5),
+            "h6" => Ok(ElementKind::H6),
             "a" => Ok(ElementKind::A),
+            "abbr" => Ok(ElementKind::Abbr),
+            "b" => Ok(ElementKind::B),
+            "bdi" => Ok(ElementKind::Bdi),
+            "bdo" => Ok(ElementKind::Bdo),
+            "br" => Ok(ElementKind::Br),
+            "cite" => Ok(ElementKind::Cite),
+            "code" => Ok(ElementKind::Code),
+            "del" => Ok(ElementKind::Del),
+            "em" => Ok(ElementKind::Em),
+            "i" => Ok(ElementKind::I),
+            "img" => Ok(ElementKind::Img),
+            "ins" => Ok(ElementKind::Ins),
+            "kbd" => Ok(ElementKind::Kbd),
+            "mark" => Ok(ElementKind::Mark),
+            "q" => Ok(ElementKind::Q),
+            "s" => Ok(ElementKind::S),
+            "samp" => Ok(ElementKind::Samp),
+            "small" => Ok(ElementKind::Small),
             "span" => Ok(ElementKind::Span),
+            "strong" => Ok(ElementKind::Strong),
+            "sub" => Ok(ElementKind::Sub),
+            "sup" => Ok(ElementKind::Sup),
+            "u" => Ok(ElementKind::U),
+            "var" => Ok(ElementKind::Var),
             _ => Err(format!("unimplemented element name {:?}", s)),
         }
     }
@@ -228,13 +435,122 @@ impl Display for ElementKind {
             Self::Head => "head",
             Self::Style => "style",
             Self::Script => "script",
+            Self::Link => "link",
             Self::Body => "body",
+            Self::Address => "address",
+            Self::Article => "article",
+            Self::Aside => "aside",
+            Self::Blockquote => "blockquote",
+            Self::Div => "div",
+            Self::Dl => "dl",
+            Self::Dt => "dt",
+            Self::Dd => "dd",
+            Self::Figcaption => "figcaption",
+            Self::Figure => "figure",
+            Self::Footer => "footer",
+            Self::Header => "header",
+            Self::Hr => "hr",
+            Self::Main => "main",
+            Self::Nav => "nav",
+            Self::Ol => "ol",
             Self::P => "p",
+            Self::Pre => "pre",
+            Self::Section => "section",
+            Self::Ul => "ul",
+            Self::Li => "li",
             Self::H1 => "h1",
             Self::H2 => "h2",
+            Self::H3 => "h3",
+            Self::H4 => "h4",
+            Self::H5 => "h5",
+            Self::H6 => "h6",
             Self::A => "a",
+            Self::Abbr => "abbr",
+            Self::B => "b",
+            Self::Bdi => "bdi",
+            Self::Bdo => "bdo",
+            Self::Br => "br",
+            Self::Cite => "cite",
+            Self::Code => "code",
+            Self::Del => "del",
+            Self::Em => "em",
+            Self::I => "i",
+            Self::Img => "img",
+            Self::Ins => "ins",
+            Self::Kbd => "kbd",
+            Self::Mark => "mark",
+            Self::Q => "q",
+            Self::S => "s",
+            Self::Samp => "samp",
+            Self::Small => "small",
             Self::Span => "span",
+            Self::Strong => "strong",
+            Self::Sub => "sub",
+            Self::Sup => "sup",
+            Self::U => "u",
+            Self::Var => "var",
         };
         write!(f, "{s}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+
+    #[test]
+    fn test_inline_style_property() {
+        let mut element = Element::new(
+            "p",
+            vec![Attribute::nv("style", "color: red; display: block")],
+        );
+        assert_eq!(
+            Some("red".to_string()),
+            element.inline_style_property("color")
+        );
+        assert_eq!(None, element.inline_style_property("font-size"));
+
+        // すでにある宣言は、その位置のまま置き換える
+        element.set_inline_style_property("color", "#0000ff", false);
+        element.set_inline_style_property("font-size", "x-large", true);
+        assert_eq!(
+            Some("color: #0000ff; display: block; font-size: x-large !important;".to_string()),
+            element.get_attribute("style")
+        );
+
+        // 1つの宣言の値として完結していない値や、!important を含む値は無視する
+        element.set_inline_style_property("color", "red; display: none", false);
+        element.set_inline_style_property("color", "red } p { color: red", false);
+        element.set_inline_style_property("display", "none !important", false);
+        assert_eq!(
+            Some("color: #0000ff; display: block; font-size: x-large !important;".to_string()),
+            element.get_attribute("style")
+        );
+
+        element.remove_inline_style_property("display");
+        element.remove_inline_style_property("color");
+        element.remove_inline_style_property("font-size");
+        assert_eq!(None, element.get_attribute("style"));
+
+        // DOM ツリーの中の要素も変更できる
+        let node = Rc::new(RefCell::new(Node::new(NodeKind::Element(element))));
+        node.borrow_mut()
+            .element_mut()
+            .expect("node should be an element")
+            .set_inline_style_property("margin", "1px 2px", false);
+        assert_eq!(
+            Some("margin: 1px 2px;".to_string()),
+            node.borrow()
+                .get_element()
+                .and_then(|e| e.get_attribute("style"))
+        );
+        assert_eq!(
+            Some("2px".to_string()),
+            node.borrow()
+                .get_element()
+                .and_then(|e| e.inline_style_property("margin-left"))
+        );
+    }
+}
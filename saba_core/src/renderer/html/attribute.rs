@@ -1,7 +1,4 @@
-use alloc::string::String;
-
-#[cfg(test)]
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Attribute {
@@ -10,6 +7,13 @@ pub struct Attribute {
 }
 
 impl Attribute {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
     pub fn add_name_char(&mut self, c: char) {
         self.name.push(c);
     }
@@ -26,6 +30,10 @@ impl Attribute {
         self.value.clone()
     }
 
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.to_string();
+    }
+
     #[cfg(test)]
     pub fn nv(name: &str, value: &str) -> Self {
         Self {
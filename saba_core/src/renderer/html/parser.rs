@@ -111,7 +111,6 @@ impl HtmlParser {
                     match *t {
                         HtmlToken::Char(c) => {
                             if c == ' ' || c == '\n' {
-                                self.insert_char(c);
                                 token = self.t.next();
                                 continue;
                             }
@@ -128,6 +127,12 @@ impl HtmlParser {
                                 token = self.t.next();
                                 continue;
                             }
+                            if tag == "link" {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.pop_current_node(ElementKind::Link);
+                                token = self.t.next();
+                                continue;
+                            }
                             // 仕様書には定められていないが、このブラウザは仕様をすべて実装しているわけではないので、
                             // <head>が省略されているHTML文書を扱うために必要。
                             // これがないと <head> が省略されている HTML 文書で無限ループが発生
@@ -163,7 +168,6 @@ impl HtmlParser {
                     match *t {
                         HtmlToken::Char(c) => {
                             if c == ' ' || c == '\n' {
-                                self.insert_char(c);
                                 token = self.t.next();
                                 continue;
                             }
@@ -196,13 +200,26 @@ impl HtmlParser {
                             ref tag,
                             self_closing: _,
                             ref attributes,
-                        } => match tag.as_str() {
-                            "p" | "h1" | "h2" | "a" | "span" => {
+                        } => match ElementKind::from_str(tag) {
+                            Ok(ElementKind::Style | ElementKind::Script) => {
+                                // <body> の中の <style> や <script> も <head> の中と同様に扱う
+                                self.insert_element(tag, attributes.to_vec());
+                                self.original_mode = self.mode;
+                                self.mode = InsertionMode::Text;
+                                token = self.t.next();
+                                continue;
+                            }
+                            Ok(ElementKind::Html | ElementKind::Head | ElementKind::Body)
+                            | Err(_) => token = self.t.next(),
+                            Ok(element_kind) => {
                                 self.insert_element(tag, attributes.to_vec());
+                                // <br> などの空要素は子ノードを持たないので、すぐにスタックから取り除く
+                                if element_kind.is_void() {
+                                    self.pop_current_node(element_kind);
+                                }
                                 token = self.t.next();
                                 continue;
                             }
-                            _ => token = self.t.next(),
                         },
                         HtmlToken::EndTag { ref tag } => {
                             match tag.as_str() {
@@ -225,15 +242,14 @@ impl HtmlParser {
                                     }
                                     continue;
                                 }
-                                "p" | "h1" | "h2" | "a" | "span" => {
-                                    let element_kind = ElementKind::from_str(tag)
-                                        .expect("failed to convert string to ElementKind");
-                                    token = self.t.next();
-                                    self.pop_until(element_kind);
-                                    continue;
-                                }
                                 _ => {
+                                    if let Ok(element_kind) = ElementKind::from_str(tag)
+                                        && self.contain_in_stack(element_kind)
+                                    {
+                                        self.pop_until(element_kind);
+                                    }
                                     token = self.t.next();
+                                    continue;
                                 }
                             }
                         }
@@ -323,35 +339,31 @@ impl HtmlParser {
     }
 
     fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
-        let window = self.window.borrow();
-        let current = match self.stack_of_open_elements.last() {
-            Some(n) => n.clone(),
-            None => window.document(),
-        };
-
+        let current = self.current_node();
         let node = Rc::new(RefCell::new(self.create_element(tag, attributes)));
+        Self::append_child(&current, &node);
+        self.stack_of_open_elements.push(node);
+    }
 
-        if let Some(mut last_sibling) = current.borrow().first_child() {
-            loop {
-                let Some(next_sibling) = last_sibling.borrow().next_sibling() else {
-                    break;
-                };
-                last_sibling = next_sibling;
-            }
+    fn current_node(&self) -> Rc<RefCell<Node>> {
+        match self.stack_of_open_elements.last() {
+            Some(n) => n.clone(),
+            None => self.window.borrow().document(),
+        }
+    }
 
-            last_sibling
-                .borrow_mut()
-                .set_next_sibling(Some(node.clone()));
+    // node を parent の最後の子ノードとして追加する
+    fn append_child(parent: &Rc<RefCell<Node>>, node: &Rc<RefCell<Node>>) {
+        if let Some(last_child) = parent.borrow().last_child().upgrade() {
+            last_child.borrow_mut().set_next_sibling(Some(node.clone()));
             node.borrow_mut()
-                .set_previous_sibling(Rc::downgrade(&last_sibling));
+                .set_previous_sibling(Rc::downgrade(&last_child));
         } else {
-            current.borrow_mut().set_first_child(Some(node.clone()));
+            parent.borrow_mut().set_first_child(Some(node.clone()));
         }
 
-        current.borrow_mut().set_last_child(Rc::downgrade(&node));
-        node.borrow_mut().set_parent(Rc::downgrade(&current));
-
-        self.stack_of_open_elements.push(node);
+        parent.borrow_mut().set_last_child(Rc::downgrade(node));
+        node.borrow_mut().set_parent(Rc::downgrade(parent));
     }
 
     fn pop_current_node(&mut self, element_kind: ElementKind) -> bool {
@@ -401,37 +413,22 @@ impl HtmlParser {
         Node::new(NodeKind::Text(s))
     }
 
+    // 文字を現在のノードの最後のテキストノードに追加する。最後の子ノードがテキストでなければ、
+    // 新しいテキストノードを作る。テキストノードは子ノードを持たないので、スタックには積まない
     fn insert_char(&mut self, c: char) {
-        let Some(current) = self.stack_of_open_elements.last() else {
+        let Some(current) = self.stack_of_open_elements.last().cloned() else {
             return;
         };
 
-        if let NodeKind::Text(ref mut s) = current.borrow_mut().kind {
+        if let Some(last_child) = current.borrow().last_child().upgrade()
+            && let NodeKind::Text(ref mut s) = last_child.borrow_mut().kind
+        {
             s.push(c);
             return;
         }
 
-        if c == '\n' || c == ' ' {
-            return;
-        }
-
         let node = Rc::new(RefCell::new(self.create_char(c)));
-
-        if let Some(first_child) = current.borrow().first_child() {
-            first_child
-                .borrow_mut()
-                .set_next_sibling(Some(node.clone()));
-            // TODO: 正誤表で消されてるけど、構造としては previous_sibling に設定すべきに見える？
-            // node.borrow_mut()
-            //     .set_previous_sibling(Rc::downgrade(&first_child));
-        } else {
-            current.borrow_mut().set_first_child(Some(node.clone()));
-        }
-
-        current.borrow_mut().set_last_child(Rc::downgrade(&node));
-        node.borrow_mut().set_parent(Rc::downgrade(current));
-
-        self.stack_of_open_elements.push(node);
+        Self::append_child(&current, &node);
     }
 }
 
@@ -519,6 +516,76 @@ mod tests {
         assert_eq!(elem_node("span", &[span_attr]), span);
     }
 
+    #[test]
+    fn test_void_element() {
+        let html =
+            "<html><head></head><body><p>a<br>b</p><hr><div></div></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .unwrap()
+            .borrow()
+            .first_child()
+            .unwrap()
+            .borrow()
+            .next_sibling()
+            .unwrap();
+
+        let p = body.borrow().first_child().unwrap();
+        assert_eq!(elem_node("p", &[]), p);
+
+        let hr = p.borrow().next_sibling().unwrap();
+        assert_eq!(elem_node("hr", &[]), hr);
+        assert_eq!(None, hr.borrow().first_child());
+
+        let div = hr.borrow().next_sibling().unwrap();
+        assert_eq!(elem_node("div", &[]), div);
+    }
+
+    #[test]
+    fn test_text_between_elements() {
+        let html =
+            "<html><head></head><body><p>Some <a>link</a> and\n<b>more</b></p></body></html>"
+                .to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .unwrap()
+            .borrow()
+            .first_child()
+            .unwrap()
+            .borrow()
+            .next_sibling()
+            .unwrap();
+        let p = body.borrow().first_child().unwrap();
+
+        // テキストの後ろの要素はテキストの兄弟になり、要素の間の空白も残る
+        let some = p.borrow().first_child().unwrap();
+        assert_eq!(text_node("Some "), some);
+        let a = some.borrow().next_sibling().unwrap();
+        assert_eq!(elem_node("a", &[]), a);
+        assert_eq!(text_node("link"), a.borrow().first_child().unwrap());
+        let and = a.borrow().next_sibling().unwrap();
+        assert_eq!(text_node(" and\n"), and);
+        let b = and.borrow().next_sibling().unwrap();
+        assert_eq!(elem_node("b", &[]), b);
+        assert_eq!(None, b.borrow().next_sibling());
+
+        assert!(Rc::ptr_eq(
+            &a,
+            &and.borrow().previous_sibling().upgrade().unwrap()
+        ));
+        assert!(Rc::ptr_eq(&b, &p.borrow().last_child().upgrade().unwrap()));
+    }
+
     fn doc_node() -> Rc<RefCell<Node>> {
         Rc::new(RefCell::new(Node::new(NodeKind::Document)))
     }
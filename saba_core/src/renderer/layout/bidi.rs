@@ -0,0 +1,810 @@
+use alloc::{vec, vec::Vec};
+
+use crate::renderer::layout::text::{Direction, UnicodeBidi};
+
+// https://www.unicode.org/reports/tr9/#Bidirectional_Character_Types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BidiClass {
+    // 強い文字
+    L,
+    R,
+    AL,
+    // 弱い文字
+    EN,
+    ES,
+    ET,
+    AN,
+    CS,
+    NSM,
+    BN,
+    // 中立の文字
+    B,
+    S,
+    WS,
+    ON,
+    // 明示的な埋め込みと上書き、孤立の制御文字
+    LRE,
+    LRO,
+    RLE,
+    RLO,
+    PDF,
+    LRI,
+    RLI,
+    FSI,
+    PDI,
+}
+
+use BidiClass::*;
+
+// 先頭の文字の順に並べた、分類の範囲の表。表にない文字は L として扱う
+static BIDI_TABLE: &[(u32, u32, BidiClass)] = &[
+    (0x0000, 0x0008, BN),
+    (0x0009, 0x0009, S),
+    (0x000A, 0x000A, B),
+    (0x000B, 0x000B, S),
+    (0x000C, 0x000C, WS),
+    (0x000D, 0x000D, B),
+    (0x000E, 0x001B, BN),
+    (0x001C, 0x001E, B),
+    (0x001F, 0x001F, S),
+    (0x0020, 0x0020, WS),
+    (0x0021, 0x0022, ON),
+    (0x0023, 0x0025, ET),
+    (0x0026, 0x002A, ON),
+    (0x002B, 0x002B, ES),
+    (0x002C, 0x002C, CS),
+    (0x002D, 0x002D, ES),
+    (0x002E, 0x002F, CS),
+    (0x0030, 0x0039, EN),
+    (0x003A, 0x003A, CS),
+    (0x003B, 0x0040, ON),
+    (0x005B, 0x0060, ON),
+    (0x007B, 0x007E, ON),
+    (0x007F, 0x0084, BN),
+    (0x0085, 0x0085, B),
+    (0x0086, 0x009F, BN),
+    (0x00A0, 0x00A0, CS),
+    (0x00A1, 0x00A1, ON),
+    (0x00A2, 0x00A5, ET),
+    (0x00A6, 0x00A9, ON),
+    (0x00AB, 0x00AC, ON),
+    (0x00AD, 0x00AD, BN),
+    (0x00AE, 0x00AF, ON),
+    (0x00B0, 0x00B1, ET),
+    (0x00B2, 0x00B3, EN),
+    (0x00B4, 0x00B4, ON),
+    (0x00B6, 0x00B8, ON),
+    (0x00B9, 0x00B9, EN),
+    (0x00BB, 0x00BF, ON),
+    (0x00D7, 0x00D7, ON),
+    (0x00F7, 0x00F7, ON),
+    (0x0300, 0x036F, NSM),
+    (0x0483, 0x0489, NSM),
+    (0x0590, 0x0590, R),
+    (0x0591, 0x05BD, NSM),
+    (0x05BE, 0x05BE, R),
+    (0x05BF, 0x05BF, NSM),
+    (0x05C0, 0x05C0, R),
+    (0x05C1, 0x05C2, NSM),
+    (0x05C3, 0x05C3, R),
+    (0x05C4, 0x05C5, NSM),
+    (0x05C6, 0x05C6, R),
+    (0x05C7, 0x05C7, NSM),
+    (0x05C8, 0x05FF, R),
+    (0x0600, 0x0605, AN),
+    (0x0606, 0x0607, ON),
+    (0x0608, 0x0608, AL),
+    (0x0609, 0x060A, ET),
+    (0x060B, 0x060B, AL),
+    (0x060C, 0x060C, CS),
+    (0x060D, 0x060D, AL),
+    (0x060E, 0x060F, ON),
+    (0x0610, 0x061A, NSM),
+    (0x061B, 0x064A, AL),
+    (0x064B, 0x065F, NSM),
+    (0x0660, 0x0669, AN),
+    (0x066A, 0x066A, ET),
+    (0x066B, 0x066C, AN),
+    (0x066D, 0x066F, AL),
+    (0x0670, 0x0670, NSM),
+    (0x0671, 0x06D5, AL),
+    (0x06D6, 0x06DC, NSM),
+    (0x06DD, 0x06DD, AN),
+    (0x06DE, 0x06DE, ON),
+    (0x06DF, 0x06E4, NSM),
+    (0x06E5, 0x06E6, AL),
+    (0x06E7, 0x06E8, NSM),
+    (0x06E9, 0x06E9, ON),
+    (0x06EA, 0x06ED, NSM),
+    (0x06EE, 0x06EF, AL),
+    (0x06F0, 0x06F9, EN),
+    (0x06FA, 0x0710, AL),
+    (0x0711, 0x0711, NSM),
+    (0x0712, 0x072F, AL),
+    (0x0730, 0x074A, NSM),
+    (0x074B, 0x07A5, AL),
+    (0x07A6, 0x07B0, NSM),
+    (0x07B1, 0x07BF, AL),
+    (0x07C0, 0x07EA, R),
+    (0x07EB, 0x07F3, NSM),
+    (0x07F4, 0x085F, R),
+    (0x0860, 0x08D2, AL),
+    (0x08D3, 0x08FF, NSM),
+    (0x1680, 0x1680, WS),
+    (0x2000, 0x200A, WS),
+    (0x200B, 0x200D, BN),
+    (0x200E, 0x200E, L),
+    (0x200F, 0x200F, R),
+    (0x2010, 0x2027, ON),
+    (0x2028, 0x2028, WS),
+    (0x2029, 0x2029, B),
+    (0x202A, 0x202A, LRE),
+    (0x202B, 0x202B, RLE),
+    (0x202C, 0x202C, PDF),
+    (0x202D, 0x202D, LRO),
+    (0x202E, 0x202E, RLO),
+    (0x202F, 0x202F, CS),
+    (0x2030, 0x2034, ET),
+    (0x2035, 0x2043, ON),
+    (0x2044, 0x2044, CS),
+    (0x2045, 0x205E, ON),
+    (0x205F, 0x205F, WS),
+    (0x2060, 0x2065, BN),
+    (0x2066, 0x2066, LRI),
+    (0x2067, 0x2067, RLI),
+    (0x2068, 0x2068, FSI),
+    (0x2069, 0x2069, PDI),
+    (0x206A, 0x206F, BN),
+    (0x2070, 0x2070, EN),
+    (0x2074, 0x2079, EN),
+    (0x207A, 0x207B, ES),
+    (0x207C, 0x207E, ON),
+    (0x2080, 0x2089, EN),
+    (0x208A, 0x208B, ES),
+    (0x208C, 0x208E, ON),
+    (0x20A0, 0x20CF, ET),
+    (0x20D0, 0x20F0, NSM),
+    (0x2190, 0x2211, ON),
+    (0x2212, 0x2212, ES),
+    (0x2213, 0x2213, ET),
+    (0x2214, 0x2335, ON),
+    (0x2460, 0x2487, ON),
+    (0x2488, 0x249B, EN),
+    (0x2500, 0x27FF, ON),
+    (0x2900, 0x2BFF, ON),
+    (0x3000, 0x3000, WS),
+    (0x3001, 0x3004, ON),
+    (0x3008, 0x3020, ON),
+    (0x3030, 0x3030, ON),
+    (0x303D, 0x303F, ON),
+    (0x3099, 0x309A, NSM),
+    (0x309B, 0x309C, ON),
+    (0x30A0, 0x30A0, ON),
+    (0x30FB, 0x30FB, ON),
+    (0xFB1D, 0xFB1D, R),
+    (0xFB1E, 0xFB1E, NSM),
+    (0xFB1F, 0xFB28, R),
+    (0xFB29, 0xFB29, ES),
+    (0xFB2A, 0xFB4F, R),
+    (0xFB50, 0xFD3D, AL),
+    (0xFD3E, 0xFD3F, ON),
+    (0xFD40, 0xFDFF, AL),
+    (0xFE00, 0xFE0F, NSM),
+    (0xFE20, 0xFE2F, NSM),
+    (0xFE50, 0xFE50, CS),
+    (0xFE51, 0xFE51, ON),
+    (0xFE52, 0xFE52, CS),
+    (0xFE54, 0xFE54, ON),
+    (0xFE55, 0xFE55, CS),
+    (0xFE56, 0xFE5E, ON),
+    (0xFE5F, 0xFE5F, ET),
+    (0xFE60, 0xFE61, ON),
+    (0xFE62, 0xFE63, ES),
+    (0xFE64, 0xFE68, ON),
+    (0xFE69, 0xFE6A, ET),
+    (0xFE6B, 0xFE6B, ON),
+    (0xFE70, 0xFEFE, AL),
+    (0xFEFF, 0xFEFF, BN),
+    (0xFF01, 0xFF02, ON),
+    (0xFF03, 0xFF05, ET),
+    (0xFF06, 0xFF0A, ON),
+    (0xFF0B, 0xFF0B, ES),
+    (0xFF0C, 0xFF0C, CS),
+    (0xFF0D, 0xFF0D, ES),
+    (0xFF0E, 0xFF0F, CS),
+    (0xFF10, 0xFF19, EN),
+    (0xFF1A, 0xFF1A, CS),
+    (0xFF1B, 0xFF20, ON),
+    (0xFF3B, 0xFF40, ON),
+    (0xFF5B, 0xFF65, ON),
+    (0xFFE0, 0xFFE1, ET),
+    (0xFFE2, 0xFFE4, ON),
+    (0xFFE5, 0xFFE6, ET),
+    (0xFFE8, 0xFFEE, ON),
+    (0xFFF9, 0xFFFD, ON),
+    (0x10800, 0x10CFF, R),
+    (0x10D00, 0x10D3F, AL),
+    (0x10E60, 0x10E7E, AN),
+    (0x10F00, 0x10F2F, R),
+    (0x10F30, 0x10F6F, AL),
+    (0x1E800, 0x1EC6F, R),
+    (0x1EC70, 0x1EEFF, AL),
+    (0x1F000, 0x1FAFF, ON),
+];
+
+pub fn bidi_class(c: char) -> BidiClass {
+    let c = c as u32;
+    match BIDI_TABLE.binary_search_by(|&(start, end, _)| {
+        if end < c {
+            core::cmp::Ordering::Less
+        } else if start > c {
+            core::cmp::Ordering::Greater
+        } else {
+            core::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(index) => BIDI_TABLE[index].2,
+        Err(_) => L,
+    }
+}
+
+// 埋め込みレベルの上限
+const MAX_DEPTH: u8 = 125;
+
+fn is_isolate_initiator(class: BidiClass) -> bool {
+    matches!(class, LRI | RLI | FSI)
+}
+
+// X9 で取り除く文字
+fn is_removed(class: BidiClass) -> bool {
+    matches!(class, LRE | RLE | LRO | RLO | PDF | BN)
+}
+
+// https://www.w3.org/TR/css-writing-modes-4/#bidi-control-codes-injection-table
+// unicode-bidi と direction に応じて、インライン要素の内容の前後に置く制御文字
+pub fn opening_controls(unicode_bidi: UnicodeBidi, direction: Direction) -> &'static [char] {
+    let rtl = direction == Direction::Rtl;
+    match unicode_bidi {
+        UnicodeBidi::Normal => &[],
+        UnicodeBidi::Embed if rtl => &['\u{202B}'],
+        UnicodeBidi::Embed => &['\u{202A}'],
+        UnicodeBidi::Isolate if rtl => &['\u{2067}'],
+        UnicodeBidi::Isolate => &['\u{2066}'],
+        UnicodeBidi::BidiOverride if rtl => &['\u{202E}'],
+        UnicodeBidi::BidiOverride => &['\u{202D}'],
+        UnicodeBidi::IsolateOverride if rtl => &['\u{2067}', '\u{202E}'],
+        UnicodeBidi::IsolateOverride => &['\u{2066}', '\u{202D}'],
+        UnicodeBidi::Plaintext => &['\u{2068}'],
+    }
+}
+
+pub fn closing_controls(unicode_bidi: UnicodeBidi) -> &'static [char] {
+    match unicode_bidi {
+        UnicodeBidi::Normal => &[],
+        UnicodeBidi::Embed | UnicodeBidi::BidiOverride => &['\u{202C}'],
+        UnicodeBidi::Isolate | UnicodeBidi::Plaintext => &['\u{2069}'],
+        UnicodeBidi::IsolateOverride => &['\u{202C}', '\u{2069}'],
+    }
+}
+
+// https://www.unicode.org/reports/tr9/#P2
+// 孤立の中を除いた最初の強い文字から、段落の埋め込みレベルを決める。強い文字がなければ None
+fn first_strong_level(classes: &[BidiClass]) -> Option<u8> {
+    let mut isolates = 0;
+    for class in classes {
+        match class {
+            LRI | RLI | FSI => isolates += 1,
+            PDI => isolates = usize::saturating_sub(isolates, 1),
+            L if isolates == 0 => return Some(0),
+            R | AL if isolates == 0 => return Some(1),
+            _ => {}
+        }
+    }
+    None
+}
+
+// 文字ごとの埋め込みレベルと、その文字を含む段落の埋め込みレベル
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BidiLevels {
+    pub levels: Vec<u8>,
+    pub paragraph_levels: Vec<u8>,
+}
+
+// https://www.unicode.org/reports/tr9/#Basic_Display_Algorithm
+// text を段落に分けて、文字ごとの埋め込みレベルを求める。
+// base は段落の埋め込みレベルで、None のときは段落の内容から決める
+pub fn resolve_levels(text: &[char], base: Option<u8>) -> BidiLevels {
+    let classes = text.iter().map(|c| bidi_class(*c)).collect::<Vec<_>>();
+    let mut levels = vec![0; text.len()];
+    let mut paragraph_levels = vec![0; text.len()];
+
+    // P1: 段落区切りの文字は、その前の段落に含める
+    let mut start = 0;
+    while start < text.len() {
+        let end = classes[start..]
+            .iter()
+            .position(|c| *c == B)
+            .map_or(text.len(), |i| start + i + 1);
+        let level = base.unwrap_or_else(|| first_strong_level(&classes[start..end]).unwrap_or(0));
+        resolve_paragraph(&classes[start..end], level, &mut levels[start..end]);
+        paragraph_levels[start..end].fill(level);
+        start = end;
+    }
+
+    BidiLevels {
+        levels,
+        paragraph_levels,
+    }
+}
+
+// BD9: 孤立の開始の文字ごとの、対応する PDI の位置
+fn matching_pdis(classes: &[BidiClass]) -> Vec<Option<usize>> {
+    let mut matching = vec![None; classes.len()];
+    let mut stack = Vec::new();
+    for (i, class) in classes.iter().enumerate() {
+        match class {
+            LRI | RLI | FSI => stack.push(i),
+            PDI => {
+                if let Some(initiator) = stack.pop() {
+                    matching[initiator] = Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    matching
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EmbeddingEntry {
+    level: u8,
+    override_class: Option<BidiClass>,
+    isolate: bool,
+}
+
+// 1つの段落の、文字ごとの埋め込みレベルを求める
+fn resolve_paragraph(original: &[BidiClass], paragraph_level: u8, levels: &mut [u8]) {
+    let n = original.len();
+    let matching = matching_pdis(original);
+    let mut classes = original.to_vec();
+
+    // X1〜X8: 明示的な埋め込みと上書き、孤立を解決する
+    let mut stack = vec![EmbeddingEntry {
+        level: paragraph_level,
+        override_class: None,
+        isolate: false,
+    }];
+    let mut overflow_isolates = 0;
+    let mut overflow_embeddings = 0;
+    let mut valid_isolates = 0;
+    let next_level = |level: u8, rtl: bool| {
+        if rtl {
+            (level + 1) | 1
+        } else {
+            (level + 2) & !1
+        }
+    };
+
+    for i in 0..n {
+        let last = *stack.last().expect("embedding stack should not be empty");
+        levels[i] = last.level;
+        match original[i] {
+            RLE | LRE | RLO | LRO => {
+                let level = next_level(last.level, matches!(original[i], RLE | RLO));
+                if level <= MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0 {
+                    stack.push(EmbeddingEntry {
+                        level,
+                        override_class: match original[i] {
+                            RLO => Some(R),
+                            LRO => Some(L),
+                            _ => None,
+                        },
+                        isolate: false,
+                    });
+                } else if overflow_isolates == 0 {
+                    overflow_embeddings += 1;
+                }
+            }
+            LRI | RLI | FSI => {
+                if let Some(class) = last.override_class {
+                    classes[i] = class;
+                }
+                let rtl = match original[i] {
+                    RLI => true,
+                    LRI => false,
+                    _ => {
+                        let end = matching[i].unwrap_or(n);
+                        first_strong_level(&original[i + 1..end]) == Some(1)
+                    }
+                };
+                let level = next_level(last.level, rtl);
+                if level <= MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0 {
+                    valid_isolates += 1;
+                    stack.push(EmbeddingEntry {
+                        level,
+                        override_class: None,
+                        isolate: true,
+                    });
+                } else {
+                    overflow_isolates += 1;
+                }
+            }
+            PDI => {
+                if overflow_isolates > 0 {
+                    overflow_isolates -= 1;
+                } else if valid_isolates > 0 {
+                    overflow_embeddings = 0;
+                    while stack.last().is_some_and(|e| !e.isolate) {
+                        stack.pop();
+                    }
+                    stack.pop();
+                    valid_isolates -= 1;
+                }
+                let last = *stack.last().expect("embedding stack should not be empty");
+                levels[i] = last.level;
+                if let Some(class) = last.override_class {
+                    classes[i] = class;
+                }
+            }
+            PDF => {
+                if overflow_isolates > 0 {
+                } else if overflow_embeddings > 0 {
+                    overflow_embeddings -= 1;
+                } else if !last.isolate && stack.len() >= 2 {
+                    stack.pop();
+                }
+            }
+            B => levels[i] = paragraph_level,
+            BN => {}
+            _ => {
+                if let Some(class) = last.override_class {
+                    classes[i] = class;
+                }
+            }
+        }
+    }
+
+    // X10: 同じレベルの文字の並びを、孤立をまたいでつないだ列ごとに解決する
+    let mut runs: Vec<Vec<usize>> = Vec::new();
+    for i in (0..n).filter(|&i| !is_removed(original[i])) {
+        match runs.last_mut() {
+            Some(run) if run.last().is_some_and(|&j| levels[j] == levels[i]) => run.push(i),
+            _ => runs.push(vec![i]),
+        }
+    }
+    let is_matched_pdi = |i: usize| original[i] == PDI && matching.contains(&Some(i));
+    for start in 0..runs.len() {
+        if is_matched_pdi(runs[start][0]) {
+            continue;
+        }
+        let mut sequence = Vec::new();
+        let mut r = start;
+        loop {
+            sequence.extend_from_slice(&runs[r]);
+            let last = *runs[r].last().expect("level run should not be empty");
+            let Some(pdi) = matching[last].filter(|_| is_isolate_initiator(original[last])) else {
+                break;
+            };
+            let Some(next) = runs.iter().position(|run| run[0] == pdi) else {
+                break;
+            };
+            r = next;
+        }
+
+        let first = sequence[0];
+        let last = *sequence.last().expect("sequence should not be empty");
+        let level = levels[first];
+        let before = (0..first)
+            .rev()
+            .find(|&j| !is_removed(original[j]))
+            .map_or(paragraph_level, |j| levels[j]);
+        let after = if is_isolate_initiator(original[last]) {
+            paragraph_level
+        } else {
+            (last + 1..n)
+                .find(|&j| !is_removed(original[j]))
+                .map_or(paragraph_level, |j| levels[j])
+        };
+        let direction_of = |level: u8| if level % 2 == 1 { R } else { L };
+        let sos = direction_of(level.max(before));
+        let eos = direction_of(level.max(after));
+
+        resolve_weak_types(&mut classes, &sequence, sos);
+        resolve_neutral_types(&mut classes, &sequence, sos, eos, level);
+
+        // I1、I2
+        for &i in &sequence {
+            let level = levels[i];
+            levels[i] = match (level % 2, classes[i]) {
+                (0, R) => level + 1,
+                (0, AN | EN) => level + 2,
+                (1, L | EN | AN) => level + 1,
+                _ => level,
+            };
+        }
+    }
+
+    // 取り除いた文字は、直前の文字と同じレベルにする
+    for i in 0..n {
+        if is_removed(original[i]) {
+            levels[i] = if i == 0 {
+                paragraph_level
+            } else {
+                levels[i - 1]
+            };
+        }
+    }
+
+    // L1: 区切りの文字と、その前と段落の末尾の空白は段落のレベルにする
+    let mut trailing = true;
+    for i in (0..n).rev() {
+        match original[i] {
+            S | B => {
+                levels[i] = paragraph_level;
+                trailing = true;
+            }
+            WS | LRI | RLI | FSI | PDI if trailing => levels[i] = paragraph_level,
+            class if is_removed(class) && trailing => levels[i] = paragraph_level,
+            _ => trailing = false,
+        }
+    }
+}
+
+// W1〜W7: 弱い文字の種類を解決する
+fn resolve_weak_types(classes: &mut [BidiClass], sequence: &[usize], sos: BidiClass) {
+    let mut previous = sos;
+    for &i in sequence {
+        if classes[i] == NSM {
+            classes[i] = if matches!(previous, LRI | RLI | FSI | PDI) {
+                ON
+            } else {
+                previous
+            };
+        }
+        previous = classes[i];
+    }
+
+    let mut last_strong = sos;
+    for &i in sequence {
+        match classes[i] {
+            L | R | AL => last_strong = classes[i],
+            EN if last_strong == AL => classes[i] = AN,
+            _ => {}
+        }
+    }
+    for &i in sequence {
+        if classes[i] == AL {
+            classes[i] = R;
+        }
+    }
+
+    for k in 1..sequence.len().saturating_sub(1) {
+        let (before, class, after) = (
+            classes[sequence[k - 1]],
+            classes[sequence[k]],
+            classes[sequence[k + 1]],
+        );
+        match (before, class, after) {
+            (EN, ES | CS, EN) => classes[sequence[k]] = EN,
+            (AN, CS, AN) => classes[sequence[k]] = AN,
+            _ => {}
+        }
+    }
+
+    let mut k = 0;
+    while k < sequence.len() {
+        if classes[sequence[k]] != ET {
+            k += 1;
+            continue;
+        }
+        let start = k;
+        while k < sequence.len() && classes[sequence[k]] == ET {
+            k += 1;
+        }
+        let adjacent_to_number = (start > 0 && classes[sequence[start - 1]] == EN)
+            || (k < sequence.len() && classes[sequence[k]] == EN);
+        if adjacent_to_number {
+            for &i in &sequence[start..k] {
+                classes[i] = EN;
+            }
+        }
+    }
+
+    for &i in sequence {
+        if matches!(classes[i], ES | ET | CS) {
+            classes[i] = ON;
+        }
+    }
+
+    let mut last_strong = sos;
+    for &i in sequence {
+        match classes[i] {
+            L | R => last_strong = classes[i],
+            EN if last_strong == L => classes[i] = L,
+            _ => {}
+        }
+    }
+}
+
+// N1、N2: 中立の文字の並びは、前後の強い文字の方向が同じならその方向に、違えば埋め込みの方向にする。
+// 括弧の対をまとめる N0 は扱わない
+fn resolve_neutral_types(
+    classes: &mut [BidiClass],
+    sequence: &[usize],
+    sos: BidiClass,
+    eos: BidiClass,
+    level: u8,
+) {
+    let is_neutral = |class: BidiClass| matches!(class, B | S | WS | ON | LRI | RLI | FSI | PDI);
+    let strong = |class: BidiClass| if class == L { L } else { R };
+    let embedding = if level % 2 == 1 { R } else { L };
+
+    let mut k = 0;
+    while k < sequence.len() {
+        if !is_neutral(classes[sequence[k]]) {
+            k += 1;
+            continue;
+        }
+        let start = k;
+        while k < sequence.len() && is_neutral(classes[sequence[k]]) {
+            k += 1;
+        }
+        let before = if start == 0 {
+            sos
+        } else {
+            strong(classes[sequence[start - 1]])
+        };
+        let after = if k == sequence.len() {
+            eos
+        } else {
+            strong(classes[sequence[k]])
+        };
+        let class = if before == after { before } else { embedding };
+        for &i in &sequence[start..k] {
+            classes[i] = class;
+        }
+    }
+}
+
+// https://www.unicode.org/reports/tr9/#L2
+// 1行の埋め込みレベルの並びから、左から右に表示する順に並べた添字を返す
+pub fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order = (0..levels.len()).collect::<Vec<_>>();
+    let (Some(&lowest), Some(&highest)) = (levels.iter().min(), levels.iter().max()) else {
+        return order;
+    };
+
+    for level in ((lowest | 1)..=highest).rev() {
+        let mut k = 0;
+        while k < order.len() {
+            if levels[order[k]] < level {
+                k += 1;
+                continue;
+            }
+            let start = k;
+            while k < order.len() && levels[order[k]] >= level {
+                k += 1;
+            }
+            order[start..k].reverse();
+        }
+    }
+
+    order
+}
+
+// https://www.unicode.org/reports/tr9/#L4
+// 右から左に表示する文字のうち、左右で対になる記号を鏡像の文字に置き換える
+pub fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '<' => '>',
+        '>' => '<',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        '〈' => '〉',
+        '〉' => '〈',
+        '《' => '》',
+        '》' => '《',
+        '「' => '」',
+        '」' => '「',
+        '『' => '』',
+        '』' => '『',
+        '【' => '】',
+        '】' => '【',
+        '〔' => '〕',
+        '〕' => '〔',
+        '（' => '）',
+        '）' => '（',
+        '［' => '］',
+        '］' => '［',
+        '｛' => '｝',
+        '｝' => '｛',
+        '＜' => '＞',
+        '＞' => '＜',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+
+    // 段落の方向を base にして、表示する順に並べた文字列。制御文字は取り除く
+    fn display(text: &str, base: Option<u8>) -> String {
+        let chars = text.chars().collect::<Vec<_>>();
+        let BidiLevels { levels, .. } = resolve_levels(&chars, base);
+        visual_order(&levels)
+            .into_iter()
+            .filter(|i| !is_removed(bidi_class(chars[*i])))
+            .map(|i| {
+                if levels[i] % 2 == 1 {
+                    mirror(chars[i])
+                } else {
+                    chars[i]
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bidi_class() {
+        assert_eq!(L, bidi_class('a'));
+        assert_eq!(L, bidi_class('あ'));
+        assert_eq!(R, bidi_class('א'));
+        assert_eq!(AL, bidi_class('ع'));
+        assert_eq!(EN, bidi_class('1'));
+        assert_eq!(AN, bidi_class('\u{0661}'));
+        assert_eq!(WS, bidi_class(' '));
+        assert_eq!(ON, bidi_class('!'));
+    }
+
+    #[test]
+    fn test_resolve_levels() {
+        let chars = "ab אב 12".chars().collect::<Vec<_>>();
+        assert_eq!(
+            vec![0, 0, 0, 1, 1, 1, 2, 2],
+            resolve_levels(&chars, Some(0)).levels
+        );
+        assert_eq!(
+            vec![2, 2, 1, 1, 1, 1, 2, 2],
+            resolve_levels(&chars, Some(1)).levels
+        );
+
+        // 段落の方向は、段落ごとに最初の強い文字から決める
+        let chars = "אב\nab".chars().collect::<Vec<_>>();
+        assert_eq!(
+            vec![1, 1, 1, 0, 0],
+            resolve_levels(&chars, None).paragraph_levels
+        );
+    }
+
+    #[test]
+    fn test_visual_order() {
+        assert_eq!("ab גדה", display("ab הדג", Some(0)));
+        // 右から左の段落では、数字と左から右の文字の並びはそのまま表示する
+        assert_eq!("abc 12 דג", display("גד abc 12", Some(1)));
+        // 右から左に表示する括弧は、鏡像にする
+        assert_eq!("ab (דג)", display("ab (גד)", Some(0)));
+        assert_eq!("(דג) ab", display("ab (גד)", Some(1)));
+    }
+
+    #[test]
+    fn test_explicit_embeddings() {
+        // 上書きは、文字の方向を無視して並べる
+        assert_eq!("a cba d", display("a \u{202E}abc\u{202C} d", Some(0)));
+        // 孤立は、外側の文字の並べ方に影響しない
+        let chars = "א \u{2066}b\u{2069} 1".chars().collect::<Vec<_>>();
+        let levels = resolve_levels(&chars, Some(0)).levels;
+        assert_eq!(2, levels[3]);
+        assert_eq!(1, levels[0]);
+    }
+}
@@ -1,270 +1,1472 @@
-use core::cell::RefCell;
+use core::str::FromStr;
 
 use alloc::{
     format,
-    rc::Rc,
     string::{String, ToString},
+    vec,
+    vec::Vec,
 };
 
 use crate::{
     error::Error,
-    renderer::dom::node::{ElementKind, Node, NodeKind},
+    renderer::{
+        css::{cascade::CssWideKeyword, cssom::ComponentValue, custom_property::CustomProperties},
+        layout::{
+            flex::{AlignContent, AlignItems, FlexBasis, FlexDirection, FlexWrap, JustifyContent},
+            float::{Clear, Float},
+            font::{Font, FontFamily, FontStyle, GenericFontFamily, LineHeight},
+            grid::{GridAutoFlow, GridLine, GridTemplateAreas, TrackList, TrackSize},
+            inline::VerticalAlign,
+            length::{ComputedLength, DEFAULT_FONT_SIZE, Size},
+            position::{Position, ZIndex},
+            text::{
+                Direction, OverflowWrap, TextAlign, TextDecoration, TextDecorationLine,
+                TextDecorationStyle, TextTransform, UnicodeBidi, WhiteSpace, WordBreak,
+            },
+        },
+    },
 };
 
-#[derive(Debug, Clone, PartialEq, Default)]
+// https://www.w3.org/TR/css-cascade-4/#defaulting
+// プロパティごとに、継承されるかどうかと初期値を定める
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PropertyDefinition {
+    pub name: &'static str,
+    pub inherited: bool,
+    // 初期値を CSS の値として書いたもの。ComputedStyle::default() はこの値を計算したものになる
+    pub initial: &'static str,
+}
+
+impl PropertyDefinition {
+    const fn new(name: &'static str, inherited: bool, initial: &'static str) -> Self {
+        Self {
+            name,
+            inherited,
+            initial,
+        }
+    }
+}
+
+pub static PROPERTIES: [PropertyDefinition; 82] = [
+    PropertyDefinition::new("background-color", false, "transparent"),
+    PropertyDefinition::new("color", true, "black"),
+    PropertyDefinition::new("display", false, "inline"),
+    PropertyDefinition::new("font-family", true, "serif"),
+    PropertyDefinition::new("font-size", true, "medium"),
+    PropertyDefinition::new("font-style", true, "normal"),
+    PropertyDefinition::new("font-weight", true, "normal"),
+    PropertyDefinition::new("line-height", true, "normal"),
+    PropertyDefinition::new("letter-spacing", true, "normal"),
+    PropertyDefinition::new("text-align", true, "start"),
+    PropertyDefinition::new("text-indent", true, "0"),
+    PropertyDefinition::new("text-transform", true, "none"),
+    PropertyDefinition::new("white-space", true, "normal"),
+    PropertyDefinition::new("word-spacing", true, "normal"),
+    PropertyDefinition::new("word-break", true, "normal"),
+    PropertyDefinition::new("overflow-wrap", true, "normal"),
+    PropertyDefinition::new("direction", true, "ltr"),
+    PropertyDefinition::new("unicode-bidi", false, "normal"),
+    PropertyDefinition::new("text-decoration-line", false, "none"),
+    PropertyDefinition::new("text-decoration-style", false, "solid"),
+    PropertyDefinition::new("text-decoration-color", false, "currentcolor"),
+    PropertyDefinition::new("vertical-align", false, "baseline"),
+    PropertyDefinition::new("height", false, "auto"),
+    PropertyDefinition::new("width", false, "auto"),
+    PropertyDefinition::new("min-height", false, "auto"),
+    PropertyDefinition::new("min-width", false, "auto"),
+    PropertyDefinition::new("max-height", false, "none"),
+    PropertyDefinition::new("max-width", false, "none"),
+    PropertyDefinition::new("box-sizing", false, "content-box"),
+    PropertyDefinition::new("float", false, "none"),
+    PropertyDefinition::new("clear", false, "none"),
+    PropertyDefinition::new("overflow", false, "visible"),
+    PropertyDefinition::new("position", false, "static"),
+    PropertyDefinition::new("top", false, "auto"),
+    PropertyDefinition::new("right", false, "auto"),
+    PropertyDefinition::new("bottom", false, "auto"),
+    PropertyDefinition::new("left", false, "auto"),
+    PropertyDefinition::new("z-index", false, "auto"),
+    PropertyDefinition::new("flex-direction", false, "row"),
+    PropertyDefinition::new("flex-wrap", false, "nowrap"),
+    PropertyDefinition::new("justify-content", false, "normal"),
+    PropertyDefinition::new("align-items", false, "normal"),
+    PropertyDefinition::new("align-self", false, "auto"),
+    PropertyDefinition::new("align-content", false, "normal"),
+    PropertyDefinition::new("row-gap", false, "normal"),
+    PropertyDefinition::new("column-gap", false, "normal"),
+    PropertyDefinition::new("flex-grow", false, "0"),
+    PropertyDefinition::new("flex-shrink", false, "1"),
+    PropertyDefinition::new("flex-basis", false, "auto"),
+    PropertyDefinition::new("order", false, "0"),
+    PropertyDefinition::new("justify-items", false, "normal"),
+    PropertyDefinition::new("justify-self", false, "auto"),
+    PropertyDefinition::new("grid-template-columns", false, "none"),
+    PropertyDefinition::new("grid-template-rows", false, "none"),
+    PropertyDefinition::new("grid-template-areas", false, "none"),
+    PropertyDefinition::new("grid-auto-columns", false, "auto"),
+    PropertyDefinition::new("grid-auto-rows", false, "auto"),
+    PropertyDefinition::new("grid-auto-flow", false, "row"),
+    PropertyDefinition::new("grid-row-start", false, "auto"),
+    PropertyDefinition::new("grid-column-start", false, "auto"),
+    PropertyDefinition::new("grid-row-end", false, "auto"),
+    PropertyDefinition::new("grid-column-end", false, "auto"),
+    PropertyDefinition::new("margin-top", false, "0"),
+    PropertyDefinition::new("margin-right", false, "0"),
+    PropertyDefinition::new("margin-bottom", false, "0"),
+    PropertyDefinition::new("margin-left", false, "0"),
+    PropertyDefinition::new("padding-top", false, "0"),
+    PropertyDefinition::new("padding-right", false, "0"),
+    PropertyDefinition::new("padding-bottom", false, "0"),
+    PropertyDefinition::new("padding-left", false, "0"),
+    PropertyDefinition::new("border-top-width", false, "medium"),
+    PropertyDefinition::new("border-right-width", false, "medium"),
+    PropertyDefinition::new("border-bottom-width", false, "medium"),
+    PropertyDefinition::new("border-left-width", false, "medium"),
+    PropertyDefinition::new("border-top-style", false, "none"),
+    PropertyDefinition::new("border-right-style", false, "none"),
+    PropertyDefinition::new("border-bottom-style", false, "none"),
+    PropertyDefinition::new("border-left-style", false, "none"),
+    PropertyDefinition::new("border-top-color", false, "currentcolor"),
+    PropertyDefinition::new("border-right-color", false, "currentcolor"),
+    PropertyDefinition::new("border-bottom-color", false, "currentcolor"),
+    PropertyDefinition::new("border-left-color", false, "currentcolor"),
+];
+
+pub fn property_definition(name: &str) -> Option<&'static PropertyDefinition> {
+    PROPERTIES.iter().find(|p| p.name == name)
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyle {
-    background_color: Option<Color>,
-    color: Option<Color>,
-    display: Option<DisplayType>,
-    font_size: Option<FontSize>,
-    text_decoration: Option<TextDecoration>,
-    height: Option<f64>,
-    width: Option<f64>,
+    custom_properties: CustomProperties,
+    background_color: ColorValue,
+    color: Color,
+    display: DisplayType,
+    font_family: Vec<FontFamily>,
+    // px で表したフォントサイズ
+    font_size: f64,
+    font_style: FontStyle,
+    font_weight: u16,
+    line_height: LineHeight,
+    // px で表した文字と単語の間隔。normal は 0 として扱う
+    letter_spacing: f64,
+    word_spacing: f64,
+    text_align: TextAlign,
+    text_indent: ComputedLength,
+    text_transform: TextTransform,
+    white_space: WhiteSpace,
+    word_break: WordBreak,
+    overflow_wrap: OverflowWrap,
+    direction: Direction,
+    unicode_bidi: UnicodeBidi,
+    text_decoration_line: TextDecorationLine,
+    text_decoration_style: TextDecorationStyle,
+    text_decoration_color: ColorValue,
+    vertical_align: VerticalAlign,
+    height: Size,
+    width: Size,
+    // min-* の auto は 0 として扱う
+    min_height: Size,
+    min_width: Size,
+    // None は none を表す
+    max_height: Option<ComputedLength>,
+    max_width: Option<ComputedLength>,
+    box_sizing: BoxSizing,
+    float: Float,
+    clear: Clear,
+    overflow: Overflow,
+    position: Position,
+    // top、right、bottom、left
+    inset: Sides<Size>,
+    z_index: ZIndex,
+    flex_direction: FlexDirection,
+    flex_wrap: FlexWrap,
+    justify_content: JustifyContent,
+    align_items: AlignItems,
+    // None は auto を表す。フレックスコンテナとグリッドコンテナの align-items に従う
+    align_self: Option<AlignItems>,
+    align_content: AlignContent,
+    // normal は 0 として扱う
+    row_gap: ComputedLength,
+    column_gap: ComputedLength,
+    flex_grow: f64,
+    flex_shrink: f64,
+    flex_basis: FlexBasis,
+    order: i64,
+    justify_items: AlignItems,
+    // None は auto を表す。グリッドコンテナの justify-items に従う
+    justify_self: Option<AlignItems>,
+    grid_template_columns: TrackList,
+    grid_template_rows: TrackList,
+    grid_template_areas: GridTemplateAreas,
+    grid_auto_columns: TrackSize,
+    grid_auto_rows: TrackSize,
+    grid_auto_flow: GridAutoFlow,
+    grid_row_start: GridLine,
+    grid_column_start: GridLine,
+    grid_row_end: GridLine,
+    grid_column_end: GridLine,
+    margin: Sides<Size>,
+    padding: Sides<ComputedLength>,
+    border_width: Sides<f64>,
+    border_style: Sides<BorderStyle>,
+    border_color: Sides<ColorValue>,
+}
+
+// すべてのプロパティが初期値になったスタイル
+impl Default for ComputedStyle {
+    fn default() -> Self {
+        Self {
+            custom_properties: CustomProperties::new(),
+            background_color: ColorValue::Color(Color::transparent()),
+            color: Color::black(),
+            display: DisplayType::Inline,
+            font_family: vec![FontFamily::Generic(GenericFontFamily::Serif)],
+            font_size: DEFAULT_FONT_SIZE,
+            font_style: FontStyle::Normal,
+            font_weight: 400,
+            line_height: LineHeight::Normal,
+            letter_spacing: 0.0,
+            word_spacing: 0.0,
+            text_align: TextAlign::Start,
+            text_indent: ComputedLength::default(),
+            text_transform: TextTransform::None,
+            white_space: WhiteSpace::Normal,
+            word_break: WordBreak::Normal,
+            overflow_wrap: OverflowWrap::Normal,
+            direction: Direction::Ltr,
+            unicode_bidi: UnicodeBidi::Normal,
+            text_decoration_line: TextDecorationLine::default(),
+            text_decoration_style: TextDecorationStyle::Solid,
+            text_decoration_color: ColorValue::CurrentColor,
+            vertical_align: VerticalAlign::Baseline,
+            height: Size::Auto,
+            width: Size::Auto,
+            min_height: Size::Auto,
+            min_width: Size::Auto,
+            max_height: None,
+            max_width: None,
+            box_sizing: BoxSizing::ContentBox,
+            float: Float::None,
+            clear: Clear::None,
+            overflow: Overflow::Visible,
+            position: Position::Static,
+            inset: Sides::all(Size::Auto),
+            z_index: ZIndex::Auto,
+            flex_direction: FlexDirection::Row,
+            flex_wrap: FlexWrap::Nowrap,
+            justify_content: JustifyContent::FlexStart,
+            align_items: AlignItems::Stretch,
+            align_self: None,
+            align_content: AlignContent::Stretch,
+            row_gap: ComputedLength::default(),
+            column_gap: ComputedLength::default(),
+            flex_grow: 0.0,
+            flex_shrink: 1.0,
+            flex_basis: FlexBasis::Auto,
+            order: 0,
+            justify_items: AlignItems::Stretch,
+            justify_self: None,
+            grid_template_columns: TrackList::default(),
+            grid_template_rows: TrackList::default(),
+            grid_template_areas: GridTemplateAreas::default(),
+            grid_auto_columns: TrackSize::default(),
+            grid_auto_rows: TrackSize::default(),
+            grid_auto_flow: GridAutoFlow::default(),
+            grid_row_start: GridLine::Auto,
+            grid_column_start: GridLine::Auto,
+            grid_row_end: GridLine::Auto,
+            grid_column_end: GridLine::Auto,
+            margin: Sides::all(Size::Length(ComputedLength::default())),
+            padding: Sides::all(ComputedLength::default()),
+            border_width: Sides::all(BORDER_WIDTH_MEDIUM),
+            border_style: Sides::all(BorderStyle::None),
+            border_color: Sides::all(ColorValue::CurrentColor),
+        }
+    }
 }
 
 impl ComputedStyle {
+    // 宣言を適用する前のスタイル。継承されるプロパティは親の値、それ以外は初期値になる。
+    // カスタムプロパティは常に継承される
+    pub fn inherit_from(parent_style: Option<&ComputedStyle>) -> Self {
+        let mut style = Self::default();
+        if let Some(parent_style) = parent_style {
+            style.custom_properties = parent_style.custom_properties.clone();
+            for property in PROPERTIES.iter().filter(|p| p.inherited) {
+                style.copy_property(property.name, parent_style);
+            }
+        }
+        style
+    }
+
+    fn copy_property(&mut self, property: &str, from: &ComputedStyle) {
+        match property {
+            "background-color" => self.background_color = from.background_color,
+            "color" => self.color = from.color,
+            "display" => self.display = from.display,
+            "font-family" => self.font_family = from.font_family.clone(),
+            "font-size" => self.font_size = from.font_size,
+            "font-style" => self.font_style = from.font_style,
+            "font-weight" => self.font_weight = from.font_weight,
+            "line-height" => self.line_height = from.line_height,
+            "letter-spacing" => self.letter_spacing = from.letter_spacing,
+            "word-spacing" => self.word_spacing = from.word_spacing,
+            "text-align" => self.text_align = from.text_align,
+            "text-indent" => self.text_indent = from.text_indent,
+            "text-transform" => self.text_transform = from.text_transform,
+            "white-space" => self.white_space = from.white_space,
+            "word-break" => self.word_break = from.word_break,
+            "overflow-wrap" => self.overflow_wrap = from.overflow_wrap,
+            "direction" => self.direction = from.direction,
+            "unicode-bidi" => self.unicode_bidi = from.unicode_bidi,
+            "text-decoration-line" => self.text_decoration_line = from.text_decoration_line,
+            "text-decoration-style" => self.text_decoration_style = from.text_decoration_style,
+            "text-decoration-color" => self.text_decoration_color = from.text_decoration_color,
+            "vertical-align" => self.vertical_align = from.vertical_align,
+            "height" => self.height = from.height,
+            "width" => self.width = from.width,
+            "min-height" => self.min_height = from.min_height,
+            "min-width" => self.min_width = from.min_width,
+            "max-height" => self.max_height = from.max_height,
+            "max-width" => self.max_width = from.max_width,
+            "box-sizing" => self.box_sizing = from.box_sizing,
+            "float" => self.float = from.float,
+            "clear" => self.clear = from.clear,
+            "overflow" => self.overflow = from.overflow,
+            "position" => self.position = from.position,
+            "top" => self.inset.top = from.inset.top,
+            "right" => self.inset.right = from.inset.right,
+            "bottom" => self.inset.bottom = from.inset.bottom,
+            "left" => self.inset.left = from.inset.left,
+            "z-index" => self.z_index = from.z_index,
+            "flex-direction" => self.flex_direction = from.flex_direction,
+            "flex-wrap" => self.flex_wrap = from.flex_wrap,
+            "justify-content" => self.justify_content = from.justify_content,
+            "align-items" => self.align_items = from.align_items,
+            "align-self" => self.align_self = from.align_self,
+            "align-content" => self.align_content = from.align_content,
+            "row-gap" => self.row_gap = from.row_gap,
+            "column-gap" => self.column_gap = from.column_gap,
+            "flex-grow" => self.flex_grow = from.flex_grow,
+            "flex-shrink" => self.flex_shrink = from.flex_shrink,
+            "flex-basis" => self.flex_basis = from.flex_basis,
+            "order" => self.order = from.order,
+            "justify-items" => self.justify_items = from.justify_items,
+            "justify-self" => self.justify_self = from.justify_self,
+            "grid-template-columns" => {
+                self.grid_template_columns = from.grid_template_columns.clone()
+            }
+            "grid-template-rows" => self.grid_template_rows = from.grid_template_rows.clone(),
+            "grid-template-areas" => self.grid_template_areas = from.grid_template_areas.clone(),
+            "grid-auto-columns" => self.grid_auto_columns = from.grid_auto_columns,
+            "grid-auto-rows" => self.grid_auto_rows = from.grid_auto_rows,
+            "grid-auto-flow" => self.grid_auto_flow = from.grid_auto_flow,
+            "grid-row-start" => self.grid_row_start = from.grid_row_start.clone(),
+            "grid-column-start" => self.grid_column_start = from.grid_column_start.clone(),
+            "grid-row-end" => self.grid_row_end = from.grid_row_end.clone(),
+            "grid-column-end" => self.grid_column_end = from.grid_column_end.clone(),
+            property => {
+                let Some((side_property, side)) = SideProperty::parse(property) else {
+                    return;
+                };
+                match side_property {
+                    SideProperty::Margin => self.margin.set(side, from.margin.get(side)),
+                    SideProperty::Padding => self.padding.set(side, from.padding.get(side)),
+                    SideProperty::BorderWidth => {
+                        self.border_width.set(side, from.border_width.get(side))
+                    }
+                    SideProperty::BorderStyle => {
+                        self.border_style.set(side, from.border_style.get(side))
+                    }
+                    SideProperty::BorderColor => {
+                        self.border_color.set(side, from.border_color.get(side))
+                    }
+                }
+            }
+        }
+    }
+
+    // https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+    // revert はカスケードの段階で前のオリジンの値に置き換わるので、ここに残っているものは unset と同じ
+    pub fn apply_keyword(
+        &mut self,
+        property: &str,
+        keyword: CssWideKeyword,
+        parent_style: &ComputedStyle,
+    ) {
+        let Some(definition) = property_definition(property) else {
+            return;
+        };
+
+        let inherit = match keyword {
+            CssWideKeyword::Inherit => true,
+            CssWideKeyword::Initial => false,
+            CssWideKeyword::Unset | CssWideKeyword::Revert => definition.inherited,
+        };
+        if inherit {
+            self.copy_property(property, parent_style);
+        } else {
+            self.copy_property(property, &Self::default());
+        }
+    }
+
+    pub fn set_custom_properties(&mut self, custom_properties: CustomProperties) {
+        self.custom_properties = custom_properties;
+    }
+
+    pub fn custom_properties(&self) -> &CustomProperties {
+        &self.custom_properties
+    }
+
     pub fn set_background_color(&mut self, color: Color) {
-        self.background_color = Some(color);
+        self.background_color = ColorValue::Color(color);
+    }
+
+    pub fn set_background_color_current_color(&mut self) {
+        self.background_color = ColorValue::CurrentColor;
     }
 
     pub fn background_color(&self) -> Color {
-        self.background_color
-            .clone()
-            .expect("failed to access CSS property: background_color")
+        match self.background_color {
+            ColorValue::Color(color) => color,
+            ColorValue::CurrentColor => self.color(),
+        }
     }
 
     pub fn set_color(&mut self, color: Color) {
-        self.color = Some(color);
+        self.color = color;
     }
 
     pub fn color(&self) -> Color {
         self.color
-            .clone()
-            .expect("failed to access CSS property: color")
     }
 
     pub fn set_display(&mut self, display: DisplayType) {
-        self.display = Some(display);
+        self.display = display;
     }
 
     pub fn display(&self) -> DisplayType {
         self.display
-            .expect("failed to access CSS property: display")
     }
 
-    pub fn font_size(&self) -> FontSize {
+    pub fn set_font_size(&mut self, font_size: f64) {
+        self.font_size = font_size;
+    }
+
+    pub fn font_size(&self) -> f64 {
         self.font_size
-            .expect("failed to access CSS property: font_size")
     }
 
-    pub fn text_decoration(&self) -> TextDecoration {
-        self.text_decoration
-            .expect("failed to access CSS property: text_decoration")
+    pub fn set_font_family(&mut self, font_family: Vec<FontFamily>) {
+        self.font_family = font_family;
     }
 
-    pub fn set_height(&mut self, height: f64) {
-        self.height = Some(height);
+    pub fn font_family(&self) -> &[FontFamily] {
+        &self.font_family
     }
 
-    pub fn height(&self) -> f64 {
-        self.height.expect("failed to access CSS property: height")
+    pub fn set_font_style(&mut self, font_style: FontStyle) {
+        self.font_style = font_style;
     }
 
-    pub fn set_width(&mut self, width: f64) {
-        self.width = Some(width);
+    pub fn font_style(&self) -> FontStyle {
+        self.font_style
     }
 
-    pub fn width(&self) -> f64 {
-        self.width.expect("failed to access CSS property: width")
+    pub fn set_font_weight(&mut self, font_weight: u16) {
+        self.font_weight = font_weight;
     }
 
-    pub fn defaulting(&mut self, node: &Rc<RefCell<Node>>, parent_style: Option<ComputedStyle>) {
-        if let Some(parent_style) = parent_style {
-            if self.background_color.is_none() && parent_style.background_color() != Color::white()
+    pub fn font_weight(&self) -> u16 {
+        self.font_weight
+    }
+
+    pub fn set_line_height(&mut self, line_height: LineHeight) {
+        self.line_height = line_height;
+    }
+
+    pub fn line_height(&self) -> LineHeight {
+        self.line_height
+    }
+
+    pub fn font(&self) -> Font {
+        Font {
+            families: self.font_family.clone(),
+            size: self.font_size,
+            weight: self.font_weight,
+            style: self.font_style,
+            line_height: self.line_height.resolve(self.font_size),
+        }
+    }
+
+    pub fn set_letter_spacing(&mut self, letter_spacing: f64) {
+        self.letter_spacing = letter_spacing;
+    }
+
+    pub fn letter_spacing(&self) -> f64 {
+        self.letter_spacing
+    }
+
+    pub fn set_word_spacing(&mut self, word_spacing: f64) {
+        self.word_spacing = word_spacing;
+    }
+
+    pub fn word_spacing(&self) -> f64 {
+        self.word_spacing
+    }
+
+    pub fn set_text_align(&mut self, text_align: TextAlign) {
+        self.text_align = text_align;
+    }
+
+    pub fn text_align(&self) -> TextAlign {
+        self.text_align
+    }
+
+    pub fn set_text_indent(&mut self, text_indent: ComputedLength) {
+        self.text_indent = text_indent;
+    }
+
+    pub fn text_indent(&self) -> ComputedLength {
+        self.text_indent
+    }
+
+    pub fn set_text_transform(&mut self, text_transform: TextTransform) {
+        self.text_transform = text_transform;
+    }
+
+    pub fn text_transform(&self) -> TextTransform {
+        self.text_transform
+    }
+
+    pub fn set_white_space(&mut self, white_space: WhiteSpace) {
+        self.white_space = white_space;
+    }
+
+    pub fn white_space(&self) -> WhiteSpace {
+        self.white_space
+    }
+
+    pub fn set_word_break(&mut self, word_break: WordBreak) {
+        self.word_break = word_break;
+    }
+
+    pub fn word_break(&self) -> WordBreak {
+        self.word_break
+    }
+
+    pub fn set_overflow_wrap(&mut self, overflow_wrap: OverflowWrap) {
+        self.overflow_wrap = overflow_wrap;
+    }
+
+    pub fn overflow_wrap(&self) -> OverflowWrap {
+        self.overflow_wrap
+    }
+
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn set_unicode_bidi(&mut self, unicode_bidi: UnicodeBidi) {
+        self.unicode_bidi = unicode_bidi;
+    }
+
+    pub fn unicode_bidi(&self) -> UnicodeBidi {
+        self.unicode_bidi
+    }
+
+    pub fn set_text_decoration_line(&mut self, line: TextDecorationLine) {
+        self.text_decoration_line = line;
+    }
+
+    pub fn text_decoration_line(&self) -> TextDecorationLine {
+        self.text_decoration_line
+    }
+
+    pub fn set_text_decoration_style(&mut self, style: TextDecorationStyle) {
+        self.text_decoration_style = style;
+    }
+
+    pub fn text_decoration_style(&self) -> TextDecorationStyle {
+        self.text_decoration_style
+    }
+
+    pub fn set_text_decoration_color(&mut self, color: Color) {
+        self.text_decoration_color = ColorValue::Color(color);
+    }
+
+    pub fn set_text_decoration_color_current_color(&mut self) {
+        self.text_decoration_color = ColorValue::CurrentColor;
+    }
+
+    pub fn text_decoration_color(&self) -> Color {
+        match self.text_decoration_color {
+            ColorValue::Color(color) => color,
+            ColorValue::CurrentColor => self.color(),
+        }
+    }
+
+    // この要素が描画する装飾。線がないときは None
+    pub fn text_decoration(&self) -> Option<TextDecoration> {
+        if self.text_decoration_line.is_none() {
+            return None;
+        }
+        Some(TextDecoration {
+            line: self.text_decoration_line,
+            style: self.text_decoration_style,
+            color: self.text_decoration_color(),
+        })
+    }
+
+    pub fn set_vertical_align(&mut self, vertical_align: VerticalAlign) {
+        self.vertical_align = vertical_align;
+    }
+
+    pub fn vertical_align(&self) -> VerticalAlign {
+        self.vertical_align
+    }
+
+    pub fn set_height(&mut self, height: Size) {
+        self.height = height;
+    }
+
+    pub fn height(&self) -> Size {
+        self.height
+    }
+
+    pub fn set_width(&mut self, width: Size) {
+        self.width = width;
+    }
+
+    pub fn width(&self) -> Size {
+        self.width
+    }
+
+    pub fn set_min_height(&mut self, min_height: Size) {
+        self.min_height = min_height;
+    }
+
+    pub fn min_height(&self) -> Size {
+        self.min_height
+    }
+
+    pub fn set_min_width(&mut self, min_width: Size) {
+        self.min_width = min_width;
+    }
+
+    pub fn min_width(&self) -> Size {
+        self.min_width
+    }
+
+    pub fn set_max_height(&mut self, max_height: Option<ComputedLength>) {
+        self.max_height = max_height;
+    }
+
+    pub fn max_height(&self) -> Option<ComputedLength> {
+        self.max_height
+    }
+
+    pub fn set_max_width(&mut self, max_width: Option<ComputedLength>) {
+        self.max_width = max_width;
+    }
+
+    pub fn max_width(&self) -> Option<ComputedLength> {
+        self.max_width
+    }
+
+    pub fn set_box_sizing(&mut self, box_sizing: BoxSizing) {
+        self.box_sizing = box_sizing;
+    }
+
+    pub fn box_sizing(&self) -> BoxSizing {
+        self.box_sizing
+    }
+
+    pub fn set_float(&mut self, float: Float) {
+        self.float = float;
+    }
+
+    pub fn float(&self) -> Float {
+        self.float
+    }
+
+    pub fn set_clear(&mut self, clear: Clear) {
+        self.clear = clear;
+    }
+
+    pub fn clear(&self) -> Clear {
+        self.clear
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    pub fn set_position(&mut self, position: Position) {
+        self.position = position;
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn set_inset(&mut self, side: Side, inset: Size) {
+        self.inset.set(side, inset);
+    }
+
+    pub fn inset(&self, side: Side) -> Size {
+        self.inset.get(side)
+    }
+
+    pub fn set_z_index(&mut self, z_index: ZIndex) {
+        self.z_index = z_index;
+    }
+
+    pub fn z_index(&self) -> ZIndex {
+        self.z_index
+    }
+
+    pub fn set_flex_direction(&mut self, flex_direction: FlexDirection) {
+        self.flex_direction = flex_direction;
+    }
+
+    pub fn flex_direction(&self) -> FlexDirection {
+        self.flex_direction
+    }
+
+    pub fn set_flex_wrap(&mut self, flex_wrap: FlexWrap) {
+        self.flex_wrap = flex_wrap;
+    }
+
+    pub fn flex_wrap(&self) -> FlexWrap {
+        self.flex_wrap
+    }
+
+    pub fn set_justify_content(&mut self, justify_content: JustifyContent) {
+        self.justify_content = justify_content;
+    }
+
+    pub fn justify_content(&self) -> JustifyContent {
+        self.justify_content
+    }
+
+    pub fn set_align_items(&mut self, align_items: AlignItems) {
+        self.align_items = align_items;
+    }
+
+    pub fn align_items(&self) -> AlignItems {
+        self.align_items
+    }
+
+    pub fn set_align_self(&mut self, align_self: Option<AlignItems>) {
+        self.align_self = align_self;
+    }
+
+    pub fn align_self(&self) -> Option<AlignItems> {
+        self.align_self
+    }
+
+    pub fn set_align_content(&mut self, align_content: AlignContent) {
+        self.align_content = align_content;
+    }
+
+    pub fn align_content(&self) -> AlignContent {
+        self.align_content
+    }
+
+    pub fn set_row_gap(&mut self, row_gap: ComputedLength) {
+        self.row_gap = row_gap;
+    }
+
+    pub fn row_gap(&self) -> ComputedLength {
+        self.row_gap
+    }
+
+    pub fn set_column_gap(&mut self, column_gap: ComputedLength) {
+        self.column_gap = column_gap;
+    }
+
+    pub fn column_gap(&self) -> ComputedLength {
+        self.column_gap
+    }
+
+    pub fn set_flex_grow(&mut self, flex_grow: f64) {
+        self.flex_grow = flex_grow;
+    }
+
+    pub fn flex_grow(&self) -> f64 {
+        self.flex_grow
+    }
+
+    pub fn set_flex_shrink(&mut self, flex_shrink: f64) {
+        self.flex_shrink = flex_shrink;
+    }
+
+    pub fn flex_shrink(&self) -> f64 {
+        self.flex_shrink
+    }
+
+    pub fn set_flex_basis(&mut self, flex_basis: FlexBasis) {
+        self.flex_basis = flex_basis;
+    }
+
+    pub fn flex_basis(&self) -> FlexBasis {
+        self.flex_basis
+    }
+
+    pub fn set_order(&mut self, order: i64) {
+        self.order = order;
+    }
+
+    pub fn order(&self) -> i64 {
+        self.order
+    }
+
+    pub fn set_justify_items(&mut self, justify_items: AlignItems) {
+        self.justify_items = justify_items;
+    }
+
+    pub fn justify_items(&self) -> AlignItems {
+        self.justify_items
+    }
+
+    pub fn set_justify_self(&mut self, justify_self: Option<AlignItems>) {
+        self.justify_self = justify_self;
+    }
+
+    pub fn justify_self(&self) -> Option<AlignItems> {
+        self.justify_self
+    }
+
+    pub fn set_grid_template_columns(&mut self, grid_template_columns: TrackList) {
+        self.grid_template_columns = grid_template_columns;
+    }
+
+    pub fn grid_template_columns(&self) -> &TrackList {
+        &self.grid_template_columns
+    }
+
+    pub fn set_grid_template_rows(&mut self, grid_template_rows: TrackList) {
+        self.grid_template_rows = grid_template_rows;
+    }
+
+    pub fn grid_template_rows(&self) -> &TrackList {
+        &self.grid_template_rows
+    }
+
+    pub fn set_grid_template_areas(&mut self, grid_template_areas: GridTemplateAreas) {
+        self.grid_template_areas = grid_template_areas;
+    }
+
+    pub fn grid_template_areas(&self) -> &GridTemplateAreas {
+        &self.grid_template_areas
+    }
+
+    pub fn set_grid_auto_columns(&mut self, grid_auto_columns: TrackSize) {
+        self.grid_auto_columns = grid_auto_columns;
+    }
+
+    pub fn grid_auto_columns(&self) -> TrackSize {
+        self.grid_auto_columns
+    }
+
+    pub fn set_grid_auto_rows(&mut self, grid_auto_rows: TrackSize) {
+        self.grid_auto_rows = grid_auto_rows;
+    }
+
+    pub fn grid_auto_rows(&self) -> TrackSize {
+        self.grid_auto_rows
+    }
+
+    pub fn set_grid_auto_flow(&mut self, grid_auto_flow: GridAutoFlow) {
+        self.grid_auto_flow = grid_auto_flow;
+    }
+
+    pub fn grid_auto_flow(&self) -> GridAutoFlow {
+        self.grid_auto_flow
+    }
+
+    pub fn set_grid_row_start(&mut self, grid_row_start: GridLine) {
+        self.grid_row_start = grid_row_start;
+    }
+
+    pub fn grid_row_start(&self) -> &GridLine {
+        &self.grid_row_start
+    }
+
+    pub fn set_grid_column_start(&mut self, grid_column_start: GridLine) {
+        self.grid_column_start = grid_column_start;
+    }
+
+    pub fn grid_column_start(&self) -> &GridLine {
+        &self.grid_column_start
+    }
+
+    pub fn set_grid_row_end(&mut self, grid_row_end: GridLine) {
+        self.grid_row_end = grid_row_end;
+    }
+
+    pub fn grid_row_end(&self) -> &GridLine {
+        &self.grid_row_end
+    }
+
+    pub fn set_grid_column_end(&mut self, grid_column_end: GridLine) {
+        self.grid_column_end = grid_column_end;
+    }
+
+    pub fn grid_column_end(&self) -> &GridLine {
+        &self.grid_column_end
+    }
+
+    pub fn set_margin(&mut self, side: Side, margin: Size) {
+        self.margin.set(side, margin);
+    }
+
+    pub fn margin(&self, side: Side) -> Size {
+        self.margin.get(side)
+    }
+
+    pub fn set_padding(&mut self, side: Side, padding: ComputedLength) {
+        self.padding.set(side, padding);
+    }
+
+    pub fn padding(&self, side: Side) -> ComputedLength {
+        self.padding.get(side)
+    }
+
+    pub fn set_border_width(&mut self, side: Side, width: f64) {
+        self.border_width.set(side, width);
+    }
+
+    // border-style が none か hidden の辺は、border-width の計算値が 0 になる
+    pub fn border_width(&self, side: Side) -> f64 {
+        match self.border_style.get(side) {
+            BorderStyle::None | BorderStyle::Hidden => 0.0,
+            _ => self.border_width.get(side),
+        }
+    }
+
+    pub fn set_border_style(&mut self, side: Side, border_style: BorderStyle) {
+        self.border_style.set(side, border_style);
+    }
+
+    pub fn border_style(&self, side: Side) -> BorderStyle {
+        self.border_style.get(side)
+    }
+
+    pub fn set_border_color(&mut self, side: Side, color: Color) {
+        self.border_color.set(side, ColorValue::Color(color));
+    }
+
+    pub fn set_border_color_current_color(&mut self, side: Side) {
+        self.border_color.set(side, ColorValue::CurrentColor);
+    }
+
+    pub fn border_color(&self, side: Side) -> Color {
+        match self.border_color.get(side) {
+            ColorValue::Color(color) => color,
+            ColorValue::CurrentColor => self.color(),
+        }
+    }
+}
+
+// border-width の medium の値
+pub static BORDER_WIDTH_MEDIUM: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Top, Side::Right, Side::Bottom, Side::Left];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Side::Top => "top",
+            Side::Right => "right",
+            Side::Bottom => "bottom",
+            Side::Left => "left",
+        }
+    }
+}
+
+// 上下左右の辺ごとの値
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sides<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+impl<T: Copy> Sides<T> {
+    pub fn all(value: T) -> Self {
+        Self {
+            top: value,
+            right: value,
+            bottom: value,
+            left: value,
+        }
+    }
+
+    pub fn get(&self, side: Side) -> T {
+        match side {
+            Side::Top => self.top,
+            Side::Right => self.right,
+            Side::Bottom => self.bottom,
+            Side::Left => self.left,
+        }
+    }
+
+    pub fn set(&mut self, side: Side, value: T) {
+        match side {
+            Side::Top => self.top = value,
+            Side::Right => self.right = value,
+            Side::Bottom => self.bottom = value,
+            Side::Left => self.left = value,
+        }
+    }
+}
+
+// margin-top や border-top-width のように、辺ごとに指定するプロパティ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SideProperty {
+    Margin,
+    Padding,
+    BorderWidth,
+    BorderStyle,
+    BorderColor,
+}
+
+impl SideProperty {
+    pub fn parse(property: &str) -> Option<(Self, Side)> {
+        for side in Side::ALL {
+            if let Some(name) = property
+                .strip_suffix(side.name())
+                .and_then(|p| p.strip_suffix('-'))
             {
-                self.background_color = Some(parent_style.background_color());
-            }
-            if self.color.is_none() && parent_style.color() != Color::black() {
-                self.color = Some(parent_style.color());
-            }
-            if self.font_size.is_none() && parent_style.font_size() != FontSize::Medium {
-                self.font_size = Some(parent_style.font_size());
+                match name {
+                    "margin" => return Some((Self::Margin, side)),
+                    "padding" => return Some((Self::Padding, side)),
+                    _ => {}
+                }
             }
-            if self.text_decoration.is_none()
-                && parent_style.text_decoration() != TextDecoration::None
+
+            if let Some(name) = property
+                .strip_prefix("border-")
+                .and_then(|p| p.strip_prefix(side.name()))
+                .and_then(|p| p.strip_prefix('-'))
             {
-                self.text_decoration = Some(parent_style.text_decoration());
+                match name {
+                    "width" => return Some((Self::BorderWidth, side)),
+                    "style" => return Some((Self::BorderStyle, side)),
+                    "color" => return Some((Self::BorderColor, side)),
+                    _ => {}
+                }
             }
         }
 
-        if self.background_color.is_none() {
-            self.background_color = Some(Color::white());
-        }
-        if self.color.is_none() {
-            self.color = Some(Color::black());
-        }
-        if self.display.is_none() {
-            self.display = Some(DisplayType::default(node));
-        }
-        if self.font_size.is_none() {
-            self.font_size = Some(FontSize::default(node));
+        None
+    }
+
+    pub fn longhand(&self, side: Side) -> String {
+        match self {
+            Self::Margin => format!("margin-{}", side.name()),
+            Self::Padding => format!("padding-{}", side.name()),
+            Self::BorderWidth => format!("border-{}-width", side.name()),
+            Self::BorderStyle => format!("border-{}-style", side.name()),
+            Self::BorderColor => format!("border-{}-color", side.name()),
         }
-        if self.text_decoration.is_none() {
-            self.text_decoration = Some(TextDecoration::default(node));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    None,
+    Hidden,
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl FromStr for BorderStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "hidden" => Ok(Self::Hidden),
+            "solid" => Ok(Self::Solid),
+            "dashed" => Ok(Self::Dashed),
+            "dotted" => Ok(Self::Dotted),
+            _ => Err(Error::UnexpectedInput(format!(
+                "border-style {s:?} is not supported yet"
+            ))),
         }
-        if self.height.is_none() {
-            self.height = Some(0.0);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoxSizing {
+    ContentBox,
+    BorderBox,
+}
+
+impl FromStr for BoxSizing {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "content-box" => Ok(Self::ContentBox),
+            "border-box" => Ok(Self::BorderBox),
+            _ => Err(Error::UnexpectedInput(format!(
+                "box-sizing {s:?} is not supported"
+            ))),
         }
-        if self.width.is_none() {
-            self.width = Some(0.0);
+    }
+}
+
+// https://www.w3.org/TR/css-overflow-3/#overflow-properties
+// visible 以外の値は、新しいブロック整形文脈を作る。はみ出した内容の切り取りはしない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl FromStr for Overflow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "visible" => Ok(Self::Visible),
+            "hidden" => Ok(Self::Hidden),
+            "clip" => Ok(Self::Clip),
+            "scroll" => Ok(Self::Scroll),
+            "auto" => Ok(Self::Auto),
+            _ => Err(Error::UnexpectedInput(format!(
+                "overflow {s:?} is not supported"
+            ))),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Default)]
+// currentColor は計算値のまま保持し、使用値を求めるときに color プロパティの値に置き換える
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColorValue {
+    Color(Color),
+    CurrentColor,
+}
+
+// sRGB の各チャンネルとアルファ値を 0〜255 の数値で保持する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Color {
-    name: Option<String>,
-    code: String,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
 }
 
 impl Color {
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn from_rgb(rgb: u32) -> Self {
+        Self::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255)
+    }
+
+    // https://www.w3.org/TR/css-color-4/#named-colors
     pub fn from_name(name: &str) -> Result<Self, Error> {
-        let code = match name {
-            "black" => "#000000",
-            "silver" => "#c0c0c0",
-            "gray" => "#808080",
-            "white" => "#ffffff",
-            "maroon" => "#800000",
-            "red" => "#ff0000",
-            "purple" => "#800080",
-            "fuchsia" => "#ff00ff",
-            "green" => "#008000",
-            "lime" => "#00ff00",
-            "olive" => "#808000",
-            "yellow" => "#ffff00",
-            "navy" => "#000080",
-            "blue" => "#0000ff",
-            "teal" => "#008080",
-            "aqua" => "#00ffff",
-            "orange" => "#ffa500",
-            "lightgray" => "#d3d3d3",
-            _ => {
-                return Err(Error::UnexpectedInput(format!(
-                    "color name {name:?} is not supported yet"
-                )));
-            }
+        let name = name.to_ascii_lowercase();
+        if name == "transparent" {
+            return Ok(Self::transparent());
         }
-        .to_string();
 
-        Ok(Self {
-            name: Some(name.to_string()),
-            code,
-        })
+        NAMED_COLORS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, rgb)| Self::from_rgb(*rgb))
+            .ok_or(Error::UnexpectedInput(format!(
+                "color name {name:?} is not supported"
+            )))
     }
 
+    // #rgb, #rgba, #rrggbb, #rrggbbaa の形式を受けつける
+    // https://www.w3.org/TR/css-color-4/#hex-notation
     pub fn from_code(code: &str) -> Result<Self, Error> {
-        if code.chars().nth(0) != Some('#') || code.len() != 7 {
-            return Err(Error::UnexpectedInput(format!("invalid color code {code}")));
+        let invalid = || Error::UnexpectedInput(format!("invalid color code {code}"));
+
+        let hex = code.strip_prefix('#').ok_or_else(invalid)?;
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+
+        match digits.as_slice() {
+            [r, g, b] => Ok(Self::new(r * 17, g * 17, b * 17, 255)),
+            [r, g, b, a] => Ok(Self::new(r * 17, g * 17, b * 17, a * 17)),
+            [r1, r2, g1, g2, b1, b2] => {
+                Ok(Self::new(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2, 255))
+            }
+            [r1, r2, g1, g2, b1, b2, a1, a2] => Ok(Self::new(
+                r1 * 16 + r2,
+                g1 * 16 + g2,
+                b1 * 16 + b2,
+                a1 * 16 + a2,
+            )),
+            _ => Err(invalid()),
         }
+    }
 
-        let name = match code {
-            "#000000" => "black",
-            "#c0c0c0" => "silver",
-            "#808080" => "gray",
-            "#ffffff" => "white",
-            "#800000" => "maroon",
-            "#ff0000" => "red",
-            "#800080" => "purple",
-            "#ff00ff" => "fuchsia",
-            "#008000" => "green",
-            "#00ff00" => "lime",
-            "#808000" => "olive",
-            "#ffff00" => "yellow",
-            "#000080" => "navy",
-            "#0000ff" => "blue",
-            "#008080" => "teal",
-            "#00ffff" => "aqua",
-            "#ffa500" => "orange",
-            "#d3d3d3" => "lightgray",
-            _ => {
-                return Err(Error::UnexpectedInput(format!(
-                    "color code {code:?} is not supported yet"
-                )));
+    // 宣言の値から色を求める。currentColor は呼び出し側で扱う
+    pub fn from_component_values(value: &[ComponentValue]) -> Result<Self, Error> {
+        match value {
+            [ComponentValue::Ident(name)] => Self::from_name(name),
+            [ComponentValue::HashToken(code)] => Self::from_code(code),
+            [
+                ComponentValue::Function(function),
+                arguments @ ..,
+                ComponentValue::CloseParenthesis,
+            ] => {
+                let arguments = parse_color_arguments(arguments).ok_or(Error::UnexpectedInput(
+                    format!("invalid arguments for {function}()"),
+                ))?;
+                match function.to_ascii_lowercase().as_str() {
+                    "rgb" | "rgba" => Self::from_rgb_arguments(&arguments),
+                    "hsl" | "hsla" => Self::from_hsl_arguments(&arguments),
+                    _ => Err(Error::UnexpectedInput(format!(
+                        "color function {function}() is not supported"
+                    ))),
+                }
             }
+            _ => Err(Error::UnexpectedInput(format!("invalid color {value:?}"))),
         }
-        .to_string();
+    }
 
-        Ok(Self {
-            name: Some(name),
-            code: code.to_string(),
-        })
+    // https://www.w3.org/TR/css-color-4/#rgb-functions
+    fn from_rgb_arguments(arguments: &ColorArguments) -> Result<Self, Error> {
+        let channel = |token: &ComponentValue| match token {
+            ComponentValue::Number(n) => Some(to_u8(n / 255.0)),
+            ComponentValue::Percentage(p) => Some(to_u8(p / 100.0)),
+            _ => None,
+        };
+        let [r, g, b] = &arguments.channels;
+        match (channel(r), channel(g), channel(b), arguments.alpha()) {
+            (Some(r), Some(g), Some(b), Some(a)) => Ok(Self::new(r, g, b, a)),
+            _ => Err(Error::UnexpectedInput("invalid rgb() color".to_string())),
+        }
+    }
+
+    // https://www.w3.org/TR/css-color-4/#the-hsl-notation
+    fn from_hsl_arguments(arguments: &ColorArguments) -> Result<Self, Error> {
+        let [h, s, l] = &arguments.channels;
+        let hue = match h {
+            ComponentValue::Number(n) => Some(*n),
+            ComponentValue::Dimension(n, unit) => match unit.to_ascii_lowercase().as_str() {
+                "deg" => Some(*n),
+                "grad" => Some(n * 0.9),
+                "rad" => Some(n * 180.0 / core::f64::consts::PI),
+                "turn" => Some(n * 360.0),
+                _ => None,
+            },
+            _ => None,
+        };
+        let ratio = |token: &ComponentValue| match token {
+            ComponentValue::Number(n) | ComponentValue::Percentage(n) => {
+                Some((n / 100.0).clamp(0.0, 1.0))
+            }
+            _ => None,
+        };
+
+        let (Some(hue), Some(s), Some(l), Some(a)) = (hue, ratio(s), ratio(l), arguments.alpha())
+        else {
+            return Err(Error::UnexpectedInput("invalid hsl() color".to_string()));
+        };
+
+        let hue = hue % 360.0;
+        let hue = if hue < 0.0 { hue + 360.0 } else { hue };
+        let f = |n: f64| {
+            let k = (n + hue / 30.0) % 12.0;
+            let amount = s * l.min(1.0 - l);
+            to_u8(l - amount * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0))
+        };
+        Ok(Self::new(f(0.0), f(8.0), f(4.0), a))
+    }
+
+    pub fn transparent() -> Self {
+        Self::new(0, 0, 0, 0)
     }
 
     pub fn white() -> Self {
-        Self {
-            name: Some("white".to_string()),
-            code: "#ffffff".to_string(),
-        }
+        Self::new(255, 255, 255, 255)
     }
 
     pub fn black() -> Self {
-        Self {
-            name: Some("black".to_string()),
-            code: "#000000".to_string(),
+        Self::new(0, 0, 0, 255)
+    }
+
+    pub fn r(&self) -> u8 {
+        self.r
+    }
+
+    pub fn g(&self) -> u8 {
+        self.g
+    }
+
+    pub fn b(&self) -> u8 {
+        self.b
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.a
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.a == 0
+    }
+
+    // 下にある色 backdrop の上に、この色を重ねた結果を返す（source-over）
+    // https://www.w3.org/TR/compositing-1/#porterduffcompositingoperators_srcover
+    pub fn blend(&self, backdrop: Color) -> Color {
+        let src_a = self.a as f64 / 255.0;
+        let dst_a = backdrop.a as f64 / 255.0;
+        let out_a = src_a + dst_a * (1.0 - src_a);
+        if out_a == 0.0 {
+            return Self::transparent();
         }
+
+        let channel = |src: u8, dst: u8| {
+            let c = (src as f64 * src_a + dst as f64 * dst_a * (1.0 - src_a)) / out_a;
+            to_u8(c / 255.0)
+        };
+        Self::new(
+            channel(self.r, backdrop.r),
+            channel(self.g, backdrop.g),
+            channel(self.b, backdrop.b),
+            to_u8(out_a),
+        )
     }
 
+    // 描画に使う 0xRRGGBB 形式の値。アルファ値は事前に blend で合成しておく
     pub fn code_u32(&self) -> u32 {
-        u32::from_str_radix(self.code.trim_start_matches('#'), 16).unwrap()
+        ((self.r as u32) << 16) | ((self.g as u32) << 8) | self.b as u32
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum FontSize {
-    Medium,
-    XLarge,
-    XXLarge,
+// rgb() や hsl() の引数。カンマ区切りの構文と、スペース区切りで `/` のあとにアルファ値を書く構文がある
+struct ColorArguments {
+    channels: [ComponentValue; 3],
+    alpha: Option<ComponentValue>,
 }
 
-impl FontSize {
-    fn default(node: &Rc<RefCell<Node>>) -> Self {
-        match &node.borrow().kind() {
-            NodeKind::Element(element) => match element.kind() {
-                ElementKind::H1 => FontSize::XXLarge,
-                ElementKind::H2 => FontSize::XLarge,
-                _ => FontSize::Medium,
-            },
-            _ => FontSize::Medium,
+impl ColorArguments {
+    fn alpha(&self) -> Option<u8> {
+        match &self.alpha {
+            None => Some(255),
+            Some(ComponentValue::Number(n)) => Some(to_u8(*n)),
+            Some(ComponentValue::Percentage(p)) => Some(to_u8(p / 100.0)),
+            Some(_) => None,
+        }
+    }
+}
+
+fn parse_color_arguments(arguments: &[ComponentValue]) -> Option<ColorArguments> {
+    let legacy = arguments.contains(&ComponentValue::Delim(','));
+    let values = if legacy {
+        // カンマ区切りの場合は、すべての値のあいだにカンマが必要
+        let values: Vec<_> = arguments.iter().step_by(2).cloned().collect();
+        let valid = arguments
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|t| *t == ComponentValue::Delim(','));
+        if !valid || arguments.len().is_multiple_of(2) {
+            return None;
         }
+        values
+    } else {
+        arguments.to_vec()
+    };
+
+    match values.as_slice() {
+        [r, g, b] => Some(ColorArguments {
+            channels: [r.clone(), g.clone(), b.clone()],
+            alpha: None,
+        }),
+        [r, g, b, a] if legacy => Some(ColorArguments {
+            channels: [r.clone(), g.clone(), b.clone()],
+            alpha: Some(a.clone()),
+        }),
+        [r, g, b, ComponentValue::Delim('/'), a] if !legacy => Some(ColorArguments {
+            channels: [r.clone(), g.clone(), b.clone()],
+            alpha: Some(a.clone()),
+        }),
+        _ => None,
     }
 }
 
+// 0.0〜1.0 の値を 0〜255 に変換する
+fn to_u8(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+// https://www.w3.org/TR/css-fonts-4/#absolute-size-mapping
+pub fn absolute_font_size(keyword: &str) -> Option<f64> {
+    let size = match keyword.to_ascii_lowercase().as_str() {
+        "xx-small" => 9.0,
+        "x-small" => 10.0,
+        "small" => 13.0,
+        "medium" => DEFAULT_FONT_SIZE,
+        "large" => 18.0,
+        "x-large" => 24.0,
+        "xx-large" => 32.0,
+        "xxx-large" => 48.0,
+        _ => return None,
+    };
+    Some(size)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DisplayType {
     Block,
     Inline,
+    // 新しいブロック整形文脈を作るブロック
+    FlowRoot,
+    // https://www.w3.org/TR/css-flexbox-1/#flex-containers
+    Flex,
+    // https://www.w3.org/TR/css-grid-1/#grid-containers
+    Grid,
     DisplayNone,
 }
 
-impl DisplayType {
-    fn default(node: &Rc<RefCell<Node>>) -> Self {
-        match &node.borrow().kind() {
-            NodeKind::Document => DisplayType::Block,
-            NodeKind::Element(e) => {
-                if e.is_block_element() {
-                    DisplayType::Block
-                } else {
-                    DisplayType::Inline
-                }
-            }
-            NodeKind::Text(_) => DisplayType::Inline,
-        }
-    }
+impl FromStr for DisplayType {
+    type Err = Error;
 
-    pub fn from_str(s: &str) -> Result<Self, Error> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "block" => Ok(Self::Block),
             "inline" => Ok(Self::Inline),
+            "flow-root" => Ok(Self::FlowRoot),
+            "flex" => Ok(Self::Flex),
+            "grid" => Ok(Self::Grid),
             "none" => Ok(Self::DisplayNone),
             _ => Err(Error::UnexpectedInput(format!(
                 "display {s:?} is not supported yet"
@@ -273,20 +1475,238 @@ impl DisplayType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum TextDecoration {
-    None,
-    Underline,
-}
+static NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
 
-impl TextDecoration {
-    fn default(node: &Rc<RefCell<Node>>) -> Self {
-        match &node.borrow().kind() {
-            NodeKind::Element(element) => match element.kind() {
-                ElementKind::A => TextDecoration::Underline,
-                _ => TextDecoration::None,
-            },
-            _ => TextDecoration::None,
-        }
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use crate::renderer::css::{cssom::CssParser, token::CssTokenizer};
+
+    use super::*;
+
+    fn parse_color(value: &str) -> Result<Color, Error> {
+        let declarations =
+            CssParser::new(CssTokenizer::new(format!("color: {value}"))).parse_declaration_list();
+        Color::from_component_values(&declarations[0].value)
+    }
+
+    #[test]
+    fn test_named_color() {
+        assert_eq!(
+            Color::from_rgb(0x663399),
+            parse_color("rebeccapurple").unwrap()
+        );
+        assert_eq!(Color::from_rgb(0xf0f8ff), parse_color("AliceBlue").unwrap());
+        assert_eq!(Color::transparent(), parse_color("transparent").unwrap());
+        assert!(parse_color("unknown").is_err());
+    }
+
+    #[test]
+    fn test_hex_color() {
+        assert_eq!(Color::from_rgb(0x123456), parse_color("#123456").unwrap());
+        assert_eq!(Color::white(), parse_color("#fff").unwrap());
+        assert_eq!(
+            Color::new(0x11, 0x22, 0x33, 0x44),
+            parse_color("#1234").unwrap()
+        );
+        assert_eq!(
+            Color::new(0x12, 0x34, 0x56, 0x78),
+            parse_color("#12345678").unwrap()
+        );
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("#ggg").is_err());
+    }
+
+    #[test]
+    fn test_color_functions() {
+        assert_eq!(
+            Color::from_rgb(0xff8000),
+            parse_color("rgb(255, 128, 0)").unwrap()
+        );
+        assert_eq!(
+            Color::new(255, 0, 0, 128),
+            parse_color("rgba(100%, 0%, 0%, 0.5)").unwrap()
+        );
+        assert_eq!(
+            Color::new(0, 0, 255, 64),
+            parse_color("rgb(0 0 255 / 25%)").unwrap()
+        );
+        assert_eq!(
+            Color::from_rgb(0x00ff00),
+            parse_color("hsl(120, 100%, 50%)").unwrap()
+        );
+        assert_eq!(
+            Color::new(0, 0, 255, 128),
+            parse_color("hsla(240deg 100% 50% / 0.5)").unwrap()
+        );
+        assert_eq!(
+            Color::from_rgb(0x808080),
+            parse_color("hsl(0.5turn, 0%, 50%)").unwrap()
+        );
+        assert!(parse_color("rgb(255, 0)").is_err());
+        assert!(parse_color("rgb(255 0, 0)").is_err());
+    }
+
+    #[test]
+    fn test_blend() {
+        let half_red = Color::new(255, 0, 0, 128);
+        assert_eq!(Color::from_rgb(0xff7f7f), half_red.blend(Color::white()));
+        assert_eq!(Color::from_rgb(0x800000), half_red.blend(Color::black()));
+        assert_eq!(Color::white(), Color::transparent().blend(Color::white()));
+        assert_eq!(half_red, half_red.blend(Color::transparent()));
+        assert_eq!(
+            String::from("0xff7f7f"),
+            format!("{:#08x}", half_red.blend(Color::white()).code_u32())
+        );
     }
 }
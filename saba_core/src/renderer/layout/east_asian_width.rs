@@ -0,0 +1,58 @@
+// https://www.unicode.org/reports/tr11/
+// East Asian Width が W（全角）か F（全角の互換文字）の文字の範囲。先頭の文字の順に並べる
+static WIDE_RANGES: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F300, 0x1F64F),
+    (0x1F900, 0x1F9FF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+// 全角で表示する文字かどうか。等幅フォントでは、半角の文字の2倍の幅で描画する
+pub fn is_wide(c: char) -> bool {
+    let c = c as u32;
+    WIDE_RANGES
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                core::cmp::Ordering::Less
+            } else if start > c {
+                core::cmp::Ordering::Greater
+            } else {
+                core::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_wide() {
+        assert!(is_wide('あ'));
+        assert!(is_wide('漢'));
+        assert!(is_wide('。'));
+        assert!(is_wide('Ａ'));
+        assert!(is_wide('한'));
+        assert!(is_wide('😀'));
+        assert!(!is_wide('a'));
+        assert!(!is_wide('é'));
+        // 半角カナは半角
+        assert!(!is_wide('ｱ'));
+    }
+}
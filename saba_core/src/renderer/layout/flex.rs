@@ -0,0 +1,637 @@
+use core::{ops::Range, str::FromStr};
+
+use alloc::{format, vec::Vec};
+
+use crate::{
+    error::Error,
+    renderer::{
+        css::cssom::ComponentValue,
+        layout::length::{ComputedLength, LengthContext, LengthPercentage},
+    },
+};
+
+// https://www.w3.org/TR/css-flexbox-1/#flex-direction-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlexDirection {
+    Row,
+    RowReverse,
+    Column,
+    ColumnReverse,
+}
+
+impl FlexDirection {
+    // 主軸が横向きかどうか
+    pub fn is_row(&self) -> bool {
+        matches!(self, Self::Row | Self::RowReverse)
+    }
+
+    // 主軸の始まりと終わりが逆になるかどうか
+    pub fn is_reverse(&self) -> bool {
+        matches!(self, Self::RowReverse | Self::ColumnReverse)
+    }
+}
+
+impl FromStr for FlexDirection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "row" => Ok(Self::Row),
+            "row-reverse" => Ok(Self::RowReverse),
+            "column" => Ok(Self::Column),
+            "column-reverse" => Ok(Self::ColumnReverse),
+            _ => Err(Error::UnexpectedInput(format!(
+                "flex-direction {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#flex-wrap-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlexWrap {
+    Nowrap,
+    Wrap,
+    WrapReverse,
+}
+
+impl FromStr for FlexWrap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nowrap" => Ok(Self::Nowrap),
+            "wrap" => Ok(Self::Wrap),
+            "wrap-reverse" => Ok(Self::WrapReverse),
+            _ => Err(Error::UnexpectedInput(format!(
+                "flex-wrap {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#justify-content-property
+// normal は flex-start と同じになる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JustifyContent {
+    FlexStart,
+    FlexEnd,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+impl JustifyContent {
+    // 余った空間 free を count 個の並べるものに分けて、最初のものの前と、隣り合うものの間に
+    // あける空間を返す。足りないときは、space-between は flex-start、space-around と
+    // space-evenly は center と同じになる
+    fn spacing(&self, free: i64, count: usize) -> (i64, i64) {
+        let count = count as i64;
+        if count == 0 {
+            return (0, 0);
+        }
+        match self {
+            Self::FlexStart => (0, 0),
+            Self::FlexEnd => (free, 0),
+            Self::Center => (free / 2, 0),
+            _ if free <= 0 => match self {
+                Self::SpaceBetween => (0, 0),
+                _ => (free / 2, 0),
+            },
+            Self::SpaceBetween if count == 1 => (0, 0),
+            Self::SpaceBetween => (0, free / (count - 1)),
+            Self::SpaceAround => (free / count / 2, free / count),
+            Self::SpaceEvenly => (free / (count + 1), free / (count + 1)),
+        }
+    }
+}
+
+impl FromStr for JustifyContent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" | "flex-start" | "start" => Ok(Self::FlexStart),
+            "flex-end" | "end" => Ok(Self::FlexEnd),
+            "center" => Ok(Self::Center),
+            "space-between" => Ok(Self::SpaceBetween),
+            "space-around" => Ok(Self::SpaceAround),
+            "space-evenly" => Ok(Self::SpaceEvenly),
+            _ => Err(Error::UnexpectedInput(format!(
+                "justify-content {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#align-items-property
+// align-self と、グリッドの justify-items と justify-self の値にもなる。
+// normal は stretch と同じで、baseline は flex-start として扱う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignItems {
+    Stretch,
+    FlexStart,
+    FlexEnd,
+    Center,
+    Baseline,
+}
+
+impl FromStr for AlignItems {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" | "stretch" => Ok(Self::Stretch),
+            "flex-start" | "start" | "self-start" => Ok(Self::FlexStart),
+            "flex-end" | "end" | "self-end" => Ok(Self::FlexEnd),
+            "center" => Ok(Self::Center),
+            "baseline" => Ok(Self::Baseline),
+            _ => Err(Error::UnexpectedInput(format!(
+                "align-items {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#align-content-property
+// normal は stretch と同じになる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignContent {
+    Stretch,
+    FlexStart,
+    FlexEnd,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+impl AlignContent {
+    // 引き伸ばさないときの、ラインの並べ方
+    fn distribution(&self) -> JustifyContent {
+        match self {
+            Self::Stretch | Self::FlexStart => JustifyContent::FlexStart,
+            Self::FlexEnd => JustifyContent::FlexEnd,
+            Self::Center => JustifyContent::Center,
+            Self::SpaceBetween => JustifyContent::SpaceBetween,
+            Self::SpaceAround => JustifyContent::SpaceAround,
+            Self::SpaceEvenly => JustifyContent::SpaceEvenly,
+        }
+    }
+}
+
+impl FromStr for AlignContent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" | "stretch" => Ok(Self::Stretch),
+            "flex-start" | "start" => Ok(Self::FlexStart),
+            "flex-end" | "end" => Ok(Self::FlexEnd),
+            "center" => Ok(Self::Center),
+            "space-between" => Ok(Self::SpaceBetween),
+            "space-around" => Ok(Self::SpaceAround),
+            "space-evenly" => Ok(Self::SpaceEvenly),
+            _ => Err(Error::UnexpectedInput(format!(
+                "align-content {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#flex-basis-property
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlexBasis {
+    // width や height の値を使う
+    Auto,
+    // 中身に合わせた大きさを使う
+    Content,
+    Length(ComputedLength),
+}
+
+impl FlexBasis {
+    pub fn parse(value: &[ComponentValue], context: &LengthContext) -> Option<Self> {
+        if let [ComponentValue::Ident(keyword)] = value {
+            return match keyword.to_ascii_lowercase().as_str() {
+                "auto" => Some(Self::Auto),
+                "content" => Some(Self::Content),
+                _ => None,
+            };
+        }
+
+        let length = LengthPercentage::parse_non_negative(value).ok()?;
+        Some(Self::Length(length.compute(context)))
+    }
+}
+
+// 主軸の方向の、フレックスアイテムの大きさ。大きさはボーダーボックスのもの
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlexItem {
+    // https://www.w3.org/TR/css-flexbox-1/#flex-base-size
+    pub base_size: i64,
+    pub min_size: i64,
+    pub max_size: Option<i64>,
+    // 主軸の始まりと終わりの側のマージン。auto は None になる
+    pub margin_start: Option<i64>,
+    pub margin_end: Option<i64>,
+    pub grow: f64,
+    pub shrink: f64,
+}
+
+impl FlexItem {
+    fn clamp(&self, size: f64) -> f64 {
+        let size = match self.max_size {
+            Some(max_size) => size.min(max_size as f64),
+            None => size,
+        };
+        size.max(self.min_size as f64)
+    }
+
+    // https://www.w3.org/TR/css-flexbox-1/#hypothetical-main-size
+    fn hypothetical_size(&self) -> i64 {
+        self.clamp(self.base_size as f64) as i64
+    }
+
+    // auto のマージンは 0 として扱う
+    fn margins(&self) -> i64 {
+        self.margin_start.unwrap_or(0) + self.margin_end.unwrap_or(0)
+    }
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#flex-lines
+// フレックスライン。items はラインに並べるアイテムの番号の範囲で、sizes はその主軸の方向の大きさ
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlexLine {
+    pub items: Range<usize>,
+    pub sizes: Vec<i64>,
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#algo-line-break
+// アイテムをフレックスラインに分けて、主軸の方向の大きさを決める。available はコンテナの
+// 主軸の方向の大きさで、None は中身に合わせて決まることを表す。gap はアイテムの間の空間
+pub fn resolve_lines(
+    items: &[FlexItem],
+    available: Option<i64>,
+    gap: i64,
+    wrap: bool,
+) -> Vec<FlexLine> {
+    let outer = |item: &FlexItem| item.hypothetical_size() + item.margins();
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < items.len() {
+        let mut end = start + 1;
+        match available {
+            // 1つ目のアイテムは、収まらなくてもラインに置く
+            Some(available) if wrap => {
+                let mut used = outer(&items[start]);
+                while let Some(item) = items.get(end)
+                    && used + gap + outer(item) <= available
+                {
+                    used += gap + outer(item);
+                    end += 1;
+                }
+            }
+            _ => end = items.len(),
+        }
+
+        let line = &items[start..end];
+        let sizes = match available {
+            Some(available) => resolve_flexible_lengths(line, available, gap),
+            None => line.iter().map(|item| item.hypothetical_size()).collect(),
+        };
+        lines.push(FlexLine {
+            items: start..end,
+            sizes,
+        });
+        start = end;
+    }
+    lines
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#resolve-flexible-lengths
+// 1本のラインのアイテムを伸び縮みさせて、available の大きさに合わせる
+fn resolve_flexible_lengths(items: &[FlexItem], available: i64, gap: i64) -> Vec<i64> {
+    let gaps = (gap * (items.len() as i64 - 1)) as f64;
+    let hypothetical: i64 = items
+        .iter()
+        .map(|item| item.hypothetical_size() + item.margins())
+        .sum();
+    let grow = (hypothetical as f64) + gaps < available as f64;
+    let factor = |item: &FlexItem| if grow { item.grow } else { item.shrink };
+
+    // 伸び縮みしないアイテムと、仮の大きさが最小や最大の大きさで決まったアイテムは、
+    // 仮の大きさで固定する
+    let mut sizes: Vec<f64> = items
+        .iter()
+        .map(|item| item.hypothetical_size() as f64)
+        .collect();
+    let mut frozen: Vec<bool> = items
+        .iter()
+        .map(|item| {
+            factor(item) == 0.0
+                || (grow && item.base_size > item.hypothetical_size())
+                || (!grow && item.base_size < item.hypothetical_size())
+        })
+        .collect();
+
+    // 固定したアイテムは決めた大きさ、それ以外は基準の大きさで並べたときに余る空間
+    let free_space = |sizes: &[f64], frozen: &[bool]| {
+        let used: f64 = items
+            .iter()
+            .enumerate()
+            .map(|(n, item)| {
+                let size = if frozen[n] {
+                    sizes[n]
+                } else {
+                    item.base_size as f64
+                };
+                size + item.margins() as f64
+            })
+            .sum();
+        available as f64 - gaps - used
+    };
+    let initial_free_space = free_space(&sizes, &frozen);
+
+    while frozen.iter().any(|f| !f) {
+        let unfrozen: Vec<usize> = (0..items.len()).filter(|n| !frozen[*n]).collect();
+
+        // 係数の和が 1 より小さいときは、余った空間のその割合だけを分ける
+        let mut remaining = free_space(&sizes, &frozen);
+        let factor_sum: f64 = unfrozen.iter().map(|n| factor(&items[*n])).sum();
+        if factor_sum < 1.0 && (initial_free_space * factor_sum).abs() < remaining.abs() {
+            remaining = initial_free_space * factor_sum;
+        }
+
+        // 伸ばすときは flex-grow の比で、縮めるときは flex-shrink と基準の大きさの積の比で分ける
+        let scaled_sum: f64 = unfrozen
+            .iter()
+            .map(|n| items[*n].shrink * items[*n].base_size as f64)
+            .sum();
+        let mut violation = 0.0;
+        let mut targets = Vec::new();
+        for n in unfrozen {
+            let item = &items[n];
+            let base_size = item.base_size as f64;
+            let target = if grow {
+                base_size + remaining * item.grow / factor_sum
+            } else if scaled_sum > 0.0 {
+                base_size + remaining * item.shrink * base_size / scaled_sum
+            } else {
+                base_size
+            };
+            let size = item.clamp(target);
+            violation += size - target;
+            targets.push((n, size, size - target));
+        }
+
+        // 最小や最大の大きさに収めた量の和の向きで、固定するアイテムを決める
+        for (n, size, adjustment) in targets {
+            sizes[n] = size;
+            if violation == 0.0
+                || (violation > 0.0 && adjustment > 0.0)
+                || (violation < 0.0 && adjustment < 0.0)
+            {
+                frozen[n] = true;
+            }
+        }
+    }
+
+    sizes.into_iter().map(|size| size as i64).collect()
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#main-alignment
+// ラインのアイテムを主軸の方向に並べて、主軸の始まりから各アイテムのボーダーボックスまでの距離を返す。
+// 余った空間は auto のマージンに分け、auto のマージンがなければ justify-content に従って分ける
+pub fn place_main(
+    items: &[FlexItem],
+    sizes: &[i64],
+    available: i64,
+    gap: i64,
+    justify_content: JustifyContent,
+) -> Vec<i64> {
+    let gaps = gap * (items.len() as i64 - 1).max(0);
+    let used: i64 = items
+        .iter()
+        .zip(sizes)
+        .map(|(item, size)| size + item.margins())
+        .sum();
+    let free = available - gaps - used;
+    let auto_margins: i64 = items
+        .iter()
+        .map(|item| item.margin_start.is_none() as i64 + item.margin_end.is_none() as i64)
+        .sum();
+
+    let (auto_margin, (mut position, between)) = if free > 0 && auto_margins > 0 {
+        (free / auto_margins, (0, 0))
+    } else {
+        (0, justify_content.spacing(free, items.len()))
+    };
+    let mut positions = Vec::new();
+    for (item, size) in items.iter().zip(sizes) {
+        position += item.margin_start.unwrap_or(auto_margin);
+        positions.push(position);
+        position += size + item.margin_end.unwrap_or(auto_margin) + gap + between;
+    }
+    positions
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#align-content-property
+// ラインを交差軸の方向に並べる。lines は各ラインの交差軸の方向の大きさで、
+// 交差軸の始まりからの各ラインの位置と、引き伸ばしたあとの大きさを返す
+pub fn place_lines(
+    lines: &[i64],
+    available: i64,
+    gap: i64,
+    align_content: AlignContent,
+) -> Vec<(i64, i64)> {
+    let gaps = gap * (lines.len() as i64 - 1).max(0);
+    let free = available - gaps - lines.iter().sum::<i64>();
+    let (mut position, between, extra) = match align_content {
+        AlignContent::Stretch if free > 0 && !lines.is_empty() => (0, 0, free / lines.len() as i64),
+        align_content => {
+            let (leading, between) = align_content.distribution().spacing(free, lines.len());
+            (leading, between, 0)
+        }
+    };
+
+    lines
+        .iter()
+        .map(|size| {
+            let line = (position, size + extra);
+            position += size + extra + gap + between;
+            line
+        })
+        .collect()
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#cross-alignment
+// ラインの中でアイテムを交差軸の方向に揃えて、ラインの始まりからボーダーボックスまでの距離を返す。
+// free はラインの大きさから、auto を 0 としたマージンボックスの大きさを引いた残りで、
+// 余った空間は auto のマージンに分ける
+pub fn cross_offset(
+    align: AlignItems,
+    free: i64,
+    margin_start: Option<i64>,
+    margin_end: Option<i64>,
+) -> i64 {
+    let offset = match (margin_start, margin_end) {
+        (None, None) => free.max(0) / 2,
+        (None, Some(_)) => free.max(0),
+        (Some(_), None) => 0,
+        (Some(_), Some(_)) => match align {
+            AlignItems::FlexEnd => free,
+            AlignItems::Center => free / 2,
+            AlignItems::Stretch | AlignItems::FlexStart | AlignItems::Baseline => 0,
+        },
+    };
+    offset + margin_start.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn item(base_size: i64, grow: f64, shrink: f64) -> FlexItem {
+        FlexItem {
+            base_size,
+            min_size: 0,
+            max_size: None,
+            margin_start: Some(0),
+            margin_end: Some(0),
+            grow,
+            shrink,
+        }
+    }
+
+    fn sizes(lines: &[FlexLine]) -> Vec<Vec<i64>> {
+        lines.iter().map(|line| line.sizes.clone()).collect()
+    }
+
+    #[test]
+    fn test_grow_and_shrink() {
+        // 余った 100px を flex-grow の比で分ける
+        let items = [item(100, 1.0, 1.0), item(100, 3.0, 1.0)];
+        assert_eq!(
+            vec![vec![125, 175]],
+            sizes(&resolve_lines(&items, Some(300), 0, false))
+        );
+
+        // 足りない 100px を、flex-shrink と基準の大きさの積の比で縮める
+        let items = [item(100, 0.0, 1.0), item(300, 0.0, 1.0)];
+        assert_eq!(
+            vec![vec![75, 225]],
+            sizes(&resolve_lines(&items, Some(300), 0, false))
+        );
+
+        // 最大の大きさで止まったアイテムの残りは、ほかのアイテムに分ける
+        let mut limited = item(0, 1.0, 1.0);
+        limited.max_size = Some(50);
+        let items = [limited, item(0, 1.0, 1.0)];
+        assert_eq!(
+            vec![vec![50, 150]],
+            sizes(&resolve_lines(&items, Some(200), 0, false))
+        );
+
+        // 係数の和が 1 より小さいときは、余った空間の一部だけを分ける
+        let items = [item(0, 0.25, 1.0), item(0, 0.25, 1.0)];
+        assert_eq!(
+            vec![vec![50, 50]],
+            sizes(&resolve_lines(&items, Some(200), 0, false))
+        );
+
+        // 大きさが決まっていないコンテナでは、仮の大きさのまま並べる
+        let items = [item(100, 1.0, 1.0), item(300, 1.0, 1.0)];
+        assert_eq!(
+            vec![vec![100, 300]],
+            sizes(&resolve_lines(&items, None, 0, true))
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let items = [
+            item(100, 0.0, 1.0),
+            item(100, 0.0, 1.0),
+            item(100, 1.0, 1.0),
+            item(400, 0.0, 1.0),
+        ];
+        let lines = resolve_lines(&items, Some(250), 10, true);
+        assert_eq!(
+            vec![0..2, 2..3, 3..4],
+            lines
+                .iter()
+                .map(|line| line.items.clone())
+                .collect::<Vec<_>>()
+        );
+        // ラインごとに伸び縮みさせる。1つだけのアイテムは、収まらなくてもそのラインに置く
+        assert_eq!(vec![vec![100, 100], vec![250], vec![250]], sizes(&lines));
+    }
+
+    #[test]
+    fn test_place_main() {
+        let items = [item(50, 0.0, 1.0), item(50, 0.0, 1.0), item(50, 0.0, 1.0)];
+        let sizes = [50, 50, 50];
+        let place = |justify_content| place_main(&items, &sizes, 300, 0, justify_content);
+        assert_eq!(vec![0, 50, 100], place(JustifyContent::FlexStart));
+        assert_eq!(vec![150, 200, 250], place(JustifyContent::FlexEnd));
+        assert_eq!(vec![75, 125, 175], place(JustifyContent::Center));
+        assert_eq!(vec![0, 125, 250], place(JustifyContent::SpaceBetween));
+        assert_eq!(vec![25, 125, 225], place(JustifyContent::SpaceAround));
+        assert_eq!(vec![37, 124, 211], place(JustifyContent::SpaceEvenly));
+
+        // 余った空間は、justify-content より先に auto のマージンに分ける
+        let mut pushed = item(50, 0.0, 1.0);
+        pushed.margin_start = None;
+        let items = [item(50, 0.0, 1.0), pushed];
+        assert_eq!(
+            vec![0, 250],
+            place_main(&items, &[50, 50], 300, 0, JustifyContent::Center)
+        );
+
+        // 足りないときは、space-between は flex-start と同じになる
+        let items = [item(200, 0.0, 1.0), item(200, 0.0, 1.0)];
+        assert_eq!(
+            vec![0, 210],
+            place_main(&items, &[200, 200], 300, 10, JustifyContent::SpaceBetween)
+        );
+    }
+
+    #[test]
+    fn test_place_lines() {
+        let lines = [20, 40];
+        assert_eq!(
+            vec![(0, 40), (50, 60)],
+            place_lines(&lines, 110, 10, AlignContent::Stretch)
+        );
+        assert_eq!(
+            vec![(40, 20), (70, 40)],
+            place_lines(&lines, 110, 10, AlignContent::FlexEnd)
+        );
+        assert_eq!(
+            vec![(0, 20), (70, 40)],
+            place_lines(&lines, 110, 10, AlignContent::SpaceBetween)
+        );
+    }
+
+    #[test]
+    fn test_cross_offset() {
+        assert_eq!(5, cross_offset(AlignItems::FlexStart, 30, Some(5), Some(0)));
+        assert_eq!(35, cross_offset(AlignItems::FlexEnd, 30, Some(5), Some(0)));
+        assert_eq!(20, cross_offset(AlignItems::Center, 30, Some(5), Some(0)));
+        // auto のマージンは、align-self より優先する
+        assert_eq!(15, cross_offset(AlignItems::FlexEnd, 30, None, None));
+        assert_eq!(0, cross_offset(AlignItems::Center, -10, None, None));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(FlexDirection::ColumnReverse), "column-reverse".parse());
+        assert_eq!(Ok(JustifyContent::FlexStart), "normal".parse());
+        assert_eq!(Ok(AlignItems::Stretch), "normal".parse());
+        assert!("auto".parse::<AlignItems>().is_err());
+    }
+}
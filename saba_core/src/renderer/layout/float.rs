@@ -0,0 +1,220 @@
+use core::str::FromStr;
+
+use alloc::{format, vec::Vec};
+
+use crate::{
+    error::Error,
+    renderer::layout::layout_object::{LayoutPoint, LayoutRect},
+};
+
+// https://www.w3.org/TR/CSS2/visuren.html#float-position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Float {
+    None,
+    Left,
+    Right,
+}
+
+impl FromStr for Float {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            _ => Err(Error::UnexpectedInput(format!(
+                "float {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/CSS2/visuren.html#flow-control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clear {
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+impl Clear {
+    fn clears(&self, side: Float) -> bool {
+        matches!(
+            (self, side),
+            (Self::Both, _) | (Self::Left, Float::Left) | (Self::Right, Float::Right)
+        )
+    }
+}
+
+impl FromStr for Clear {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "both" => Ok(Self::Both),
+            _ => Err(Error::UnexpectedInput(format!(
+                "clear {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// ブロック整形文脈に置いたフロート。位置は、ブロック整形文脈を作るボックスの
+// コンテンツボックスの左上からの相対位置で表す
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FloatContext {
+    // フロートの側と、マージンボックス。置いた順に並ぶ
+    floats: Vec<(Float, LayoutRect)>,
+}
+
+impl FloatContext {
+    pub fn is_empty(&self) -> bool {
+        self.floats.is_empty()
+    }
+
+    // 原点を origin に動かしたフロート。子孫のブロックのコンテンツボックスから見た位置にする
+    pub fn translated(&self, origin: LayoutPoint) -> Self {
+        let floats = self
+            .floats
+            .iter()
+            .map(|(side, rect)| {
+                (
+                    *side,
+                    LayoutRect::new(
+                        rect.point.x - origin.x,
+                        rect.point.y - origin.y,
+                        rect.size.width,
+                        rect.size.height,
+                    ),
+                )
+            })
+            .collect();
+        Self { floats }
+    }
+
+    // top から bottom の範囲に重なるフロート
+    fn overlapping(&self, top: i64, bottom: i64) -> impl Iterator<Item = &(Float, LayoutRect)> {
+        self.floats.iter().filter(move |(_, rect)| {
+            rect.point.y < bottom.max(top + 1) && top < rect.point.y + rect.size.height
+        })
+    }
+
+    // top から bottom の範囲で、left から right の間のフロートに覆われていない範囲の左端と右端
+    pub fn free_space(&self, top: i64, bottom: i64, left: i64, right: i64) -> (i64, i64) {
+        let mut free = (left, right);
+        for (side, rect) in self.overlapping(top, bottom) {
+            match side {
+                Float::Left => free.0 = free.0.max(rect.point.x + rect.size.width),
+                Float::Right => free.1 = free.1.min(rect.point.x),
+                Float::None => {}
+            }
+        }
+        free
+    }
+
+    // top から bottom の範囲に重なるフロートの下端のうち、最も上のもの。
+    // 重なるフロートがなければ None になる
+    pub fn next_bottom(&self, top: i64, bottom: i64) -> Option<i64> {
+        self.overlapping(top, bottom)
+            .map(|(_, rect)| rect.point.y + rect.size.height)
+            .min()
+    }
+
+    // https://www.w3.org/TR/CSS2/visuren.html#float-rules
+    // 大きさが width と height のマージンボックスを、top より下で、left から right の間の
+    // できるだけ上の、side の側に寄せた位置に置き、その左上の位置を返す。
+    // 前に置いたフロートより上には置かない
+    pub fn place(
+        &mut self,
+        side: Float,
+        width: i64,
+        height: i64,
+        top: i64,
+        left: i64,
+        right: i64,
+    ) -> LayoutPoint {
+        let mut y = top.max(self.floats.last().map_or(top, |(_, rect)| rect.point.y));
+        loop {
+            let (free_left, free_right) = self.free_space(y, y + height, left, right);
+            // 収まらなくても、横にフロートがなければそこに置く
+            let fits = free_right - free_left >= width;
+            let next = self.next_bottom(y, y + height);
+            if fits || next.is_none() {
+                let x = match side {
+                    Float::Right => free_right - width,
+                    Float::Left | Float::None => free_left,
+                };
+                let point = LayoutPoint { x, y };
+                self.floats
+                    .push((side, LayoutRect::new(x, y, width, height)));
+                return point;
+            }
+            y = next.unwrap_or(y);
+        }
+    }
+
+    // clear で避けるフロートの下端。避けるフロートがなければ None になる
+    pub fn clearance(&self, clear: Clear) -> Option<i64> {
+        self.floats
+            .iter()
+            .filter(|(side, _)| clear.clears(*side))
+            .map(|(_, rect)| rect.point.y + rect.size.height)
+            .max()
+    }
+
+    // すべてのフロートの下端。ブロック整形文脈を作るボックスの高さは、フロートを含むように決める
+    pub fn bottom(&self) -> i64 {
+        self.clearance(Clear::Both).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place() {
+        let mut floats = FloatContext::default();
+        // 左右のフロートは、包含ブロックの端から順に並べる
+        assert_eq!(
+            LayoutPoint { x: 0, y: 0 },
+            floats.place(Float::Left, 40, 30, 0, 0, 100)
+        );
+        assert_eq!(
+            LayoutPoint { x: 40, y: 0 },
+            floats.place(Float::Left, 20, 10, 0, 0, 100)
+        );
+        assert_eq!(
+            LayoutPoint { x: 70, y: 0 },
+            floats.place(Float::Right, 30, 20, 0, 0, 100)
+        );
+        assert_eq!((60, 70), floats.free_space(0, 10, 0, 100));
+        assert_eq!((40, 70), floats.free_space(10, 20, 0, 100));
+        assert_eq!((0, 100), floats.free_space(30, 40, 0, 100));
+        assert_eq!(Some(10), floats.next_bottom(0, 20));
+
+        // 横に収まらないフロートは、収まるところまで下げる
+        assert_eq!(
+            LayoutPoint { x: 40, y: 20 },
+            floats.place(Float::Left, 50, 10, 0, 0, 100)
+        );
+        // 前のフロートより上には置かない
+        assert_eq!(
+            LayoutPoint { x: 90, y: 20 },
+            floats.place(Float::Right, 10, 10, 0, 0, 100)
+        );
+
+        assert_eq!(Some(30), floats.clearance(Clear::Left));
+        assert_eq!(Some(30), floats.clearance(Clear::Right));
+        assert_eq!(None, FloatContext::default().clearance(Clear::Both));
+        assert_eq!(30, floats.bottom());
+
+        let translated = floats.translated(LayoutPoint { x: 10, y: 5 });
+        assert_eq!((50, 60), translated.free_space(0, 5, -10, 90));
+    }
+}
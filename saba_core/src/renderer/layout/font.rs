@@ -0,0 +1,301 @@
+use core::{fmt::Debug, str::FromStr};
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{
+    constants::{CHAR_HEIGHT, CHAR_HEIGHT_WITH_PADDING, CHAR_WIDTH},
+    error::Error,
+    renderer::{
+        css::cssom::ComponentValue,
+        layout::{
+            east_asian_width::is_wide,
+            layout_object::font_size_ratio,
+            length::{LengthContext, LengthPercentage, clamp_length},
+        },
+    },
+};
+
+// 描画するときに使うフォントの情報。バックエンドはこれをもとに書体と行送りを選ぶ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Font {
+    // 優先度の高い順に並んだフォントファミリー
+    pub families: Vec<FontFamily>,
+    // px で表したフォントサイズ
+    pub size: f64,
+    pub weight: u16,
+    pub style: FontStyle,
+    // px で表した行の高さ
+    pub line_height: f64,
+}
+
+// フォントの縦方向の大きさ。どれも px で表し、ascent はベースラインより上、descent は下の高さ。
+// line-height が normal の行の高さは、3つを足したものになる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerticalMetrics {
+    pub ascent: i64,
+    pub descent: i64,
+    pub line_gap: i64,
+}
+
+// テキストを描画するバックエンドが、文字の送り幅とフォントの縦方向の大きさを提供する。
+// レイアウトはこの値をもとに、テキストの幅と行の高さを決める
+pub trait FontMetrics: Debug {
+    // c を font で描画したときの送り幅
+    fn advance(&self, font: &Font, c: char) -> i64;
+
+    fn vertical_metrics(&self, font: &Font) -> VerticalMetrics;
+}
+
+// 本書のブラウザのフォント。全角の文字は半角の文字の2倍の幅で描画する等幅フォントで、
+// フォントサイズに応じて1倍から3倍に拡大して描画する。文字の高さの 3/4 がベースラインより上にある
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedWidthFontMetrics;
+
+impl FontMetrics for FixedWidthFontMetrics {
+    fn advance(&self, font: &Font, c: char) -> i64 {
+        let width = CHAR_WIDTH * font_size_ratio(font.size);
+        if is_wide(c) { width * 2 } else { width }
+    }
+
+    fn vertical_metrics(&self, font: &Font) -> VerticalMetrics {
+        let ratio = font_size_ratio(font.size);
+        VerticalMetrics {
+            ascent: CHAR_HEIGHT * ratio * 3 / 4,
+            descent: CHAR_HEIGHT * ratio / 4,
+            line_gap: (CHAR_HEIGHT_WITH_PADDING - CHAR_HEIGHT) * ratio,
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-fonts-4/#font-family-prop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontFamily {
+    Named(String),
+    Generic(GenericFontFamily),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenericFontFamily {
+    Serif,
+    SansSerif,
+    Monospace,
+    Cursive,
+    Fantasy,
+    SystemUi,
+}
+
+impl FromStr for GenericFontFamily {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "serif" => Ok(Self::Serif),
+            "sans-serif" => Ok(Self::SansSerif),
+            "monospace" => Ok(Self::Monospace),
+            "cursive" => Ok(Self::Cursive),
+            "fantasy" => Ok(Self::Fantasy),
+            "system-ui" => Ok(Self::SystemUi),
+            _ => Err(Error::UnexpectedInput(format!(
+                "generic font family {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// カンマで区切られたフォントファミリーのリストをパースする。
+// 引用符で囲まれていない名前は、空白で区切られた複数の識別子を1つの名前として扱う
+pub fn parse_font_family(value: &[ComponentValue]) -> Option<Vec<FontFamily>> {
+    let mut families = Vec::new();
+
+    for family in value.split(|v| *v == ComponentValue::Delim(',')) {
+        let family = match family {
+            [ComponentValue::StringToken(name)] => FontFamily::Named(name.clone()),
+            [ComponentValue::Ident(name)] => match GenericFontFamily::from_str(name) {
+                Ok(generic) => FontFamily::Generic(generic),
+                Err(_) => FontFamily::Named(name.clone()),
+            },
+            [_, _, ..] => {
+                let names = family
+                    .iter()
+                    .map(|v| match v {
+                        ComponentValue::Ident(name) => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                FontFamily::Named(names.join(" "))
+            }
+            _ => return None,
+        };
+        families.push(family);
+    }
+
+    Some(families)
+}
+
+// https://www.w3.org/TR/css-fonts-4/#font-style-prop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl FromStr for FontStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            "italic" => Ok(Self::Italic),
+            "oblique" => Ok(Self::Oblique),
+            _ => Err(Error::UnexpectedInput(format!(
+                "font-style {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-fonts-4/#font-weight-prop
+// 指定値の font-weight。bolder と lighter は親の太さから計算値を求める
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontWeight {
+    Absolute(u16),
+    Bolder,
+    Lighter,
+}
+
+impl FontWeight {
+    pub fn parse(value: &[ComponentValue]) -> Option<Self> {
+        match value {
+            [ComponentValue::Ident(keyword)] => match keyword.to_ascii_lowercase().as_str() {
+                "normal" => Some(Self::Absolute(400)),
+                "bold" => Some(Self::Absolute(700)),
+                "bolder" => Some(Self::Bolder),
+                "lighter" => Some(Self::Lighter),
+                _ => None,
+            },
+            [ComponentValue::Number(n)] if (1.0..=1000.0).contains(n) => {
+                Some(Self::Absolute((n + 0.5) as u16))
+            }
+            _ => None,
+        }
+    }
+
+    // https://www.w3.org/TR/css-fonts-4/#relative-weights
+    pub fn compute(&self, parent_weight: u16) -> u16 {
+        match self {
+            Self::Absolute(weight) => *weight,
+            Self::Bolder => match parent_weight {
+                0..350 => 400,
+                350..550 => 700,
+                550..900 => 900,
+                _ => parent_weight,
+            },
+            Self::Lighter => match parent_weight {
+                0..100 => parent_weight,
+                100..550 => 100,
+                550..750 => 400,
+                _ => 700,
+            },
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-inline-3/#line-height-property
+// 数値の line-height は、子孫のフォントサイズに合わせられるように数値のまま継承する
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineHeight {
+    Normal,
+    Number(f64),
+    // px で表した長さ
+    Length(f64),
+}
+
+impl LineHeight {
+    pub fn parse(value: &[ComponentValue], context: &LengthContext) -> Option<Self> {
+        match value {
+            [ComponentValue::Ident(keyword)] if keyword.eq_ignore_ascii_case("normal") => {
+                Some(Self::Normal)
+            }
+            [ComponentValue::Number(n)] if *n >= 0.0 => Some(Self::Number(*n)),
+            _ => {
+                // パーセンテージは要素自身のフォントサイズに対する割合
+                let length = LengthPercentage::parse_non_negative(value).ok()?;
+                let px = length.compute(context).resolve(context.font_size);
+                Some(Self::Length(px.max(0.0)))
+            }
+        }
+    }
+
+    // normal のときはフォントサイズの 1.2 倍にする
+    pub fn resolve(&self, font_size: f64) -> f64 {
+        match self {
+            Self::Normal => font_size * 1.2,
+            Self::Number(n) => clamp_length(font_size * n),
+            Self::Length(px) => *px,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use crate::renderer::css::{cssom::CssParser, media::Viewport, token::CssTokenizer};
+
+    use super::*;
+
+    fn value(css: &str) -> Vec<ComponentValue> {
+        CssParser::new(CssTokenizer::new(format!("x: {css}")))
+            .parse_declaration_list()
+            .remove(0)
+            .value
+    }
+
+    #[test]
+    fn test_font_family() {
+        assert_eq!(
+            Some(vec![
+                FontFamily::Named("Times New Roman".to_string()),
+                FontFamily::Named("serif".to_string()),
+                FontFamily::Generic(GenericFontFamily::Monospace),
+            ]),
+            parse_font_family(&value(r#"Times New Roman, "serif", monospace"#))
+        );
+        assert_eq!(None, parse_font_family(&value("Arial, , serif")));
+        assert_eq!(None, parse_font_family(&value("12px")));
+    }
+
+    #[test]
+    fn test_font_weight() {
+        assert_eq!(
+            Some(FontWeight::Absolute(700)),
+            FontWeight::parse(&value("bold"))
+        );
+        assert_eq!(
+            Some(FontWeight::Absolute(350)),
+            FontWeight::parse(&value("350"))
+        );
+        assert_eq!(None, FontWeight::parse(&value("0")));
+
+        assert_eq!(700, FontWeight::Bolder.compute(400));
+        assert_eq!(900, FontWeight::Bolder.compute(700));
+        assert_eq!(100, FontWeight::Lighter.compute(400));
+        assert_eq!(700, FontWeight::Lighter.compute(900));
+    }
+
+    #[test]
+    fn test_line_height() {
+        let context = LengthContext::new(20.0, 16.0, &Viewport::default());
+        let line_height = |css: &str| LineHeight::parse(&value(css), &context);
+
+        assert_eq!(Some(LineHeight::Normal), line_height("normal"));
+        assert_eq!(Some(LineHeight::Number(1.5)), line_height("1.5"));
+        assert_eq!(Some(LineHeight::Length(30.0)), line_height("150%"));
+        assert_eq!(Some(LineHeight::Length(40.0)), line_height("2em"));
+        assert_eq!(None, line_height("-1"));
+
+        assert_eq!(24.0, LineHeight::Normal.resolve(20.0));
+        assert_eq!(30.0, LineHeight::Number(1.5).resolve(20.0));
+    }
+}
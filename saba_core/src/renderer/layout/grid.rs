@@ -0,0 +1,1358 @@
+use core::ops::Range;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::renderer::{
+    css::{cssom::ComponentValue, shorthand::split_components},
+    layout::{
+        computed_style::ComputedStyle,
+        length::{ComputedLength, LengthContext, LengthPercentage},
+    },
+};
+
+// 大きすぎる repeat() の繰り返しは、この数で打ち切る
+const MAX_REPETITIONS: usize = 10000;
+
+// https://www.w3.org/TR/css-grid-1/#typedef-track-breadth
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackBreadth {
+    Length(ComputedLength),
+    // fr 単位の、余った空間を分ける割合
+    Flex(f64),
+    MinContent,
+    MaxContent,
+    Auto,
+}
+
+impl TrackBreadth {
+    fn parse(value: &[ComponentValue], context: &LengthContext) -> Option<Self> {
+        match value {
+            [ComponentValue::Ident(keyword)] => match keyword.to_ascii_lowercase().as_str() {
+                "auto" => Some(Self::Auto),
+                "min-content" => Some(Self::MinContent),
+                "max-content" => Some(Self::MaxContent),
+                _ => None,
+            },
+            [ComponentValue::Dimension(n, unit)] if unit.eq_ignore_ascii_case("fr") => {
+                (*n >= 0.0).then_some(Self::Flex(*n))
+            }
+            _ => {
+                let length = LengthPercentage::parse_non_negative(value).ok()?;
+                Some(Self::Length(length.compute(context)))
+            }
+        }
+    }
+
+    // 中身に依存しないで決まる大きさ。basis はパーセンテージの基準で、決まっていないときの
+    // パーセンテージは auto として扱う
+    fn fixed(&self, basis: Option<i64>) -> Option<f64> {
+        match self {
+            Self::Length(length) if !length.has_percentage() || basis.is_some() => {
+                Some(length.resolve(basis.unwrap_or(0) as f64).max(0.0))
+            }
+            _ => None,
+        }
+    }
+
+    fn flex(&self) -> Option<f64> {
+        match self {
+            Self::Flex(flex) => Some(*flex),
+            _ => None,
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-grid-1/#typedef-track-size
+// トラックの最小と最大の大きさ。minmax() でない値は、最小と最大に同じ値を使う。
+// ただし、fr は最小の大きさにはならず、auto になる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackSize {
+    pub min: TrackBreadth,
+    pub max: TrackBreadth,
+}
+
+impl Default for TrackSize {
+    fn default() -> Self {
+        Self {
+            min: TrackBreadth::Auto,
+            max: TrackBreadth::Auto,
+        }
+    }
+}
+
+impl TrackSize {
+    pub fn parse(value: &[ComponentValue], context: &LengthContext) -> Option<Self> {
+        if let [
+            ComponentValue::Function(function),
+            arguments @ ..,
+            ComponentValue::CloseParenthesis,
+        ] = value
+            && function.eq_ignore_ascii_case("minmax")
+        {
+            let comma = arguments
+                .iter()
+                .position(|t| *t == ComponentValue::Delim(','))?;
+            let min = TrackBreadth::parse(&arguments[..comma], context)?;
+            let max = TrackBreadth::parse(&arguments[comma + 1..], context)?;
+            if min.flex().is_some() {
+                return None;
+            }
+            return Some(Self { min, max });
+        }
+
+        let breadth = TrackBreadth::parse(value, context)?;
+        let min = if breadth.flex().is_some() {
+            TrackBreadth::Auto
+        } else {
+            breadth
+        };
+        Some(Self { min, max: breadth })
+    }
+
+    // repeat(auto-fill) の繰り返しの数を決めるときの大きさ。最大の大きさが決まっていなければ、最小の大きさを使う
+    fn fixed(&self, basis: Option<i64>) -> Option<f64> {
+        self.max.fixed(basis).or_else(|| self.min.fixed(basis))
+    }
+}
+
+// https://www.w3.org/TR/css-grid-1/#track-sizing
+// grid-template-columns と grid-template-rows の値。トラックがないものは none を表す
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrackList {
+    pub tracks: Vec<TrackSize>,
+    // それぞれの線の名前。トラックがあるときは tracks より1つ多く、収まるだけ繰り返すトラックが
+    // あるときは、その前と後ろの線の名前を分けて持つので2つ多い
+    pub line_names: Vec<Vec<String>>,
+    pub auto_repeat: Option<AutoRepeat>,
+}
+
+// https://www.w3.org/TR/css-grid-1/#auto-repeat
+// repeat(auto-fill, ...) と repeat(auto-fit, ...) で、コンテナに収まるだけ繰り返すトラック
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoRepeat {
+    // 繰り返すトラックを入れる位置。この番号のトラックの前に入る
+    pub index: usize,
+    pub tracks: Vec<TrackSize>,
+    pub line_names: Vec<Vec<String>>,
+    // auto-fit では、アイテムのないトラックをなくす
+    pub fit: bool,
+}
+
+impl TrackList {
+    pub fn parse(value: &[ComponentValue], context: &LengthContext) -> Option<Self> {
+        if let [ComponentValue::Ident(keyword)] = value
+            && keyword.eq_ignore_ascii_case("none")
+        {
+            return Some(Self::default());
+        }
+        let list = Self::parse_tracks(value, context, true)?;
+        (!list.tracks.is_empty() || list.auto_repeat.is_some()).then_some(list)
+    }
+
+    // トラックの大きさと、[ ] で囲んだ線の名前の並びをパースする。repeat() の中では repeat() を使えない
+    fn parse_tracks(
+        value: &[ComponentValue],
+        context: &LengthContext,
+        allow_repeat: bool,
+    ) -> Option<Self> {
+        let mut list = Self::empty();
+        let components = split_components(value);
+        let mut i = 0;
+        while i < components.len() {
+            match components[i] {
+                [ComponentValue::Delim('[')] => loop {
+                    i += 1;
+                    match components.get(i)? {
+                        [ComponentValue::Delim(']')] => break,
+                        [ComponentValue::Ident(name)] => {
+                            list.line_names.last_mut()?.push(name.clone());
+                        }
+                        _ => return None,
+                    }
+                },
+                [
+                    ComponentValue::Function(function),
+                    arguments @ ..,
+                    ComponentValue::CloseParenthesis,
+                ] if function.eq_ignore_ascii_case("repeat") => {
+                    if !allow_repeat {
+                        return None;
+                    }
+                    let comma = arguments
+                        .iter()
+                        .position(|t| *t == ComponentValue::Delim(','))?;
+                    let repeated = Self::parse_tracks(&arguments[comma + 1..], context, false)?;
+                    if repeated.tracks.is_empty() {
+                        return None;
+                    }
+                    match &arguments[..comma] {
+                        [ComponentValue::Number(n)] if *n >= 1.0 && *n == (*n as i64) as f64 => {
+                            for _ in 0..(*n as usize).min(MAX_REPETITIONS) {
+                                list.append(&repeated);
+                            }
+                        }
+                        // 収まるだけ繰り返すトラックは、中身に依存しない大きさでなければならない
+                        [ComponentValue::Ident(keyword)]
+                            if list.auto_repeat.is_none()
+                                && repeated.tracks.iter().all(|t| t.fixed(Some(0)).is_some()) =>
+                        {
+                            let fit = match keyword.to_ascii_lowercase().as_str() {
+                                "auto-fill" => false,
+                                "auto-fit" => true,
+                                _ => return None,
+                            };
+                            list.auto_repeat = Some(AutoRepeat {
+                                index: list.tracks.len(),
+                                tracks: repeated.tracks,
+                                line_names: repeated.line_names,
+                                fit,
+                            });
+                            list.line_names.push(Vec::new());
+                        }
+                        _ => return None,
+                    }
+                }
+                component => {
+                    list.tracks.push(TrackSize::parse(component, context)?);
+                    list.line_names.push(Vec::new());
+                }
+            }
+            i += 1;
+        }
+        Some(list)
+    }
+
+    fn empty() -> Self {
+        Self {
+            tracks: Vec::new(),
+            line_names: vec![Vec::new()],
+            auto_repeat: None,
+        }
+    }
+
+    // 後ろにトラックを足す。つなぎ目の線の名前は、両方の名前を持つ
+    fn append(&mut self, other: &TrackList) {
+        if let Some((first, rest)) = other.line_names.split_first() {
+            if let Some(last) = self.line_names.last_mut() {
+                last.extend(first.iter().cloned());
+            }
+            self.line_names.extend(rest.iter().cloned());
+        }
+        self.tracks.extend(other.tracks.iter().copied());
+    }
+
+    // https://www.w3.org/TR/css-grid-1/#auto-repeat
+    // 収まるだけ繰り返すトラックを繰り返す数。available はグリッドコンテナのコンテンツボックスの大きさで、
+    // 決まっていないときは1回になる
+    pub fn repetitions(&self, available: Option<i64>, gap: i64) -> usize {
+        let Some(repeat) = &self.auto_repeat else {
+            return 0;
+        };
+        let Some(available) = available else {
+            return 1;
+        };
+        let size = |tracks: &[TrackSize]| -> f64 {
+            tracks
+                .iter()
+                .map(|t| t.fixed(Some(available)).unwrap_or(0.0) + gap as f64)
+                .sum()
+        };
+        let repeated = size(&repeat.tracks);
+        if repeated <= 0.0 {
+            return 1;
+        }
+        let count = (available + gap) as f64 - size(&self.tracks);
+        ((count / repeated).max(1.0) as usize).min(MAX_REPETITIONS)
+    }
+
+    // 収まるだけ繰り返すトラックを count 回繰り返したトラックの並び
+    pub fn expand(&self, count: usize) -> TrackList {
+        let mut list = Self::empty();
+        if self.tracks.is_empty() && self.auto_repeat.is_none() {
+            return list;
+        }
+        let Some(repeat) = &self.auto_repeat else {
+            list.append(self);
+            return list;
+        };
+        list.append(&TrackList {
+            tracks: self.tracks[..repeat.index].to_vec(),
+            line_names: self.line_names[..=repeat.index].to_vec(),
+            auto_repeat: None,
+        });
+        let repeated = TrackList {
+            tracks: repeat.tracks.clone(),
+            line_names: repeat.line_names.clone(),
+            auto_repeat: None,
+        };
+        for _ in 0..count {
+            list.append(&repeated);
+        }
+        list.append(&TrackList {
+            tracks: self.tracks[repeat.index..].to_vec(),
+            line_names: self.line_names[repeat.index + 1..].to_vec(),
+            auto_repeat: None,
+        });
+        list
+    }
+}
+
+// https://www.w3.org/TR/css-grid-1/#grid-template-areas-property
+// 名前のついた領域。値が none のときは領域がない
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GridTemplateAreas {
+    pub rows: usize,
+    pub columns: usize,
+    pub areas: Vec<GridArea>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridArea {
+    pub name: String,
+    pub rows: Range<usize>,
+    pub columns: Range<usize>,
+}
+
+impl GridTemplateAreas {
+    // 文字列ごとに1つの行になる。同じ名前のセルは長方形に並んでいなければならない
+    pub fn parse(value: &[ComponentValue]) -> Option<Self> {
+        if let [ComponentValue::Ident(keyword)] = value
+            && keyword.eq_ignore_ascii_case("none")
+        {
+            return Some(Self::default());
+        }
+
+        let mut cells = Vec::new();
+        for token in value {
+            let ComponentValue::StringToken(row) = token else {
+                return None;
+            };
+            cells.push(Self::cells(row)?);
+        }
+        let columns = cells.first()?.len();
+        if columns == 0 || cells.iter().any(|row| row.len() != columns) {
+            return None;
+        }
+
+        let mut areas: Vec<GridArea> = Vec::new();
+        for (r, row) in cells.iter().enumerate() {
+            for (c, name) in row.iter().enumerate() {
+                let Some(name) = name else {
+                    continue;
+                };
+                match areas.iter_mut().find(|a| a.name == *name) {
+                    Some(area) => {
+                        area.rows.end = area.rows.end.max(r + 1);
+                        area.columns.start = area.columns.start.min(c);
+                        area.columns.end = area.columns.end.max(c + 1);
+                    }
+                    None => areas.push(GridArea {
+                        name: name.clone(),
+                        rows: r..r + 1,
+                        columns: c..c + 1,
+                    }),
+                }
+            }
+        }
+        for area in &areas {
+            let is_area = |n: &Option<String>| n.as_deref() == Some(area.name.as_str());
+            let filled = cells[area.rows.clone()]
+                .iter()
+                .all(|row| row[area.columns.clone()].iter().all(is_area));
+            let count: usize = cells
+                .iter()
+                .map(|row| row.iter().filter(|n| is_area(n)).count())
+                .sum();
+            if !filled || count != area.rows.len() * area.columns.len() {
+                return None;
+            }
+        }
+
+        Some(Self {
+            rows: cells.len(),
+            columns,
+            areas,
+        })
+    }
+
+    // 文字列をセルの名前に分ける。. の並びは名前のないセルになる
+    fn cells(row: &str) -> Option<Vec<Option<String>>> {
+        let is_name = |c: char| c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii();
+        let mut cells = Vec::new();
+        for mut rest in row.split_whitespace() {
+            while let Some(c) = rest.chars().next() {
+                let is_dot = c == '.';
+                let end = rest
+                    .find(|c: char| (c == '.') != is_dot)
+                    .unwrap_or(rest.len());
+                let (token, tail) = rest.split_at(end);
+                if is_dot {
+                    cells.push(None);
+                } else if token.chars().all(is_name) {
+                    cells.push(Some(token.to_string()));
+                } else {
+                    return None;
+                }
+                rest = tail;
+            }
+        }
+        Some(cells)
+    }
+}
+
+// https://www.w3.org/TR/css-grid-1/#typedef-grid-row-start-grid-line
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum GridLine {
+    #[default]
+    Auto,
+    // n 番目の線。名前があるときは、その名前の線の中で数える。負の数は終わりの側から数える
+    Line(i64, Option<String>),
+    // 名前だけを指定した線。領域の名前のときは、領域の端の線になる
+    Named(String),
+    // 反対側の線から数えたトラックの数
+    Span(i64),
+}
+
+impl GridLine {
+    pub fn parse(value: &[ComponentValue]) -> Option<Self> {
+        let integer = |n: f64| (n == (n as i64) as f64).then_some(n as i64);
+        let is_name =
+            |name: &str| !name.eq_ignore_ascii_case("span") && !name.eq_ignore_ascii_case("auto");
+        match value {
+            [ComponentValue::Ident(keyword)] if keyword.eq_ignore_ascii_case("auto") => {
+                Some(Self::Auto)
+            }
+            [ComponentValue::Ident(name)] if is_name(name) => Some(Self::Named(name.clone())),
+            [ComponentValue::Number(n)] => {
+                integer(*n).filter(|n| *n != 0).map(|n| Self::Line(n, None))
+            }
+            [ComponentValue::Number(n), ComponentValue::Ident(name)]
+            | [ComponentValue::Ident(name), ComponentValue::Number(n)]
+                if is_name(name) =>
+            {
+                integer(*n)
+                    .filter(|n| *n != 0)
+                    .map(|n| Self::Line(n, Some(name.clone())))
+            }
+            [ComponentValue::Ident(span), ComponentValue::Number(n)]
+            | [ComponentValue::Number(n), ComponentValue::Ident(span)]
+                if span.eq_ignore_ascii_case("span") =>
+            {
+                integer(*n).filter(|n| *n > 0).map(Self::Span)
+            }
+            _ => None,
+        }
+    }
+
+    fn span(&self) -> Option<i64> {
+        match self {
+            Self::Span(span) => Some(*span),
+            _ => None,
+        }
+    }
+
+    // 明示的なグリッドの最初の線を 0 とした、線の位置。auto と span は None になる。
+    // names はそれぞれの線の名前。指定した名前の線が足りないときは、明示的なグリッドの外の
+    // 暗黙の線がすべてその名前を持つとみなす
+    fn resolve(&self, names: &[Vec<String>], is_start: bool) -> Option<i64> {
+        let last = names.len() as i64 - 1;
+        let named = |name: &str| -> Vec<i64> {
+            (0..names.len())
+                .filter(|i| names[*i].iter().any(|n| n == name))
+                .map(|i| i as i64)
+                .collect()
+        };
+        let nth = |n: i64, name: &str| {
+            let lines = named(name);
+            let count = lines.len() as i64;
+            if n > 0 {
+                lines
+                    .get(n as usize - 1)
+                    .copied()
+                    .unwrap_or(last + n - count)
+            } else if -n <= count {
+                lines[(count + n) as usize]
+            } else {
+                count + n
+            }
+        };
+        match self {
+            Self::Auto | Self::Span(_) => None,
+            Self::Line(n, None) if *n > 0 => Some(n - 1),
+            Self::Line(n, None) => Some(last + 1 + n),
+            Self::Line(n, Some(name)) => Some(nth(*n, name)),
+            // 領域の名前は、-start と -end のついた名前の線になる
+            Self::Named(name) => {
+                let suffix = if is_start { "-start" } else { "-end" };
+                let area_line = named(&format!("{name}{suffix}")).first().copied();
+                Some(area_line.unwrap_or_else(|| nth(1, name)))
+            }
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-grid-1/#grid-auto-flow-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GridAutoFlow {
+    // 行ではなく列を埋めていくかどうか
+    pub column: bool,
+    // 前にあいたセルを埋めるかどうか
+    pub dense: bool,
+}
+
+impl GridAutoFlow {
+    pub fn parse(value: &[ComponentValue]) -> Option<Self> {
+        let mut direction = None;
+        let mut dense = false;
+        for token in value {
+            let ComponentValue::Ident(keyword) = token else {
+                return None;
+            };
+            match keyword.to_ascii_lowercase().as_str() {
+                "row" if direction.is_none() => direction = Some(false),
+                "column" if direction.is_none() => direction = Some(true),
+                "dense" if !dense => dense = true,
+                _ => return None,
+            }
+        }
+        if value.is_empty() {
+            return None;
+        }
+        Some(Self {
+            column: direction.unwrap_or(false),
+            dense,
+        })
+    }
+}
+
+// アイテムの grid-row-start、grid-column-start、grid-row-end、grid-column-end の値
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GridItemLines {
+    pub row_start: GridLine,
+    pub column_start: GridLine,
+    pub row_end: GridLine,
+    pub column_end: GridLine,
+}
+
+// アイテムを置いた行と列の範囲。トラックの番号は、明示的なグリッドより前の暗黙のトラックを含む
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridPlacement {
+    pub rows: Range<usize>,
+    pub columns: Range<usize>,
+}
+
+// アイテムを置いたグリッド。areas はアイテムの順に並ぶ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    pub rows: Vec<TrackSize>,
+    pub columns: Vec<TrackSize>,
+    pub areas: Vec<GridPlacement>,
+}
+
+// 1つの軸のアイテムの位置。Definite の線は、明示的なグリッドの最初の線を 0 とする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AxisPlacement {
+    Definite(i64, i64),
+    Auto(i64),
+}
+
+impl AxisPlacement {
+    // https://www.w3.org/TR/css-grid-1/#grid-placement-errors
+    // 始まりと終わりの線が逆のときは入れ替え、同じときは終わりの線を1つ後ろにする
+    fn resolve(start: &GridLine, end: &GridLine, names: &[Vec<String>]) -> Self {
+        match (start.resolve(names, true), end.resolve(names, false)) {
+            (Some(s), Some(e)) if s == e => Self::Definite(s, s + 1),
+            (Some(s), Some(e)) => Self::Definite(s.min(e), s.max(e)),
+            (Some(s), None) => Self::Definite(s, s + end.span().unwrap_or(1)),
+            (None, Some(e)) => Self::Definite(e - start.span().unwrap_or(1), e),
+            (None, None) => Self::Auto(start.span().or(end.span()).unwrap_or(1)),
+        }
+    }
+
+    fn span(&self) -> usize {
+        match self {
+            Self::Definite(start, end) => (end - start) as usize,
+            Self::Auto(span) => *span as usize,
+        }
+    }
+
+    // 前にできた暗黙のトラックの数 offset を足した範囲
+    fn shifted(&self, offset: usize) -> Option<Range<usize>> {
+        match self {
+            Self::Definite(start, end) => {
+                Some((start + offset as i64) as usize..(end + offset as i64) as usize)
+            }
+            Self::Auto(_) => None,
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-grid-1/#layout-algorithm
+// グリッドコンテナの明示的なグリッドを決めて、アイテムを置く。available はコンテナの
+// コンテンツボックスの横幅と高さで、gap は行の間と列の間の空間
+pub fn build_grid(
+    style: &ComputedStyle,
+    items: &[GridItemLines],
+    available: (Option<i64>, Option<i64>),
+    (row_gap, column_gap): (i64, i64),
+) -> Grid {
+    let areas = style.grid_template_areas();
+    let (template_rows, template_columns) =
+        (style.grid_template_rows(), style.grid_template_columns());
+    let row_count = template_rows.repetitions(available.1, row_gap);
+    let column_count = template_columns.repetitions(available.0, column_gap);
+    let mut rows = template_rows.expand(row_count);
+    let mut columns = template_columns.expand(column_count);
+
+    // https://www.w3.org/TR/css-grid-1/#implicit-named-lines
+    // 領域の名前は、領域の端の線に -start と -end をつけた名前を与える
+    for area in &areas.areas {
+        for (list, range) in [(&mut rows, &area.rows), (&mut columns, &area.columns)] {
+            if list.line_names.len() <= range.end {
+                list.line_names.resize(range.end + 1, Vec::new());
+            }
+            list.line_names[range.start].push(format!("{}-start", area.name));
+            list.line_names[range.end].push(format!("{}-end", area.name));
+        }
+    }
+
+    let row_placements: Vec<AxisPlacement> = items
+        .iter()
+        .map(|i| AxisPlacement::resolve(&i.row_start, &i.row_end, &rows.line_names))
+        .collect();
+    let column_placements: Vec<AxisPlacement> = items
+        .iter()
+        .map(|i| AxisPlacement::resolve(&i.column_start, &i.column_end, &columns.line_names))
+        .collect();
+    let explicit = (rows.line_names.len() - 1, columns.line_names.len() - 1);
+    let flow = style.grid_auto_flow();
+    let (mut areas, offsets, counts) = if flow.column {
+        let (areas, (column_offset, row_offset), (column_total, row_total)) = place_items(
+            &column_placements,
+            &row_placements,
+            (explicit.1, explicit.0),
+            flow.dense,
+        );
+        let areas = areas
+            .into_iter()
+            .map(|(columns, rows)| GridPlacement { rows, columns })
+            .collect::<Vec<_>>();
+        (
+            areas,
+            (row_offset, column_offset),
+            (row_total, column_total),
+        )
+    } else {
+        let (areas, offsets, counts) =
+            place_items(&row_placements, &column_placements, explicit, flow.dense);
+        let areas = areas
+            .into_iter()
+            .map(|(rows, columns)| GridPlacement { rows, columns })
+            .collect::<Vec<_>>();
+        (areas, offsets, counts)
+    };
+
+    // 明示的なグリッドの外のトラックと、テンプレートで指定していないトラックは、grid-auto-rows と
+    // grid-auto-columns の大きさになる
+    let tracks = |list: &TrackList, offset: usize, count: usize, auto: TrackSize| {
+        (0..count)
+            .map(|i| {
+                i.checked_sub(offset)
+                    .and_then(|i| list.tracks.get(i))
+                    .copied()
+                    .unwrap_or(auto)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut row_tracks = tracks(&rows, offsets.0, counts.0, style.grid_auto_rows());
+    let mut column_tracks = tracks(&columns, offsets.1, counts.1, style.grid_auto_columns());
+
+    // repeat(auto-fit) で繰り返したトラックのうち、アイテムのないトラックはなくす
+    if let Some(repeat) = &template_rows.auto_repeat
+        && repeat.fit
+    {
+        let start = offsets.0 + repeat.index;
+        let repeated = start..start + row_count * repeat.tracks.len();
+        let mut spans: Vec<Range<usize>> = areas.iter().map(|a| a.rows.clone()).collect();
+        collapse_empty_tracks(&mut row_tracks, repeated, &mut spans);
+        for (area, span) in areas.iter_mut().zip(spans) {
+            area.rows = span;
+        }
+    }
+    if let Some(repeat) = &template_columns.auto_repeat
+        && repeat.fit
+    {
+        let start = offsets.1 + repeat.index;
+        let repeated = start..start + column_count * repeat.tracks.len();
+        let mut spans: Vec<Range<usize>> = areas.iter().map(|a| a.columns.clone()).collect();
+        collapse_empty_tracks(&mut column_tracks, repeated, &mut spans);
+        for (area, span) in areas.iter_mut().zip(spans) {
+            area.columns = span;
+        }
+    }
+
+    Grid {
+        rows: row_tracks,
+        columns: column_tracks,
+        areas,
+    }
+}
+
+// 2つの軸の、トラックの範囲
+type Area = (Range<usize>, Range<usize>);
+
+// https://www.w3.org/TR/css-grid-1/#auto-placement-algo
+// アイテムをグリッドに置く。primary はアイテムを埋めていくにつれて増えていく軸で、行を埋めていくときは
+// 行、列を埋めていくときは列になる。explicit は2つの軸の明示的なトラックの数。
+// アイテムの範囲と、明示的なグリッドより前にできた暗黙のトラックの数と、トラックの数を返す
+fn place_items(
+    primary: &[AxisPlacement],
+    secondary: &[AxisPlacement],
+    (primary_explicit, secondary_explicit): (usize, usize),
+    dense: bool,
+) -> (Vec<Area>, (usize, usize), (usize, usize)) {
+    let offset = |placements: &[AxisPlacement]| {
+        placements
+            .iter()
+            .filter_map(|p| match p {
+                AxisPlacement::Definite(start, _) => Some(-start),
+                AxisPlacement::Auto(_) => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .max(0) as usize
+    };
+    let (primary_offset, secondary_offset) = (offset(primary), offset(secondary));
+    let mut secondary_count = secondary
+        .iter()
+        .map(|p| match p.shifted(secondary_offset) {
+            Some(range) => range.end,
+            None => p.span(),
+        })
+        .fold(secondary_offset + secondary_explicit, usize::max);
+
+    let mut placed: Vec<Option<Area>> = vec![None; primary.len()];
+    let overlaps = |placed: &[Option<Area>], p: &Range<usize>, s: &Range<usize>| {
+        placed.iter().flatten().any(|(pp, ss)| {
+            pp.start < p.end && p.start < pp.end && ss.start < s.end && s.start < ss.end
+        })
+    };
+
+    // 両方の軸の位置が決まっているアイテムを置く
+    for i in 0..primary.len() {
+        if let (Some(p), Some(s)) = (
+            primary[i].shifted(primary_offset),
+            secondary[i].shifted(secondary_offset),
+        ) {
+            placed[i] = Some((p, s));
+        }
+    }
+
+    // 埋めていく軸の位置だけが決まっているアイテムを、その行の空いているところに置く。
+    // dense でないときは、同じ行に前に置いたアイテムより後ろに置く
+    let mut cursors: Vec<(usize, usize)> = Vec::new();
+    for i in 0..primary.len() {
+        let (Some(p), None) = (
+            primary[i].shifted(primary_offset),
+            secondary[i].shifted(secondary_offset),
+        ) else {
+            continue;
+        };
+        let span = secondary[i].span();
+        let cursor = cursors.iter_mut().find(|(line, _)| *line == p.start);
+        let mut s = match (&cursor, dense) {
+            (Some((_, cursor)), false) => *cursor,
+            _ => 0,
+        };
+        while overlaps(&placed, &p, &(s..s + span)) {
+            s += 1;
+        }
+        match cursor {
+            Some((_, cursor)) => *cursor = s + span,
+            None => cursors.push((p.start, s + span)),
+        }
+        secondary_count = secondary_count.max(s + span);
+        placed[i] = Some((p, s..s + span));
+    }
+
+    // 残りのアイテムを、カーソルの位置から空いているところを探して置く
+    let (mut cursor_p, mut cursor_s) = (0, 0);
+    for i in 0..primary.len() {
+        if placed[i].is_some() {
+            continue;
+        }
+        let p_span = primary[i].span();
+        match secondary[i].shifted(secondary_offset) {
+            Some(s) => {
+                if dense {
+                    cursor_p = 0;
+                } else if s.start < cursor_s {
+                    cursor_p += 1;
+                }
+                while overlaps(&placed, &(cursor_p..cursor_p + p_span), &s) {
+                    cursor_p += 1;
+                }
+                cursor_s = s.start;
+                placed[i] = Some((cursor_p..cursor_p + p_span, s));
+            }
+            None => {
+                let s_span = secondary[i].span();
+                if dense {
+                    (cursor_p, cursor_s) = (0, 0);
+                }
+                loop {
+                    if cursor_s + s_span > secondary_count {
+                        (cursor_p, cursor_s) = (cursor_p + 1, 0);
+                    } else if overlaps(
+                        &placed,
+                        &(cursor_p..cursor_p + p_span),
+                        &(cursor_s..cursor_s + s_span),
+                    ) {
+                        cursor_s += 1;
+                    } else {
+                        break;
+                    }
+                }
+                placed[i] = Some((cursor_p..cursor_p + p_span, cursor_s..cursor_s + s_span));
+                cursor_s += s_span;
+            }
+        }
+    }
+
+    let placed: Vec<Area> = placed.into_iter().flatten().collect();
+    let primary_count = placed
+        .iter()
+        .map(|(p, _)| p.end)
+        .fold(primary_offset + primary_explicit, usize::max);
+    (
+        placed,
+        (primary_offset, secondary_offset),
+        (primary_count, secondary_count),
+    )
+}
+
+// tracks のうち、range の中でアイテムのないトラックを取り除いて、アイテムの範囲 spans を詰める
+fn collapse_empty_tracks(
+    tracks: &mut Vec<TrackSize>,
+    range: Range<usize>,
+    spans: &mut [Range<usize>],
+) {
+    let keep: Vec<bool> = (0..tracks.len())
+        .map(|i| !range.contains(&i) || spans.iter().any(|s| s.contains(&i)))
+        .collect();
+    // それぞれの線より前に残るトラックの数
+    let mut lines = vec![0];
+    for k in &keep {
+        lines.push(lines.last().copied().unwrap_or(0) + usize::from(*k));
+    }
+    for span in spans.iter_mut() {
+        *span = lines[span.start]..lines[span.end];
+    }
+    let mut keep = keep.into_iter();
+    tracks.retain(|_| keep.next().unwrap_or(true));
+}
+
+// トラックの大きさを決めるための、アイテムのマージンボックスの大きさ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridItemSize {
+    pub tracks: Range<usize>,
+    pub min_content: i64,
+    pub max_content: i64,
+}
+
+// https://www.w3.org/TR/css-grid-1/#algo-track-sizing
+// トラックの大きさを決める。available はグリッドコンテナのコンテンツボックスの大きさで、
+// 決まっていないときは中身に合わせる。gap はトラックの間の空間
+pub fn size_tracks(
+    tracks: &[TrackSize],
+    items: &[GridItemSize],
+    available: Option<i64>,
+    gap: i64,
+) -> Vec<i64> {
+    let gaps = |count: usize| (gap * (count as i64 - 1).max(0)) as f64;
+    let flex = |i: usize| tracks[i].max.flex();
+
+    // https://www.w3.org/TR/css-grid-1/#algo-init
+    // 中身に依存しない大きさから始める。決まっていない最大の大きさは無限大にする
+    let mut bases: Vec<f64> = tracks
+        .iter()
+        .map(|t| t.min.fixed(available).unwrap_or(0.0))
+        .collect();
+    let mut limits: Vec<f64> = tracks
+        .iter()
+        .zip(&bases)
+        .map(|(t, base)| match t.max {
+            TrackBreadth::Flex(_) => *base,
+            max => max.fixed(available).unwrap_or(f64::INFINITY).max(*base),
+        })
+        .collect();
+
+    // https://www.w3.org/TR/css-grid-1/#algo-content
+    // またがるトラックの少ないアイテムから順に、中身に合わせてトラックを広げる。
+    // fr のトラックにまたがるアイテムは、fr のトラックだけを広げる
+    let mut sorted: Vec<&GridItemSize> = items.iter().filter(|i| !i.tracks.is_empty()).collect();
+    sorted.sort_by_key(|i| i.tracks.len());
+    for item in &sorted {
+        let range = item.tracks.clone();
+        let is_intrinsic = |i: usize| tracks[i].min.fixed(available).is_none();
+        if range.clone().any(|i| flex(i).is_some()) {
+            let grows = |i: usize| flex(i).is_some() && is_intrinsic(i);
+            distribute(
+                &mut bases,
+                range,
+                item.min_content as f64,
+                gaps(item.tracks.len()),
+                grows,
+            );
+            continue;
+        }
+        let min = |i: usize| tracks[i].min;
+        let grows = |i: usize| matches!(min(i), TrackBreadth::Auto | TrackBreadth::MinContent);
+        distribute(
+            &mut bases,
+            range.clone(),
+            item.min_content as f64,
+            gaps(range.len()),
+            grows,
+        );
+        let grows = |i: usize| min(i) == TrackBreadth::MaxContent;
+        distribute(
+            &mut bases,
+            range.clone(),
+            item.max_content as f64,
+            gaps(range.len()),
+            grows,
+        );
+    }
+    for item in &sorted {
+        let range = item.tracks.clone();
+        if range.clone().any(|i| flex(i).is_some()) {
+            continue;
+        }
+        // 無限大の最大の大きさは、広げる前に最小の大きさにする
+        let mut finite: Vec<f64> = limits
+            .iter()
+            .zip(&bases)
+            .map(|(l, b)| if l.is_finite() { *l } else { *b })
+            .collect();
+        let is_intrinsic = |i: usize| tracks[i].max.fixed(available).is_none();
+        let max = |i: usize| tracks[i].max;
+        let grows = |i: usize| is_intrinsic(i) && max(i) == TrackBreadth::MinContent;
+        distribute(
+            &mut finite,
+            range.clone(),
+            item.min_content as f64,
+            gaps(range.len()),
+            grows,
+        );
+        let grows = |i: usize| is_intrinsic(i) && max(i) != TrackBreadth::MinContent;
+        distribute(
+            &mut finite,
+            range.clone(),
+            item.max_content as f64,
+            gaps(range.len()),
+            grows,
+        );
+        for i in range {
+            if limits[i].is_finite() || finite[i] > bases[i] {
+                limits[i] = finite[i];
+            }
+        }
+    }
+    for (limit, base) in limits.iter_mut().zip(&bases) {
+        if !limit.is_finite() || *limit < *base {
+            *limit = *base;
+        }
+    }
+
+    // https://www.w3.org/TR/css-grid-1/#algo-grow-tracks
+    // 余った空間で、トラックを最大の大きさまで等しく広げる。コンテナの大きさが決まっていないときは、
+    // 最大の大きさにする
+    let total_gaps = gaps(tracks.len());
+    match available {
+        Some(available) => {
+            let mut free = available as f64 - total_gaps - bases.iter().sum::<f64>();
+            for _ in 0..tracks.len() {
+                let growing: Vec<usize> = (0..tracks.len())
+                    .filter(|i| limits[*i] > bases[*i])
+                    .collect();
+                if free <= 0.0 || growing.is_empty() {
+                    break;
+                }
+                let share = free / growing.len() as f64;
+                for i in growing {
+                    let grow = share.min(limits[i] - bases[i]);
+                    bases[i] += grow;
+                    free -= grow;
+                }
+            }
+        }
+        None => bases.clone_from(&limits),
+    }
+
+    // https://www.w3.org/TR/css-grid-1/#algo-flex-tracks
+    // 1fr の大きさを求めて、fr のトラックを広げる。コンテナの大きさが決まっていないときは、
+    // fr のトラックにまたがるアイテムが収まる大きさにする
+    let flexible: Vec<usize> = (0..tracks.len()).filter(|i| flex(*i).is_some()).collect();
+    if !flexible.is_empty() {
+        let fr = match available {
+            Some(available) => find_fr_size(
+                tracks,
+                &bases,
+                0..tracks.len(),
+                available as f64 - total_gaps,
+            ),
+            None => {
+                let mut fr = flexible
+                    .iter()
+                    .map(|i| bases[*i] / flex(*i).unwrap_or(0.0).max(1.0))
+                    .fold(0.0, f64::max);
+                for item in &sorted {
+                    if item.tracks.clone().any(|i| flex(i).is_some()) {
+                        let space = item.max_content as f64 - gaps(item.tracks.len());
+                        fr = fr.max(find_fr_size(tracks, &bases, item.tracks.clone(), space));
+                    }
+                }
+                fr
+            }
+        };
+        for i in flexible {
+            bases[i] = bases[i].max(fr * flex(i).unwrap_or(0.0));
+        }
+    } else if let Some(available) = available {
+        // https://www.w3.org/TR/css-grid-1/#algo-stretch
+        // fr のトラックがないときは、残りの空間を最大の大きさが auto のトラックに等しく分ける
+        let free = available as f64 - total_gaps - bases.iter().sum::<f64>();
+        let auto: Vec<usize> = (0..tracks.len())
+            .filter(|i| tracks[*i].max == TrackBreadth::Auto)
+            .collect();
+        if free > 0.0 && !auto.is_empty() {
+            for i in &auto {
+                bases[*i] += free / auto.len() as f64;
+            }
+        }
+    }
+
+    bases.into_iter().map(|b| b as i64).collect()
+}
+
+// size が、range のトラックの sizes とその間の gaps の和より大きいときに、grows のトラックに
+// 足りない分を等しく分けて広げる
+fn distribute(
+    sizes: &mut [f64],
+    range: Range<usize>,
+    size: f64,
+    gaps: f64,
+    grows: impl Fn(usize) -> bool,
+) {
+    let growing: Vec<usize> = range.clone().filter(|i| grows(*i)).collect();
+    let extra = size - gaps - range.map(|i| sizes[i]).sum::<f64>();
+    if growing.is_empty() || extra <= 0.0 {
+        return;
+    }
+    for i in &growing {
+        sizes[*i] += extra / growing.len() as f64;
+    }
+}
+
+// https://www.w3.org/TR/css-grid-1/#algo-find-fr-size
+// range のトラックで space を埋める 1fr の大きさ。fr で分けた大きさが最小の大きさより小さくなる
+// トラックは、fr のトラックとして扱わない
+fn find_fr_size(tracks: &[TrackSize], bases: &[f64], range: Range<usize>, space: f64) -> f64 {
+    let mut inflexible = vec![false; tracks.len()];
+    loop {
+        let flexible = |i: usize| !inflexible[i] && tracks[i].max.flex().is_some();
+        let leftover = space
+            - range
+                .clone()
+                .filter(|i| !flexible(*i))
+                .map(|i| bases[i])
+                .sum::<f64>();
+        let flex_sum: f64 = range
+            .clone()
+            .filter(|i| flexible(*i))
+            .filter_map(|i| tracks[i].max.flex())
+            .sum();
+        let fr = leftover.max(0.0) / flex_sum.max(1.0);
+        let too_small: Vec<usize> = range
+            .clone()
+            .filter(|i| flexible(*i) && fr * tracks[*i].max.flex().unwrap_or(0.0) < bases[*i])
+            .collect();
+        if too_small.is_empty() {
+            return fr;
+        }
+        for i in too_small {
+            inflexible[i] = true;
+        }
+    }
+}
+
+// それぞれのトラックの始まりの位置。トラックの間には gap の空間をあける
+pub fn track_positions(sizes: &[i64], gap: i64) -> Vec<i64> {
+    let mut position = 0;
+    sizes
+        .iter()
+        .map(|size| {
+            let start = position;
+            position += size + gap;
+            start
+        })
+        .collect()
+}
+
+// range のトラックと、その間の空間を合わせた大きさ
+pub fn span_size(sizes: &[i64], range: Range<usize>, gap: i64) -> i64 {
+    sizes[range.clone()].iter().sum::<i64>() + gap * (range.len() as i64 - 1).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::css::{media::Viewport, token::CssTokenizer};
+
+    use super::*;
+
+    fn tokens(css: &str) -> Vec<ComponentValue> {
+        CssTokenizer::new(css.to_string()).collect()
+    }
+
+    fn context() -> LengthContext {
+        LengthContext::new(16.0, 16.0, &Viewport::new(800, 600))
+    }
+
+    fn px(px: f64) -> TrackBreadth {
+        TrackBreadth::Length(ComputedLength { px, percent: 0.0 })
+    }
+
+    fn track(min: TrackBreadth, max: TrackBreadth) -> TrackSize {
+        TrackSize { min, max }
+    }
+
+    #[test]
+    fn test_parse_track_list() {
+        let list = TrackList::parse(
+            &tokens("[a] 100px repeat(2, [b] 1fr) minmax(min-content, 2fr) [c d]"),
+            &context(),
+        )
+        .expect("track list should be valid");
+        let fr = |n: f64| track(TrackBreadth::Auto, TrackBreadth::Flex(n));
+        assert_eq!(
+            vec![
+                track(px(100.0), px(100.0)),
+                fr(1.0),
+                fr(1.0),
+                track(TrackBreadth::MinContent, TrackBreadth::Flex(2.0)),
+            ],
+            list.tracks
+        );
+        let names: Vec<Vec<&str>> = list
+            .line_names
+            .iter()
+            .map(|names| names.iter().map(|n| n.as_str()).collect())
+            .collect();
+        assert_eq!(
+            vec![vec!["a"], vec!["b"], vec!["b"], vec![], vec!["c", "d"]],
+            names
+        );
+
+        assert_eq!(
+            Some(TrackList::default()),
+            TrackList::parse(&tokens("none"), &context())
+        );
+        // fr は最小の大きさにできず、repeat() は入れ子にできない
+        assert_eq!(
+            None,
+            TrackList::parse(&tokens("minmax(1fr, 100px)"), &context())
+        );
+        assert_eq!(
+            None,
+            TrackList::parse(&tokens("repeat(2, repeat(2, 10px))"), &context())
+        );
+        assert_eq!(
+            None,
+            TrackList::parse(&tokens("repeat(auto-fill, 1fr)"), &context())
+        );
+    }
+
+    #[test]
+    fn test_auto_repeat() {
+        let list = TrackList::parse(
+            &tokens("50px repeat(auto-fill, minmax(100px, 1fr))"),
+            &context(),
+        )
+        .expect("track list should be valid");
+
+        // 50 + (100 + 10) * 3 = 380 が 400 に収まる
+        assert_eq!(3, list.repetitions(Some(400), 10));
+        assert_eq!(1, list.repetitions(Some(100), 10));
+        assert_eq!(1, list.repetitions(None, 10));
+        assert_eq!(4, list.expand(3).tracks.len());
+    }
+
+    #[test]
+    fn test_parse_areas() {
+        let areas = GridTemplateAreas::parse(&tokens(r#""head head" "nav main" ". main""#))
+            .expect("areas should be valid");
+        assert_eq!(3, areas.rows);
+        assert_eq!(2, areas.columns);
+        assert_eq!(
+            vec![
+                GridArea {
+                    name: "head".to_string(),
+                    rows: 0..1,
+                    columns: 0..2
+                },
+                GridArea {
+                    name: "nav".to_string(),
+                    rows: 1..2,
+                    columns: 0..1
+                },
+                GridArea {
+                    name: "main".to_string(),
+                    rows: 1..3,
+                    columns: 1..2
+                },
+            ],
+            areas.areas
+        );
+
+        // 長方形でない領域と、列の数が違う行は無効
+        assert_eq!(None, GridTemplateAreas::parse(&tokens(r#""a a" "a b""#)));
+        assert_eq!(None, GridTemplateAreas::parse(&tokens(r#""a b" "c""#)));
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            Some(GridLine::Line(-1, None)),
+            GridLine::parse(&tokens("-1"))
+        );
+        assert_eq!(Some(GridLine::Span(2)), GridLine::parse(&tokens("span 2")));
+        assert_eq!(
+            Some(GridLine::Line(2, Some("a".to_string()))),
+            GridLine::parse(&tokens("2 a"))
+        );
+        assert_eq!(
+            Some(GridLine::Named("a".to_string())),
+            GridLine::parse(&tokens("a"))
+        );
+        assert_eq!(None, GridLine::parse(&tokens("0")));
+        assert_eq!(None, GridLine::parse(&tokens("span 0")));
+        assert_eq!(
+            Some(GridAutoFlow {
+                column: true,
+                dense: true
+            }),
+            GridAutoFlow::parse(&tokens("dense column"))
+        );
+    }
+
+    #[test]
+    fn test_place_items() {
+        use AxisPlacement::{Auto, Definite};
+
+        // 行を埋めていく3列のグリッド。位置の決まったアイテムを先に置き、残りを空いたセルに置く
+        let rows = [Auto(1), Definite(0, 1), Auto(1), Auto(2), Auto(1)];
+        let columns = [Auto(1), Definite(1, 3), Definite(0, 1), Auto(1), Auto(3)];
+        let (areas, offsets, counts) = place_items(&rows, &columns, (0, 3), false);
+        assert_eq!(
+            vec![
+                (0..1, 0..1),
+                (0..1, 1..3),
+                (1..2, 0..1),
+                (1..3, 1..2),
+                (3..4, 0..3),
+            ],
+            areas
+        );
+        assert_eq!((0, 0), offsets);
+        assert_eq!((4, 3), counts);
+
+        // dense では、前にあいたセルを埋める
+        let rows = [Auto(1), Auto(1), Auto(1)];
+        let columns = [Auto(2), Auto(2), Auto(1)];
+        let (areas, _, _) = place_items(&rows, &columns, (0, 3), true);
+        assert_eq!(vec![(0..1, 0..2), (1..2, 0..2), (0..1, 2..3)], areas);
+
+        // 明示的なグリッドより前の線は、暗黙のトラックになる
+        let (areas, offsets, counts) = place_items(&[Definite(-2, -1)], &[Auto(1)], (0, 2), false);
+        assert_eq!(vec![(0..1, 0..1)], areas);
+        assert_eq!((2, 0), offsets);
+        assert_eq!((2, 2), counts);
+    }
+
+    #[test]
+    fn test_size_tracks() {
+        let fr = |n: f64| track(TrackBreadth::Auto, TrackBreadth::Flex(n));
+        let auto = TrackSize::default();
+        let item = |tracks: Range<usize>, min_content: i64, max_content: i64| GridItemSize {
+            tracks,
+            min_content,
+            max_content,
+        };
+
+        // 固定の大きさを除いた空間を fr で分ける
+        let tracks = [track(px(100.0), px(100.0)), fr(1.0), fr(2.0)];
+        assert_eq!(
+            vec![100, 100, 200],
+            size_tracks(&tracks, &[], Some(420), 10)
+        );
+        // fr のトラックは、中身の最小の大きさより小さくならない
+        assert_eq!(
+            vec![100, 150, 150],
+            size_tracks(&tracks, &[item(1..2, 150, 200)], Some(420), 10)
+        );
+
+        // auto のトラックは中身に合わせてから、残りの空間を等しく分ける
+        let tracks = [auto, auto];
+        assert_eq!(
+            vec![80, 120],
+            size_tracks(
+                &tracks,
+                &[item(0..1, 20, 40), item(1..2, 30, 80)],
+                Some(200),
+                0
+            )
+        );
+        // 足りないときは、中身の最小の大きさから最大の大きさに向けて等しく広げる
+        assert_eq!(
+            vec![35, 45],
+            size_tracks(
+                &tracks,
+                &[item(0..1, 20, 40), item(1..2, 30, 80)],
+                Some(80),
+                0
+            )
+        );
+        // 複数のトラックにまたがるアイテムは、足りない分を等しく分ける
+        assert_eq!(
+            vec![45, 45],
+            size_tracks(&tracks, &[item(0..2, 100, 100)], None, 10)
+        );
+
+        // minmax() は最小の大きさから最大の大きさまで広がる
+        let tracks = [track(px(50.0), TrackBreadth::MaxContent), auto];
+        assert_eq!(
+            vec![60, 140],
+            size_tracks(&tracks, &[item(0..1, 10, 60)], Some(200), 0)
+        );
+
+        // 大きさが決まっていないときは、fr のトラックにまたがるアイテムが収まるようにする
+        let tracks = [fr(1.0), fr(1.0)];
+        assert_eq!(
+            vec![60, 60],
+            size_tracks(&tracks, &[item(0..1, 10, 60)], None, 0)
+        );
+    }
+
+    #[test]
+    fn test_track_positions() {
+        assert_eq!(vec![0, 110, 170], track_positions(&[100, 50, 30], 10));
+        assert_eq!(90, span_size(&[100, 50, 30], 1..3, 10));
+        assert_eq!(0, span_size(&[], 0..0, 10));
+    }
+}
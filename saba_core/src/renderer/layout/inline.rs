@@ -0,0 +1,1985 @@
+use alloc::{collections::VecDeque, string::String, vec, vec::Vec};
+
+use crate::renderer::{
+    css::cssom::ComponentValue,
+    layout::{
+        bidi::{closing_controls, mirror, opening_controls, resolve_levels, visual_order},
+        float::FloatContext,
+        layout_object::LayoutRect,
+        length::{ComputedLength, LengthContext, LengthPercentage},
+        line_break::break_opportunities,
+        text::{
+            Direction, OverflowWrap, TextAlign, TextMetrics, UnicodeBidi, WhiteSpace, WordBreak,
+        },
+    },
+};
+
+// https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerticalAlign {
+    Baseline,
+    Sub,
+    Super,
+    TextTop,
+    TextBottom,
+    Middle,
+    Top,
+    Bottom,
+    // 上に動かす量。パーセンテージは要素自身の line-height に対する割合
+    Length(ComputedLength),
+}
+
+impl VerticalAlign {
+    pub fn parse(value: &[ComponentValue], context: &LengthContext) -> Option<Self> {
+        if let [ComponentValue::Ident(keyword)] = value {
+            return match keyword.to_ascii_lowercase().as_str() {
+                "baseline" => Some(Self::Baseline),
+                "sub" => Some(Self::Sub),
+                "super" => Some(Self::Super),
+                "text-top" => Some(Self::TextTop),
+                "text-bottom" => Some(Self::TextBottom),
+                "middle" => Some(Self::Middle),
+                "top" => Some(Self::Top),
+                "bottom" => Some(Self::Bottom),
+                _ => None,
+            };
+        }
+
+        let length = LengthPercentage::parse(value).ok()?;
+        Some(Self::Length(length.compute(context)))
+    }
+
+    // 親のベースラインから、ボックスのベースラインを上に動かす量。
+    // top と bottom は行の高さが決まってから位置を決めるので None になる
+    fn raise(&self, metrics: &BoxMetrics, parent: &BoxMetrics) -> Option<i64> {
+        let raise = match self {
+            Self::Baseline => 0,
+            Self::Sub => -(parent.font_size / 5.0) as i64,
+            Self::Super => (parent.font_size / 3.0) as i64,
+            // ボックスの上端を親のフォントの上端に揃える
+            Self::TextTop => parent.ascent - metrics.top_offset(),
+            Self::TextBottom => metrics.bottom_offset() - parent.descent,
+            // ボックスの中央を、親のベースラインから x-height の半分だけ上の位置に揃える。
+            // x-height は ascent の半分とする
+            Self::Middle => parent.ascent / 4 + (metrics.descent - metrics.ascent) / 2,
+            Self::Length(length) => length.resolve(metrics.line_height as f64) as i64,
+            Self::Top | Self::Bottom => return None,
+        };
+        Some(raise)
+    }
+}
+
+// フォントと line-height から決まる、インラインボックスの縦方向の大きさ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxMetrics {
+    pub ascent: i64,
+    pub descent: i64,
+    pub line_height: i64,
+    // sub と super のずれの基準になる、px で表したフォントサイズ
+    pub font_size: f64,
+}
+
+impl BoxMetrics {
+    // line-height とフォントの高さの差（leading）を上下に半分ずつ割り当てたときの、
+    // ボックスの上端からベースラインまでの距離
+    fn top_offset(&self) -> i64 {
+        self.ascent + (self.line_height - self.ascent - self.descent) / 2
+    }
+
+    // ベースラインからボックスの下端までの距離
+    fn bottom_offset(&self) -> i64 {
+        self.line_height - self.top_offset()
+    }
+}
+
+// インラインボックスの、レイアウトに必要なスタイル
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InlineBoxStyle {
+    pub metrics: BoxMetrics,
+    pub vertical_align: VerticalAlign,
+    // 行の始まりの側と終わりの側の margin と、border と padding の和
+    pub margin_start: i64,
+    pub margin_end: i64,
+    pub inner_start: i64,
+    pub inner_end: i64,
+    // 上下の border と padding の和。行の高さには影響しない
+    pub inner_top: i64,
+    pub inner_bottom: i64,
+    pub direction: Direction,
+    pub unicode_bidi: UnicodeBidi,
+}
+
+impl InlineBoxStyle {
+    fn has_edges(&self) -> bool {
+        self.margin_start != 0
+            || self.margin_end != 0
+            || self.inner_start != 0
+            || self.inner_end != 0
+    }
+}
+
+// インライン整形文脈に並べる内容。owner は呼び出し側で内容を区別するための番号で、
+// 結果の断片にそのまま付く
+#[derive(Debug, Clone, PartialEq)]
+pub enum InlineItem {
+    OpenBox {
+        owner: usize,
+        style: InlineBoxStyle,
+    },
+    CloseBox,
+    // white-space と text-transform で変換済みの文字列
+    Text {
+        owner: usize,
+        text: String,
+        white_space: WhiteSpace,
+        word_break: WordBreak,
+        overflow_wrap: OverflowWrap,
+        metrics: TextMetrics,
+    },
+    // <br> による改行
+    ForcedBreak,
+    // 行の中に置くブロック。前後で改行し、大きさは margin を含む
+    Atomic {
+        owner: usize,
+        width: i64,
+        height: i64,
+    },
+}
+
+// 行ボックス。y は整形文脈の上端から、baseline は行の上端からの距離
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineBox {
+    pub y: i64,
+    pub height: i64,
+    pub baseline: i64,
+}
+
+// 行ボックスの中に置いた、テキストやインラインボックスの一部分。
+// 位置は整形文脈の左上からの相対位置で、テキストの断片は文字の上端と高さを、
+// インラインボックスの断片はボーダーボックスを表す
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineFragment {
+    pub owner: usize,
+    pub line: usize,
+    pub rect: LayoutRect,
+    // テキストの断片が描画する文字列。インラインボックスやブロックの断片では空になる
+    pub text: String,
+    // インラインボックスが、この断片で始まるか、この断片で終わるか
+    pub is_first: bool,
+    pub is_last: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InlineLayout {
+    pub lines: Vec<LineBox>,
+    pub fragments: Vec<InlineFragment>,
+    pub height: i64,
+}
+
+// 行に分ける単位。box は InlineLayouter::boxes の添字で、parent はテキストを含むボックス。
+// level は UAX #9 で決めた埋め込みレベル
+#[derive(Debug, Clone, PartialEq)]
+enum Atom {
+    Open {
+        box_index: usize,
+    },
+    Close {
+        box_index: usize,
+    },
+    Word {
+        owner: usize,
+        parent: Option<usize>,
+        text: String,
+        metrics: TextMetrics,
+        // 収まらないときに語の途中で折り返せるかどうか
+        wraps: bool,
+        level: u8,
+    },
+    Space {
+        owner: usize,
+        parent: Option<usize>,
+        metrics: TextMetrics,
+        collapsible: bool,
+        // 空白の後ろで折り返せるかどうか
+        wraps: bool,
+        level: u8,
+    },
+    // 語と語の間の、空白のない折り返し位置
+    Opportunity,
+    Break,
+    Atomic {
+        owner: usize,
+        parent: Option<usize>,
+        width: i64,
+        height: i64,
+        level: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BoxInfo {
+    owner: usize,
+    style: InlineBoxStyle,
+    parent: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Line {
+    atoms: Vec<Atom>,
+    // 強制改行で終わるかどうか
+    forced: bool,
+    // 行の上端と、フロートに重ならない範囲の左端と横幅
+    top: i64,
+    left: i64,
+    width: i64,
+}
+
+// 行の中で並べ替える単位。語、空白、ブロックと、内容のないボックスの位置を表す
+struct LineLeaf {
+    // Line::atoms の添字
+    atom: usize,
+    parent: Option<usize>,
+    level: u8,
+}
+
+// 行の中に置いたボックスの範囲
+struct BoxRange {
+    box_index: usize,
+    start: i64,
+    end: i64,
+    is_first: bool,
+    is_last: bool,
+}
+
+// 行の中に置いたブロック
+struct PlacedAtomic {
+    owner: usize,
+    parent: Option<usize>,
+    x: i64,
+    width: i64,
+    height: i64,
+}
+
+// 行の中で、文字列を置いた位置
+struct Segment {
+    owner: usize,
+    parent: Option<usize>,
+    text: String,
+    metrics: TextMetrics,
+    x: i64,
+}
+
+// 行ボックスの並べ方を決める、包含ブロックのスタイル
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParagraphStyle {
+    pub text_align: TextAlign,
+    // 段落の方向を決める
+    pub direction: Direction,
+    pub unicode_bidi: UnicodeBidi,
+    // 最初の行だけに適用する字下げ
+    pub text_indent: i64,
+}
+
+struct InlineLayouter {
+    boxes: Vec<BoxInfo>,
+    strut: BoxMetrics,
+    text_align: TextAlign,
+    direction: Direction,
+    unicode_bidi: UnicodeBidi,
+    width: i64,
+    text_indent: i64,
+    floats: FloatContext,
+    // 強制改行で区切った段落ごとの埋め込みレベル
+    paragraph_levels: Vec<u8>,
+}
+
+// https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+// 内容を幅 width の行ボックスに分けて並べる。strut は包含ブロックのフォントから決まる、
+// 各行の先頭に置かれる幅 0 のボックスの大きさ。floats は包含ブロックのコンテンツボックスから見た
+// フロートで、フロートに重なる行ボックスは短くする
+pub fn layout_inline(
+    items: &[InlineItem],
+    strut: BoxMetrics,
+    paragraph: ParagraphStyle,
+    width: i64,
+    floats: &FloatContext,
+) -> InlineLayout {
+    let mut layouter = InlineLayouter {
+        boxes: Vec::new(),
+        strut,
+        text_align: paragraph.text_align,
+        direction: paragraph.direction,
+        unicode_bidi: paragraph.unicode_bidi,
+        width,
+        text_indent: paragraph.text_indent,
+        floats: floats.clone(),
+        paragraph_levels: Vec::new(),
+    };
+    let atoms = layouter.segment(items);
+    let lines = layouter.break_lines(atoms);
+    layouter.place_lines(lines)
+}
+
+impl InlineLayouter {
+    // 内容を語と空白に分ける。まとめられる空白は、段落の先頭と直前の空白の後ろでは取り除く。
+    // 語は UAX #14 の折り返し位置と、埋め込みレベルの変わる位置で区切る
+    fn segment(&mut self, items: &[InlineItem]) -> VecDeque<Atom> {
+        let mut atoms = VecDeque::new();
+        let mut stack: Vec<usize> = Vec::new();
+        let mut after_space = true;
+
+        // 折り返し位置は、段落の内容をつないだ文字列で決める。
+        // <br> は改行文字、行の中に置くブロックはオブジェクト置換文字として扱う。
+        // 埋め込みレベルは、ボックスの direction と unicode-bidi を制御文字に置き換えて加えた
+        // 文字列で決める。positions は chars の各文字の、その文字列での位置
+        let mut chars = Vec::new();
+        let mut bidi_text = Vec::new();
+        let mut positions = Vec::new();
+        let mut styles: Vec<InlineBoxStyle> = Vec::new();
+        if matches!(
+            self.unicode_bidi,
+            UnicodeBidi::BidiOverride | UnicodeBidi::IsolateOverride
+        ) {
+            bidi_text
+                .extend_from_slice(opening_controls(UnicodeBidi::BidiOverride, self.direction));
+        }
+        for item in items {
+            let mut push = |c: char, word_break: WordBreak| {
+                chars.push((c, word_break));
+                positions.push(bidi_text.len());
+                bidi_text.push(c);
+            };
+            match item {
+                InlineItem::Text {
+                    text, word_break, ..
+                } => text.chars().for_each(|c| push(c, *word_break)),
+                InlineItem::ForcedBreak => push('\n', WordBreak::Normal),
+                InlineItem::Atomic { .. } => push('\u{FFFC}', WordBreak::Normal),
+                InlineItem::OpenBox { style, .. } => {
+                    bidi_text
+                        .extend_from_slice(opening_controls(style.unicode_bidi, style.direction));
+                    styles.push(*style);
+                }
+                InlineItem::CloseBox => {
+                    if let Some(style) = styles.pop() {
+                        bidi_text.extend_from_slice(closing_controls(style.unicode_bidi));
+                    }
+                }
+            }
+        }
+        let opportunities = break_opportunities(&chars);
+        // unicode-bidi が plaintext なら、段落の方向を最初の強い文字で決める
+        let base = (self.unicode_bidi != UnicodeBidi::Plaintext).then(|| self.direction.level());
+        let bidi = resolve_levels(&bidi_text, base);
+        let level_at = |index: usize| positions.get(index).map(|&p| bidi.levels[p]);
+        let direction_level = self.direction.level();
+        let paragraph_level_at = |index: usize| {
+            positions
+                .get(index)
+                .map_or(direction_level, |&p| bidi.paragraph_levels[p])
+        };
+        self.paragraph_levels.push(paragraph_level_at(0));
+        let mut index = 0;
+
+        for item in items {
+            match item {
+                InlineItem::OpenBox { owner, style } => {
+                    let box_index = self.boxes.len();
+                    self.boxes.push(BoxInfo {
+                        owner: *owner,
+                        style: *style,
+                        parent: stack.last().copied(),
+                    });
+                    atoms.push_back(Atom::Open { box_index });
+                    stack.push(box_index);
+                }
+                InlineItem::CloseBox => {
+                    if let Some(box_index) = stack.pop() {
+                        atoms.push_back(Atom::Close { box_index });
+                    }
+                }
+                InlineItem::Text {
+                    owner,
+                    text,
+                    white_space,
+                    word_break,
+                    overflow_wrap,
+                    metrics,
+                } => {
+                    let parent = stack.last().copied();
+                    // 折り返し位置がなく収まらない語を、途中で折り返すかどうか
+                    let breaks_words = white_space.wraps()
+                        && (overflow_wrap.breaks_words() || *word_break == WordBreak::BreakWord);
+                    let mut word = String::new();
+                    let mut word_level = 0;
+                    let flush = |word: &mut String, level: u8, atoms: &mut VecDeque<Atom>| {
+                        if !word.is_empty() {
+                            atoms.push_back(Atom::Word {
+                                owner: *owner,
+                                parent,
+                                text: core::mem::take(word),
+                                metrics: metrics.clone(),
+                                wraps: breaks_words,
+                                level,
+                            });
+                        }
+                    };
+
+                    for c in text.chars() {
+                        let opportunity = opportunities[index];
+                        let level = level_at(index).unwrap_or(0);
+                        index += 1;
+                        match c {
+                            '\n' => {
+                                flush(&mut word, word_level, &mut atoms);
+                                atoms.push_back(Atom::Break);
+                                self.paragraph_levels.push(paragraph_level_at(index));
+                                after_space = true;
+                            }
+                            ' ' => {
+                                flush(&mut word, word_level, &mut atoms);
+                                let collapsible = white_space.collapses_spaces();
+                                if collapsible && after_space {
+                                    continue;
+                                }
+                                atoms.push_back(Atom::Space {
+                                    owner: *owner,
+                                    parent,
+                                    metrics: metrics.clone(),
+                                    collapsible,
+                                    wraps: white_space.wraps(),
+                                    level,
+                                });
+                                after_space = collapsible;
+                            }
+                            c => {
+                                if level != word_level {
+                                    flush(&mut word, word_level, &mut atoms);
+                                }
+                                word_level = level;
+                                if opportunity && white_space.wraps() {
+                                    flush(&mut word, word_level, &mut atoms);
+                                    // 直前で開いたボックスは、折り返し位置の後ろに置く
+                                    let opens = atoms
+                                        .iter()
+                                        .rev()
+                                        .take_while(|a| matches!(a, Atom::Open { .. }))
+                                        .count();
+                                    atoms.insert(atoms.len() - opens, Atom::Opportunity);
+                                }
+                                word.push(c);
+                                after_space = false;
+                            }
+                        }
+                    }
+                    flush(&mut word, word_level, &mut atoms);
+                }
+                InlineItem::ForcedBreak => {
+                    atoms.push_back(Atom::Break);
+                    after_space = true;
+                    index += 1;
+                    self.paragraph_levels.push(paragraph_level_at(index));
+                }
+                InlineItem::Atomic {
+                    owner,
+                    width,
+                    height,
+                } => {
+                    atoms.push_back(Atom::Atomic {
+                        owner: *owner,
+                        parent: stack.last().copied(),
+                        width: *width,
+                        height: *height,
+                        level: level_at(index).unwrap_or(0),
+                    });
+                    after_space = false;
+                    index += 1;
+                }
+            }
+        }
+
+        atoms
+    }
+
+    // top の位置から始まる行。フロートに重なる行は、重ならない範囲に縮める
+    fn line_at(&self, top: i64) -> Line {
+        let (left, right) =
+            self.floats
+                .free_space(top, top + self.strut.line_height, 0, self.width);
+        Line {
+            atoms: Vec::new(),
+            forced: false,
+            top,
+            left,
+            width: right - left,
+        }
+    }
+
+    fn atom_width(&self, atom: &Atom) -> i64 {
+        match atom {
+            Atom::Open { box_index } => {
+                let style = &self.boxes[*box_index].style;
+                style.margin_start + style.inner_start
+            }
+            Atom::Close { box_index } => {
+                let style = &self.boxes[*box_index].style;
+                style.inner_end + style.margin_end
+            }
+            Atom::Word { text, metrics, .. } => metrics.measure(text),
+            Atom::Space { metrics, .. } => metrics.measure(" "),
+            Atom::Opportunity | Atom::Break => 0,
+            Atom::Atomic { width, .. } => *width,
+        }
+    }
+
+    // 貪欲法で行に分ける。語が収まらないときは最後の折り返し位置で改行し、
+    // 折り返し位置がなければ語の途中で改行する
+    fn break_lines(&self, mut atoms: VecDeque<Atom>) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut line = self.line_at(0);
+        let mut width = 0;
+        // 行の高さを求めるための、前の行から続いているボックス
+        let mut continued: Vec<usize> = Vec::new();
+        // 折り返し位置の直後の、line.atoms の添字
+        let mut opportunity: Option<usize> = None;
+        // ブロックの後ろでは、次の内容の前で改行する
+        let mut break_before_content = false;
+
+        // 行を終えて、その下に次の行を始める
+        let finish = |lines: &mut Vec<Line>,
+                      line: &mut Line,
+                      width: &mut i64,
+                      opportunity: &mut Option<usize>,
+                      continued: &mut Vec<usize>,
+                      forced: bool| {
+            line.forced = forced;
+            trim_trailing_spaces(line);
+            let top = line.top + self.line_height(&line.atoms, continued);
+            lines.push(core::mem::replace(line, self.line_at(top)));
+            *width = 0;
+            *opportunity = None;
+        };
+
+        while let Some(atom) = atoms.pop_front() {
+            let available = if lines.is_empty() {
+                line.width - self.text_indent
+            } else {
+                line.width
+            };
+
+            if break_before_content && !matches!(atom, Atom::Close { .. }) {
+                break_before_content = false;
+                finish(
+                    &mut lines,
+                    &mut line,
+                    &mut width,
+                    &mut opportunity,
+                    &mut continued,
+                    false,
+                );
+            }
+
+            match atom {
+                Atom::Open { .. } | Atom::Close { .. } => {
+                    width += self.atom_width(&atom);
+                    line.atoms.push(atom);
+                }
+                Atom::Space {
+                    collapsible, wraps, ..
+                } => {
+                    // 折り返した行の先頭の空白は取り除く
+                    if collapsible && !has_content(&line.atoms) {
+                        continue;
+                    }
+                    width += self.atom_width(&atom);
+                    line.atoms.push(atom);
+                    if wraps {
+                        opportunity = Some(line.atoms.len());
+                    }
+                }
+                Atom::Opportunity => {
+                    if has_content(&line.atoms) {
+                        opportunity = Some(line.atoms.len());
+                    }
+                }
+                Atom::Break => {
+                    line.atoms.push(atom);
+                    finish(
+                        &mut lines,
+                        &mut line,
+                        &mut width,
+                        &mut opportunity,
+                        &mut continued,
+                        true,
+                    );
+                }
+                Atom::Atomic { .. } => {
+                    if has_content(&line.atoms) {
+                        atoms.push_front(atom);
+                        finish(
+                            &mut lines,
+                            &mut line,
+                            &mut width,
+                            &mut opportunity,
+                            &mut continued,
+                            false,
+                        );
+                        continue;
+                    }
+                    width += self.atom_width(&atom);
+                    line.atoms.push(atom);
+                    break_before_content = true;
+                }
+                Atom::Word {
+                    owner,
+                    parent,
+                    ref text,
+                    ref metrics,
+                    wraps,
+                    level,
+                } => {
+                    let atom_width = self.atom_width(&atom);
+                    if width + atom_width <= available {
+                        width += atom_width;
+                        line.atoms.push(atom);
+                        continue;
+                    }
+
+                    // フロートの横に収まらない行は、フロートの下に動かす
+                    if !has_content(&line.atoms)
+                        && line.width < self.width
+                        && let Some(bottom) = self
+                            .floats
+                            .next_bottom(line.top, line.top + self.strut.line_height)
+                    {
+                        let moved = core::mem::take(&mut line.atoms);
+                        line = Line {
+                            atoms: moved,
+                            ..self.line_at(bottom)
+                        };
+                        atoms.push_front(atom);
+                        continue;
+                    }
+
+                    if let Some(index) = opportunity {
+                        // 折り返し位置の直後に閉じるボックスは、前の行に残す
+                        let mut rest = line.atoms.split_off(index);
+                        let closes = rest
+                            .iter()
+                            .take_while(|a| matches!(a, Atom::Close { .. }))
+                            .count();
+                        line.atoms.extend(rest.drain(..closes));
+                        atoms.push_front(atom);
+                        for a in rest.into_iter().rev() {
+                            atoms.push_front(a);
+                        }
+                        finish(
+                            &mut lines,
+                            &mut line,
+                            &mut width,
+                            &mut opportunity,
+                            &mut continued,
+                            false,
+                        );
+                        continue;
+                    }
+
+                    if !wraps {
+                        width += atom_width;
+                        line.atoms.push(atom);
+                        continue;
+                    }
+
+                    // 1語だけで収まらないので、収まるところまで置く
+                    let mut split = 0;
+                    for (i, c) in text.char_indices() {
+                        let end = i + c.len_utf8();
+                        if width + metrics.measure(&text[..end]) > available {
+                            break;
+                        }
+                        split = end;
+                    }
+                    if split == 0 && has_content(&line.atoms) {
+                        atoms.push_front(atom);
+                        finish(
+                            &mut lines,
+                            &mut line,
+                            &mut width,
+                            &mut opportunity,
+                            &mut continued,
+                            false,
+                        );
+                        continue;
+                    }
+                    // 1文字も入らない場合でも、行の先頭には少なくとも1文字は置く
+                    if split == 0 {
+                        split = text.chars().next().map_or(0, |c| c.len_utf8());
+                    }
+                    if split >= text.len() {
+                        width += atom_width;
+                        line.atoms.push(atom);
+                        continue;
+                    }
+
+                    let (head, tail) = text.split_at(split);
+                    let word = |text: &str| Atom::Word {
+                        owner,
+                        parent,
+                        text: String::from(text),
+                        metrics: metrics.clone(),
+                        wraps,
+                        level,
+                    };
+                    atoms.push_front(word(tail));
+                    line.atoms.push(word(head));
+                    finish(
+                        &mut lines,
+                        &mut line,
+                        &mut width,
+                        &mut opportunity,
+                        &mut continued,
+                        false,
+                    );
+                }
+            }
+        }
+
+        if !line.atoms.is_empty() {
+            trim_trailing_spaces(&mut line);
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    // 行の高さ。continued は前の行から続いているボックスで、次の行に続くボックスに更新する
+    fn line_height(&self, atoms: &[Atom], continued: &mut Vec<usize>) -> i64 {
+        let present = present_boxes(atoms, continued);
+        if self.is_phantom(atoms, &present) {
+            return 0;
+        }
+        let atomics = atoms
+            .iter()
+            .filter_map(|a| match a {
+                Atom::Atomic {
+                    owner,
+                    parent,
+                    width,
+                    height,
+                    ..
+                } => Some(PlacedAtomic {
+                    owner: *owner,
+                    parent: *parent,
+                    x: 0,
+                    width: *width,
+                    height: *height,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        self.align_line(&present, &atomics).height
+    }
+
+    // 内容がなく、ボックスの横方向の margin、border、padding もない行は、高さを持たない
+    fn is_phantom(&self, atoms: &[Atom], present: &[usize]) -> bool {
+        !atoms.iter().any(|a| match a {
+            Atom::Word { .. } | Atom::Break | Atom::Atomic { .. } => true,
+            Atom::Space { collapsible, .. } => !collapsible,
+            Atom::Opportunity | Atom::Open { .. } | Atom::Close { .. } => false,
+        }) && !present.iter().any(|b| self.boxes[*b].style.has_edges())
+    }
+
+    fn place_lines(&self, lines: Vec<Line>) -> InlineLayout {
+        let mut layout = InlineLayout::default();
+        // 前の行から続いているボックス
+        let mut continued: Vec<usize> = Vec::new();
+        let mut paragraph = 0;
+
+        let count = lines.len();
+        for (i, line) in lines.into_iter().enumerate() {
+            let paragraph_level = self
+                .paragraph_levels
+                .get(paragraph)
+                .copied()
+                .unwrap_or_else(|| self.direction.level());
+            let rtl = paragraph_level % 2 == 1;
+            if line.forced {
+                paragraph += 1;
+            }
+
+            let indent = if i == 0 { self.text_indent } else { 0 };
+            let content_width: i64 = line.atoms.iter().map(|a| self.atom_width(a)).sum();
+            let free = (line.width - indent - content_width).max(0);
+
+            // 両端揃えでは、余った幅を空白に均等に割り当てる。
+            // 強制改行の直前の行と最後の行は、両端揃えにしない
+            let justify = self.text_align == TextAlign::Justify && !line.forced && i + 1 < count;
+            let spaces = line
+                .atoms
+                .iter()
+                .filter(|a| matches!(a, Atom::Space { .. }))
+                .count() as i64;
+            let (extra, remainder) = if justify && spaces > 0 {
+                (free / spaces, free % spaces)
+            } else {
+                (0, 0)
+            };
+
+            // start と end、両端揃えにしない行は、段落の方向に応じて左右に揃える。
+            // 右から左の段落では、text-indent は行の右側に適用する
+            let align_left = match (self.text_align, rtl) {
+                (TextAlign::Left, _) => true,
+                (TextAlign::Right | TextAlign::Center, _) => false,
+                (TextAlign::Start | TextAlign::Justify, rtl) => !rtl || (justify && spaces > 0),
+                (TextAlign::End, rtl) => rtl,
+            };
+            let offset = line.left
+                + if rtl { 0 } else { indent }
+                + match self.text_align {
+                    TextAlign::Center => free / 2,
+                    _ if align_left => 0,
+                    _ => free,
+                };
+
+            // 行の中の内容を、埋め込みレベルに従って表示する順に並べる
+            let leaves = self.line_leaves(&line.atoms, paragraph_level);
+            let order = visual_order(&leaves.iter().map(|l| l.level).collect::<Vec<_>>());
+            let chains = leaves
+                .iter()
+                .map(|l| self.ancestors(l.parent))
+                .collect::<Vec<_>>();
+
+            // ボックスごとの、この行の中で始まるか、終わるかと、並べ替えで分かれた部分の数
+            let mut starts_here = vec![false; self.boxes.len()];
+            let mut ends_here = vec![false; self.boxes.len()];
+            for atom in &line.atoms {
+                match atom {
+                    Atom::Open { box_index } => starts_here[*box_index] = true,
+                    Atom::Close { box_index } => ends_here[*box_index] = true,
+                    _ => {}
+                }
+            }
+            let mut piece_counts = vec![0; self.boxes.len()];
+            let mut stack: Vec<usize> = Vec::new();
+            for &k in &order {
+                let common = common_prefix(&stack, &chains[k]);
+                stack.truncate(common);
+                for &b in &chains[k][common..] {
+                    piece_counts[b] += 1;
+                    stack.push(b);
+                }
+            }
+
+            // 横方向の位置を決める。左から順に並べ、ボックスの部分は (添字, 左端, 始まりの辺と終わりの辺を
+            // 含むかどうか) で持つ。ボックスの margin、border、padding は CSS 2.1 の 9.10 節に従い、
+            // 左から右のボックスでは始まりの側を最も左の部分に、終わりの側を最も右の部分に置く
+            let mut x = offset;
+            let mut open: Vec<(usize, i64, bool, bool)> = Vec::new();
+            let mut pieces = vec![0; self.boxes.len()];
+            let mut box_ranges: Vec<BoxRange> = Vec::new();
+            let mut segments: Vec<Segment> = Vec::new();
+            let mut atomics: Vec<PlacedAtomic> = Vec::new();
+            let mut space_index = 0;
+            let close_piece = |x: &mut i64,
+                               (box_index, start, is_first, is_last): (usize, i64, bool, bool),
+                               box_ranges: &mut Vec<BoxRange>| {
+                let style = &self.boxes[box_index].style;
+                let (inner, margin) = match style.direction {
+                    Direction::Ltr if is_last => (style.inner_end, style.margin_end),
+                    Direction::Rtl if is_first => (style.inner_start, style.margin_start),
+                    _ => (0, 0),
+                };
+                *x += inner;
+                box_ranges.push(BoxRange {
+                    box_index,
+                    start,
+                    end: *x,
+                    is_first,
+                    is_last,
+                });
+                *x += margin;
+            };
+
+            for &k in &order {
+                let common = common_prefix(
+                    &open.iter().map(|(b, _, _, _)| *b).collect::<Vec<_>>(),
+                    &chains[k],
+                );
+                while open.len() > common {
+                    let piece = open.pop().expect("open should not be empty");
+                    close_piece(&mut x, piece, &mut box_ranges);
+                }
+                for &b in &chains[k][common..] {
+                    let style = &self.boxes[b].style;
+                    pieces[b] += 1;
+                    let (first, last) = (pieces[b] == 1, pieces[b] == piece_counts[b]);
+                    let (is_first, is_last, (margin, inner)) = match style.direction {
+                        Direction::Ltr => (
+                            starts_here[b] && first,
+                            ends_here[b] && last,
+                            if starts_here[b] && first {
+                                (style.margin_start, style.inner_start)
+                            } else {
+                                (0, 0)
+                            },
+                        ),
+                        Direction::Rtl => (
+                            starts_here[b] && last,
+                            ends_here[b] && first,
+                            if ends_here[b] && first {
+                                (style.margin_end, style.inner_end)
+                            } else {
+                                (0, 0)
+                            },
+                        ),
+                    };
+                    x += margin;
+                    open.push((b, x, is_first, is_last));
+                    x += inner;
+                }
+
+                let leaf = &leaves[k];
+                let atom = &line.atoms[leaf.atom];
+                match atom {
+                    Atom::Word {
+                        owner,
+                        parent,
+                        text,
+                        metrics,
+                        ..
+                    } => {
+                        // 右から左に表示する語は、文字を逆に並べる
+                        let text = if leaf.level % 2 == 1 {
+                            text.chars().rev().map(mirror).collect()
+                        } else {
+                            text.clone()
+                        };
+                        segments.push(Segment {
+                            owner: *owner,
+                            parent: *parent,
+                            text,
+                            metrics: metrics.clone(),
+                            x,
+                        });
+                        x += self.atom_width(atom);
+                    }
+                    Atom::Space {
+                        owner,
+                        parent,
+                        metrics,
+                        ..
+                    } => {
+                        segments.push(Segment {
+                            owner: *owner,
+                            parent: *parent,
+                            text: String::from(" "),
+                            metrics: metrics.clone(),
+                            x,
+                        });
+                        x += self.atom_width(atom) + extra + i64::from(space_index < remainder);
+                        space_index += 1;
+                    }
+                    Atom::Atomic {
+                        owner,
+                        parent,
+                        width,
+                        height,
+                        ..
+                    } => {
+                        atomics.push(PlacedAtomic {
+                            owner: *owner,
+                            parent: *parent,
+                            x,
+                            width: *width,
+                            height: *height,
+                        });
+                        x += width;
+                    }
+                    // 内容のないボックスは、ボックスの部分だけを置く
+                    Atom::Open { .. } | Atom::Close { .. } | Atom::Opportunity | Atom::Break => {}
+                }
+            }
+            while let Some(piece) = open.pop() {
+                close_piece(&mut x, piece, &mut box_ranges);
+            }
+
+            // 縦方向の位置を決める
+            let present = present_boxes(&line.atoms, &mut continued);
+            let phantom = self.is_phantom(&line.atoms, &present);
+            let baselines = self.align_line(&present, &atomics);
+            let (height, baseline) = if phantom {
+                (0, 0)
+            } else {
+                (baselines.height, baselines.root)
+            };
+            let line_box = LineBox {
+                y: line.top,
+                height,
+                baseline,
+            };
+            let baseline_of = |parent: Option<usize>| {
+                if phantom {
+                    0
+                } else {
+                    line_box.y + baselines.of(parent)
+                }
+            };
+            let metrics_of = |parent: Option<usize>| match parent {
+                Some(b) => self.boxes[b].style.metrics,
+                None => self.strut,
+            };
+
+            for range in box_ranges {
+                let info = &self.boxes[range.box_index];
+                let metrics = info.style.metrics;
+                let top =
+                    baseline_of(Some(range.box_index)) - metrics.ascent - info.style.inner_top;
+                let height = metrics.ascent
+                    + metrics.descent
+                    + info.style.inner_top
+                    + info.style.inner_bottom;
+                layout.fragments.push(InlineFragment {
+                    owner: info.owner,
+                    line: i,
+                    rect: LayoutRect::new(range.start, top, range.end - range.start, height),
+                    text: String::new(),
+                    is_first: range.is_first,
+                    is_last: range.is_last,
+                });
+            }
+
+            for atomic in atomics {
+                layout.fragments.push(InlineFragment {
+                    owner: atomic.owner,
+                    line: i,
+                    rect: LayoutRect::new(
+                        atomic.x,
+                        baseline_of(atomic.parent) - atomic.height,
+                        atomic.width,
+                        atomic.height,
+                    ),
+                    text: String::new(),
+                    is_first: true,
+                    is_last: true,
+                });
+            }
+
+            // 断片の前後の空白は描画しない
+            for segment in merge_segments(segments) {
+                let metrics = metrics_of(segment.parent);
+                let text = segment.text.trim_matches(' ');
+                if text.is_empty() {
+                    continue;
+                }
+                let leading = segment.text.len() - segment.text.trim_start_matches(' ').len();
+                let x = segment.x + segment.metrics.measure(&segment.text[..leading]);
+                layout.fragments.push(InlineFragment {
+                    owner: segment.owner,
+                    line: i,
+                    rect: LayoutRect::new(
+                        x,
+                        baseline_of(segment.parent) - metrics.ascent,
+                        segment.metrics.measure(text),
+                        metrics.ascent + metrics.descent,
+                    ),
+                    text: String::from(text),
+                    is_first: false,
+                    is_last: false,
+                });
+            }
+
+            layout.height = line_box.y + line_box.height;
+            layout.lines.push(line_box);
+        }
+
+        layout
+    }
+
+    // 行の中で並べ替える単位を、行の中の順に返す。行末の空白は段落の埋め込みレベルにし（UAX #9 の L1）、
+    // 内容のないボックスは、前後の単位の低い方の埋め込みレベルにする
+    fn line_leaves(&self, atoms: &[Atom], paragraph_level: u8) -> Vec<LineLeaf> {
+        let mut has_content = vec![false; self.boxes.len()];
+        for atom in atoms {
+            if let Atom::Word { parent, .. }
+            | Atom::Space { parent, .. }
+            | Atom::Atomic { parent, .. } = atom
+            {
+                for b in self.ancestors(*parent) {
+                    has_content[b] = true;
+                }
+            }
+        }
+
+        let mut leaves: Vec<LineLeaf> = Vec::new();
+        // 内容のないボックスの単位の添字
+        let mut anchors = Vec::new();
+        let mut anchored = vec![false; self.boxes.len()];
+        for (i, atom) in atoms.iter().enumerate() {
+            match atom {
+                Atom::Word { parent, level, .. }
+                | Atom::Space { parent, level, .. }
+                | Atom::Atomic { parent, level, .. } => leaves.push(LineLeaf {
+                    atom: i,
+                    parent: *parent,
+                    level: *level,
+                }),
+                Atom::Open { box_index } | Atom::Close { box_index }
+                    if !has_content[*box_index] && !anchored[*box_index] =>
+                {
+                    anchored[*box_index] = true;
+                    anchors.push(leaves.len());
+                    leaves.push(LineLeaf {
+                        atom: i,
+                        parent: Some(*box_index),
+                        level: paragraph_level,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for leaf in leaves.iter_mut().rev() {
+            match atoms[leaf.atom] {
+                Atom::Space { .. } => leaf.level = paragraph_level,
+                Atom::Open { .. } | Atom::Close { .. } => {}
+                _ => break,
+            }
+        }
+
+        let content_level =
+            |leaves: &[LineLeaf], i: usize| (!anchors.contains(&i)).then(|| leaves[i].level);
+        for &i in &anchors {
+            let before = (0..i)
+                .rev()
+                .find_map(|j| content_level(&leaves, j))
+                .unwrap_or(paragraph_level);
+            let after = (i + 1..leaves.len())
+                .find_map(|j| content_level(&leaves, j))
+                .unwrap_or(paragraph_level);
+            leaves[i].level = before.min(after);
+        }
+
+        leaves
+    }
+
+    // ボックスと、その祖先のボックス。外側から順に並べる
+    fn ancestors(&self, parent: Option<usize>) -> Vec<usize> {
+        let mut chain = Vec::new();
+        let mut current = parent;
+        while let Some(b) = current {
+            chain.push(b);
+            current = self.boxes[b].parent;
+        }
+        chain.reverse();
+        chain
+    }
+
+    // 行の中のボックスのベースラインの位置を求める。
+    // ボックスは親のベースラインから vertical-align の分だけずらし、top と bottom のボックスは
+    // 子孫とまとめて、行の上端か下端に揃える
+    fn align_line(&self, present: &[usize], atomics: &[PlacedAtomic]) -> LineBaselines {
+        // ボックスごとに、揃える基準のボックス（top か bottom のボックス。None は行全体）と、
+        // その基準のベースラインからの下向きの距離
+        let mut groups: Vec<Option<(Option<usize>, i64)>> = vec![None; self.boxes.len()];
+        let mut ordered = present.to_vec();
+        ordered.sort_unstable();
+        ordered.dedup();
+
+        // 基準ごとの上端と下端
+        let mut extents: Vec<(Option<usize>, i64, i64)> =
+            vec![(None, -self.strut.top_offset(), self.strut.bottom_offset())];
+        let mut extend = |group: Option<usize>, top: i64, bottom: i64| match extents
+            .iter_mut()
+            .find(|(g, _, _)| *g == group)
+        {
+            Some((_, t, b)) => {
+                *t = (*t).min(top);
+                *b = (*b).max(bottom);
+            }
+            None => extents.push((group, top, bottom)),
+        };
+
+        for b in ordered {
+            let info = &self.boxes[b];
+            let (parent_group, parent_baseline, parent_metrics) = match info.parent {
+                Some(p) => {
+                    let (group, baseline) = groups[p].unwrap_or((None, 0));
+                    (group, baseline, self.boxes[p].style.metrics)
+                }
+                None => (None, 0, self.strut),
+            };
+            let metrics = info.style.metrics;
+            let (group, baseline) = match info.style.vertical_align.raise(&metrics, &parent_metrics)
+            {
+                Some(raise) => (parent_group, parent_baseline - raise),
+                None => (Some(b), 0),
+            };
+            groups[b] = Some((group, baseline));
+            extend(
+                group,
+                baseline - metrics.top_offset(),
+                baseline + metrics.bottom_offset(),
+            );
+        }
+
+        for atomic in atomics {
+            let (group, baseline) = atomic.parent.and_then(|p| groups[p]).unwrap_or((None, 0));
+            extend(group, baseline - atomic.height, baseline);
+        }
+
+        // 行全体で揃える内容の高さに、top と bottom のボックスが収まるように行を高くする
+        let (_, root_top, root_bottom) = extents[0];
+        let mut height = root_bottom - root_top;
+        for (_, top, bottom) in &extents[1..] {
+            height = height.max(bottom - top);
+        }
+
+        let group_baselines = extents
+            .iter()
+            .map(|(group, top, bottom)| {
+                let baseline = match group {
+                    None => -top,
+                    Some(b) if self.boxes[*b].style.vertical_align == VerticalAlign::Bottom => {
+                        height - bottom
+                    }
+                    Some(_) => -top,
+                };
+                (*group, baseline)
+            })
+            .collect::<Vec<_>>();
+        let baselines = groups
+            .iter()
+            .map(|g| {
+                g.map(|(group, baseline)| {
+                    let group_baseline = group_baselines
+                        .iter()
+                        .find(|(g, _)| *g == group)
+                        .map_or(0, |(_, b)| *b);
+                    group_baseline + baseline
+                })
+            })
+            .collect();
+
+        LineBaselines {
+            height,
+            root: -root_top,
+            boxes: baselines,
+        }
+    }
+}
+
+// 行の高さと、行の上端からのベースラインの位置
+struct LineBaselines {
+    height: i64,
+    root: i64,
+    boxes: Vec<Option<i64>>,
+}
+
+impl LineBaselines {
+    // テキストやブロックを含むボックスのベースライン。None は行全体のベースライン
+    fn of(&self, parent: Option<usize>) -> i64 {
+        parent
+            .and_then(|b| self.boxes.get(b).copied().flatten())
+            .unwrap_or(self.root)
+    }
+}
+
+// 行の中にあるボックス。continued は前の行から続いているボックスで、次の行に続くボックスに更新する
+fn present_boxes(atoms: &[Atom], continued: &mut Vec<usize>) -> Vec<usize> {
+    let mut present = continued.clone();
+    for atom in atoms {
+        match atom {
+            Atom::Open { box_index } => {
+                present.push(*box_index);
+                continued.push(*box_index);
+            }
+            Atom::Close { box_index } => {
+                if let Some(position) = continued.iter().rposition(|b| b == box_index) {
+                    continued.remove(position);
+                }
+            }
+            _ => {}
+        }
+    }
+    present
+}
+
+// 行末の空白は取り除く。折り返せる場合は、残した空白も行末からはみ出して見えなくなる
+fn trim_trailing_spaces(line: &mut Line) {
+    while let Some(index) = line
+        .atoms
+        .iter()
+        .rposition(|a| !matches!(a, Atom::Open { .. } | Atom::Close { .. } | Atom::Break))
+    {
+        let Atom::Space {
+            collapsible, wraps, ..
+        } = line.atoms[index]
+        else {
+            break;
+        };
+        if !collapsible && !wraps {
+            break;
+        }
+        line.atoms.remove(index);
+    }
+}
+
+// 2つのボックスの並びの、先頭から一致する部分の長さ
+fn common_prefix(a: &[usize], b: &[usize]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+// 行の中に語や空白、ブロックがあるかどうか
+fn has_content(atoms: &[Atom]) -> bool {
+    atoms.iter().any(|a| {
+        matches!(
+            a,
+            Atom::Word { .. }
+                | Atom::Atomic { .. }
+                | Atom::Space {
+                    collapsible: false,
+                    ..
+                }
+        )
+    })
+}
+
+// 同じテキストの、間をあけずに並んだ文字列をまとめる。
+// letter-spacing があるときは文字の間をあけて描画するので、1文字ずつに分ける
+fn merge_segments(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut merged: Vec<Segment> = Vec::new();
+    for segment in segments {
+        if segment.metrics.letter_spacing != 0 {
+            let mut x = segment.x;
+            for c in segment.text.chars() {
+                let text = String::from(c);
+                let width = segment.metrics.measure(&text);
+                merged.push(Segment {
+                    text,
+                    metrics: segment.metrics.clone(),
+                    x,
+                    ..segment
+                });
+                x += width;
+            }
+            continue;
+        }
+
+        if let Some(last) = merged.last_mut()
+            && last.owner == segment.owner
+            && last.metrics.letter_spacing == 0
+            && last.x + last.metrics.measure_glyphs(&last.text) == segment.x
+        {
+            last.text.push_str(&segment.text);
+            continue;
+        }
+        merged.push(segment);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{rc::Rc, string::ToString};
+
+    use crate::renderer::layout::{
+        computed_style::ComputedStyle, float::Float, font::FixedWidthFontMetrics,
+    };
+
+    use super::*;
+
+    fn metrics(letter_spacing: i64, word_spacing: i64) -> TextMetrics {
+        TextMetrics {
+            font: ComputedStyle::default().font(),
+            font_metrics: Rc::new(FixedWidthFontMetrics),
+            letter_spacing,
+            word_spacing,
+        }
+    }
+
+    fn strut() -> BoxMetrics {
+        BoxMetrics {
+            ascent: 12,
+            descent: 4,
+            line_height: 20,
+            font_size: 16.0,
+        }
+    }
+
+    fn text(
+        owner: usize,
+        text: &str,
+        white_space: WhiteSpace,
+        metrics: &TextMetrics,
+    ) -> InlineItem {
+        InlineItem::Text {
+            owner,
+            text: text.to_string(),
+            white_space,
+            word_break: WordBreak::Normal,
+            overflow_wrap: OverflowWrap::Normal,
+            metrics: metrics.clone(),
+        }
+    }
+
+    fn breaking_text(
+        text: &str,
+        word_break: WordBreak,
+        overflow_wrap: OverflowWrap,
+        metrics: &TextMetrics,
+    ) -> InlineItem {
+        InlineItem::Text {
+            owner: 0,
+            text: text.to_string(),
+            white_space: WhiteSpace::Normal,
+            word_break,
+            overflow_wrap,
+            metrics: metrics.clone(),
+        }
+    }
+
+    fn open(owner: usize, style: InlineBoxStyle) -> InlineItem {
+        InlineItem::OpenBox { owner, style }
+    }
+
+    fn box_style(vertical_align: VerticalAlign, metrics: BoxMetrics) -> InlineBoxStyle {
+        InlineBoxStyle {
+            metrics,
+            vertical_align,
+            margin_start: 0,
+            margin_end: 0,
+            inner_start: 0,
+            inner_end: 0,
+            inner_top: 0,
+            inner_bottom: 0,
+            direction: Direction::Ltr,
+            unicode_bidi: UnicodeBidi::Normal,
+        }
+    }
+
+    // 行ごとの、テキストの断片の文字列と x 座標
+    fn texts(layout: &InlineLayout) -> Vec<Vec<(&str, i64)>> {
+        let mut lines = vec![Vec::new(); layout.lines.len()];
+        for fragment in layout.fragments.iter().filter(|f| !f.text.is_empty()) {
+            lines[fragment.line].push((fragment.text.as_str(), fragment.rect.point.x));
+        }
+        lines
+    }
+
+    fn layout_text(
+        s: &str,
+        white_space: WhiteSpace,
+        text_align: TextAlign,
+        width: i64,
+        indent: i64,
+        metrics: &TextMetrics,
+    ) -> InlineLayout {
+        layout_inline(
+            &[text(0, s, white_space, metrics)],
+            strut(),
+            ParagraphStyle {
+                text_align,
+                direction: Direction::Ltr,
+                unicode_bidi: UnicodeBidi::Normal,
+                text_indent: indent,
+            },
+            width,
+            &FloatContext::default(),
+        )
+    }
+
+    #[test]
+    fn test_wrap_and_align() {
+        let m = metrics(0, 0);
+        // 1行に 10 文字まで入る
+        let layout = layout_text(
+            "aaa bbb ccc dddddddddddd",
+            WhiteSpace::Normal,
+            TextAlign::Left,
+            80,
+            0,
+            &m,
+        );
+        // 折り返し位置のない語は、行からはみ出す
+        assert_eq!(
+            vec![
+                vec![("aaa bbb", 0)],
+                vec![("ccc", 0)],
+                vec![("dddddddddddd", 0)],
+            ],
+            texts(&layout)
+        );
+        assert_eq!(60, layout.height);
+
+        let layout = layout_text("ab", WhiteSpace::Normal, TextAlign::Right, 80, 0, &m);
+        assert_eq!(vec![vec![("ab", 64)]], texts(&layout));
+        let layout = layout_text("ab", WhiteSpace::Normal, TextAlign::Center, 80, 16, &m);
+        assert_eq!(vec![vec![("ab", 40)]], texts(&layout));
+
+        let layout = layout_text(
+            "aaa bbb ccc",
+            WhiteSpace::Nowrap,
+            TextAlign::Left,
+            40,
+            0,
+            &m,
+        );
+        assert_eq!(vec![vec![("aaa bbb ccc", 0)]], texts(&layout));
+
+        // 改行を残す場合は、改行ごとに行を分ける
+        let layout = layout_text("a\n\nb", WhiteSpace::Pre, TextAlign::Left, 80, 0, &m);
+        assert_eq!(vec![vec![("a", 0)], vec![], vec![("b", 0)]], texts(&layout));
+        assert_eq!(60, layout.height);
+    }
+
+    #[test]
+    fn test_line_break_opportunities() {
+        let m = metrics(0, 0);
+        let layout = |items: &[InlineItem], width: i64| {
+            layout_inline(
+                items,
+                strut(),
+                ParagraphStyle {
+                    text_align: TextAlign::Left,
+                    direction: Direction::Ltr,
+                    unicode_bidi: UnicodeBidi::Normal,
+                    text_indent: 0,
+                },
+                width,
+                &FloatContext::default(),
+            )
+        };
+
+        // 日本語は文字の間で折り返すが、句点は行頭に置かない
+        let layout_ja = layout(&[text(0, "日本語の文章です。", WhiteSpace::Normal, &m)], 64);
+        assert_eq!(
+            vec![
+                vec![("日本語の", 0)],
+                vec![("文章で", 0)],
+                vec![("す。", 0)],
+            ],
+            texts(&layout_ja)
+        );
+
+        // ボックスの境界でも折り返せる
+        let layout_box = layout(
+            &[
+                text(0, "日本", WhiteSpace::Normal, &m),
+                open(1, box_style(VerticalAlign::Baseline, strut())),
+                text(1, "語", WhiteSpace::Normal, &m),
+                InlineItem::CloseBox,
+            ],
+            40,
+        );
+        assert_eq!(vec![vec![("日本", 0)], vec![("語", 0)]], texts(&layout_box));
+
+        // overflow-wrap が anywhere なら、収まらない語を途中で折り返す
+        let layout_anywhere = layout(
+            &[breaking_text(
+                "aaa dddddddddddd",
+                WordBreak::Normal,
+                OverflowWrap::Anywhere,
+                &m,
+            )],
+            80,
+        );
+        assert_eq!(
+            vec![vec![("aaa", 0)], vec![("dddddddddd", 0)], vec![("dd", 0)]],
+            texts(&layout_anywhere)
+        );
+
+        // break-all は英単語の途中でも折り返し、keep-all は日本語の文字の間で折り返さない
+        let layout_break_all = layout(
+            &[breaking_text(
+                "aaa bbbbbb",
+                WordBreak::BreakAll,
+                OverflowWrap::Normal,
+                &m,
+            )],
+            56,
+        );
+        assert_eq!(
+            vec![vec![("aaa bbb", 0)], vec![("bbb", 0)]],
+            texts(&layout_break_all)
+        );
+        let layout_keep_all = layout(
+            &[breaking_text(
+                "日本語 文章",
+                WordBreak::KeepAll,
+                OverflowWrap::Normal,
+                &m,
+            )],
+            64,
+        );
+        assert_eq!(
+            vec![vec![("日本語", 0)], vec![("文章", 0)]],
+            texts(&layout_keep_all)
+        );
+    }
+
+    #[test]
+    fn test_justify_and_spacing() {
+        // 最後の行以外は、空白の幅を広げて両端を揃える
+        let layout = layout_text(
+            "a b c dddddd",
+            WhiteSpace::Normal,
+            TextAlign::Justify,
+            64,
+            0,
+            &metrics(0, 0),
+        );
+        assert_eq!(
+            vec![vec![("a", 0), ("b", 28), ("c", 56)], vec![("dddddd", 0)]],
+            texts(&layout)
+        );
+
+        let layout = layout_text(
+            "ab c",
+            WhiteSpace::Normal,
+            TextAlign::Left,
+            100,
+            0,
+            &metrics(0, 4),
+        );
+        assert_eq!(vec![vec![("ab", 0), ("c", 28)]], texts(&layout));
+
+        let layout = layout_text(
+            "ab c",
+            WhiteSpace::Normal,
+            TextAlign::Left,
+            100,
+            0,
+            &metrics(2, 0),
+        );
+        assert_eq!(vec![vec![("a", 0), ("b", 10), ("c", 30)]], texts(&layout));
+    }
+
+    #[test]
+    fn test_inline_boxes_across_lines() {
+        // <p>Some <a>link text</a> and more</p> を 1行 12 文字の幅に並べる
+        let m = metrics(0, 0);
+        let mut style = box_style(VerticalAlign::Baseline, strut());
+        style.inner_start = 2;
+        style.inner_end = 2;
+        let items = [
+            text(0, "Some ", WhiteSpace::Normal, &m),
+            open(1, style),
+            text(2, "link text", WhiteSpace::Normal, &m),
+            InlineItem::CloseBox,
+            text(3, " and more", WhiteSpace::Normal, &m),
+        ];
+        let layout = layout_inline(
+            &items,
+            strut(),
+            ParagraphStyle {
+                text_align: TextAlign::Left,
+                direction: Direction::Ltr,
+                unicode_bidi: UnicodeBidi::Normal,
+                text_indent: 0,
+            },
+            96,
+            &FloatContext::default(),
+        );
+
+        assert_eq!(
+            vec![
+                vec![("Some", 0), ("link", 42)],
+                vec![("text", 0), ("and", 42)],
+                vec![("more", 0)],
+            ],
+            texts(&layout)
+        );
+        assert_eq!(
+            vec![
+                LineBox {
+                    y: 0,
+                    height: 20,
+                    baseline: 14,
+                },
+                LineBox {
+                    y: 20,
+                    height: 20,
+                    baseline: 14,
+                },
+                LineBox {
+                    y: 40,
+                    height: 20,
+                    baseline: 14,
+                },
+            ],
+            layout.lines
+        );
+
+        // インラインボックスは2行に分かれ、左の padding は最初の断片、右の padding は最後の断片にだけ付く
+        let boxes = layout
+            .fragments
+            .iter()
+            .filter(|f| f.owner == 1)
+            .map(|f| (f.rect, f.is_first, f.is_last))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (LayoutRect::new(40, 2, 34, 16), true, false),
+                (LayoutRect::new(0, 22, 34, 16), false, true),
+            ],
+            boxes
+        );
+    }
+
+    #[test]
+    fn test_collapse_spaces_between_items() {
+        let m = metrics(0, 0);
+        let items = [
+            text(0, " a ", WhiteSpace::Normal, &m),
+            open(1, box_style(VerticalAlign::Baseline, strut())),
+            text(2, " b", WhiteSpace::Normal, &m),
+            InlineItem::CloseBox,
+            InlineItem::ForcedBreak,
+            text(3, " c ", WhiteSpace::Normal, &m),
+        ];
+        let layout = layout_inline(
+            &items,
+            strut(),
+            ParagraphStyle {
+                text_align: TextAlign::Left,
+                direction: Direction::Ltr,
+                unicode_bidi: UnicodeBidi::Normal,
+                text_indent: 0,
+            },
+            400,
+            &FloatContext::default(),
+        );
+        assert_eq!(
+            vec![vec![("a", 0), ("b", 16)], vec![("c", 0)]],
+            texts(&layout)
+        );
+
+        // 空白しかない行は高さが 0 になる
+        let layout = layout_inline(
+            &[text(0, "   ", WhiteSpace::Normal, &m)],
+            strut(),
+            ParagraphStyle {
+                text_align: TextAlign::Left,
+                direction: Direction::Ltr,
+                unicode_bidi: UnicodeBidi::Normal,
+                text_indent: 0,
+            },
+            400,
+            &FloatContext::default(),
+        );
+        assert_eq!(0, layout.height);
+    }
+
+    #[test]
+    fn test_vertical_align() {
+        let m = metrics(0, 0);
+        let big = BoxMetrics {
+            ascent: 24,
+            descent: 8,
+            line_height: 40,
+            font_size: 32.0,
+        };
+        let small = BoxMetrics {
+            ascent: 6,
+            descent: 2,
+            line_height: 10,
+            font_size: 8.0,
+        };
+        let layout_with = |vertical_align: VerticalAlign, metrics: BoxMetrics| {
+            let items = [
+                text(0, "a", WhiteSpace::Normal, &m),
+                open(1, box_style(vertical_align, metrics)),
+                text(2, "b", WhiteSpace::Normal, &m),
+                InlineItem::CloseBox,
+            ];
+            layout_inline(
+                &items,
+                strut(),
+                ParagraphStyle {
+                    text_align: TextAlign::Left,
+                    direction: Direction::Ltr,
+                    unicode_bidi: UnicodeBidi::Normal,
+                    text_indent: 0,
+                },
+                400,
+                &FloatContext::default(),
+            )
+        };
+        // 行の上端からの、a と b の文字の上端の位置と行の高さ
+        let tops = |layout: &InlineLayout| {
+            let top = |owner: usize| {
+                layout
+                    .fragments
+                    .iter()
+                    .find(|f| f.owner == owner)
+                    .map(|f| f.rect.point.y)
+                    .unwrap()
+            };
+            (top(0), top(2), layout.height)
+        };
+
+        // ベースラインを揃えるので、大きなフォントの b の分だけ a が下がる
+        assert_eq!(
+            (16, 4, 40),
+            tops(&layout_with(VerticalAlign::Baseline, big))
+        );
+        // super は親のフォントサイズの 1/3 だけ上がり、sub は 1/5 だけ下がる
+        assert_eq!(
+            (7, 2, 25),
+            tops(&layout_with(VerticalAlign::Super, strut()))
+        );
+        assert_eq!((2, 5, 23), tops(&layout_with(VerticalAlign::Sub, strut())));
+        // 長さを指定すると、その分だけ上がる
+        let raised = VerticalAlign::Length(ComputedLength {
+            px: 4.0,
+            percent: 0.0,
+        });
+        assert_eq!((6, 2, 24), tops(&layout_with(raised, strut())));
+        // text-top は b の行ボックスの上端を a の文字の上端に揃える
+        assert_eq!((2, 6, 42), tops(&layout_with(VerticalAlign::TextTop, big)));
+        // top と bottom は行の高さが決まってから、行の上端か下端に揃える。
+        // 収まらないときは行を高くする
+        assert_eq!((2, 1, 20), tops(&layout_with(VerticalAlign::Top, small)));
+        assert_eq!(
+            (2, 11, 20),
+            tops(&layout_with(VerticalAlign::Bottom, small))
+        );
+        assert_eq!((2, 4, 40), tops(&layout_with(VerticalAlign::Top, big)));
+    }
+
+    #[test]
+    fn test_atomic() {
+        let m = metrics(0, 0);
+        let items = [
+            text(0, "a ", WhiteSpace::Normal, &m),
+            InlineItem::Atomic {
+                owner: 1,
+                width: 100,
+                height: 30,
+            },
+            text(2, " b", WhiteSpace::Normal, &m),
+        ];
+        let layout = layout_inline(
+            &items,
+            strut(),
+            ParagraphStyle {
+                text_align: TextAlign::Left,
+                direction: Direction::Ltr,
+                unicode_bidi: UnicodeBidi::Normal,
+                text_indent: 0,
+            },
+            400,
+            &FloatContext::default(),
+        );
+
+        // ブロックは前後で改行し、1行を占める
+        assert_eq!(vec![vec![("a", 0)], vec![], vec![("b", 0)]], texts(&layout));
+        let block = layout.fragments.iter().find(|f| f.owner == 1).unwrap();
+        assert_eq!(LayoutRect::new(0, 20, 100, 30), block.rect);
+        assert_eq!(20 + 36 + 20, layout.height);
+    }
+
+    #[test]
+    fn test_bidi() {
+        let m = metrics(0, 0);
+        let layout_bidi =
+            |items: &[InlineItem], direction: Direction, unicode_bidi: UnicodeBidi| {
+                layout_inline(
+                    items,
+                    strut(),
+                    ParagraphStyle {
+                        text_align: TextAlign::Start,
+                        direction,
+                        unicode_bidi,
+                        text_indent: 0,
+                    },
+                    80,
+                    &FloatContext::default(),
+                )
+            };
+
+        // 右から左の段落は右に揃え、語を右から順に並べる
+        let layout = layout_bidi(
+            &[text(0, "אבג דה", WhiteSpace::Normal, &m)],
+            Direction::Rtl,
+            UnicodeBidi::Normal,
+        );
+        assert_eq!(vec![vec![("הד גבא", 32)]], texts(&layout));
+
+        // 左から右の段落の中の右から左の部分は、その部分だけを逆に並べる
+        let layout = layout_bidi(
+            &[text(0, "ab אבג cd", WhiteSpace::Normal, &m)],
+            Direction::Ltr,
+            UnicodeBidi::Normal,
+        );
+        assert_eq!(vec![vec![("ab גבא cd", 0)]], texts(&layout));
+
+        // plaintext では、段落の方向を最初の強い文字で決める
+        let layout = layout_bidi(
+            &[text(0, "אב cd", WhiteSpace::Normal, &m)],
+            Direction::Ltr,
+            UnicodeBidi::Plaintext,
+        );
+        assert_eq!(vec![vec![("cd בא", 40)]], texts(&layout));
+
+        // 右から左のボックスは、始まりの側の border と padding を右に置く
+        let style = InlineBoxStyle {
+            inner_start: 2,
+            inner_end: 3,
+            direction: Direction::Rtl,
+            unicode_bidi: UnicodeBidi::Isolate,
+            ..box_style(VerticalAlign::Baseline, strut())
+        };
+        let layout = layout_bidi(
+            &[
+                text(0, "a ", WhiteSpace::Normal, &m),
+                open(1, style),
+                text(2, "גד הו", WhiteSpace::Normal, &m),
+                InlineItem::CloseBox,
+                text(0, " c", WhiteSpace::Normal, &m),
+            ],
+            Direction::Ltr,
+            UnicodeBidi::Normal,
+        );
+        assert_eq!(
+            vec![vec![("a", 0), ("וה דג", 19), ("c", 69)]],
+            texts(&layout)
+        );
+        let fragment = layout
+            .fragments
+            .iter()
+            .find(|f| f.owner == 1)
+            .expect("box should have a fragment");
+        assert_eq!((16, 45), (fragment.rect.point.x, fragment.rect.size.width));
+        assert!(fragment.is_first && fragment.is_last);
+    }
+
+    #[test]
+    fn test_floats() {
+        let m = metrics(0, 0);
+        let mut floats = FloatContext::default();
+        floats.place(Float::Left, 56, 30, 0, 0, 80);
+        floats.place(Float::Right, 16, 50, 0, 0, 80);
+        let layout = layout_inline(
+            &[text(0, "aa bbb cc", WhiteSpace::Normal, &m)],
+            strut(),
+            ParagraphStyle {
+                text_align: TextAlign::Left,
+                direction: Direction::Ltr,
+                unicode_bidi: UnicodeBidi::Normal,
+                text_indent: 0,
+            },
+            80,
+            &floats,
+        );
+
+        // フロートの間に語が入らない行は、フロートの下まで下げる。
+        // 行はフロートに重ならない範囲に並べる
+        assert_eq!(vec![vec![("aa bbb", 0)], vec![("cc", 0)]], texts(&layout));
+        assert_eq!(
+            vec![30, 50],
+            layout.lines.iter().map(|l| l.y).collect::<Vec<_>>()
+        );
+        assert_eq!(70, layout.height);
+    }
+}
@@ -1,6 +1,5 @@
-use core::cell::RefCell;
+use core::{cell::RefCell, str::FromStr};
 
-use alloc::string::String;
 use alloc::vec;
 use alloc::{
     rc::{Rc, Weak},
@@ -8,14 +7,46 @@ use alloc::{
     vec::Vec,
 };
 
-use crate::constants::{WINDOW_PADDING, WINDOW_WIDTH};
 use crate::{
-    constants::{CHAR_HEIGHT_WITH_PADDING, CHAR_WIDTH, CONTENT_AREA_WIDTH},
     display_item::DisplayItem,
     renderer::{
-        css::cssom::{ComponentValue, Declaration, Selector},
-        dom::node::{Node, NodeKind},
-        layout::computed_style::{Color, ComputedStyle, DisplayType, FontSize},
+        css::{
+            cascade::{CascadedStyle, CssWideKeyword},
+            cssom::{ComponentValue, Declaration, Selector, is_custom_property},
+            custom_property::{compute_custom_properties, contains_var, substitute_var},
+            media::Viewport,
+            shorthand::{expand_shorthand, longhands},
+        },
+        dom::node::{ElementKind, Node, NodeKind},
+        layout::{
+            computed_style::{
+                BORDER_WIDTH_MEDIUM, BorderStyle, BoxSizing, Color, ComputedStyle, DisplayType,
+                Overflow, Side, SideProperty, Sides, absolute_font_size,
+            },
+            flex::{
+                AlignContent, AlignItems, FlexBasis, FlexDirection, FlexItem, FlexWrap,
+                JustifyContent, cross_offset, place_lines, place_main, resolve_lines,
+            },
+            float::{Clear, Float, FloatContext},
+            font::{
+                FontMetrics, FontStyle, FontWeight, LineHeight, VerticalMetrics, parse_font_family,
+            },
+            grid::{
+                GridAutoFlow, GridItemLines, GridItemSize, GridLine, GridTemplateAreas, TrackList,
+                TrackSize, build_grid, size_tracks, span_size, track_positions,
+            },
+            inline::{
+                BoxMetrics, InlineBoxStyle, InlineFragment, InlineItem, ParagraphStyle,
+                VerticalAlign, layout_inline,
+            },
+            length::{ComputedLength, LengthContext, LengthPercentage, Size},
+            position::{Position, PositionContext, ZIndex, sticky_offset},
+            text::{
+                Direction, OverflowWrap, TextAlign, TextDecoration, TextDecorationLine,
+                TextDecorationStyle, TextMetrics, TextTransform, UnicodeBidi, WhiteSpace,
+                WordBreak, transform_text,
+            },
+        },
     },
 };
 
@@ -27,7 +58,34 @@ pub struct LayoutObject {
     next_sibling: Option<Rc<RefCell<LayoutObject>>>,
     parent: Weak<RefCell<LayoutObject>>,
     style: ComputedStyle,
+    cascaded_style: CascadedStyle,
+    // ボーダーボックスの位置と大きさ。インライン要素とテキストでは、すべての断片を囲む矩形
     rect: LayoutRect,
+    // ブロックの、包含ブロックのコンテンツボックスの左上からのボーダーボックスの位置。
+    // 絶対位置指定のボックスでは、包含ブロックのパディングボックスの左上からの位置
+    offset: LayoutPoint,
+    // 絶対位置指定のボックスが通常のフローに置かれるはずだった、親のコンテンツボックスの
+    // 左上からのマージンボックスの位置
+    static_offset: LayoutPoint,
+    // 相対位置指定と sticky のボックスを、通常のフローの位置からずらす量
+    relative_offset: LayoutPoint,
+    margin: LayoutEdges,
+    border: LayoutEdges,
+    padding: LayoutEdges,
+    // 最初と最後の子ノードのマージンと相殺したあとの、上下のマージン
+    collapsed_margin_top: CollapsedMargin,
+    collapsed_margin_bottom: CollapsedMargin,
+    // テキストに描画する、祖先の要素の装飾。外側の要素のものから順に並ぶ
+    text_decorations: Vec<TextDecoration>,
+    // インライン要素とテキストを行ボックスに並べた断片
+    fragments: Vec<InlineFragment>,
+    // 匿名ブロックボックスかどうか。匿名ブロックの node は、ボックスを生成した親の要素
+    anonymous: bool,
+    // フレックスコンテナかグリッドコンテナの、通常のフローの子ノードかどうか
+    flex_or_grid_item: bool,
+    // ブロックを含むために分割したインライン要素の部分で、前と後ろに続く部分があるかどうか
+    split_before: bool,
+    split_after: bool,
 }
 
 impl LayoutObject {
@@ -44,6 +102,7 @@ impl LayoutObject {
             next_sibling: None,
             parent,
             style: ComputedStyle::default(),
+            cascaded_style: CascadedStyle::default(),
             rect: LayoutRect {
                 point: LayoutPoint { x: 0, y: 0 },
                 size: LayoutSize {
@@ -51,13 +110,68 @@ impl LayoutObject {
                     height: 0,
                 },
             },
+            offset: LayoutPoint { x: 0, y: 0 },
+            static_offset: LayoutPoint { x: 0, y: 0 },
+            relative_offset: LayoutPoint { x: 0, y: 0 },
+            margin: LayoutEdges::default(),
+            border: LayoutEdges::default(),
+            padding: LayoutEdges::default(),
+            collapsed_margin_top: CollapsedMargin::default(),
+            collapsed_margin_bottom: CollapsedMargin::default(),
+            text_decorations: Vec::new(),
+            fragments: Vec::new(),
+            anonymous: false,
+            flex_or_grid_item: false,
+            split_before: false,
+            split_after: false,
         }
     }
 
+    // https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+    // 連続するインラインレベルの子ノードを囲む、匿名ブロックボックスを作る
+    pub fn new_anonymous_block(parent: &Rc<RefCell<LayoutObject>>) -> Self {
+        let mut object = Self::new(parent.borrow().node(), &Some(parent.clone()));
+        object.anonymous = true;
+        // フレックスコンテナとグリッドコンテナの中のテキストを囲む匿名ブロックは、アイテムになる
+        object.flex_or_grid_item = parent.borrow().kind.is_item_container();
+        object.inherit_anonymous_style(&parent.borrow().style);
+        object
+    }
+
+    // 匿名ボックスのスタイルは、継承されるプロパティは親の値、それ以外は初期値になる
+    pub fn inherit_anonymous_style(&mut self, parent_style: &ComputedStyle) {
+        self.style = ComputedStyle::inherit_from(Some(parent_style));
+        self.style.set_display(DisplayType::Block);
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.split_before || self.split_after
+    }
+
+    // ブロックを含むインライン要素を分割して、このノードの後ろに続く部分を作る。
+    // 続く部分は同じ要素とスタイルを持ち、子ノードは持たない
+    pub fn split_continuation(&mut self) -> Self {
+        self.split_after = true;
+        let mut continuation = self.clone();
+        continuation.split_before = true;
+        continuation.split_after = false;
+        continuation.first_child = None;
+        continuation.next_sibling = None;
+        continuation
+    }
+
     pub fn kind(&self) -> LayoutObjectKind {
         self.kind
     }
 
+    pub fn node(&self) -> Rc<RefCell<Node>> {
+        self.node.clone()
+    }
+
     pub fn node_kind(&self) -> NodeKind {
         self.node.borrow().kind().clone()
     }
@@ -86,6 +200,14 @@ impl LayoutObject {
         self.style.clone()
     }
 
+    pub fn set_cascaded_style(&mut self, cascaded_style: CascadedStyle) {
+        self.cascaded_style = cascaded_style;
+    }
+
+    pub fn cascaded_style(&self) -> &CascadedStyle {
+        &self.cascaded_style
+    }
+
     pub fn rect(&self) -> LayoutRect {
         self.rect
     }
@@ -98,93 +220,675 @@ impl LayoutObject {
         self.rect.size
     }
 
+    pub fn margin(&self) -> LayoutEdges {
+        self.margin
+    }
+
+    pub fn border(&self) -> LayoutEdges {
+        self.border
+    }
+
+    pub fn padding(&self) -> LayoutEdges {
+        self.padding
+    }
+
+    pub fn collapsed_margin_top(&self) -> CollapsedMargin {
+        self.collapsed_margin_top
+    }
+
+    pub fn set_offset(&mut self, offset: LayoutPoint) {
+        self.offset = offset;
+    }
+
+    pub fn fragments(&self) -> &[InlineFragment] {
+        &self.fragments
+    }
+
+    // ブロックはボーダーボックス、インライン要素とテキストは断片のどれかに含まれていれば当たる
+    pub fn is_hit(&self, point: LayoutPoint) -> bool {
+        match self.kind {
+            LayoutObjectKind::Block | LayoutObjectKind::Flex | LayoutObjectKind::Grid => {
+                self.rect.is_hit(point)
+            }
+            LayoutObjectKind::Inline | LayoutObjectKind::Text => {
+                self.fragments.iter().any(|f| f.rect.is_hit(point))
+            }
+        }
+    }
+
+    // コンテンツボックスの左上の位置。インライン要素の上下の padding と border はレイアウトに影響しない
+    pub fn content_point(&self) -> LayoutPoint {
+        let mut point = LayoutPoint {
+            x: self.rect.point.x + self.border.left + self.padding.left,
+            y: self.rect.point.y,
+        };
+        if self.kind.is_block_level() {
+            point.y += self.border.top + self.padding.top;
+        }
+        point
+    }
+
+    pub fn content_size(&self) -> LayoutSize {
+        LayoutSize {
+            width: self.rect.size.width - self.border.horizontal() - self.padding.horizontal(),
+            height: self.rect.size.height - self.border.vertical() - self.padding.vertical(),
+        }
+    }
+
+    // https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+    // 上側に border と padding がないブロックは、最初の子ノードの上マージンと相殺する。
+    // ブロック整形文脈を作るブロックのマージンは、子ノードのマージンと相殺しない
+    pub fn collapses_with_first_child(&self) -> bool {
+        self.kind == LayoutObjectKind::Block
+            && self.border.top == 0
+            && self.padding.top == 0
+            && !self.establishes_block_formatting_context()
+    }
+
+    // 下側の場合は、さらに高さが auto のときだけ相殺する
+    fn collapses_with_last_child(&self) -> bool {
+        self.kind == LayoutObjectKind::Block
+            && self.border.bottom == 0
+            && self.padding.bottom == 0
+            && self.has_auto_height()
+            && !self.establishes_block_formatting_context()
+    }
+
+    // 包含ブロックの高さは中身に依存して決まるので、パーセンテージの高さは auto として扱う
+    fn has_auto_height(&self) -> bool {
+        match self.style.height() {
+            Size::Auto => true,
+            Size::Length(height) => height.has_percentage(),
+        }
+    }
+
+    // https://www.w3.org/TR/CSS2/visuren.html#block-formatting
+    // フロートと、絶対位置指定のボックスと、overflow が visible でないブロックと、
+    // display: flow-root のブロックは、新しいブロック整形文脈を作る。
+    // フレックスコンテナとグリッドコンテナと、その中のアイテムも、外のフロートやマージンに影響しない
+    fn establishes_block_formatting_context(&self) -> bool {
+        match self.kind {
+            LayoutObjectKind::Flex | LayoutObjectKind::Grid => true,
+            LayoutObjectKind::Block => {
+                self.is_out_of_flow()
+                    || self.flex_or_grid_item
+                    || self.style.overflow() != Overflow::Visible
+                    || self.style.display() == DisplayType::FlowRoot
+            }
+            LayoutObjectKind::Inline | LayoutObjectKind::Text => false,
+        }
+    }
+
+    // フロートと絶対位置指定のボックスは、通常のフローから外れる。フレックスアイテムとグリッドアイテムの
+    // float は効かない
+    fn is_out_of_flow(&self) -> bool {
+        (self.style.float() != Float::None && !self.flex_or_grid_item)
+            || self.style.position().is_absolutely_positioned()
+    }
+
+    // 通常のフローから外れたボックスを除いた、最初の子ノード
+    fn first_in_flow_child(&self) -> Option<Rc<RefCell<LayoutObject>>> {
+        let mut child = self.first_child();
+        while let Some(c) = child {
+            if !c.borrow().is_out_of_flow() {
+                return Some(c);
+            }
+            child = c.borrow().next_sibling();
+        }
+        None
+    }
+
     pub fn is_node_selected(&self, selector: &Selector) -> bool {
         let NodeKind::Element(element) = &self.node_kind() else {
             return false;
         };
 
-        match selector {
-            Selector::TypeSelector(type_name) => element.kind().to_string() == *type_name,
-            Selector::ClassSelector(class_name) => element
-                .attributes()
-                .iter()
-                .any(|a| a.name() == "class" && a.value() == *class_name),
-            Selector::IdSelector(id_name) => element
-                .attributes()
-                .iter()
-                .any(|a| a.name() == "id" && a.value() == *id_name),
-            Selector::UnknownSelector => false,
+        selector.matches(element)
+    }
+
+    // 継承されるプロパティは親の値、それ以外は初期値でスタイルを初期化する。
+    // 宣言はその後に cascading_style で適用する
+    pub fn defaulting_style(&mut self, parent_style: Option<&ComputedStyle>) {
+        self.style = ComputedStyle::inherit_from(parent_style);
+
+        // text-decoration は継承されないが、祖先の装飾はその中のテキストにも描画される。
+        // 色とスタイルは、装飾を指定した要素のものを使う
+        if let NodeKind::Text(_) = self.node_kind() {
+            self.text_decorations.clear();
+            let mut ancestor = self.parent.upgrade();
+            while let Some(a) = ancestor {
+                if let Some(text_decoration) = a.borrow().style.text_decoration() {
+                    self.text_decorations.insert(0, text_decoration);
+                }
+                ancestor = a.borrow().parent().upgrade();
+            }
         }
     }
 
-    pub fn cascading_style(&mut self, declarations: Vec<Declaration>) {
+    // root_font_size は rem の基準になる、ルート要素のフォントサイズ
+    pub fn cascading_style(
+        &mut self,
+        declarations: Vec<Declaration>,
+        parent_style: Option<&ComputedStyle>,
+        root_font_size: f64,
+        viewport: &Viewport,
+    ) {
+        // ルート要素では inherit も初期値になる
+        let initial_style = ComputedStyle::default();
+        let parent_style = parent_style.unwrap_or(&initial_style);
+        let custom_properties =
+            compute_custom_properties(&declarations, parent_style.custom_properties());
+
+        // var() を置き換えた値。置き換えられない宣言は computed-value time で無効になり、unset として扱う
+        let mut values = Vec::new();
         for declaration in declarations {
-            match declaration.property.as_ref() {
-                "background-color" => {
-                    if let ComponentValue::Ident(value) = &declaration.value {
-                        let color = match Color::from_name(value) {
-                            Ok(color) => color,
-                            Err(_) => Color::white(),
-                        };
-                        self.style.set_background_color(color);
-                        continue;
-                    }
+            if is_custom_property(&declaration.property) {
+                continue;
+            }
 
-                    if let ComponentValue::HashToken(color_code) = &declaration.value {
-                        let color = match Color::from_code(color_code) {
-                            Ok(color) => color,
-                            Err(_) => Color::white(),
-                        };
-                        self.style.set_background_color(color);
-                        continue;
-                    }
+            let unset = || vec![ComponentValue::Ident("unset".to_string())];
+            let value = if contains_var(&declaration.value) {
+                substitute_var(&declaration.value, &custom_properties).unwrap_or_else(unset)
+            } else {
+                declaration.value
+            };
+
+            // var() を含んでいたショートハンドは、置き換えたあとに展開する
+            if let Some(longhands) = longhands(&declaration.property) {
+                match expand_shorthand(&declaration.property, &value) {
+                    Some(expanded) => values.extend(expanded),
+                    None => values.extend(longhands.into_iter().map(|l| (l, unset()))),
+                }
+                continue;
+            }
+            values.push((declaration.property, value));
+        }
+
+        // em は要素自身のフォントサイズを基準にするので、font-size を先に計算する。
+        // font-size 自身の em やパーセンテージは、親のフォントサイズを基準にする
+        let parent_context = LengthContext::new(parent_style.font_size(), root_font_size, viewport);
+        for (_, value) in values
+            .iter()
+            .filter(|(property, _)| property == "font-size")
+        {
+            self.apply_declaration("font-size", value, parent_style, &parent_context);
+        }
+
+        let context = LengthContext::new(self.style.font_size(), root_font_size, viewport);
+        for (property, value) in values {
+            if property == "font-size" {
+                continue;
+            }
+            self.apply_declaration(&property, &value, parent_style, &context);
+        }
+
+        self.style.set_custom_properties(custom_properties);
+    }
+
+    fn apply_declaration(
+        &mut self,
+        property: &str,
+        value: &[ComponentValue],
+        parent_style: &ComputedStyle,
+        context: &LengthContext,
+    ) {
+        if let Some(keyword) = CssWideKeyword::parse(value) {
+            self.style.apply_keyword(property, keyword, parent_style);
+            return;
+        }
+
+        match property {
+            "background-color" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && value.eq_ignore_ascii_case("currentcolor")
+                {
+                    self.style.set_background_color_current_color();
+                    return;
+                }
+
+                // 不正な色の宣言は無視する
+                if let Ok(color) = Color::from_component_values(value) {
+                    self.style.set_background_color(color);
+                }
+            }
+            "color" => {
+                // color プロパティの currentColor は、親の color を継承するのと同じ
+                if let [ComponentValue::Ident(value)] = value
+                    && value.eq_ignore_ascii_case("currentcolor")
+                {
+                    self.style
+                        .apply_keyword(property, CssWideKeyword::Inherit, parent_style);
+                    return;
                 }
-                "color" => {
-                    if let ComponentValue::Ident(value) = &declaration.value {
-                        let color = match Color::from_name(value) {
-                            Ok(color) => color,
-                            Err(_) => Color::black(),
-                        };
-                        self.style.set_color(color);
-                    }
 
-                    if let ComponentValue::HashToken(color_code) = &declaration.value {
-                        let color = match Color::from_code(color_code) {
-                            Ok(color) => color,
-                            Err(_) => Color::black(),
-                        };
-                        self.style.set_color(color);
+                if let Ok(color) = Color::from_component_values(value) {
+                    self.style.set_color(color);
+                }
+            }
+            "font-size" => {
+                if let Some(size) = compute_font_size(value, context) {
+                    self.style.set_font_size(size);
+                }
+            }
+            "font-family" => {
+                if let Some(font_family) = parse_font_family(value) {
+                    self.style.set_font_family(font_family);
+                }
+            }
+            "font-style" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(font_style) = FontStyle::from_str(value)
+                {
+                    self.style.set_font_style(font_style);
+                }
+            }
+            "font-weight" => {
+                if let Some(font_weight) = FontWeight::parse(value) {
+                    self.style
+                        .set_font_weight(font_weight.compute(parent_style.font_weight()));
+                }
+            }
+            "line-height" => {
+                if let Some(line_height) = LineHeight::parse(value, context) {
+                    self.style.set_line_height(line_height);
+                }
+            }
+            "width" => {
+                if let Some(size) = compute_size(value, context) {
+                    self.style.set_width(size);
+                }
+            }
+            "height" => {
+                if let Some(size) = compute_size(value, context) {
+                    self.style.set_height(size);
+                }
+            }
+            "min-width" => {
+                if let Some(size) = compute_size(value, context) {
+                    self.style.set_min_width(size);
+                }
+            }
+            "min-height" => {
+                if let Some(size) = compute_size(value, context) {
+                    self.style.set_min_height(size);
+                }
+            }
+            "max-width" => {
+                if let Some(size) = compute_max_size(value, context) {
+                    self.style.set_max_width(size);
+                }
+            }
+            "max-height" => {
+                if let Some(size) = compute_max_size(value, context) {
+                    self.style.set_max_height(size);
+                }
+            }
+            "box-sizing" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(box_sizing) = BoxSizing::from_str(value)
+                {
+                    self.style.set_box_sizing(box_sizing);
+                }
+            }
+            "float" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(float) = Float::from_str(value)
+                {
+                    self.style.set_float(float);
+                }
+            }
+            "clear" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(clear) = Clear::from_str(value)
+                {
+                    self.style.set_clear(clear);
+                }
+            }
+            "overflow" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(overflow) = Overflow::from_str(value)
+                {
+                    self.style.set_overflow(overflow);
+                }
+            }
+            "position" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(position) = Position::from_str(value)
+                {
+                    self.style.set_position(position);
+                }
+            }
+            "top" | "right" | "bottom" | "left" => {
+                if let Some(side) = Side::ALL.into_iter().find(|s| s.name() == property)
+                    && let Some(inset) = compute_inset(value, context)
+                {
+                    self.style.set_inset(side, inset);
+                }
+            }
+            "z-index" => {
+                if let Some(z_index) = ZIndex::parse(value) {
+                    self.style.set_z_index(z_index);
+                }
+            }
+            "display" => {
+                // サポートしていない値の宣言は無視する
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(display_type) = DisplayType::from_str(value)
+                {
+                    self.style.set_display(display_type);
+                }
+            }
+            "flex-direction" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(flex_direction) = FlexDirection::from_str(value)
+                {
+                    self.style.set_flex_direction(flex_direction);
+                }
+            }
+            "flex-wrap" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(flex_wrap) = FlexWrap::from_str(value)
+                {
+                    self.style.set_flex_wrap(flex_wrap);
+                }
+            }
+            "justify-content" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(justify_content) = JustifyContent::from_str(value)
+                {
+                    self.style.set_justify_content(justify_content);
+                }
+            }
+            "align-items" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(align_items) = AlignItems::from_str(value)
+                {
+                    self.style.set_align_items(align_items);
+                }
+            }
+            "align-self" => {
+                if let [ComponentValue::Ident(value)] = value {
+                    if value.eq_ignore_ascii_case("auto") {
+                        self.style.set_align_self(None);
+                    } else if let Ok(align_self) = AlignItems::from_str(value) {
+                        self.style.set_align_self(Some(align_self));
                     }
                 }
-                "display" => {
-                    if let ComponentValue::Ident(value) = declaration.value {
-                        let display_type = match DisplayType::from_str(&value) {
-                            Ok(display_type) => display_type,
-                            Err(_) => DisplayType::DisplayNone,
-                        };
-                        self.style.set_display(display_type);
+            }
+            "align-content" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(align_content) = AlignContent::from_str(value)
+                {
+                    self.style.set_align_content(align_content);
+                }
+            }
+            "row-gap" => {
+                if let Some(gap) = compute_gap(value, context) {
+                    self.style.set_row_gap(gap);
+                }
+            }
+            "column-gap" => {
+                if let Some(gap) = compute_gap(value, context) {
+                    self.style.set_column_gap(gap);
+                }
+            }
+            "flex-grow" => {
+                if let [ComponentValue::Number(n)] = value
+                    && *n >= 0.0
+                {
+                    self.style.set_flex_grow(*n);
+                }
+            }
+            "flex-shrink" => {
+                if let [ComponentValue::Number(n)] = value
+                    && *n >= 0.0
+                {
+                    self.style.set_flex_shrink(*n);
+                }
+            }
+            "flex-basis" => {
+                if let Some(flex_basis) = FlexBasis::parse(value, context) {
+                    self.style.set_flex_basis(flex_basis);
+                }
+            }
+            "order" => {
+                if let [ComponentValue::Number(n)] = value
+                    && *n == (*n as i64) as f64
+                {
+                    self.style.set_order(*n as i64);
+                }
+            }
+            "justify-items" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(justify_items) = AlignItems::from_str(value)
+                {
+                    self.style.set_justify_items(justify_items);
+                }
+            }
+            "justify-self" => {
+                if let [ComponentValue::Ident(value)] = value {
+                    if value.eq_ignore_ascii_case("auto") {
+                        self.style.set_justify_self(None);
+                    } else if let Ok(justify_self) = AlignItems::from_str(value) {
+                        self.style.set_justify_self(Some(justify_self));
                     }
                 }
-                _ => {}
+            }
+            "grid-template-columns" => {
+                if let Some(tracks) = TrackList::parse(value, context) {
+                    self.style.set_grid_template_columns(tracks);
+                }
+            }
+            "grid-template-rows" => {
+                if let Some(tracks) = TrackList::parse(value, context) {
+                    self.style.set_grid_template_rows(tracks);
+                }
+            }
+            "grid-template-areas" => {
+                if let Some(areas) = GridTemplateAreas::parse(value) {
+                    self.style.set_grid_template_areas(areas);
+                }
+            }
+            "grid-auto-columns" => {
+                if let Some(track) = TrackSize::parse(value, context) {
+                    self.style.set_grid_auto_columns(track);
+                }
+            }
+            "grid-auto-rows" => {
+                if let Some(track) = TrackSize::parse(value, context) {
+                    self.style.set_grid_auto_rows(track);
+                }
+            }
+            "grid-auto-flow" => {
+                if let Some(flow) = GridAutoFlow::parse(value) {
+                    self.style.set_grid_auto_flow(flow);
+                }
+            }
+            "grid-row-start" => {
+                if let Some(line) = GridLine::parse(value) {
+                    self.style.set_grid_row_start(line);
+                }
+            }
+            "grid-column-start" => {
+                if let Some(line) = GridLine::parse(value) {
+                    self.style.set_grid_column_start(line);
+                }
+            }
+            "grid-row-end" => {
+                if let Some(line) = GridLine::parse(value) {
+                    self.style.set_grid_row_end(line);
+                }
+            }
+            "grid-column-end" => {
+                if let Some(line) = GridLine::parse(value) {
+                    self.style.set_grid_column_end(line);
+                }
+            }
+            "letter-spacing" => {
+                if let Some(spacing) = compute_spacing(value, context) {
+                    self.style.set_letter_spacing(spacing);
+                }
+            }
+            "word-spacing" => {
+                if let Some(spacing) = compute_spacing(value, context) {
+                    self.style.set_word_spacing(spacing);
+                }
+            }
+            "text-align" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(text_align) = TextAlign::from_str(value)
+                {
+                    self.style.set_text_align(text_align);
+                }
+            }
+            "text-indent" => {
+                if let Ok(length) = LengthPercentage::parse(value) {
+                    self.style.set_text_indent(length.compute(context));
+                }
+            }
+            "text-transform" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(text_transform) = TextTransform::from_str(value)
+                {
+                    self.style.set_text_transform(text_transform);
+                }
+            }
+            "white-space" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(white_space) = WhiteSpace::from_str(value)
+                {
+                    self.style.set_white_space(white_space);
+                }
+            }
+            "word-break" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(word_break) = WordBreak::from_str(value)
+                {
+                    self.style.set_word_break(word_break);
+                }
+            }
+            "overflow-wrap" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(overflow_wrap) = OverflowWrap::from_str(value)
+                {
+                    self.style.set_overflow_wrap(overflow_wrap);
+                }
+            }
+            "direction" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(direction) = Direction::from_str(value)
+                {
+                    self.style.set_direction(direction);
+                }
+            }
+            "unicode-bidi" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(unicode_bidi) = UnicodeBidi::from_str(value)
+                {
+                    self.style.set_unicode_bidi(unicode_bidi);
+                }
+            }
+            "text-decoration-line" => {
+                if let Some(line) = TextDecorationLine::parse(value) {
+                    self.style.set_text_decoration_line(line);
+                }
+            }
+            "text-decoration-style" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && let Ok(style) = TextDecorationStyle::from_str(value)
+                {
+                    self.style.set_text_decoration_style(style);
+                }
+            }
+            "text-decoration-color" => {
+                if let [ComponentValue::Ident(value)] = value
+                    && value.eq_ignore_ascii_case("currentcolor")
+                {
+                    self.style.set_text_decoration_color_current_color();
+                } else if let Ok(color) = Color::from_component_values(value) {
+                    self.style.set_text_decoration_color(color);
+                }
+            }
+            "vertical-align" => {
+                if let Some(vertical_align) = VerticalAlign::parse(value, context) {
+                    self.style.set_vertical_align(vertical_align);
+                }
+            }
+            property => {
+                if let Some((side_property, side)) = SideProperty::parse(property) {
+                    self.apply_side_declaration(side_property, side, value, context);
+                }
             }
         }
     }
 
-    pub fn defaulting_style(
+    fn apply_side_declaration(
         &mut self,
-        node: &Rc<RefCell<Node>>,
-        parent_style: Option<ComputedStyle>,
+        side_property: SideProperty,
+        side: Side,
+        value: &[ComponentValue],
+        context: &LengthContext,
     ) {
-        self.style.defaulting(node, parent_style);
+        let keyword = match value {
+            [ComponentValue::Ident(keyword)] => Some(keyword.to_ascii_lowercase()),
+            _ => None,
+        };
+
+        match side_property {
+            SideProperty::Margin => {
+                if keyword.as_deref() == Some("auto") {
+                    self.style.set_margin(side, Size::Auto);
+                } else if let Ok(length) = LengthPercentage::parse(value) {
+                    self.style
+                        .set_margin(side, Size::Length(length.compute(context)));
+                }
+            }
+            SideProperty::Padding => {
+                if let Ok(length) = LengthPercentage::parse_non_negative(value) {
+                    self.style.set_padding(side, length.compute(context));
+                }
+            }
+            SideProperty::BorderWidth => {
+                if let Some(width) = compute_border_width(value, context) {
+                    self.style.set_border_width(side, width);
+                }
+            }
+            SideProperty::BorderStyle => {
+                if let Some(Ok(border_style)) = keyword.as_deref().map(BorderStyle::from_str) {
+                    self.style.set_border_style(side, border_style);
+                }
+            }
+            SideProperty::BorderColor => {
+                if keyword.as_deref() == Some("currentcolor") {
+                    self.style.set_border_color_current_color(side);
+                } else if let Ok(color) = Color::from_component_values(value) {
+                    self.style.set_border_color(side, color);
+                }
+            }
+        }
     }
 
     pub fn update_kind(&mut self) {
+        self.flex_or_grid_item = !self.style.position().is_absolutely_positioned()
+            && self
+                .parent
+                .upgrade()
+                .is_some_and(|p| p.borrow().kind.is_item_container());
         self.kind = match self.node_kind() {
             NodeKind::Document => {
                 panic!("should not create Blocka layout object for a Document node")
             }
             NodeKind::Element(_) => match self.style.display() {
-                DisplayType::Block => LayoutObjectKind::Block,
+                DisplayType::Block | DisplayType::FlowRoot => LayoutObjectKind::Block,
+                DisplayType::Flex => LayoutObjectKind::Flex,
+                DisplayType::Grid => LayoutObjectKind::Grid,
+                // https://www.w3.org/TR/CSS2/visuren.html#dis-pos-flo
+                // https://www.w3.org/TR/css-flexbox-1/#flex-items
+                // https://www.w3.org/TR/css-grid-1/#grid-items
+                // フロートと絶対位置指定のボックスと、フレックスアイテムとグリッドアイテムは、ブロックとして並べる
+                DisplayType::Inline if self.is_out_of_flow() || self.flex_or_grid_item => {
+                    LayoutObjectKind::Block
+                }
                 DisplayType::Inline => LayoutObjectKind::Inline,
                 DisplayType::DisplayNone => {
                     panic!("hould not create a layout object for display:none")
@@ -194,162 +898,1772 @@ impl LayoutObject {
         };
     }
 
-    pub fn compute_size(&mut self, parent_size: LayoutSize) {
-        let mut size = LayoutSize {
-            width: 0,
-            height: 0,
-        };
-
+    // 横幅と横方向のマージンを決める。parent_size は包含ブロックのコンテンツボックスの大きさ。
+    // font_metrics は、テキストの幅と行の高さを求めるためにバックエンドが提供するフォントの情報
+    pub fn compute_width(&mut self, parent_size: LayoutSize, font_metrics: &Rc<dyn FontMetrics>) {
         match self.kind() {
-            LayoutObjectKind::Block => {
-                size.width = parent_size.width;
-
-                // すべての子ノードの高さを足し合わせた結果が高さになる。
-                // ただし、インライン要素が横に並んでいる場合は注意が必要
-                let mut height = 0;
-                let mut child = self.first_child();
-                let mut prev_child_kind = LayoutObjectKind::Block;
-                while let Some(c) = child {
-                    if prev_child_kind == LayoutObjectKind::Block
-                        || c.borrow().kind() == LayoutObjectKind::Block
-                    {
-                        height += c.borrow().size().height;
+            LayoutObjectKind::Block | LayoutObjectKind::Flex | LayoutObjectKind::Grid => {
+                self.compute_box_edges(parent_size.width);
+                let containing_width = parent_size.width as f64;
+                let horizontal = (self.border.horizontal() + self.padding.horizontal()) as f64;
+                let is_float = self.style.float() != Float::None;
+                // https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+                // 絶対位置指定のボックスは、left と right の間に置く
+                let absolute = self.style.position().is_absolutely_positioned();
+                let (inset_left, inset_right) = if absolute {
+                    (
+                        self.inset(Side::Left, parent_size.width),
+                        self.inset(Side::Right, parent_size.width),
+                    )
+                } else {
+                    (None, None)
+                };
+                let between_insets = inset_left.is_some() && inset_right.is_some();
+
+                let margin_left = self.style.margin(Side::Left);
+                let margin_right = self.style.margin(Side::Right);
+                let used_margin = |margin: Size| match margin {
+                    Size::Auto => 0.0,
+                    Size::Length(m) => m.resolve(containing_width),
+                };
+                let available = containing_width
+                    - used_margin(margin_left)
+                    - used_margin(margin_right)
+                    - horizontal
+                    - (inset_left.unwrap_or(0) + inset_right.unwrap_or(0)) as f64;
+
+                let width = match self.style.width() {
+                    // https://www.w3.org/TR/CSS2/visudet.html#float-width
+                    // 横幅が auto のフロートと、left か right が auto の絶対位置指定のボックスは、
+                    // 内容に合わせて縮める
+                    Size::Auto if is_float || (absolute && !between_insets) => {
+                        let (min, max) = self.preferred_widths(font_metrics);
+                        available.max(min as f64).min(max as f64)
                     }
+                    Size::Auto => available,
+                    Size::Length(width) => self.content_width(width, containing_width),
+                };
+                let width = self.clamp_content_width(width, containing_width).max(0.0);
 
-                    prev_child_kind = c.borrow().kind();
-                    child = c.borrow().next_sibling();
-                }
-                size.height = height;
-            }
-            LayoutObjectKind::Inline => {
-                // すべての子ノードの高さと横幅を足し合わせた結果が現在のノードの高さと横幅とになる
-                let mut width = 0;
-                let mut height = 0;
-                let mut child = self.first_child();
-                while let Some(c) = child {
-                    let c = c.borrow();
-                    width += c.size().width;
-                    height += c.size().height;
-                    child = c.next_sibling();
-                }
-                size.width = width;
-                size.height = height;
-            }
-            LayoutObjectKind::Text => {
-                if let NodeKind::Text(t) = self.node_kind() {
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
-                    };
-                    let width = CHAR_WIDTH * ratio * t.len() as i64;
-                    if width > CONTENT_AREA_WIDTH {
-                        size.width = CONTENT_AREA_WIDTH;
-                        let line_num = if width.wrapping_rem(CONTENT_AREA_WIDTH) == 0 {
-                            width.wrapping_div(CONTENT_AREA_WIDTH)
-                        } else {
-                            width.wrapping_div(CONTENT_AREA_WIDTH) + 1
-                        };
-                        size.height = CHAR_HEIGHT_WITH_PADDING * ratio * line_num;
-                    } else {
-                        size.width = width;
-                        size.height = CHAR_HEIGHT_WITH_PADDING * ratio;
+                // 余った横幅を auto のマージンに割り当てる。足りないときと、フロートと、
+                // left か right が auto の絶対位置指定のボックスでは、auto のマージンは 0 になる
+                let remaining = (available - width).max(0.0);
+                let (left, right) = match (margin_left, margin_right) {
+                    _ if is_float || (absolute && !between_insets) => {
+                        (used_margin(margin_left), used_margin(margin_right))
                     }
-                }
+                    (Size::Auto, Size::Auto) => (remaining / 2.0, remaining / 2.0),
+                    (Size::Auto, right) => (remaining, used_margin(right)),
+                    (left, Size::Auto) => (used_margin(left), remaining),
+                    (left, right) => (used_margin(left), used_margin(right)),
+                };
+                self.margin.left = left as i64;
+                self.margin.right = right as i64;
+                self.rect.size.width = (width + horizontal) as i64;
             }
+            // 位置と大きさは、包含ブロックが行ボックスに並べるときに決まる
+            LayoutObjectKind::Inline => self.compute_box_edges(parent_size.width),
+            LayoutObjectKind::Text => return,
         }
-
-        self.rect.size = size;
+        self.compute_relative_offset(parent_size.width);
     }
 
-    pub fn compute_position(
-        &mut self,
-        parent_point: LayoutPoint,
-        prev_sibling_kind: LayoutObjectKind,
-        prev_sibling_rect: Option<LayoutRect>,
-    ) {
-        let mut point = LayoutPoint { x: 0, y: 0 };
-
-        match (self.kind(), prev_sibling_kind) {
-            (LayoutObjectKind::Block, _) | (_, LayoutObjectKind::Block) => {
-                if let Some(LayoutRect { point: pos, size }) = prev_sibling_rect {
-                    point.y = pos.y + size.height;
-                } else {
-                    point.y = parent_point.y;
-                }
-                point.x = parent_point.x;
-            }
-            (LayoutObjectKind::Inline, LayoutObjectKind::Inline) => {
-                if let Some(LayoutRect { point: pos, size }) = prev_sibling_rect {
-                    point.x = pos.x + size.width;
-                    point.y = pos.y;
-                } else {
-                    point.x = parent_point.x;
-                    point.y = parent_point.y;
-                }
-            }
-            _ => {
-                point.x = parent_point.x;
-                point.y = parent_point.y;
+    // width、min-width、max-width の値を、コンテンツボックスの横幅にする
+    fn content_width(&self, width: ComputedLength, containing_width: f64) -> f64 {
+        let width = width.resolve(containing_width);
+        match self.style.box_sizing() {
+            BoxSizing::ContentBox => width,
+            BoxSizing::BorderBox => {
+                width - (self.border.horizontal() + self.padding.horizontal()) as f64
             }
         }
-
-        self.rect.point = point;
     }
 
-    pub fn paint(&mut self) -> Vec<DisplayItem> {
+    // コンテンツボックスの横幅を、min-width と max-width の間に収める
+    fn clamp_content_width(&self, width: f64, containing_width: f64) -> f64 {
+        let mut width = width;
+        if let Some(max_width) = self.style.max_width() {
+            width = width.min(self.content_width(max_width, containing_width));
+        }
+        if let Size::Length(min_width) = self.style.min_width() {
+            width = width.max(self.content_width(min_width, containing_width));
+        }
+        width
+    }
+
+    // height、min-height、max-height の値を、コンテンツボックスの高さにする
+    fn content_height(&self, height: ComputedLength) -> f64 {
+        match self.style.box_sizing() {
+            BoxSizing::ContentBox => height.px,
+            BoxSizing::BorderBox => {
+                height.px - (self.border.vertical() + self.padding.vertical()) as f64
+            }
+        }
+    }
+
+    // コンテンツボックスの高さを、min-height と max-height の間に収める。パーセンテージは無視する
+    fn clamp_content_height(&self, height: f64) -> f64 {
+        let mut height = height;
+        if let Some(max_height) = self.style.max_height()
+            && !max_height.has_percentage()
+        {
+            height = height.min(self.content_height(max_height));
+        }
+        if let Size::Length(min_height) = self.style.min_height()
+            && !min_height.has_percentage()
+        {
+            height = height.max(self.content_height(min_height));
+        }
+        height
+    }
+
+    // 中身に依存しないで決まる、コンテンツボックスの高さ
+    fn definite_content_height(&self) -> Option<i64> {
+        match self.style.height() {
+            Size::Length(height) if !height.has_percentage() => {
+                let height = self.clamp_content_height(self.content_height(height));
+                Some(height.max(0.0) as i64)
+            }
+            _ => None,
+        }
+    }
+
+    // box-sizing に従って、指定した大きさをボーダーボックスの大きさにする。edges は border と padding の和
+    fn border_box_size(&self, size: f64, edges: i64) -> i64 {
+        match self.style.box_sizing() {
+            BoxSizing::ContentBox => size as i64 + edges,
+            BoxSizing::BorderBox => (size as i64).max(edges),
+        }
+    }
+
+    // https://www.w3.org/TR/css-flexbox-1/#algo-main-item
+    // フレックスアイテムの、主軸の方向の大きさ。containing_width はコンテナのコンテンツボックスの横幅で、
+    // available は主軸の方向の大きさ。縦の主軸のアイテムは、横幅を決めて中身を並べてあること
+    fn flex_item(
+        &self,
+        direction: FlexDirection,
+        containing_width: i64,
+        available: Option<i64>,
+        font_metrics: &Rc<dyn FontMetrics>,
+    ) -> FlexItem {
+        let is_row = direction.is_row();
+        let (start, end) = match direction {
+            FlexDirection::Row => (Side::Left, Side::Right),
+            FlexDirection::RowReverse => (Side::Right, Side::Left),
+            FlexDirection::Column => (Side::Top, Side::Bottom),
+            FlexDirection::ColumnReverse => (Side::Bottom, Side::Top),
+        };
+        let margin = |side: Side| match self.style.margin(side) {
+            Size::Auto => None,
+            Size::Length(margin) => Some(margin.resolve(containing_width as f64) as i64),
+        };
+
+        // 中身に合わせた最小と最大の大きさと、width や height で指定した大きさ。
+        // 縦の主軸では、並べた中身の高さになる
+        let (edges, (min_content, max_content), specified) = if is_row {
+            let edges = self.border.horizontal() + self.padding.horizontal();
+            let (min, max) = self.preferred_widths(font_metrics);
+            let specified = match self.style.width() {
+                Size::Length(width) => {
+                    Some(self.border_box_size(width.resolve(containing_width as f64), edges))
+                }
+                Size::Auto => None,
+            };
+            (edges, (min + edges, max + edges), specified)
+        } else {
+            let edges = self.border.vertical() + self.padding.vertical();
+            let height = self.rect.size.height;
+            (
+                edges,
+                (height, height),
+                self.definite_content_height().map(|h| h + edges),
+            )
+        };
+
+        // https://www.w3.org/TR/css-flexbox-1/#flex-base-size
+        // パーセンテージの flex-basis は、コンテナの大きさが決まっていないときは content として扱う
+        let base_size = match self.style.flex_basis() {
+            FlexBasis::Length(basis) if !basis.has_percentage() || available.is_some() => {
+                self.border_box_size(basis.resolve(available.unwrap_or(0) as f64), edges)
+            }
+            FlexBasis::Auto => specified.unwrap_or(max_content),
+            FlexBasis::Content | FlexBasis::Length(_) => max_content,
+        };
+
+        // https://www.w3.org/TR/css-flexbox-1/#min-size-auto
+        // min-width や min-height が auto のアイテムは、中身の最小の大きさより小さくしない
+        let basis = available.unwrap_or(0) as f64;
+        let (min, max) = if is_row {
+            (self.style.min_width(), self.style.max_width())
+        } else {
+            (self.style.min_height(), self.style.max_height())
+        };
+        let min_size = match min {
+            Size::Length(min) => self.border_box_size(min.resolve(basis), edges),
+            Size::Auto if self.style.overflow() == Overflow::Visible => {
+                specified.map_or(min_content, |s| s.min(min_content))
+            }
+            Size::Auto => 0,
+        };
+
+        FlexItem {
+            base_size,
+            min_size,
+            max_size: max.map(|max| self.border_box_size(max.resolve(basis), edges)),
+            margin_start: margin(start),
+            margin_end: margin(end),
+            grow: self.style.flex_grow(),
+            shrink: self.style.flex_shrink(),
+        }
+    }
+
+    // 縦の主軸のフレックスアイテムと、グリッドアイテムのボーダーボックスの横幅。stretch は
+    // 引き伸ばすときのマージンボックスの横幅で、引き伸ばさないときは中身に合わせて縮める
+    fn fit_width(
+        &self,
+        containing_width: i64,
+        stretch: Option<i64>,
+        font_metrics: &Rc<dyn FontMetrics>,
+    ) -> i64 {
+        let containing = containing_width as f64;
+        let edges = self.border.horizontal() + self.padding.horizontal();
+        let width = match self.style.width() {
+            Size::Length(width) => self.content_width(width, containing),
+            Size::Auto => match stretch {
+                Some(stretch) => (stretch - self.margin.horizontal() - edges) as f64,
+                None => {
+                    let (min, max) = self.preferred_widths(font_metrics);
+                    let available = containing_width - self.margin.horizontal() - edges;
+                    available.max(min).min(max) as f64
+                }
+            },
+        };
+        self.clamp_content_width(width, containing).max(0.0) as i64 + edges
+    }
+
+    // top、right、bottom、left の使用値。auto は None になる。base はパーセンテージの基準
+    fn inset(&self, side: Side, base: i64) -> Option<i64> {
+        match self.style.inset(side) {
+            Size::Auto => None,
+            Size::Length(length) => Some(length.resolve(base as f64) as i64),
+        }
+    }
+
+    // https://www.w3.org/TR/CSS2/visuren.html#relative-positioning
+    // 相対位置指定のボックスは、通常のフローの位置から left と top の分だけずらす。
+    // left と top は right と bottom より優先する。包含ブロックの高さは中身に依存して
+    // 決まるので、top と bottom のパーセンテージは 0 として扱う
+    fn compute_relative_offset(&mut self, containing_width: i64) {
+        self.relative_offset = LayoutPoint { x: 0, y: 0 };
+        if self.style.position() != Position::Relative {
+            return;
+        }
+        self.relative_offset = LayoutPoint {
+            x: self
+                .inset(Side::Left, containing_width)
+                .or_else(|| self.inset(Side::Right, containing_width).map(|r| -r))
+                .unwrap_or(0),
+            y: self
+                .inset(Side::Top, 0)
+                .or_else(|| self.inset(Side::Bottom, 0).map(|b| -b))
+                .unwrap_or(0),
+        };
+    }
+
+    // https://www.w3.org/TR/CSS2/visudet.html#float-width
+    // 内容を最も狭く並べたときの横幅と、改行しないで並べたときの横幅
+    fn preferred_widths(&self, font_metrics: &Rc<dyn FontMetrics>) -> (i64, i64) {
+        // グリッドコンテナでは、列の幅をアイテムの中身に合わせて決めた幅になる
+        if self.kind == LayoutObjectKind::Grid {
+            let children = grid_items(self);
+            let gap = self.style.column_gap().resolve(0.0) as i64;
+            let lines: Vec<GridItemLines> = children
+                .iter()
+                .map(|c| c.borrow().grid_item_lines())
+                .collect();
+            let grid = build_grid(&self.style, &lines, (None, None), (0, gap));
+            let sizes: Vec<GridItemSize> = children
+                .iter()
+                .zip(&grid.areas)
+                .map(|(c, area)| {
+                    let (min, max) = c.borrow_mut().outer_preferred_widths(font_metrics);
+                    GridItemSize {
+                        tracks: area.columns.clone(),
+                        min_content: min,
+                        max_content: max,
+                    }
+                })
+                .collect();
+            let width = |sizes: &[GridItemSize]| {
+                let columns = size_tracks(&grid.columns, sizes, None, gap);
+                span_size(&columns, 0..columns.len(), gap)
+            };
+            let min_sizes: Vec<GridItemSize> = sizes
+                .iter()
+                .map(|s| GridItemSize {
+                    max_content: s.min_content,
+                    ..s.clone()
+                })
+                .collect();
+            return (width(&min_sizes), width(&sizes));
+        }
+
+        let mut child = self.first_child();
+        if child
+            .as_ref()
+            .is_some_and(|c| !c.borrow().kind().is_block_level())
+        {
+            let mut items = Vec::new();
+            let mut owners = Vec::new();
+            while let Some(c) = child {
+                prepare_inline(&c, 0, font_metrics);
+                collect_inline_items(&c, &mut items, &mut owners, font_metrics);
+                child = c.borrow().next_sibling();
+            }
+            let paragraph = ParagraphStyle {
+                text_align: TextAlign::Left,
+                direction: self.style.direction(),
+                unicode_bidi: self.style.unicode_bidi(),
+                text_indent: 0,
+            };
+            let measure = |width: i64| {
+                layout_inline(
+                    &items,
+                    self.box_metrics(font_metrics.as_ref()),
+                    paragraph,
+                    width,
+                    &FloatContext::default(),
+                )
+                .fragments
+                .iter()
+                .map(|f| f.rect.point.x + f.rect.size.width)
+                .max()
+                .unwrap_or(0)
+            };
+            return (measure(0), measure(i64::MAX / 4));
+        }
+
+        // 横の主軸のフレックスコンテナでは、アイテムを横に並べた幅になる。
+        // 折り返すときの最も狭い幅は、最も広いアイテムの幅になる
+        let in_row = self.kind == LayoutObjectKind::Flex && self.style.flex_direction().is_row();
+        let wraps = self.style.flex_wrap() != FlexWrap::Nowrap;
+        let gap = self.style.column_gap().resolve(0.0) as i64;
+        let mut widths: Option<(i64, i64)> = None;
+        while let Some(c) = child {
+            child = c.borrow().next_sibling();
+            if c.borrow().style.position().is_absolutely_positioned() {
+                continue;
+            }
+            let (min, max) = c.borrow_mut().outer_preferred_widths(font_metrics);
+            widths = Some(match widths {
+                None => (min, max),
+                Some((total_min, total_max)) if in_row => (
+                    if wraps {
+                        total_min.max(min)
+                    } else {
+                        total_min + gap + min
+                    },
+                    total_max + gap + max,
+                ),
+                Some((total_min, total_max)) => (total_min.max(min), total_max.max(max)),
+            });
+        }
+        widths.unwrap_or((0, 0))
+    }
+
+    // ブロックレベルの子ノードの、マージンボックスの最も狭い横幅と最も広い横幅。
+    // パーセンテージの横幅とマージンは、包含ブロックの横幅が決まっていないので使わない
+    fn outer_preferred_widths(&mut self, font_metrics: &Rc<dyn FontMetrics>) -> (i64, i64) {
+        self.compute_box_edges(0);
+        let edges = self.border.horizontal() + self.padding.horizontal();
+        let (min, max) = match self.style.width() {
+            Size::Length(width) if !width.has_percentage() => {
+                let width = match self.style.box_sizing() {
+                    BoxSizing::ContentBox => width.px as i64 + edges,
+                    BoxSizing::BorderBox => (width.px as i64).max(edges),
+                };
+                (width, width)
+            }
+            _ => {
+                let (min, max) = self.preferred_widths(font_metrics);
+                (min + edges, max + edges)
+            }
+        };
+        (
+            min + self.margin.horizontal(),
+            max + self.margin.horizontal(),
+        )
+    }
+
+    fn grid_item_lines(&self) -> GridItemLines {
+        GridItemLines {
+            row_start: self.style.grid_row_start().clone(),
+            column_start: self.style.grid_column_start().clone(),
+            row_end: self.style.grid_row_end().clone(),
+            column_end: self.style.grid_column_end().clone(),
+        }
+    }
+
+    // margin、border、padding の使用値を求める。パーセンテージは上下も含めて包含ブロックの横幅が基準になる
+    fn compute_box_edges(&mut self, containing_width: i64) {
+        let containing_width = containing_width as f64;
+        for side in Side::ALL {
+            let margin = match self.style.margin(side) {
+                Size::Auto => 0,
+                Size::Length(margin) => margin.resolve(containing_width) as i64,
+            };
+            let padding = self.style.padding(side).resolve(containing_width).max(0.0) as i64;
+            self.margin.set(side, margin);
+            self.padding.set(side, padding);
+            self.border.set(side, self.style.border_width(side) as i64);
+        }
+    }
+
+    // 直前の兄弟のブロックの下端、または最初の子ノードなら親のコンテンツボックスの上端から、
+    // このブロックのボーダーボックスの上端までの距離
+    fn margin_before(
+        &self,
+        prev_sibling: Option<&LayoutObject>,
+        collapses_with_parent: bool,
+    ) -> i64 {
+        match prev_sibling {
+            // 親と相殺したマージンは、親の外側に出る
+            None if collapses_with_parent => 0,
+            None => self.collapsed_margin_top.value(),
+            // 隣り合うブロックの上下のマージンは相殺する
+            Some(prev) => prev
+                .collapsed_margin_bottom
+                .join(self.collapsed_margin_top)
+                .value(),
+        }
+    }
+
+    // text-indent は、要素の最初の行だけを字下げする。
+    // 匿名ブロックの行は、匿名ブロックが親の最初の子ノードのときだけ要素の最初の行になる
+    fn has_first_line(&self) -> bool {
+        if !self.anonymous {
+            return true;
+        }
+        self.parent
+            .upgrade()
+            .and_then(|parent| parent.borrow().first_child())
+            .is_some_and(|first| core::ptr::eq(first.as_ptr(), self))
+    }
+
+    // インラインレベルの子ノードをコンテンツボックスの上端から行ボックスに並べ、並べた高さを返す。
+    // floats はコンテンツボックスの左上から見たフロート
+    fn layout_inline_run(
+        &self,
+        run: &[Rc<RefCell<LayoutObject>>],
+        width: i64,
+        text_indent: i64,
+        floats: &FloatContext,
+        font_metrics: &Rc<dyn FontMetrics>,
+    ) -> i64 {
+        let mut items = Vec::new();
+        let mut owners = Vec::new();
+        for object in run {
+            collect_inline_items(object, &mut items, &mut owners, font_metrics);
+        }
+
+        let layout = layout_inline(
+            &items,
+            self.box_metrics(font_metrics.as_ref()),
+            ParagraphStyle {
+                text_align: self.style.text_align(),
+                direction: self.style.direction(),
+                unicode_bidi: self.style.unicode_bidi(),
+                text_indent,
+            },
+            width,
+            floats,
+        );
+
+        for owner in &owners {
+            owner.borrow_mut().fragments.clear();
+        }
+        for fragment in layout.fragments {
+            let mut owner = owners[fragment.owner].borrow_mut();
+            if owner.kind.is_block_level() {
+                // 行の中のブロックは、margin を含めた大きさで並べている
+                owner.offset = LayoutPoint {
+                    x: fragment.rect.point.x + owner.margin.left,
+                    y: fragment.rect.point.y + owner.margin.top,
+                };
+            } else {
+                owner.fragments.push(fragment);
+            }
+        }
+
+        layout.height
+    }
+
+    // フォントの縦方向の大きさと line-height から決まる、行の中での縦方向の大きさ
+    fn box_metrics(&self, font_metrics: &dyn FontMetrics) -> BoxMetrics {
+        let font_size = self.style.font_size();
+        let VerticalMetrics {
+            ascent,
+            descent,
+            line_gap,
+        } = font_metrics.vertical_metrics(&self.style.font());
+        let line_height = match self.style.line_height() {
+            LineHeight::Normal => ascent + descent + line_gap,
+            line_height => line_height.resolve(font_size) as i64,
+        };
+        BoxMetrics {
+            ascent,
+            descent,
+            line_height,
+            font_size,
+        }
+    }
+
+    fn inline_box_style(&self, font_metrics: &dyn FontMetrics) -> InlineBoxStyle {
+        // 右から左のボックスでは、始まりの側が右になる
+        let direction = self.style.direction();
+        let (start, end) = match direction {
+            Direction::Ltr => (Side::Left, Side::Right),
+            Direction::Rtl => (Side::Right, Side::Left),
+        };
+        let inner = |side: Side| self.border.get(side) + self.padding.get(side);
+        InlineBoxStyle {
+            metrics: self.box_metrics(font_metrics),
+            vertical_align: self.style.vertical_align(),
+            // 分割したインライン要素の始まりと終わりの側の margin、border、padding は、
+            // 最初と最後の部分にだけある
+            margin_start: if self.split_before {
+                0
+            } else {
+                self.margin.get(start)
+            },
+            margin_end: if self.split_after {
+                0
+            } else {
+                self.margin.get(end)
+            },
+            inner_start: if self.split_before { 0 } else { inner(start) },
+            inner_end: if self.split_after { 0 } else { inner(end) },
+            inner_top: self.border.top + self.padding.top,
+            inner_bottom: self.border.bottom + self.padding.bottom,
+            direction,
+            unicode_bidi: self.style.unicode_bidi(),
+        }
+    }
+
+    // containing は包含ブロックのコンテンツボックス。context は位置指定したボックスを置く基準
+    pub fn compute_position(&mut self, containing: LayoutRect, context: &PositionContext) {
+        let containing_point = containing.point;
+        match self.kind {
+            LayoutObjectKind::Block | LayoutObjectKind::Flex | LayoutObjectKind::Grid => {
+                self.rect.point = if self.style.position().is_absolutely_positioned() {
+                    self.absolute_position(containing_point, context)
+                } else {
+                    LayoutPoint {
+                        x: containing_point.x + self.offset.x,
+                        y: containing_point.y + self.offset.y,
+                    }
+                };
+                if self.style.position() == Position::Sticky {
+                    self.relative_offset = sticky_offset(
+                        self.rect,
+                        self.sticky_insets(context.viewport.size),
+                        context.viewport,
+                        containing,
+                    );
+                }
+                self.rect.point.x += self.relative_offset.x;
+                self.rect.point.y += self.relative_offset.y;
+            }
+            LayoutObjectKind::Inline | LayoutObjectKind::Text => {
+                for fragment in &mut self.fragments {
+                    fragment.rect.point.x += containing_point.x;
+                    fragment.rect.point.y += containing_point.y;
+                }
+
+                let Some(first) = self.fragments.first() else {
+                    self.rect = LayoutRect {
+                        point: containing_point,
+                        size: LayoutSize {
+                            width: 0,
+                            height: 0,
+                        },
+                    };
+                    return;
+                };
+                let mut left = first.rect.point.x;
+                let mut top = first.rect.point.y;
+                let mut right = left + first.rect.size.width;
+                let mut bottom = top + first.rect.size.height;
+                for fragment in &self.fragments {
+                    let LayoutRect { point, size } = fragment.rect;
+                    left = left.min(point.x);
+                    top = top.min(point.y);
+                    right = right.max(point.x + size.width);
+                    bottom = bottom.max(point.y + size.height);
+                }
+                self.rect = LayoutRect::new(left, top, right - left, bottom - top);
+
+                if self.style.position() == Position::Sticky {
+                    self.relative_offset = sticky_offset(
+                        self.rect,
+                        self.sticky_insets(context.viewport.size),
+                        context.viewport,
+                        containing,
+                    );
+                }
+                let LayoutPoint { x, y } = self.relative_offset;
+                for fragment in &mut self.fragments {
+                    fragment.rect.point.x += x;
+                    fragment.rect.point.y += y;
+                }
+                self.rect.point.x += x;
+                self.rect.point.y += y;
+            }
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.style.position()
+    }
+
+    // 相対位置指定と sticky のボックスを、通常のフローの位置からずらす量
+    pub fn relative_offset(&self) -> LayoutPoint {
+        self.relative_offset
+    }
+
+    // 絶対位置指定のボックスの位置。left と right、または top と bottom が両方 auto の軸では、
+    // 通常のフローに置かれるはずだった位置に置く
+    fn absolute_position(
+        &self,
+        containing_point: LayoutPoint,
+        context: &PositionContext,
+    ) -> LayoutPoint {
+        let origin = match self.style.position() {
+            Position::Fixed => context.viewport.point,
+            _ => context.absolute,
+        };
+        let is_static = |start: Side, end: Side| {
+            self.style.inset(start) == Size::Auto && self.style.inset(end) == Size::Auto
+        };
+        LayoutPoint {
+            x: if is_static(Side::Left, Side::Right) {
+                containing_point.x + self.static_offset.x + self.margin.left
+            } else {
+                origin.x + self.offset.x
+            },
+            y: if is_static(Side::Top, Side::Bottom) {
+                containing_point.y + self.static_offset.y + self.margin.top
+            } else {
+                origin.y + self.offset.y
+            },
+        }
+    }
+
+    // sticky のボックスが留まる、ビューポートの端からの距離
+    fn sticky_insets(&self, viewport: LayoutSize) -> Sides<Option<i64>> {
+        Sides {
+            top: self.inset(Side::Top, viewport.height),
+            right: self.inset(Side::Right, viewport.width),
+            bottom: self.inset(Side::Bottom, viewport.height),
+            left: self.inset(Side::Left, viewport.width),
+        }
+    }
+
+    // パディングボックス。位置指定したブロックは、絶対位置指定の子孫の包含ブロックになる
+    pub fn padding_box(&self) -> LayoutRect {
+        LayoutRect::new(
+            self.rect.point.x + self.border.left,
+            self.rect.point.y + self.border.top,
+            self.rect.size.width - self.border.horizontal(),
+            self.rect.size.height - self.border.vertical(),
+        )
+    }
+
+    // backdrop はこのノードより下に描画されている背景色。半透明の色はこの色と合成して描画する
+    pub fn paint(&mut self, backdrop: Color) -> Vec<DisplayItem> {
         if self.style.display() == DisplayType::DisplayNone {
             return Vec::new();
         }
 
         match self.kind {
-            LayoutObjectKind::Block => {
+            LayoutObjectKind::Block | LayoutObjectKind::Flex | LayoutObjectKind::Grid => {
                 if let NodeKind::Element(_) = self.node_kind() {
-                    return vec![DisplayItem::Rect {
-                        style: self.style(),
-                        layout_rect: self.rect,
-                    }];
+                    let mut items = Vec::new();
+
+                    // 背景はボーダーボックス全体に描画する
+                    let background = self.style.background_color().blend(backdrop);
+                    if !self.style.background_color().is_transparent() {
+                        let mut style = self.style();
+                        style.set_background_color(background);
+                        items.push(DisplayItem::Rect {
+                            style,
+                            layout_rect: self.rect,
+                        });
+                    }
+
+                    items.extend(self.paint_borders(self.rect, &Side::ALL, background));
+                    return items;
                 }
             }
             LayoutObjectKind::Inline => {
-                // 本書のブラウザでは、描画するインライン要素はない。
-                // <img> タグなどをサポートした場合はこのアームの中で処理をする
-            }
-            LayoutObjectKind::Text => {
-                if let NodeKind::Text(t) = self.node_kind() {
-                    let mut v = Vec::new();
+                // 行ごとの断片に背景と border を描画する。左右の border は、
+                // 要素が始まる断片と終わる断片にだけ描画する
+                let mut items = Vec::new();
+                let background = self.style.background_color().blend(backdrop);
+                for fragment in &self.fragments {
+                    if !self.style.background_color().is_transparent() {
+                        let mut style = self.style();
+                        style.set_background_color(background);
+                        items.push(DisplayItem::Rect {
+                            style,
+                            layout_rect: fragment.rect,
+                        });
+                    }
 
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
+                    // 右から左のボックスでは、始まりの側が右になる
+                    let (start, end) = match self.style.direction() {
+                        Direction::Ltr => (Side::Left, Side::Right),
+                        Direction::Rtl => (Side::Right, Side::Left),
                     };
-                    let plain_text = t
-                        .replace("\n", " ")
-                        .split(' ')
-                        .filter(|s| !s.is_empty())
-                        .collect::<Vec<_>>()
-                        .join(" ");
-                    let lines = split_text(plain_text, CHAR_WIDTH * ratio);
-                    for (i, line) in lines.into_iter().enumerate() {
-                        let item = DisplayItem::Text {
-                            text: line,
-                            style: self.style(),
-                            layout_point: LayoutPoint {
-                                x: self.rect.point.x,
-                                y: self.rect.point.y + CHAR_HEIGHT_WITH_PADDING * i as i64,
-                            },
-                        };
-                        v.push(item);
+                    let mut sides = vec![Side::Top, Side::Bottom];
+                    if fragment.is_first && !self.split_before {
+                        sides.push(start);
+                    }
+                    if fragment.is_last && !self.split_after {
+                        sides.push(end);
                     }
+                    items.extend(self.paint_borders(fragment.rect, &sides, background));
+                }
+                return items;
+            }
+            LayoutObjectKind::Text => {
+                let mut v = Vec::new();
+
+                let mut style = self.style();
+                style.set_color(self.style.color().blend(backdrop));
+                let font = self.style.font();
+                for fragment in &self.fragments {
+                    v.push(DisplayItem::Text {
+                        text: fragment.text.clone(),
+                        style: style.clone(),
+                        font: font.clone(),
+                        layout_point: fragment.rect.point,
+                    });
+                }
 
-                    return v;
+                // 装飾は、行ごとにその行の最も左の断片から最も右の断片まで引く
+                let mut start = 0;
+                while start < self.fragments.len() {
+                    let line = self.fragments[start].line;
+                    let end = self.fragments[start..]
+                        .iter()
+                        .position(|f| f.line != line)
+                        .map_or(self.fragments.len(), |i| start + i);
+                    let fragments = &self.fragments[start..end];
+                    let first = fragments[0].rect;
+                    let left = fragments.iter().map(|f| f.rect.point.x).min().unwrap_or(0);
+                    let right = fragments
+                        .iter()
+                        .map(|f| f.rect.point.x + f.rect.size.width)
+                        .max()
+                        .unwrap_or(0);
+                    let line_rect =
+                        LayoutRect::new(left, first.point.y, right - left, first.size.height);
+                    v.extend(self.paint_decorations(line_rect, backdrop));
+                    start = end;
                 }
+
+                return v;
             }
         }
 
         Vec::new()
     }
+
+    fn text_metrics(&self, font_metrics: &Rc<dyn FontMetrics>) -> TextMetrics {
+        TextMetrics {
+            font: self.style.font(),
+            font_metrics: font_metrics.clone(),
+            letter_spacing: self.style.letter_spacing() as i64,
+            word_spacing: self.style.word_spacing() as i64,
+        }
+    }
+
+    // 1行ぶんのテキストの装飾を描画する。rect は行の中の文字を囲む矩形で、
+    // 下線は矩形の下端に、取り消し線は中央に引く。線の太さはフォントの倍率と同じにする
+    fn paint_decorations(&self, rect: LayoutRect, backdrop: Color) -> Vec<DisplayItem> {
+        let mut items = Vec::new();
+
+        let ratio = font_size_ratio(self.style.font_size());
+        let LayoutRect { point, size } = rect;
+        for decoration in &self.text_decorations {
+            let positions = [
+                (decoration.line.underline, size.height),
+                (decoration.line.overline, 0),
+                (decoration.line.line_through, size.height / 2),
+            ];
+            for (_, offset) in positions.into_iter().filter(|(enabled, _)| *enabled) {
+                items.push(DisplayItem::Decoration {
+                    decoration_style: decoration.style,
+                    color: decoration.color.blend(backdrop),
+                    layout_rect: LayoutRect::new(point.x, point.y + offset, size.width, ratio),
+                });
+            }
+        }
+
+        items
+    }
+
+    // 辺ごとに、rect の縁に沿った帯として border を描画する
+    fn paint_borders(
+        &self,
+        rect: LayoutRect,
+        sides: &[Side],
+        background: Color,
+    ) -> Vec<DisplayItem> {
+        let LayoutRect { point, size } = rect;
+        let mut items = Vec::new();
+
+        for side in sides.iter().copied() {
+            let width = self.border.get(side);
+            if width <= 0 {
+                continue;
+            }
+
+            let layout_rect = match side {
+                Side::Top => LayoutRect::new(point.x, point.y, size.width, width),
+                Side::Right => {
+                    LayoutRect::new(point.x + size.width - width, point.y, width, size.height)
+                }
+                Side::Bottom => {
+                    LayoutRect::new(point.x, point.y + size.height - width, size.width, width)
+                }
+                Side::Left => LayoutRect::new(point.x, point.y, width, size.height),
+            };
+            items.push(DisplayItem::Border {
+                border_style: self.style.border_style(side),
+                color: self.style.border_color(side).blend(background),
+                layout_rect,
+            });
+        }
+
+        items
+    }
+}
+
+// https://www.w3.org/TR/CSS2/visuren.html#block-formatting
+// 横幅を決めたブロックの子ノードを並べて、高さを決める。floats はブロックが属する
+// ブロック整形文脈のフロートで、origin はブロックのコンテンツボックスの、整形文脈の中での位置
+pub fn layout_block(
+    block: &Rc<RefCell<LayoutObject>>,
+    floats: &mut FloatContext,
+    origin: LayoutPoint,
+    font_metrics: &Rc<dyn FontMetrics>,
+) {
+    // フレックスコンテナとグリッドコンテナのマージンは、中のアイテムのマージンと相殺しない
+    let kind = block.borrow().kind();
+    if kind.is_item_container() {
+        let height = if kind == LayoutObjectKind::Flex {
+            layout_flex(block, font_metrics)
+        } else {
+            layout_grid(block, font_metrics)
+        };
+        {
+            let mut b = block.borrow_mut();
+            b.collapsed_margin_top = CollapsedMargin::new(b.margin.top);
+            b.collapsed_margin_bottom = CollapsedMargin::new(b.margin.bottom);
+        }
+        resolve_height(block, height, font_metrics);
+        return;
+    }
+
+    // 新しいブロック整形文脈の中のフロートは、外のボックスに影響しない
+    let mut own_floats = FloatContext::default();
+    let establishes_context = block.borrow().establishes_block_formatting_context();
+    let (floats, origin) = if establishes_context {
+        (&mut own_floats, LayoutPoint { x: 0, y: 0 })
+    } else {
+        (floats, origin)
+    };
+
+    // 子ノードは、匿名ブロックによってすべてブロックか、すべてインラインレベルになっている。
+    // ブロックは上から順に並べて間にマージンをあけ、インラインレベルのノードは行ボックスに並べる
+    let content_size = block.borrow().content_size();
+    let mut height = 0;
+    let mut prev_child: Option<Rc<RefCell<LayoutObject>>> = None;
+    let mut child = block.borrow().first_child();
+    if child
+        .as_ref()
+        .is_some_and(|c| !c.borrow().kind().is_block_level())
+    {
+        let mut run = Vec::new();
+        while let Some(c) = child {
+            prepare_inline(&c, content_size.width, font_metrics);
+            child = c.borrow().next_sibling();
+            run.push(c);
+        }
+        let b = block.borrow();
+        let text_indent = if b.has_first_line() {
+            b.style.text_indent().resolve(content_size.width as f64) as i64
+        } else {
+            0
+        };
+        height = b.layout_inline_run(
+            &run,
+            content_size.width,
+            text_indent,
+            &floats.translated(origin),
+            font_metrics,
+        );
+    }
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+
+        // 絶対位置指定のボックスは、包含ブロックを並べたあとに置く。ここでは、
+        // 通常のフローに置かれるはずだった位置だけを覚えておく
+        if c.borrow().style.position().is_absolutely_positioned() {
+            c.borrow_mut().static_offset = LayoutPoint { x: 0, y: height };
+            continue;
+        }
+
+        c.borrow_mut().compute_width(content_size, font_metrics);
+
+        // https://www.w3.org/TR/CSS2/visuren.html#float-position
+        // フロートは、直前のブロックの下端より下で、できるだけ上の左端か右端に置く
+        let side = c.borrow().style.float();
+        if side != Float::None {
+            layout_block(
+                &c,
+                &mut FloatContext::default(),
+                LayoutPoint { x: 0, y: 0 },
+                font_metrics,
+            );
+            let mut top = origin.y
+                + height
+                + prev_child
+                    .as_ref()
+                    .map_or(0, |p| p.borrow().collapsed_margin_bottom.value());
+            if let Some(bottom) = floats.clearance(c.borrow().style.clear()) {
+                top = top.max(bottom);
+            }
+            let mut c = c.borrow_mut();
+            let point = floats.place(
+                side,
+                c.margin.horizontal() + c.rect.size.width,
+                c.margin.vertical() + c.rect.size.height,
+                top,
+                origin.x,
+                origin.x + content_size.width,
+            );
+            c.offset = LayoutPoint {
+                x: point.x - origin.x + c.margin.left,
+                y: point.y - origin.y + c.margin.top,
+            };
+            continue;
+        }
+
+        collapse_top_margin(&c, font_metrics);
+        {
+            let prev = prev_child.as_ref().map(|p| p.borrow());
+            height += c
+                .borrow()
+                .margin_before(prev.as_deref(), block.borrow().collapses_with_first_child());
+        }
+
+        // https://www.w3.org/TR/CSS2/visuren.html#flow-control
+        // clear を指定したブロックは、避けるフロートの下に置く
+        if let Some(bottom) = floats.clearance(c.borrow().style.clear()) {
+            height = height.max(bottom - origin.y);
+        }
+
+        // ブロック整形文脈を作るブロックは、フロートに重ならないように横幅を縮める
+        let mut x = c.borrow().margin.left;
+        if c.borrow().establishes_block_formatting_context() {
+            let top = origin.y + height;
+            let (left, right) =
+                floats.free_space(top, top + 1, origin.x, origin.x + content_size.width);
+            if right - left < content_size.width {
+                let narrowed = LayoutSize {
+                    width: right - left,
+                    height: content_size.height,
+                };
+                c.borrow_mut().compute_width(narrowed, font_metrics);
+                x = left - origin.x + c.borrow().margin.left;
+            }
+        }
+
+        let child_origin = {
+            let mut c = c.borrow_mut();
+            c.offset = LayoutPoint { x, y: height };
+            LayoutPoint {
+                x: origin.x + x + c.border.left + c.padding.left,
+                y: origin.y + height + c.border.top + c.padding.top,
+            }
+        };
+        layout_block(&c, floats, child_origin, font_metrics);
+        height += c.borrow().size().height;
+        prev_child = Some(c);
+    }
+
+    let mut b = block.borrow_mut();
+    b.collapsed_margin_top = CollapsedMargin::new(b.margin.top);
+    b.collapsed_margin_bottom = CollapsedMargin::new(b.margin.bottom);
+    if let Some(first) = b.first_in_flow_child()
+        && first.borrow().kind().is_block_level()
+        && b.collapses_with_first_child()
+    {
+        b.collapsed_margin_top = b
+            .collapsed_margin_top
+            .join(first.borrow().collapsed_margin_top);
+    }
+    if let Some(last) = prev_child {
+        let last_margin = last.borrow().collapsed_margin_bottom;
+        if b.collapses_with_last_child() {
+            b.collapsed_margin_bottom = b.collapsed_margin_bottom.join(last_margin);
+        } else {
+            height += last_margin.value();
+        }
+    }
+    // https://www.w3.org/TR/CSS2/visudet.html#root-height
+    // ブロック整形文脈を作るブロックの高さは、中のフロートを含む
+    if establishes_context {
+        height = height.max(floats.bottom());
+    }
+    drop(b);
+    resolve_height(block, height, font_metrics);
+}
+
+// 並べた中身の高さ content_height と height、min-height、max-height から、ブロックの高さを決める。
+// 位置指定したブロックは、絶対位置指定の子孫の包含ブロックになるので、それも並べる
+fn resolve_height(
+    block: &Rc<RefCell<LayoutObject>>,
+    content_height: i64,
+    font_metrics: &Rc<dyn FontMetrics>,
+) {
+    let mut b = block.borrow_mut();
+    let height = b
+        .definite_content_height()
+        .unwrap_or_else(|| b.clamp_content_height(content_height as f64).max(0.0) as i64);
+    b.rect.size.height = height + b.border.vertical() + b.padding.vertical();
+
+    if b.style.position().is_positioned() {
+        let padding_box = b.padding_box().size;
+        drop(b);
+        for descendant in absolute_descendants(block) {
+            layout_absolute(&descendant, padding_box, font_metrics);
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+// 横幅を決めたフレックスコンテナの中にフレックスアイテムを並べて、並べた中身の高さを返す
+fn layout_flex(container: &Rc<RefCell<LayoutObject>>, font_metrics: &Rc<dyn FontMetrics>) -> i64 {
+    let (style, content_size, definite_height) = {
+        let c = container.borrow();
+        (c.style(), c.content_size(), c.definite_content_height())
+    };
+    let direction = style.flex_direction();
+    let is_row = direction.is_row();
+    let single_line = style.flex_wrap() == FlexWrap::Nowrap;
+    // 行の間は row-gap、列の間は column-gap になる。row-gap のパーセンテージは、
+    // コンテナの高さが決まっていないときは 0 として扱う
+    let row_gap = style.row_gap().resolve(definite_height.unwrap_or(0) as f64) as i64;
+    let column_gap = style.column_gap().resolve(content_size.width as f64) as i64;
+    let (main_gap, cross_gap) = if is_row {
+        (column_gap, row_gap)
+    } else {
+        (row_gap, column_gap)
+    };
+    let align = |item: &Rc<RefCell<LayoutObject>>| {
+        item.borrow()
+            .style
+            .align_self()
+            .unwrap_or(style.align_items())
+    };
+    let (cross_start, cross_end) = if is_row {
+        (Side::Top, Side::Bottom)
+    } else {
+        (Side::Left, Side::Right)
+    };
+    let has_auto_cross_margin = |item: &Rc<RefCell<LayoutObject>>| {
+        let style = &item.borrow().style;
+        style.margin(cross_start) == Size::Auto || style.margin(cross_end) == Size::Auto
+    };
+
+    // 絶対位置指定の子ノードはフレックスアイテムにならず、コンテナのコンテンツボックスの左上に置く
+    let mut children = Vec::new();
+    let mut child = container.borrow().first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        if c.borrow().style.position().is_absolutely_positioned() {
+            c.borrow_mut().static_offset = LayoutPoint { x: 0, y: 0 };
+            continue;
+        }
+        let mut item = c.borrow_mut();
+        item.compute_box_edges(content_size.width);
+        item.compute_relative_offset(content_size.width);
+        drop(item);
+        children.push(c);
+    }
+    // https://www.w3.org/TR/css-flexbox-1/#order-property
+    // アイテムは order の小さい順に並べる。order が同じアイテムは文書の順のまま
+    children.sort_by_key(|c| c.borrow().style.order());
+
+    // 縦の主軸では、横幅を決めて中身を並べてから高さを測る。1行のコンテナで引き伸ばす
+    // アイテムはコンテナの横幅に合わせ、それ以外は中身に合わせて縮める
+    if !is_row {
+        for c in &children {
+            let stretch =
+                (single_line && align(c) == AlignItems::Stretch && !has_auto_cross_margin(c))
+                    .then_some(content_size.width);
+            let width = c
+                .borrow()
+                .fit_width(content_size.width, stretch, font_metrics);
+            c.borrow_mut().rect.size.width = width;
+            layout_block(
+                c,
+                &mut FloatContext::default(),
+                LayoutPoint { x: 0, y: 0 },
+                font_metrics,
+            );
+        }
+    }
+
+    // アイテムをラインに分けて主軸の方向の大きさを決め、横の主軸では決めた横幅で中身を並べる
+    let available_main = if is_row {
+        Some(content_size.width)
+    } else {
+        definite_height
+    };
+    let items: Vec<FlexItem> = children
+        .iter()
+        .map(|c| {
+            c.borrow()
+                .flex_item(direction, content_size.width, available_main, font_metrics)
+        })
+        .collect();
+    let lines = resolve_lines(&items, available_main, main_gap, !single_line);
+    for line in &lines {
+        for (c, size) in children[line.items.clone()].iter().zip(&line.sizes) {
+            if is_row {
+                c.borrow_mut().rect.size.width = *size;
+                layout_block(
+                    c,
+                    &mut FloatContext::default(),
+                    LayoutPoint { x: 0, y: 0 },
+                    font_metrics,
+                );
+            } else {
+                c.borrow_mut().rect.size.height = *size;
+            }
+        }
+    }
+
+    // https://www.w3.org/TR/css-flexbox-1/#algo-cross-line
+    // ラインの交差軸の方向の大きさは、最も大きいアイテムのマージンボックスに合わせる。
+    // 1行のコンテナでは、コンテナの大きさに合わせる
+    let outer_cross = |c: &Rc<RefCell<LayoutObject>>| {
+        let c = c.borrow();
+        if is_row {
+            c.rect.size.height + c.margin.vertical()
+        } else {
+            c.rect.size.width + c.margin.horizontal()
+        }
+    };
+    let clamp_height = |height: i64| {
+        container
+            .borrow()
+            .clamp_content_height(height as f64)
+            .max(0.0) as i64
+    };
+    let mut line_sizes: Vec<i64> = lines
+        .iter()
+        .map(|line| {
+            children[line.items.clone()]
+                .iter()
+                .map(outer_cross)
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    if single_line && let Some(line_size) = line_sizes.first_mut() {
+        *line_size = match (is_row, definite_height) {
+            (true, Some(height)) => height,
+            (true, None) => clamp_height(*line_size),
+            (false, _) => content_size.width,
+        };
+    }
+    let cross_size = if is_row {
+        let gaps = cross_gap * (line_sizes.len() as i64 - 1).max(0);
+        definite_height.unwrap_or_else(|| clamp_height(line_sizes.iter().sum::<i64>() + gaps))
+    } else {
+        content_size.width
+    };
+    let main_size = available_main.unwrap_or_else(|| {
+        let longest = lines
+            .iter()
+            .map(|line| {
+                let gaps = main_gap * (line.sizes.len() as i64 - 1);
+                let margins: i64 = items[line.items.clone()]
+                    .iter()
+                    .map(|i| i.margin_start.unwrap_or(0) + i.margin_end.unwrap_or(0))
+                    .sum();
+                line.sizes.iter().sum::<i64>() + margins + gaps
+            })
+            .max()
+            .unwrap_or(0);
+        clamp_height(longest)
+    });
+    let placed_lines = place_lines(&line_sizes, cross_size, cross_gap, style.align_content());
+
+    // https://www.w3.org/TR/css-flexbox-1/#algo-main-align
+    // アイテムを主軸と交差軸の方向に揃えて置く。逆向きの主軸と wrap-reverse では、
+    // 始まりの側から並べた位置を反転する
+    for (line, (line_position, line_size)) in lines.iter().zip(placed_lines) {
+        let line_items = &items[line.items.clone()];
+        let positions = place_main(
+            line_items,
+            &line.sizes,
+            main_size,
+            main_gap,
+            style.justify_content(),
+        );
+        for (n, c) in children[line.items.clone()].iter().enumerate() {
+            if align(c) == AlignItems::Stretch && !has_auto_cross_margin(c) {
+                stretch_item(c, is_row, line_size, content_size.width, font_metrics);
+            }
+
+            let margin = |side: Side| match c.borrow().style.margin(side) {
+                Size::Auto => None,
+                Size::Length(_) => Some(c.borrow().margin.get(side)),
+            };
+            let cross_item_size = outer_cross(c)
+                - c.borrow().margin.get(cross_start)
+                - c.borrow().margin.get(cross_end);
+            let free = line_size - outer_cross(c);
+            let mut cross = line_position
+                + cross_offset(align(c), free, margin(cross_start), margin(cross_end));
+            if style.flex_wrap() == FlexWrap::WrapReverse {
+                cross = cross_size - cross - cross_item_size;
+            }
+            let mut main = positions[n];
+            if direction.is_reverse() {
+                main = main_size - main - line.sizes[n];
+            }
+            c.borrow_mut().offset = if is_row {
+                LayoutPoint { x: main, y: cross }
+            } else {
+                LayoutPoint { x: cross, y: main }
+            };
+        }
+    }
+
+    if is_row { cross_size } else { main_size }
+}
+
+// グリッドアイテムになる、通常のフローの子ノード。order の小さい順に並べる
+fn grid_items(container: &LayoutObject) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut children = Vec::new();
+    let mut child = container.first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        if !c.borrow().style.position().is_absolutely_positioned() {
+            children.push(c);
+        }
+    }
+    children.sort_by_key(|c| c.borrow().style.order());
+    children
+}
+
+// https://www.w3.org/TR/css-grid-1/#layout-algorithm
+// 横幅を決めたグリッドコンテナの中にグリッドアイテムを並べて、並べた中身の高さを返す
+fn layout_grid(container: &Rc<RefCell<LayoutObject>>, font_metrics: &Rc<dyn FontMetrics>) -> i64 {
+    let (style, content_size, definite_height) = {
+        let c = container.borrow();
+        (c.style(), c.content_size(), c.definite_content_height())
+    };
+    // row-gap のパーセンテージは、コンテナの高さが決まっていないときは 0 として扱う
+    let row_gap = style.row_gap().resolve(definite_height.unwrap_or(0) as f64) as i64;
+    let column_gap = style.column_gap().resolve(content_size.width as f64) as i64;
+
+    // 絶対位置指定の子ノードはグリッドアイテムにならず、コンテナのコンテンツボックスの左上に置く
+    let mut child = container.borrow().first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        if c.borrow().style.position().is_absolutely_positioned() {
+            c.borrow_mut().static_offset = LayoutPoint { x: 0, y: 0 };
+        }
+    }
+    let children = grid_items(&container.borrow());
+    let lines: Vec<GridItemLines> = children
+        .iter()
+        .map(|c| c.borrow().grid_item_lines())
+        .collect();
+    let grid = build_grid(
+        &style,
+        &lines,
+        (Some(content_size.width), definite_height),
+        (row_gap, column_gap),
+    );
+
+    // https://www.w3.org/TR/css-grid-1/#algo-overview
+    // 列の幅を中身に合わせて決めてから、アイテムを領域の横幅で並べて、行の高さを決める
+    let column_sizes: Vec<GridItemSize> = children
+        .iter()
+        .zip(&grid.areas)
+        .map(|(c, area)| {
+            let (min, max) = c.borrow_mut().outer_preferred_widths(font_metrics);
+            GridItemSize {
+                tracks: area.columns.clone(),
+                min_content: min,
+                max_content: max,
+            }
+        })
+        .collect();
+    let columns = size_tracks(
+        &grid.columns,
+        &column_sizes,
+        Some(content_size.width),
+        column_gap,
+    );
+
+    let justify = |item: &Rc<RefCell<LayoutObject>>| {
+        item.borrow()
+            .style
+            .justify_self()
+            .unwrap_or(style.justify_items())
+    };
+    let align = |item: &Rc<RefCell<LayoutObject>>| {
+        item.borrow()
+            .style
+            .align_self()
+            .unwrap_or(style.align_items())
+    };
+    let has_auto_margin = |item: &Rc<RefCell<LayoutObject>>, start: Side, end: Side| {
+        let style = &item.borrow().style;
+        style.margin(start) == Size::Auto || style.margin(end) == Size::Auto
+    };
+    for (c, area) in children.iter().zip(&grid.areas) {
+        let width = span_size(&columns, area.columns.clone(), column_gap);
+        let stretch = (justify(c) == AlignItems::Stretch
+            && !has_auto_margin(c, Side::Left, Side::Right))
+        .then_some(width);
+        let mut item = c.borrow_mut();
+        item.compute_box_edges(width);
+        item.compute_relative_offset(width);
+        item.rect.size.width = item.fit_width(width, stretch, font_metrics);
+        drop(item);
+        layout_block(
+            c,
+            &mut FloatContext::default(),
+            LayoutPoint { x: 0, y: 0 },
+            font_metrics,
+        );
+    }
+    let row_sizes: Vec<GridItemSize> = children
+        .iter()
+        .zip(&grid.areas)
+        .map(|(c, area)| {
+            let c = c.borrow();
+            let height = c.rect.size.height + c.margin.vertical();
+            GridItemSize {
+                tracks: area.rows.clone(),
+                min_content: height,
+                max_content: height,
+            }
+        })
+        .collect();
+    let rows = size_tracks(&grid.rows, &row_sizes, definite_height, row_gap);
+
+    // https://www.w3.org/TR/css-grid-1/#alignment
+    // アイテムを、行と列の範囲の領域の中で揃えて置く
+    let column_positions = track_positions(&columns, column_gap);
+    let row_positions = track_positions(&rows, row_gap);
+    for (c, area) in children.iter().zip(&grid.areas) {
+        let width = span_size(&columns, area.columns.clone(), column_gap);
+        let height = span_size(&rows, area.rows.clone(), row_gap);
+        if align(c) == AlignItems::Stretch && !has_auto_margin(c, Side::Top, Side::Bottom) {
+            stretch_item(c, true, height, width, font_metrics);
+        }
+
+        let margin = |side: Side| match c.borrow().style.margin(side) {
+            Size::Auto => None,
+            Size::Length(_) => Some(c.borrow().margin.get(side)),
+        };
+        let (free_width, free_height) = {
+            let c = c.borrow();
+            (
+                width - c.rect.size.width - c.margin.horizontal(),
+                height - c.rect.size.height - c.margin.vertical(),
+            )
+        };
+        let x = column_positions
+            .get(area.columns.start)
+            .copied()
+            .unwrap_or(0)
+            + cross_offset(
+                justify(c),
+                free_width,
+                margin(Side::Left),
+                margin(Side::Right),
+            );
+        let y = row_positions.get(area.rows.start).copied().unwrap_or(0)
+            + cross_offset(
+                align(c),
+                free_height,
+                margin(Side::Top),
+                margin(Side::Bottom),
+            );
+        c.borrow_mut().offset = LayoutPoint { x, y };
+    }
+
+    span_size(&rows, 0..rows.len(), row_gap)
+}
+
+// https://www.w3.org/TR/css-flexbox-1/#algo-stretch
+// 交差軸の方向の大きさが auto のアイテムを、フレックスのラインやグリッドの行の大きさ line_size に
+// 引き伸ばす。横幅が変わったアイテムは、中身を並べ直す
+fn stretch_item(
+    item: &Rc<RefCell<LayoutObject>>,
+    is_row: bool,
+    line_size: i64,
+    containing_width: i64,
+    font_metrics: &Rc<dyn FontMetrics>,
+) {
+    if is_row {
+        let mut i = item.borrow_mut();
+        if i.has_auto_height() {
+            let vertical = i.border.vertical() + i.padding.vertical();
+            let height = (line_size - i.margin.vertical() - vertical) as f64;
+            i.rect.size.height = i.clamp_content_height(height).max(0.0) as i64 + vertical;
+        }
+        return;
+    }
+
+    if item.borrow().style.width() != Size::Auto {
+        return;
+    }
+    let (width, height) = {
+        let i = item.borrow();
+        let width = i.fit_width(containing_width, Some(line_size), font_metrics);
+        (width, i.rect.size.height)
+    };
+    if width != item.borrow().rect.size.width {
+        item.borrow_mut().rect.size.width = width;
+        layout_block(
+            item,
+            &mut FloatContext::default(),
+            LayoutPoint { x: 0, y: 0 },
+            font_metrics,
+        );
+        item.borrow_mut().rect.size.height = height;
+    }
+}
+
+// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-height
+// 絶対位置指定のボックスの大きさを決めて、包含ブロックのパディングボックスの左上からの位置を
+// 求める。containing は包含ブロックのパディングボックスの大きさ
+pub fn layout_absolute(
+    object: &Rc<RefCell<LayoutObject>>,
+    containing: LayoutSize,
+    font_metrics: &Rc<dyn FontMetrics>,
+) {
+    object.borrow_mut().compute_width(containing, font_metrics);
+    layout_block(
+        object,
+        &mut FloatContext::default(),
+        LayoutPoint { x: 0, y: 0 },
+        font_metrics,
+    );
+
+    let mut o = object.borrow_mut();
+    let top = o.inset(Side::Top, containing.height);
+    let bottom = o.inset(Side::Bottom, containing.height);
+    let left = o.inset(Side::Left, containing.width);
+    let right = o.inset(Side::Right, containing.width);
+
+    // top と bottom を指定して高さが auto のボックスは、その間に広げる
+    if let (Some(top), Some(bottom)) = (top, bottom)
+        && o.style.height() == Size::Auto
+    {
+        let edges = o.border.vertical() + o.padding.vertical();
+        o.rect.size.height = (containing.height - top - bottom - o.margin.vertical()).max(edges);
+    }
+
+    // left と top は right と bottom より優先する。両方が auto の軸は、通常のフローの位置に置く
+    let size = o.rect.size;
+    o.offset = LayoutPoint {
+        x: match (left, right) {
+            (Some(left), _) => left + o.margin.left,
+            (None, Some(right)) => containing.width - right - o.margin.right - size.width,
+            (None, None) => 0,
+        },
+        y: match (top, bottom) {
+            (Some(top), _) => top + o.margin.top,
+            (None, Some(bottom)) => containing.height - bottom - o.margin.bottom - size.height,
+            (None, None) => 0,
+        },
+    };
+}
+
+// 包含ブロックが block になる、絶対位置指定の子孫。固定位置指定のボックスは含まない
+pub fn absolute_descendants(block: &Rc<RefCell<LayoutObject>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut found = Vec::new();
+    let mut child = block.borrow().first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        let (position, kind) = (c.borrow().style.position(), c.borrow().kind());
+        match position {
+            Position::Absolute => found.push(c),
+            Position::Fixed => {}
+            // 位置指定した子孫のブロックが、その中のボックスの包含ブロックになる。
+            // 位置指定したインライン要素は、包含ブロックにしない
+            _ if position.is_positioned() && kind.is_block_level() => {}
+            _ => found.extend(absolute_descendants(&c)),
+        }
+    }
+    found
+}
+
+// 固定位置指定の子孫。ツリーの順に並ぶ
+pub fn fixed_descendants(object: &Rc<RefCell<LayoutObject>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut found = Vec::new();
+    let mut child = object.borrow().first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        if c.borrow().style.position() == Position::Fixed {
+            found.push(c.clone());
+        }
+        found.extend(fixed_descendants(&c));
+    }
+    found
+}
+
+// 子孫を並べる前に、ブロックの上マージンと相殺する子孫の上マージンを求める
+pub fn collapse_top_margin(block: &Rc<RefCell<LayoutObject>>, font_metrics: &Rc<dyn FontMetrics>) {
+    let first = {
+        let mut b = block.borrow_mut();
+        b.collapsed_margin_top = CollapsedMargin::new(b.margin.top);
+        if !b.collapses_with_first_child() {
+            return;
+        }
+        b.first_in_flow_child()
+    };
+    let Some(first) = first else { return };
+    if !first.borrow().kind().is_block_level() {
+        return;
+    }
+
+    let content_size = block.borrow().content_size();
+    first.borrow_mut().compute_width(content_size, font_metrics);
+    collapse_top_margin(&first, font_metrics);
+    let first_margin = first.borrow().collapsed_margin_top;
+    let mut b = block.borrow_mut();
+    b.collapsed_margin_top = b.collapsed_margin_top.join(first_margin);
+}
+
+// 行ボックスに並べる前に、インラインレベルのノードの margin、border、padding と、
+// 行の中に置くブロックの大きさを決める
+fn prepare_inline(
+    object: &Rc<RefCell<LayoutObject>>,
+    containing_width: i64,
+    font_metrics: &Rc<dyn FontMetrics>,
+) {
+    let kind = object.borrow().kind();
+    match kind {
+        LayoutObjectKind::Block | LayoutObjectKind::Flex | LayoutObjectKind::Grid => {
+            let containing_size = LayoutSize {
+                width: containing_width,
+                height: 0,
+            };
+            object
+                .borrow_mut()
+                .compute_width(containing_size, font_metrics);
+            layout_block(
+                object,
+                &mut FloatContext::default(),
+                LayoutPoint { x: 0, y: 0 },
+                font_metrics,
+            );
+        }
+        LayoutObjectKind::Inline => {
+            object.borrow_mut().compute_box_edges(containing_width);
+            let mut child = object.borrow().first_child();
+            while let Some(c) = child {
+                prepare_inline(&c, containing_width, font_metrics);
+                child = c.borrow().next_sibling();
+            }
+        }
+        LayoutObjectKind::Text => {}
+    }
+}
+
+// インラインレベルのノードとその子孫を、行ボックスに並べる内容に変換する。
+// owners には内容の owner の番号に対応するノードを追加する
+fn collect_inline_items(
+    object: &Rc<RefCell<LayoutObject>>,
+    items: &mut Vec<InlineItem>,
+    owners: &mut Vec<Rc<RefCell<LayoutObject>>>,
+    font_metrics: &Rc<dyn FontMetrics>,
+) {
+    let owner = owners.len();
+    owners.push(object.clone());
+
+    let o = object.borrow();
+    match o.kind {
+        LayoutObjectKind::Block | LayoutObjectKind::Flex | LayoutObjectKind::Grid => {
+            items.push(InlineItem::Atomic {
+                owner,
+                width: o.margin.horizontal() + o.rect.size.width,
+                height: o.margin.vertical() + o.rect.size.height,
+            })
+        }
+        LayoutObjectKind::Inline => {
+            if let NodeKind::Element(element) = o.node_kind()
+                && element.kind() == ElementKind::Br
+            {
+                items.push(InlineItem::ForcedBreak);
+                return;
+            }
+
+            items.push(InlineItem::OpenBox {
+                owner,
+                style: o.inline_box_style(font_metrics.as_ref()),
+            });
+            let mut child = o.first_child();
+            while let Some(c) = child {
+                collect_inline_items(&c, items, owners, font_metrics);
+                child = c.borrow().next_sibling();
+            }
+            items.push(InlineItem::CloseBox);
+        }
+        LayoutObjectKind::Text => {
+            if let NodeKind::Text(t) = o.node_kind() {
+                items.push(InlineItem::Text {
+                    owner,
+                    text: transform_text(&t, o.style.white_space(), o.style.text_transform()),
+                    white_space: o.style.white_space(),
+                    word_break: o.style.word_break(),
+                    overflow_wrap: o.style.overflow_wrap(),
+                    metrics: o.text_metrics(font_metrics),
+                });
+            }
+        }
+    }
+}
+
+// font-size の値から、px で表したフォントサイズを求める
+fn compute_font_size(value: &[ComponentValue], parent_context: &LengthContext) -> Option<f64> {
+    if let [ComponentValue::Ident(keyword)] = value {
+        // https://www.w3.org/TR/css-fonts-4/#relative-size-value
+        return match keyword.to_ascii_lowercase().as_str() {
+            "smaller" => Some(parent_context.font_size / 1.2),
+            "larger" => Some(parent_context.font_size * 1.2),
+            keyword => absolute_font_size(keyword),
+        };
+    }
+
+    let length = LengthPercentage::parse_non_negative(value).ok()?;
+    // パーセンテージは親のフォントサイズに対する割合
+    let font_size = length
+        .compute(parent_context)
+        .resolve(parent_context.font_size);
+    Some(font_size.max(0.0))
+}
+
+// width や height の値を計算する。負の値は無効
+fn compute_size(value: &[ComponentValue], context: &LengthContext) -> Option<Size> {
+    if let [ComponentValue::Ident(keyword)] = value
+        && keyword.eq_ignore_ascii_case("auto")
+    {
+        return Some(Size::Auto);
+    }
+
+    let length = LengthPercentage::parse_non_negative(value).ok()?;
+    Some(Size::Length(length.compute(context)))
+}
+
+// top、right、bottom、left の値を計算する。負の値も使える
+fn compute_inset(value: &[ComponentValue], context: &LengthContext) -> Option<Size> {
+    if let [ComponentValue::Ident(keyword)] = value
+        && keyword.eq_ignore_ascii_case("auto")
+    {
+        return Some(Size::Auto);
+    }
+
+    let length = LengthPercentage::parse(value).ok()?;
+    Some(Size::Length(length.compute(context)))
+}
+
+// max-width や max-height の値を計算する。none は Some(None) になる
+fn compute_max_size(
+    value: &[ComponentValue],
+    context: &LengthContext,
+) -> Option<Option<ComputedLength>> {
+    if let [ComponentValue::Ident(keyword)] = value
+        && keyword.eq_ignore_ascii_case("none")
+    {
+        return Some(None);
+    }
+
+    let length = LengthPercentage::parse_non_negative(value).ok()?;
+    Some(Some(length.compute(context)))
+}
+
+// border-width の値を px で求める。パーセンテージは指定できない
+fn compute_border_width(value: &[ComponentValue], context: &LengthContext) -> Option<f64> {
+    if let [ComponentValue::Ident(keyword)] = value {
+        return match keyword.to_ascii_lowercase().as_str() {
+            "thin" => Some(1.0),
+            "medium" => Some(BORDER_WIDTH_MEDIUM),
+            "thick" => Some(5.0),
+            _ => None,
+        };
+    }
+
+    let length = LengthPercentage::parse_non_negative(value).ok()?;
+    if length.percent != 0.0 {
+        return None;
+    }
+    Some(length.compute(context).px.max(0.0))
+}
+
+// row-gap や column-gap の値を計算する。normal は 0 になる
+fn compute_gap(value: &[ComponentValue], context: &LengthContext) -> Option<ComputedLength> {
+    if let [ComponentValue::Ident(keyword)] = value
+        && keyword.eq_ignore_ascii_case("normal")
+    {
+        return Some(ComputedLength::default());
+    }
+
+    let length = LengthPercentage::parse_non_negative(value).ok()?;
+    Some(length.compute(context))
+}
+
+// letter-spacing や word-spacing の値を px で求める。normal は 0 になる
+fn compute_spacing(value: &[ComponentValue], context: &LengthContext) -> Option<f64> {
+    if let [ComponentValue::Ident(keyword)] = value
+        && keyword.eq_ignore_ascii_case("normal")
+    {
+        return Some(0.0);
+    }
+
+    let length = LengthPercentage::parse(value).ok()?;
+    if length.percent != 0.0 {
+        return None;
+    }
+    Some(length.compute(context).px)
+}
+
+// 本書のブラウザはフォントを 1〜3 倍に拡大して描画するので、フォントサイズをもっとも近い倍率に丸める
+pub fn font_size_ratio(font_size: f64) -> i64 {
+    if font_size < 20.0 {
+        1
+    } else if font_size < 28.0 {
+        2
+    } else {
+        3
+    }
 }
 
 impl PartialEq for LayoutObject {
@@ -361,10 +2675,26 @@ impl PartialEq for LayoutObject {
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LayoutObjectKind {
     Block,
+    // https://www.w3.org/TR/css-flexbox-1/#flex-containers
+    Flex,
+    // https://www.w3.org/TR/css-grid-1/#grid-containers
+    Grid,
     Inline,
     Text,
 }
 
+impl LayoutObjectKind {
+    // ブロックの中に、上から順に並べるボックスかどうか
+    pub fn is_block_level(&self) -> bool {
+        matches!(self, Self::Block | Self::Flex | Self::Grid)
+    }
+
+    // 通常のフローの子ノードを、アイテムとして並べるコンテナかどうか
+    pub fn is_item_container(&self) -> bool {
+        matches!(self, Self::Flex | Self::Grid)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct LayoutPoint {
     pub x: i64,
@@ -384,6 +2714,13 @@ pub struct LayoutRect {
 }
 
 impl LayoutRect {
+    pub fn new(x: i64, y: i64, width: i64, height: i64) -> Self {
+        Self {
+            point: LayoutPoint { x, y },
+            size: LayoutSize { width, height },
+        }
+    }
+
     pub fn is_hit(&self, point: LayoutPoint) -> bool {
         let is_hit_x = self.point.x <= point.x && point.x <= (self.point.x + self.size.width);
         let is_hit_y = self.point.y <= point.y && point.y <= (self.point.y + self.size.height);
@@ -391,28 +2728,66 @@ impl LayoutRect {
     }
 }
 
-fn find_index_for_line_break(line: &str, max_index: usize) -> usize {
-    for i in (0..max_index).rev() {
-        if line.chars().collect::<Vec<char>>()[i] == ' ' {
-            return i;
+// margin、border、padding の辺ごとの使用値
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct LayoutEdges {
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+    pub left: i64,
+}
+
+impl LayoutEdges {
+    pub fn get(&self, side: Side) -> i64 {
+        match side {
+            Side::Top => self.top,
+            Side::Right => self.right,
+            Side::Bottom => self.bottom,
+            Side::Left => self.left,
         }
     }
-    max_index
+
+    fn set(&mut self, side: Side, value: i64) {
+        match side {
+            Side::Top => self.top = value,
+            Side::Right => self.right = value,
+            Side::Bottom => self.bottom = value,
+            Side::Left => self.left = value,
+        }
+    }
+
+    pub fn horizontal(&self) -> i64 {
+        self.left + self.right
+    }
+
+    pub fn vertical(&self) -> i64 {
+        self.top + self.bottom
+    }
 }
 
-fn split_text(line: String, char_width: i64) -> Vec<String> {
-    let mut result: Vec<String> = Vec::new();
-    let width = WINDOW_WIDTH + WINDOW_PADDING;
-    if line.len() as i64 * char_width > width {
-        let s = line.split_at(find_index_for_line_break(
-            &line,
-            (width / char_width) as usize,
-        ));
-        result.push(s.0.to_string());
-        result.extend(split_text(s.1.trim().to_string(), char_width));
-    } else {
-        result.push(line);
+// 相殺される複数のマージン。正の値の最大値と負の値の最小値の和が、相殺したあとのマージンになる
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct CollapsedMargin {
+    positive: i64,
+    negative: i64,
+}
+
+impl CollapsedMargin {
+    pub fn new(margin: i64) -> Self {
+        Self {
+            positive: margin.max(0),
+            negative: margin.min(0),
+        }
     }
 
-    result
+    pub fn join(self, other: Self) -> Self {
+        Self {
+            positive: self.positive.max(other.positive),
+            negative: self.negative.min(other.negative),
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        self.positive + self.negative
+    }
 }
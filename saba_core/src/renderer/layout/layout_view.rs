@@ -1,19 +1,35 @@
-use core::cell::RefCell;
+use core::{
+    cell::{Cell, RefCell},
+    fmt::Write,
+};
 
-use alloc::{rc::Rc, vec::Vec};
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 
 use crate::{
-    constants::CONTENT_AREA_WIDTH,
     display_item::DisplayItem,
     renderer::{
-        css::cssom::StyleSheet,
+        css::{cascade::CascadedStyle, cssom::StyleSheet, media::Viewport, rule_index::RuleIndex},
         dom::{
-            api::get_target_element_node,
-            node::{ElementKind, Node},
+            api::{clear_style_dirty, get_target_element_node},
+            node::{ElementKind, Node, NodeKind},
         },
         layout::{
-            computed_style::DisplayType,
-            layout_object::{LayoutObject, LayoutObjectKind, LayoutPoint, LayoutRect, LayoutSize},
+            computed_style::{Color, ComputedStyle, DisplayType},
+            float::{Float, FloatContext},
+            font::FontMetrics,
+            layout_object::{
+                LayoutObject, LayoutObjectKind, LayoutPoint, LayoutRect, LayoutSize,
+                absolute_descendants, collapse_top_margin, fixed_descendants, layout_absolute,
+                layout_block,
+            },
+            length::DEFAULT_FONT_SIZE,
+            position::{PositionContext, ZIndex},
         },
     },
 };
@@ -21,17 +37,50 @@ use crate::{
 #[derive(Debug, Clone)]
 pub struct LayoutView {
     root: Option<Rc<RefCell<LayoutObject>>>,
+    // <html> のスタイル。<body> の継承元と rem の基準になる
+    root_style: Option<ComputedStyle>,
+    // 直前のスタイルの計算で、スタイルを計算した要素の数
+    restyled_elements: usize,
+    // 初期包含ブロックの大きさ。ビューポートと同じ大きさになる
+    initial_containing_block: LayoutSize,
+    // ブラウザを組み込む側が提供する、テキストの幅と行の高さを求めるためのフォントの情報
+    font_metrics: Rc<dyn FontMetrics>,
+    // ビューポートの左上が、ページの左上からどれだけスクロールしているか
+    scroll_offset: LayoutPoint,
 }
 
 impl LayoutView {
-    pub fn new(root: Rc<RefCell<Node>>, cssom: &StyleSheet) -> Self {
+    pub fn new(
+        root: Rc<RefCell<Node>>,
+        stylesheets: &[StyleSheet],
+        viewport: &Viewport,
+        font_metrics: Rc<dyn FontMetrics>,
+    ) -> Self {
         // レイアウトツリーは描画される要素だけを持つツリーなので、<body>タグを取得し、
         // その子要素以下をレイアウトツリーのノードに変換する。
-        let body_root = get_target_element_node(Some(root), ElementKind::Body);
+        let body_root = get_target_element_node(Some(root.clone()), ElementKind::Body);
+
+        // <html> はレイアウトツリーに含めないが、<body> の継承元と rem の基準になるのでスタイルだけ計算する
+        let mut context = StyleContext::new(stylesheets, viewport, None);
+        context.root_style =
+            get_target_element_node(Some(root.clone()), ElementKind::Html).map(|html| {
+                let mut layout_object = LayoutObject::new(html, &None);
+                compute_style(&mut layout_object, None, &context);
+                layout_object.style()
+            });
 
         let mut tree = Self {
-            root: build_layout_tree(&body_root, &None, cssom),
+            root: build_layout_tree(&body_root, &None, &context),
+            root_style: context.root_style.clone(),
+            restyled_elements: context.styled_elements.get(),
+            initial_containing_block: LayoutSize {
+                width: viewport.width,
+                height: viewport.height,
+            },
+            font_metrics,
+            scroll_offset: LayoutPoint { x: 0, y: 0 },
         };
+        clear_style_dirty(&root);
         tree.update_layout();
 
         tree
@@ -41,86 +90,231 @@ impl LayoutView {
         self.root.clone()
     }
 
-    fn update_layout(&mut self) {
-        Self::calculate_node_size(
-            &self.root,
-            LayoutSize {
-                width: CONTENT_AREA_WIDTH,
-                height: 0,
-            },
-        );
-        Self::calculate_node_position(
-            &self.root,
-            LayoutPoint { x: 0, y: 0 },
-            LayoutObjectKind::Block,
-            None,
-        );
+    pub fn restyled_elements(&self) -> usize {
+        self.restyled_elements
     }
 
-    fn calculate_node_size(node: &Option<Rc<RefCell<LayoutObject>>>, parent_size: LayoutSize) {
-        let Some(node) = node else { return };
+    // 固定位置指定と sticky のボックスの位置はスクロールした位置で変わるので、レイアウトをやり直す
+    pub fn set_scroll_offset(&mut self, scroll_offset: LayoutPoint) {
+        if self.scroll_offset == scroll_offset {
+            return;
+        }
+        self.scroll_offset = scroll_offset;
+        self.update_layout();
+    }
 
-        // ノードがブロック要素の場合、子ノードのレイアウトを計算する前に横幅を決める
-        if node.borrow().kind() == LayoutObjectKind::Block {
-            node.borrow_mut().compute_size(parent_size);
+    // 再計算が必要だと印のついたノードと、スタイルが変わったノードの子孫だけスタイルを計算し直し、
+    // レイアウトをやり直す。レイアウトツリーの形が変わる場合は false を返すので、LayoutView を作り直す
+    pub fn restyle(
+        &mut self,
+        root: Rc<RefCell<Node>>,
+        stylesheets: &[StyleSheet],
+        viewport: &Viewport,
+    ) -> bool {
+        if !root.borrow().has_dirty_descendant() {
+            self.restyled_elements = 0;
+            return true;
         }
 
-        let first_child = node.borrow().first_child();
-        Self::calculate_node_size(&first_child, parent_size);
+        // <html> のスタイルが変わると、rem の基準が変わりうるのですべて計算し直す
+        let html = get_target_element_node(Some(root.clone()), ElementKind::Html);
+        if html.is_some_and(|html| html.borrow().is_style_dirty()) {
+            return false;
+        }
 
-        let next_sibling = node.borrow().next_sibling();
-        Self::calculate_node_size(&next_sibling, parent_size);
+        let context = StyleContext::new(stylesheets, viewport, self.root_style.clone());
+        if let Some(body) = &self.root
+            && !restyle_object(body, context.root_style.clone(), false, &context)
+        {
+            return false;
+        }
+
+        self.restyled_elements = context.styled_elements.get();
+        clear_style_dirty(&root);
+        self.update_layout();
+        true
+    }
+
+    fn update_layout(&mut self) {
+        // ルートのブロックは、初期包含ブロックの左上からマージンの分だけ離して置き、
+        // 横幅を決めてから子孫を上から順に並べる
+        if let Some(root) = &self.root {
+            root.borrow_mut()
+                .compute_width(self.initial_containing_block, &self.font_metrics);
+            collapse_top_margin(root, &self.font_metrics);
+            let offset = LayoutPoint {
+                x: root.borrow().margin().left,
+                y: root.borrow().collapsed_margin_top().value(),
+            };
+            root.borrow_mut().set_offset(offset);
+            let origin = {
+                let r = root.borrow();
+                LayoutPoint {
+                    x: offset.x + r.border().left + r.padding().left,
+                    y: offset.y + r.border().top + r.padding().top,
+                }
+            };
+            layout_block(
+                root,
+                &mut FloatContext::default(),
+                origin,
+                &self.font_metrics,
+            );
+
+            // 位置指定した祖先のない絶対位置指定のボックスは初期包含ブロックに、
+            // 固定位置指定のボックスはビューポートに置く
+            if !root.borrow().position().is_positioned() {
+                for descendant in absolute_descendants(root) {
+                    layout_absolute(
+                        &descendant,
+                        self.initial_containing_block,
+                        &self.font_metrics,
+                    );
+                }
+            }
+            for descendant in fixed_descendants(root) {
+                layout_absolute(
+                    &descendant,
+                    self.initial_containing_block,
+                    &self.font_metrics,
+                );
+            }
+        }
 
-        // 子ノードのサイズが決まったあとにサイズを計算する
-        // ブロック要素のとき、高さは子ノードの高さに依存する
-        // インライン要素のとき、高さも横幅も子ノードに依存する
-        node.borrow_mut().compute_size(parent_size);
+        let context = PositionContext {
+            absolute: LayoutPoint { x: 0, y: 0 },
+            viewport: LayoutRect {
+                point: self.scroll_offset,
+                size: self.initial_containing_block,
+            },
+        };
+        let initial_containing_block = LayoutRect {
+            point: LayoutPoint { x: 0, y: 0 },
+            size: self.initial_containing_block,
+        };
+        Self::calculate_node_position(&self.root, initial_containing_block, context);
     }
 
+    // containing は包含ブロックのコンテンツボックス。context は位置指定したボックスを置く基準
     fn calculate_node_position(
         node: &Option<Rc<RefCell<LayoutObject>>>,
-        parent_point: LayoutPoint,
-        prev_sibling_kind: LayoutObjectKind,
-        prev_sibling_rect: Option<LayoutRect>,
+        containing: LayoutRect,
+        context: PositionContext,
     ) {
         let Some(node) = node else { return };
 
-        node.borrow_mut()
-            .compute_position(parent_point, prev_sibling_kind, prev_sibling_rect);
+        node.borrow_mut().compute_position(containing, &context);
 
+        let (children_containing, children_context) = {
+            let n = node.borrow();
+            if n.kind().is_block_level() {
+                // 位置指定したブロックは、絶対位置指定の子孫の包含ブロックになる
+                let mut children_context = context;
+                if n.position().is_positioned() {
+                    children_context.absolute = n.padding_box().point;
+                }
+                let content_box = LayoutRect {
+                    point: n.content_point(),
+                    size: n.content_size(),
+                };
+                (content_box, children_context)
+            } else {
+                // インライン要素の子ノードの包含ブロックは、インライン要素を含むブロックのまま。
+                // 相対位置指定したインライン要素の子孫は、一緒にずらす
+                let shift = n.relative_offset();
+                let shifted = LayoutRect {
+                    point: LayoutPoint {
+                        x: containing.point.x + shift.x,
+                        y: containing.point.y + shift.y,
+                    },
+                    size: containing.size,
+                };
+                (shifted, context)
+            }
+        };
         let first_child = node.borrow().first_child();
-        Self::calculate_node_position(
-            &first_child,
-            node.borrow().point(),
-            LayoutObjectKind::Block,
-            None,
-        );
+        Self::calculate_node_position(&first_child, children_containing, children_context);
 
         let next_sibling = node.borrow().next_sibling();
-        Self::calculate_node_position(
-            &next_sibling,
-            parent_point,
-            node.borrow().kind(),
-            Some(node.borrow().rect()),
-        );
+        Self::calculate_node_position(&next_sibling, containing, context);
+    }
+
+    // https://www.w3.org/TR/CSS2/zindex.html
+    // node を重ね合わせコンテキストの根として、node とその子孫を描画する。z-index が auto の
+    // 位置指定したボックスとフロートも、z-index が 0 の重ね合わせコンテキストと同じように描画する
+    fn paint_stacking_context(
+        node: &Rc<RefCell<LayoutObject>>,
+        backdrop: Color,
+        display_items: &mut Vec<DisplayItem>,
+    ) {
+        display_items.extend(node.borrow_mut().paint(backdrop));
+
+        let mut layers = StackingLayers::default();
+        let first_child = node.borrow().first_child();
+        Self::collect_layers(&first_child, children_backdrop(node, backdrop), &mut layers);
+
+        // z-index が同じボックスは、ツリーの順に描画する
+        layers.negative.sort_by_key(|(z_index, _, _)| *z_index);
+        layers.positioned.sort_by_key(|(z_index, _, _)| *z_index);
+        for (_, n, backdrop) in layers.negative {
+            Self::paint_stacking_context(&n, backdrop, display_items);
+        }
+        display_items.extend(layers.blocks);
+        for (n, backdrop) in layers.floats {
+            Self::paint_stacking_context(&n, backdrop, display_items);
+        }
+        display_items.extend(layers.inlines);
+        for (_, n, backdrop) in layers.positioned {
+            Self::paint_stacking_context(&n, backdrop, display_items);
+        }
     }
 
-    fn paint_node(node: &Option<Rc<RefCell<LayoutObject>>>, display_items: &mut Vec<DisplayItem>) {
+    // 重ね合わせコンテキストの中の子孫を、描画する層に分ける
+    fn collect_layers(
+        node: &Option<Rc<RefCell<LayoutObject>>>,
+        backdrop: Color,
+        layers: &mut StackingLayers,
+    ) {
         let Some(node) = node else { return };
 
-        display_items.extend(node.borrow_mut().paint());
+        let style = node.borrow().style();
+        let kind = node.borrow().kind();
+        if style.position().is_positioned() {
+            match style.z_index() {
+                ZIndex::Integer(z_index) if z_index < 0 => {
+                    layers.negative.push((z_index, node.clone(), backdrop))
+                }
+                ZIndex::Integer(z_index) => {
+                    layers.positioned.push((z_index, node.clone(), backdrop))
+                }
+                ZIndex::Auto => layers.positioned.push((0, node.clone(), backdrop)),
+            }
+        } else if style.float() != Float::None && kind == LayoutObjectKind::Block {
+            layers.floats.push((node.clone(), backdrop));
+        } else {
+            let items = node.borrow_mut().paint(backdrop);
+            if kind.is_block_level() {
+                layers.blocks.extend(items);
+            } else {
+                layers.inlines.extend(items);
+            }
 
-        let first_child = node.borrow().first_child();
-        Self::paint_node(&first_child, display_items);
+            // 子ノードは、このノードの背景の上に描画される
+            let first_child = node.borrow().first_child();
+            Self::collect_layers(&first_child, children_backdrop(node, backdrop), layers);
+        }
 
         let next_sibling = node.borrow().next_sibling();
-        Self::paint_node(&next_sibling, display_items);
+        Self::collect_layers(&next_sibling, backdrop, layers);
     }
 
     pub fn paint(&self) -> Vec<DisplayItem> {
         let mut display_items = Vec::new();
 
-        Self::paint_node(&self.root, &mut display_items);
+        // コンテンツエリアは白で塗りつぶされている
+        if let Some(root) = &self.root {
+            Self::paint_stacking_context(root, Color::white(), &mut display_items);
+        }
 
         display_items
     }
@@ -148,35 +342,101 @@ impl LayoutView {
             return res;
         }
 
-        if node.borrow().rect().is_hit(position) {
+        if node.borrow().is_hit(position) {
             return Some(node.clone());
         }
 
         None
     }
+
+    // レイアウトツリーを、1行に1ノードずつ深さに応じて字下げして書き出す。
+    // 匿名ブロックと、分割したインライン要素の部分は、それとわかるように書き出す
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        Self::dump_node(&self.root, 0, &mut out);
+        out
+    }
+
+    fn dump_node(node: &Option<Rc<RefCell<LayoutObject>>>, depth: usize, out: &mut String) {
+        let Some(node) = node else { return };
+
+        {
+            let n = node.borrow();
+            let name = match n.node_kind() {
+                _ if n.is_anonymous() => "(anonymous)".to_string(),
+                NodeKind::Document => "#document".to_string(),
+                NodeKind::Element(e) => format!("<{}>", e.kind()),
+                NodeKind::Text(t) => format!("{t:?}"),
+            };
+            let split = if n.is_split() { " (split)" } else { "" };
+            let LayoutRect { point, size } = n.rect();
+            let _ = writeln!(
+                out,
+                "{}{:?} {name}{split} ({}, {}) {}x{}",
+                "  ".repeat(depth),
+                n.kind(),
+                point.x,
+                point.y,
+                size.width,
+                size.height,
+            );
+        }
+
+        let first_child = node.borrow().first_child();
+        Self::dump_node(&first_child, depth + 1, out);
+
+        let next_sibling = node.borrow().next_sibling();
+        Self::dump_node(&next_sibling, depth, out);
+    }
+}
+
+// https://www.w3.org/TR/CSS2/zindex.html#painting-order
+// 重ね合わせコンテキストの中の子孫を、描画する順に分けたもの。位置指定したボックスと
+// フロートは、その中に描画するものの背景色と一緒に持つ
+#[derive(Default)]
+struct StackingLayers {
+    // z-index が負の、位置指定したボックス
+    negative: Vec<(i64, Rc<RefCell<LayoutObject>>, Color)>,
+    // 通常のフローのブロックの背景と border
+    blocks: Vec<DisplayItem>,
+    // 位置指定していないフロート
+    floats: Vec<(Rc<RefCell<LayoutObject>>, Color)>,
+    // 通常のフローのインライン要素とテキスト
+    inlines: Vec<DisplayItem>,
+    // z-index が 0 以上か auto の、位置指定したボックス
+    positioned: Vec<(i64, Rc<RefCell<LayoutObject>>, Color)>,
+}
+
+// 子ノードを描画する下の背景色。テキストでないノードの背景は、子ノードより下に描画される
+fn children_backdrop(node: &Rc<RefCell<LayoutObject>>, backdrop: Color) -> Color {
+    if node.borrow().kind() != LayoutObjectKind::Text {
+        node.borrow().style().background_color().blend(backdrop)
+    } else {
+        backdrop
+    }
 }
 
 fn build_layout_tree(
     node: &Option<Rc<RefCell<Node>>>,
     parent_obj: &Option<Rc<RefCell<LayoutObject>>>,
-    cssom: &StyleSheet,
+    context: &StyleContext,
 ) -> Option<Rc<RefCell<LayoutObject>>> {
     let mut target_node = node.clone();
-    let mut layout_object = create_layout_object(node, parent_obj, cssom);
+    let mut layout_object = create_layout_object(node, parent_obj, context);
 
     while layout_object.is_none() {
         let Some(n) = target_node else {
             return layout_object;
         };
         target_node = n.borrow().next_sibling().clone();
-        layout_object = create_layout_object(&target_node, parent_obj, cssom);
+        layout_object = create_layout_object(&target_node, parent_obj, context);
     }
 
     if let Some(n) = target_node {
         let original_first_child = n.borrow().first_child();
         let original_next_sibling = n.borrow().next_sibling();
-        let mut first_child = build_layout_tree(&original_first_child, &layout_object, cssom);
-        let mut next_sibling = build_layout_tree(&original_next_sibling, &None, cssom);
+        let mut first_child = build_layout_tree(&original_first_child, &layout_object, context);
+        let mut next_sibling = build_layout_tree(&original_next_sibling, parent_obj, context);
 
         if first_child.is_none()
             && let Some(original_first_child) = original_first_child
@@ -184,7 +444,7 @@ fn build_layout_tree(
             let mut original_dom_node = original_first_child.borrow().next_sibling();
 
             loop {
-                first_child = build_layout_tree(&original_dom_node, &layout_object, cssom);
+                first_child = build_layout_tree(&original_dom_node, &layout_object, context);
 
                 if first_child.is_none()
                     && let Some(odn) = original_dom_node
@@ -203,7 +463,7 @@ fn build_layout_tree(
             let mut original_dom_node = original_next_sibling.borrow().next_sibling();
 
             loop {
-                next_sibling = build_layout_tree(&original_dom_node, &None, cssom);
+                next_sibling = build_layout_tree(&original_dom_node, parent_obj, context);
 
                 if next_sibling.is_none()
                     && let Some(odn) = original_dom_node
@@ -219,6 +479,9 @@ fn build_layout_tree(
         let Some(ref obj) = layout_object else {
             panic!("render object should exist here");
         };
+        if obj.borrow().kind().is_block_level() {
+            first_child = create_anonymous_blocks(obj, first_child);
+        }
         obj.borrow_mut().set_first_child(first_child);
         obj.borrow_mut().set_next_sibling(next_sibling);
     }
@@ -226,27 +489,154 @@ fn build_layout_tree(
     layout_object
 }
 
+// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+// ブロックの子ノードにブロックとインラインレベルのノードが混ざっているときは、連続する
+// インラインレベルのノードを匿名ブロックで囲み、子ノードをすべてブロックにする。
+// フレックスコンテナの中の連続するテキストは、混ざっていなくても匿名ブロックで囲む。
+// ブロックを含むインライン要素は、ブロックの前後で分割する。新しい最初の子ノードを返す
+fn create_anonymous_blocks(
+    parent: &Rc<RefCell<LayoutObject>>,
+    first_child: Option<Rc<RefCell<LayoutObject>>>,
+) -> Option<Rc<RefCell<LayoutObject>>> {
+    let mut children = Vec::new();
+    let mut child = first_child;
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        if c.borrow().kind() == LayoutObjectKind::Inline {
+            children.extend(split_inline(&c));
+        } else {
+            children.push(c);
+        }
+    }
+    let children = remove_collapsible_whitespace(children);
+
+    let is_block = |c: &Rc<RefCell<LayoutObject>>| c.borrow().kind().is_block_level();
+    let is_container = parent.borrow().kind().is_item_container();
+    if children.iter().all(is_block) || (!is_container && !children.iter().any(is_block)) {
+        return link_siblings(&children);
+    }
+
+    let mut boxes = Vec::new();
+    let mut run = Vec::new();
+    for c in children {
+        if !is_block(&c) {
+            run.push(c);
+            continue;
+        }
+        if !run.is_empty() {
+            boxes.push(create_anonymous_block(parent, &run));
+            run.clear();
+        }
+        boxes.push(c);
+    }
+    if !run.is_empty() {
+        boxes.push(create_anonymous_block(parent, &run));
+    }
+    link_siblings(&boxes)
+}
+
+fn create_anonymous_block(
+    parent: &Rc<RefCell<LayoutObject>>,
+    children: &[Rc<RefCell<LayoutObject>>],
+) -> Rc<RefCell<LayoutObject>> {
+    let block = Rc::new(RefCell::new(LayoutObject::new_anonymous_block(parent)));
+    block.borrow_mut().set_first_child(link_siblings(children));
+    block
+}
+
+// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+// インライン要素を、子孫のブロックの前後で分割し、分割した部分とブロックを順に並べて返す。
+// ブロックを含まないインライン要素は、そのまま返す
+fn split_inline(inline: &Rc<RefCell<LayoutObject>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut children = Vec::new();
+    let mut child = inline.borrow().first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        if c.borrow().kind() == LayoutObjectKind::Inline {
+            children.extend(split_inline(&c));
+        } else {
+            children.push(c);
+        }
+    }
+    if children.iter().all(|c| !c.borrow().kind().is_block_level()) {
+        return vec![inline.clone()];
+    }
+
+    let mut parts = Vec::new();
+    let mut part = inline.clone();
+    let mut part_children = Vec::new();
+    for c in children {
+        if !c.borrow().kind().is_block_level() {
+            part_children.push(c);
+            continue;
+        }
+        part.borrow_mut()
+            .set_first_child(link_siblings(&part_children));
+        part_children.clear();
+        let continuation = Rc::new(RefCell::new(part.borrow_mut().split_continuation()));
+        parts.push(part);
+        parts.push(c);
+        part = continuation;
+    }
+    part.borrow_mut()
+        .set_first_child(link_siblings(&part_children));
+    parts.push(part);
+    parts
+}
+
+// ノードを順に兄弟としてつなぎ、最初のノードを返す
+fn link_siblings(children: &[Rc<RefCell<LayoutObject>>]) -> Option<Rc<RefCell<LayoutObject>>> {
+    for (i, c) in children.iter().enumerate() {
+        c.borrow_mut()
+            .set_next_sibling(children.get(i + 1).cloned());
+    }
+    children.first().cloned()
+}
+
+// https://www.w3.org/TR/css-text-3/#white-space-phase-1
+// ブロックの子ノードのうち、まとめられる空白だけのテキストで、ブロックの先頭や末尾、
+// ブロックの隣にあるものは行ボックスを作らないので取り除く
+fn remove_collapsible_whitespace(
+    children: Vec<Rc<RefCell<LayoutObject>>>,
+) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let is_block = |c: Option<&Rc<RefCell<LayoutObject>>>| {
+        c.is_none_or(|c| c.borrow().kind().is_block_level())
+    };
+    let mut kept: Vec<Rc<RefCell<LayoutObject>>> = Vec::new();
+    for (i, c) in children.iter().enumerate() {
+        if is_collapsible_whitespace(&c.borrow())
+            && (is_block(kept.last()) || is_block(children.get(i + 1)))
+        {
+            continue;
+        }
+        kept.push(c.clone());
+    }
+    kept
+}
+
+fn is_collapsible_whitespace(object: &LayoutObject) -> bool {
+    let NodeKind::Text(text) = object.node_kind() else {
+        return false;
+    };
+    object.style().white_space().collapses_spaces()
+        && text.chars().all(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+}
+
 fn create_layout_object(
     node: &Option<Rc<RefCell<Node>>>,
     parent_obj: &Option<Rc<RefCell<LayoutObject>>>,
-    cssom: &StyleSheet,
+    context: &StyleContext,
 ) -> Option<Rc<RefCell<LayoutObject>>> {
     let node = node.as_ref()?;
 
     let layout_object = Rc::new(RefCell::new(LayoutObject::new(node.clone(), parent_obj)));
 
-    for rule in &cssom.rules {
-        if layout_object.borrow().is_node_selected(&rule.selector) {
-            layout_object
-                .borrow_mut()
-                .cascading_style(rule.declarations.clone());
-        }
-    }
-
-    let parent_style = parent_obj.as_ref().map(|parent| parent.borrow().style());
-    layout_object
-        .borrow_mut()
-        .defaulting_style(node, parent_style);
+    // 親がいない <body> は、<html> のスタイルを継承する
+    let parent_style = match parent_obj {
+        Some(parent) => Some(parent.borrow().style()),
+        None => context.root_style.clone(),
+    };
+    compute_style(&mut layout_object.borrow_mut(), parent_style, context);
 
     if layout_object.borrow().style().display() == DisplayType::DisplayNone {
         return None;
@@ -256,32 +646,226 @@ fn create_layout_object(
     Some(layout_object)
 }
 
+// レイアウトオブジェクトとその子孫のスタイルを計算し直す。
+// force が true のときは、親のスタイルが変わったので印がなくても計算し直す
+fn restyle_object(
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    parent_style: Option<ComputedStyle>,
+    force: bool,
+    context: &StyleContext,
+) -> bool {
+    let node = layout_object.borrow().node();
+    let mut force_children = force;
+
+    if force || node.borrow().is_style_dirty() {
+        let old_style = layout_object.borrow().style();
+        compute_style(&mut layout_object.borrow_mut(), parent_style, context);
+
+        let style = layout_object.borrow().style();
+        if style.display() == DisplayType::DisplayNone {
+            return false;
+        }
+        // ブロックとインラインが入れ替わると、行ボックスに並べるノードや取り除く空白が変わる
+        let old_kind = layout_object.borrow().kind();
+        layout_object.borrow_mut().update_kind();
+        if layout_object.borrow().kind() != old_kind {
+            return false;
+        }
+        // 継承されるプロパティや text-decoration が子孫に伝わるので、子孫もすべて計算し直す
+        force_children |= style != old_style;
+    }
+
+    if !force_children && !node.borrow().has_dirty_descendant() {
+        return true;
+    }
+
+    // 匿名ブロックは親のスタイルを継承し直し、その子ノードを DOM の子ノードと対応させる。
+    // 分割したインライン要素は DOM の子ノードと対応しないので、レイアウトツリーを作り直す
+    let style = layout_object.borrow().style();
+    let mut layout_children = Vec::new();
+    let mut layout_child = layout_object.borrow().first_child();
+    while let Some(lc) = layout_child {
+        layout_child = lc.borrow().next_sibling();
+        if !lc.borrow().is_anonymous() {
+            layout_children.push(lc);
+            continue;
+        }
+        lc.borrow_mut().inherit_anonymous_style(&style);
+        let mut grandchild = lc.borrow().first_child();
+        while let Some(gc) = grandchild {
+            grandchild = gc.borrow().next_sibling();
+            layout_children.push(gc);
+        }
+    }
+    if layout_children.iter().any(|lc| lc.borrow().is_split()) {
+        return false;
+    }
+
+    // DOM の子ノードと、レイアウトツリーの子ノードを順に対応させる。
+    // レイアウトオブジェクトのない要素は display: none なので、スタイルだけを計算し直す。
+    // レイアウトオブジェクトのないテキストは取り除いた空白なので、空白を残すようになったときだけ作り直す
+    let mut layout_children = layout_children.into_iter().peekable();
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        match layout_children.next_if(|lc| Rc::ptr_eq(&lc.borrow().node(), &c)) {
+            Some(lc) => {
+                if !restyle_object(&lc, Some(style.clone()), force_children, context) {
+                    return false;
+                }
+            }
+            None if matches!(c.borrow().kind(), NodeKind::Text(_)) => {
+                if force_children && !style.white_space().collapses_spaces() {
+                    return false;
+                }
+            }
+            _ => {
+                let dirty = c.borrow().is_style_dirty() || c.borrow().has_dirty_descendant();
+                if force_children || dirty {
+                    let mut hidden = LayoutObject::new(c.clone(), &Some(layout_object.clone()));
+                    compute_style(&mut hidden, Some(style.clone()), context);
+                    if hidden.style().display() != DisplayType::DisplayNone {
+                        return false;
+                    }
+                }
+            }
+        }
+        child = c.borrow().next_sibling();
+    }
+
+    true
+}
+
+// スタイルを計算するときに、レイアウトツリー全体で共通して使う情報
+struct StyleContext<'a> {
+    // 要素ごとにすべてのルールを調べずに済むように、スタイルシートのルールを索引にしたもの
+    rule_index: RuleIndex,
+    viewport: &'a Viewport,
+    root_style: Option<ComputedStyle>,
+    // スタイルを計算した要素の数
+    styled_elements: Cell<usize>,
+}
+
+impl<'a> StyleContext<'a> {
+    fn new(
+        stylesheets: &'a [StyleSheet],
+        viewport: &'a Viewport,
+        root_style: Option<ComputedStyle>,
+    ) -> Self {
+        Self {
+            rule_index: RuleIndex::new(stylesheets, viewport),
+            viewport,
+            root_style,
+            styled_elements: Cell::new(0),
+        }
+    }
+}
+
+fn compute_style(
+    layout_object: &mut LayoutObject,
+    parent_style: Option<ComputedStyle>,
+    context: &StyleContext,
+) {
+    let root_font_size = context
+        .root_style
+        .as_ref()
+        .map_or(DEFAULT_FONT_SIZE, |style| style.font_size());
+
+    layout_object.defaulting_style(parent_style.as_ref());
+
+    // マッチしたすべての宣言をカスケード順に並べ、優先度の低いものから適用する
+    if let NodeKind::Element(element) = layout_object.node_kind() {
+        context
+            .styled_elements
+            .set(context.styled_elements.get() + 1);
+        let cascaded_style = CascadedStyle::from_index(&element, &context.rule_index);
+        layout_object.cascading_style(
+            cascaded_style.sorted_declarations(),
+            parent_style.as_ref(),
+            root_font_size,
+            context.viewport,
+        );
+        layout_object.set_cascaded_style(cascaded_style);
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use alloc::{
-        string::{String, ToString},
-        vec::Vec,
-    };
-
     use crate::renderer::{
-        css::{cssom::CssParser, token::CssTokenizer},
+        css::{
+            cssom::{CssParser, Selector},
+            token::CssTokenizer,
+            user_agent::user_agent_stylesheet,
+        },
         dom::{
-            api::get_style_content,
-            node::{Element, NodeKind},
+            api::{StyleSource, get_style_sources},
+            node::Element,
         },
         html::{parser::HtmlParser, token::HtmlTokenizer},
+        layout::{
+            computed_style::{BorderStyle, PROPERTIES},
+            font::{
+                FixedWidthFontMetrics, Font, FontFamily, FontStyle, GenericFontFamily,
+                VerticalMetrics,
+            },
+            length::{ComputedLength, MAX_LENGTH, Size},
+            text::TextDecorationStyle,
+        },
     };
 
+    use crate::constants::CHAR_HEIGHT_WITH_PADDING;
+
     use super::*;
 
     fn create_layout_view(html: String) -> LayoutView {
+        create_layout_view_with_viewport(html, &Viewport::default())
+    }
+
+    fn create_layout_view_with_viewport(html: String, viewport: &Viewport) -> LayoutView {
+        create_layout_view_with(html, viewport, Rc::new(FixedWidthFontMetrics))
+    }
+
+    fn create_layout_view_with(
+        html: String,
+        viewport: &Viewport,
+        font_metrics: Rc<dyn FontMetrics>,
+    ) -> LayoutView {
         let t = HtmlTokenizer::new(html);
         let window = HtmlParser::new(t).construct_tree();
         let dom = window.borrow().document();
-        let css = get_style_content(dom.clone());
-        let css_tokenizer = CssTokenizer::new(css);
-        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
-        LayoutView::new(dom, &cssom)
+        let mut stylesheets = vec![user_agent_stylesheet()];
+        for source in get_style_sources(dom.clone()) {
+            if let StyleSource::Inline(css) = source {
+                let css_tokenizer = CssTokenizer::new(css);
+                stylesheets.push(CssParser::new(css_tokenizer).parse_stylesheet());
+            }
+        }
+        LayoutView::new(dom, &stylesheets, viewport, font_metrics)
+    }
+
+    // 文字ごとに幅の違うフォント。i と l は 4px、m と w は 12px、ASCII でない文字は 16px、
+    // それ以外は 8px で、フォントサイズに比例して大きくなる。行の間隔は広めにとる
+    #[derive(Debug)]
+    struct ProportionalFontMetrics;
+
+    impl FontMetrics for ProportionalFontMetrics {
+        fn advance(&self, font: &Font, c: char) -> i64 {
+            let width = match c {
+                'i' | 'l' => 4,
+                'm' | 'w' => 12,
+                c if !c.is_ascii() => 16,
+                _ => 8,
+            };
+            width * font.size as i64 / 16
+        }
+
+        fn vertical_metrics(&self, font: &Font) -> VerticalMetrics {
+            let size = font.size as i64;
+            VerticalMetrics {
+                ascent: size,
+                descent: size / 2,
+                line_gap: size / 2,
+            }
+        }
     }
 
     #[test]
@@ -376,4 +960,1463 @@ mod tests {
         );
         assert_eq!(LayoutObjectKind::Text, text.borrow().kind());
     }
+
+    #[test]
+    fn test_cascade_order() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                .x { color: blue; }
+                p { color: red; background-color: yellow; }
+              </style>
+            </head>
+            <body>
+              <p class="x">text</p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        assert_eq!(
+            Color::from_name("blue").unwrap(),
+            p.borrow().style().color()
+        );
+        assert_eq!(
+            Color::from_name("yellow").unwrap(),
+            p.borrow().style().background_color()
+        );
+
+        let p = p.borrow();
+        let explained = p.cascaded_style().explain("color");
+        assert_eq!(2, explained.len());
+        assert_eq!(
+            Some(Selector::ClassSelector("x".to_string())),
+            explained[0].selector
+        );
+        assert_eq!(
+            Some(Selector::TypeSelector("p".to_string())),
+            explained[1].selector
+        );
+    }
+
+    #[test]
+    fn test_user_agent_style() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                .plain { text-decoration: none; }
+              </style>
+            </head>
+            <body>
+              <h1>title</h1>
+              <div><a>link</a><a class="plain">plain</a></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let h1 = body.borrow().first_child().expect("h1 node should exist");
+        assert_eq!(LayoutObjectKind::Block, h1.borrow().kind());
+        assert_eq!(32.0, h1.borrow().style().font_size());
+
+        let div = h1.borrow().next_sibling().expect("div node should exist");
+        assert_eq!(LayoutObjectKind::Block, div.borrow().kind());
+
+        let a = div.borrow().first_child().expect("a node should exist");
+        assert_eq!(LayoutObjectKind::Inline, a.borrow().kind());
+        assert!(a.borrow().style().text_decoration_line().underline);
+
+        let plain = a.borrow().next_sibling().expect("a node should exist");
+        assert!(plain.borrow().style().text_decoration().is_none());
+    }
+
+    #[test]
+    fn test_inline_style() {
+        let html = r#"
+            <html>
+            <head><style>#main { color: red; }</style></head>
+            <body>
+              <p id="main" style="color: blue">text</p>
+              <p style="display: none">hidden</p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        assert_eq!(
+            Color::from_name("blue").unwrap(),
+            p.borrow().style().color()
+        );
+        assert!(p.borrow().next_sibling().is_none());
+    }
+
+    #[test]
+    fn test_media_query() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                p { color: red; }
+                @media (max-width: 600px) { p { color: blue; } }
+                @media print { p { color: green; } }
+              </style>
+            </head>
+            <body><p>text</p></body>
+            </html>
+        "#
+        .to_string();
+
+        let color = |viewport: &Viewport| {
+            let layout_view = create_layout_view_with_viewport(html.clone(), viewport);
+            let body = layout_view.root().expect("root should exist");
+            let p = body.borrow().first_child().expect("p node should exist");
+            p.borrow().style().color()
+        };
+        assert_eq!(
+            Color::from_name("blue").unwrap(),
+            color(&Viewport::new(320, 480))
+        );
+        assert_eq!(
+            Color::from_name("red").unwrap(),
+            color(&Viewport::new(1024, 768))
+        );
+    }
+
+    #[test]
+    fn test_custom_properties() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body { --main-color: blue; --decoration: underline; }
+                div { --main-color: green; }
+                p { color: var(--main-color); text-decoration: var(--decoration); }
+                span { color: red; color: var(--undefined); }
+              </style>
+            </head>
+            <body>
+              <p>blue</p>
+              <div><p>green</p></div>
+              <span>black</span>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        assert_eq!(
+            Color::from_name("blue").unwrap(),
+            p.borrow().style().color()
+        );
+        assert!(p.borrow().style().text_decoration_line().underline);
+
+        let div = p.borrow().next_sibling().expect("div node should exist");
+        let nested_p = div.borrow().first_child().expect("p node should exist");
+        assert_eq!(
+            Color::from_name("green").unwrap(),
+            nested_p.borrow().style().color()
+        );
+
+        // 解決できない var() は前の宣言を打ち消し、継承値か初期値になる
+        let span = div.borrow().next_sibling().expect("span node should exist");
+        assert_eq!(
+            Color::from_name("black").unwrap(),
+            span.borrow().style().color()
+        );
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                div { color: red; background-color: blue; font-size: 20px; }
+                .black { color: black; }
+                .inherit { background-color: inherit; }
+                .initial { color: initial; font-size: initial; }
+                .unset { color: green; color: unset; background-color: red; background-color: unset; }
+                .revert { display: inline; display: revert; }
+              </style>
+            </head>
+            <body>
+              <div><p class="black">a</p><p>b</p><p class="inherit">c</p><p class="initial">d</p><p class="unset">e</p><p class="revert">f</p></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let div = body.borrow().first_child().expect("div node should exist");
+        let children = {
+            let mut children = Vec::new();
+            let mut child = div.borrow().first_child();
+            while let Some(c) = child {
+                child = c.borrow().next_sibling();
+                children.push(c.borrow().style());
+            }
+            children
+        };
+        let red = Color::from_name("red").unwrap();
+        let blue = Color::from_name("blue").unwrap();
+
+        // 親と値が異なっていれば、初期値と同じ値でも指定したとおりになる
+        assert_eq!(Color::black(), children[0].color());
+        // color や font-size は継承されるが、background-color は継承されない
+        assert_eq!(red, children[1].color());
+        assert_eq!(20.0, children[1].font_size());
+        assert_eq!(Color::transparent(), children[1].background_color());
+        assert_eq!(blue, children[2].background_color());
+        assert_eq!(Color::black(), children[3].color());
+        assert_eq!(DEFAULT_FONT_SIZE, children[3].font_size());
+        assert_eq!(red, children[4].color());
+        assert_eq!(Color::transparent(), children[4].background_color());
+        // 作成者スタイルシートの revert は UA スタイルシートの値に戻る
+        assert_eq!(DisplayType::Block, children[5].display());
+    }
+
+    #[test]
+    fn test_initial_values() {
+        // プロパティ表に書いた初期値を計算すると、ComputedStyle の初期値と一致する
+        let declarations = PROPERTIES
+            .iter()
+            .map(|p| format!("{}: {}", p.name, p.initial))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let html = format!(
+            r#"<html><head><style>span {{ {} }}</style></head><body><p><span>x</span></p></body></html>"#,
+            declarations
+        );
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        let span = p.borrow().first_child().expect("span node should exist");
+        assert_eq!(ComputedStyle::default(), span.borrow().style());
+    }
+
+    #[test]
+    fn test_paint_with_alpha() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                div { background-color: #0000ff; }
+                p { background-color: rgba(255, 255, 255, 0.5); color: hsl(0, 100%, 50%, 50%); }
+              </style>
+            </head>
+            <body>
+              <div><p>text</p></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+        let display_items = layout_view.paint();
+
+        let backgrounds = display_items
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Rect { style, .. } => Some(style.background_color()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // body は背景が透明なので描画されず、p の背景は div の青と合成される
+        assert_eq!(
+            vec![Color::from_rgb(0x0000ff), Color::from_rgb(0x8080ff)],
+            backgrounds
+        );
+
+        let text_color = display_items
+            .iter()
+            .find_map(|item| match item {
+                DisplayItem::Text { style, .. } => Some(style.color()),
+                _ => None,
+            })
+            .expect("text should be painted");
+        assert_eq!(Color::from_rgb(0xc0407f), text_color);
+    }
+
+    #[test]
+    fn test_lengths() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                html { font-size: 20px; }
+                div { font-size: 150%; width: calc(50% - 2em); height: 3rem; }
+                p { font-size: 0.5em; width: 10vw; height: 50%; }
+              </style>
+            </head>
+            <body>
+              <div><p>text</p></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view_with_viewport(html, &Viewport::new(400, 300));
+
+        let body = layout_view.root().expect("root should exist");
+        assert_eq!(20.0, body.borrow().style().font_size());
+
+        let div = body.borrow().first_child().expect("div node should exist");
+        assert_eq!(30.0, div.borrow().style().font_size());
+        // 横幅は body のコンテンツボックスの横幅の半分から 2em（60px）を引いたもの、高さは 3rem（60px）
+        assert_eq!(
+            LayoutSize {
+                width: (400 - 16) / 2 - 60,
+                height: 60,
+            },
+            div.borrow().size()
+        );
+
+        let p = div.borrow().first_child().expect("p node should exist");
+        assert_eq!(15.0, p.borrow().style().font_size());
+        // vw はビューポートの横幅が基準になり、パーセンテージの高さは auto になる
+        assert_eq!(40, p.borrow().size().width);
+        assert_eq!(
+            Size::Length(ComputedLength {
+                px: 0.0,
+                percent: 50.0
+            }),
+            p.borrow().style().height()
+        );
+        assert_eq!(CHAR_HEIGHT_WITH_PADDING, p.borrow().size().height);
+    }
+
+    #[test]
+    fn test_huge_lengths() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                div {
+                  margin: 99999999999999999999px;
+                  padding: 99999999999999999999px;
+                  border: 99999999999999999999px solid;
+                  line-height: 99999999999999999999;
+                }
+                p { margin-left: -99999999999999999999px; width: 99999999999999999999%; }
+              </style>
+            </head>
+            <body>
+              <div><p>text</p></div>
+              <div style="display: flex"><p>flex</p></div>
+              <div style="display: grid"><p>grid</p></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        // 範囲外の長さは丸められ、位置や大きさの計算があふれない
+        let body = layout_view.root().expect("root should exist");
+        let div = body.borrow().first_child().expect("div node should exist");
+        assert_eq!(MAX_LENGTH as i64, div.borrow().margin().top);
+        assert_eq!(MAX_LENGTH as i64, div.borrow().padding().left);
+
+        let p = div.borrow().first_child().expect("p node should exist");
+        assert_eq!(-MAX_LENGTH as i64, p.borrow().margin().left);
+        // body のマージン 8px と、div のマージンとボーダーとパディングから p の負のマージンを引いた位置
+        assert_eq!(8 + 2 * MAX_LENGTH as i64, p.borrow().point().x);
+        assert!(!layout_view.paint().is_empty());
+    }
+
+    #[test]
+    fn test_box_model() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body { margin: 0; }
+                #outer { margin: 10px 0 30px; padding: 5px 10%; border: 2px solid red; height: 100px; }
+                #inner { width: 100px; margin: 0 auto 20px; box-sizing: border-box; padding: 10px; border: 5px dashed; }
+                #next { margin-top: 40px; min-height: 10px; max-width: 50px; }
+              </style>
+            </head>
+            <body>
+              <div id="outer"><div id="inner"></div></div>
+              <div id="next"></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let outer = body
+            .borrow()
+            .first_child()
+            .expect("outer node should exist");
+        // 親の最初の子ノードと上マージンが相殺して、body の外側に出る
+        assert_eq!(LayoutPoint { x: 0, y: 10 }, outer.borrow().point());
+        let viewport_width = Viewport::default().width;
+        let padding = viewport_width / 10;
+        assert_eq!(
+            LayoutSize {
+                width: viewport_width,
+                height: 100 + 10 + 4,
+            },
+            outer.borrow().size()
+        );
+
+        // border-box の横幅には padding と border が含まれ、auto の左右のマージンで中央に寄せる
+        let inner = outer
+            .borrow()
+            .first_child()
+            .expect("inner node should exist");
+        let content_width = viewport_width - padding * 2 - 4;
+        assert_eq!(
+            LayoutSize {
+                width: 100,
+                height: 30,
+            },
+            inner.borrow().size()
+        );
+        assert_eq!(
+            LayoutPoint {
+                x: 2 + padding + (content_width - 100) / 2,
+                y: 10 + 2 + 5,
+            },
+            inner.borrow().point()
+        );
+
+        // 隣り合うブロックのマージンは、大きいほうの 40px に相殺する
+        let next = outer
+            .borrow()
+            .next_sibling()
+            .expect("next node should exist");
+        assert_eq!(
+            LayoutRect::new(0, 10 + 114 + 40, 50, 10),
+            next.borrow().rect()
+        );
+
+        let borders = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Border {
+                    border_style,
+                    color,
+                    layout_rect,
+                } => Some((border_style, color, layout_rect)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(8, borders.len());
+        assert_eq!(
+            (
+                BorderStyle::Solid,
+                Color::from_name("red").unwrap(),
+                LayoutRect::new(0, 10, viewport_width, 2)
+            ),
+            borders[0]
+        );
+        // border-color の初期値は currentColor
+        assert_eq!(
+            (
+                BorderStyle::Dashed,
+                Color::black(),
+                LayoutRect::new(inner.borrow().point().x, 17, 5, 30)
+            ),
+            borders[7]
+        );
+    }
+
+    #[test]
+    fn test_fonts() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                p { font: 20px/1.5 "Noto Sans", sans-serif; }
+              </style>
+            </head>
+            <body>
+              <p><b>bold</b><em>italic</em><code>code</code></p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let fonts = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { text, font, .. } => Some((text, font)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(3, fonts.len());
+
+        let (text, font) = &fonts[0];
+        assert_eq!("bold", text);
+        assert_eq!(700, font.weight);
+        assert_eq!(FontStyle::Normal, font.style);
+        assert_eq!(
+            vec![
+                FontFamily::Named("Noto Sans".to_string()),
+                FontFamily::Generic(GenericFontFamily::SansSerif),
+            ],
+            font.families
+        );
+        // 数値の line-height は、フォントサイズに掛けて行の高さを求める
+        assert_eq!(30.0, font.line_height);
+
+        let (_, font) = &fonts[1];
+        assert_eq!(400, font.weight);
+        assert_eq!(FontStyle::Italic, font.style);
+
+        let (_, font) = &fonts[2];
+        assert_eq!(
+            vec![FontFamily::Generic(GenericFontFamily::Monospace)],
+            font.families
+        );
+        assert_eq!(20.0, font.size);
+    }
+
+    #[test]
+    fn test_text_properties() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                .right { text-align: right; text-transform: uppercase; }
+                .indent { text-indent: 16px; letter-spacing: 2px; }
+                .decorated { text-decoration: underline overline dotted red; }
+              </style>
+            </head>
+            <body>
+              <p class="right">ab</p>
+              <p class="indent">xy</p>
+              <p class="decorated">under <s>strike</s></p>
+              <pre>a  b
+c</pre>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+        let items = layout_view.paint();
+
+        let texts = items
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text.as_str(), layout_point.x)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // body のコンテンツボックスは x = 8 から始まり、横幅はビューポートの横幅から 16px 引いたもの
+        let viewport_width = Viewport::default().width;
+        assert_eq!(
+            vec![
+                ("AB", viewport_width - 8 - 16),
+                ("x", 24),
+                ("y", 34),
+                ("under", 8),
+                ("strike", 56),
+                ("a  b", 8),
+                ("c", 8),
+            ],
+            texts
+        );
+
+        let decorations = items
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Decoration {
+                    decoration_style,
+                    color,
+                    ..
+                } => Some((*decoration_style, *color)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let red = Color::from_name("red").unwrap();
+        let black = Color::from_name("black").unwrap();
+        // <s> の中のテキストには、<p> の下線と上線、<s> の取り消し線を描画する
+        assert_eq!(
+            vec![
+                (TextDecorationStyle::Dotted, red),
+                (TextDecorationStyle::Dotted, red),
+                (TextDecorationStyle::Dotted, red),
+                (TextDecorationStyle::Dotted, red),
+                (TextDecorationStyle::Solid, black),
+            ],
+            decorations
+        );
+    }
+
+    #[test]
+    fn test_inline_formatting_context() {
+        // 1行に 12 文字まで入る幅で、リンクの前後のテキストとリンクを行ボックスに並べる
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, p { margin: 0; }
+                p { width: 96px; }
+                a { padding: 0 2px; background-color: yellow; }
+              </style>
+            </head>
+            <body>
+              <p>Some <a>link text</a> and more</p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let texts = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text, layout_point.x, layout_point.y)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // 行の高さは 20px で、文字は行の上端から 2px 下に描画する
+        assert_eq!(
+            vec![
+                ("Some".to_string(), 0, 2),
+                ("link".to_string(), 42, 2),
+                ("text".to_string(), 0, 22),
+                ("and".to_string(), 42, 22),
+                ("more".to_string(), 0, 42),
+            ],
+            texts
+        );
+
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        assert_eq!(60, p.borrow().size().height);
+
+        // リンクは2行に分かれ、左右の padding はリンクが始まる行と終わる行にだけ付く
+        let a = p
+            .borrow()
+            .first_child()
+            .expect("text node should exist")
+            .borrow()
+            .next_sibling()
+            .expect("a node should exist");
+        let fragments = a
+            .borrow()
+            .fragments()
+            .iter()
+            .map(|f| f.rect)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                LayoutRect::new(40, 2, 34, 16),
+                LayoutRect::new(0, 22, 34, 16)
+            ],
+            fragments
+        );
+        let backgrounds = layout_view
+            .paint()
+            .into_iter()
+            .filter(|item| matches!(item, DisplayItem::Rect { .. }))
+            .count();
+        assert_eq!(2, backgrounds);
+
+        // 2行目のリンクの文字をクリックすると、リンクの中のテキストが見つかる
+        let link_text = layout_view
+            .find_node_by_position(LayoutPoint { x: 4, y: 25 })
+            .expect("text node should be hit");
+        assert_eq!(
+            NodeKind::Text("link text".to_string()),
+            link_text.borrow().node_kind()
+        );
+        assert!(
+            layout_view
+                .find_node_by_position(LayoutPoint { x: 80, y: 25 })
+                .is_some_and(|node| node.borrow().kind() == LayoutObjectKind::Block)
+        );
+    }
+
+    #[test]
+    fn test_vertical_align() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, p { margin: 0; }
+                .big { font-size: 32px; }
+                .up { vertical-align: 4px; }
+              </style>
+            </head>
+            <body>
+              <p>a<span class="big">b</span><br>c<span class="up">d</span></p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        let texts = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text, layout_point)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // 1行目は大きなフォントの b に合わせて高さが 60px になり、ベースラインは上端から 42px の位置にある。
+        // <br> のあとの 2行目では、d が 4px 上がる分だけ行が高くなる
+        assert_eq!(
+            vec![
+                ("a".to_string(), LayoutPoint { x: 0, y: 42 - 12 }),
+                ("b".to_string(), LayoutPoint { x: 8, y: 42 - 36 }),
+                (
+                    "c".to_string(),
+                    LayoutPoint {
+                        x: 0,
+                        y: 60 + 18 - 12
+                    }
+                ),
+                (
+                    "d".to_string(),
+                    LayoutPoint {
+                        x: 8,
+                        y: 60 + 14 - 12
+                    }
+                ),
+            ],
+            texts
+        );
+
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        assert_eq!(60 + 24, p.borrow().size().height);
+    }
+
+    #[test]
+    fn test_anonymous_block() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body { margin: 0; }
+                p { margin: 4px 0; }
+                div { text-indent: 16px; }
+              </style>
+            </head>
+            <body>
+              <div>before<p>block</p>after <em>em</em></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        // ブロックの前後のインラインレベルのノードは、それぞれ匿名ブロックに囲まれる。
+        // 字下げするのは、<div> の最初の行と、text-indent を継承した <p> の最初の行だけ
+        assert_eq!(
+            [
+                "Block <body> (0, 0) 590x68",
+                "  Block <div> (0, 0) 590x68",
+                "    Block (anonymous) (0, 0) 590x20",
+                "      Text \"before\" (16, 2) 48x16",
+                "    Block <p> (0, 24) 590x20",
+                "      Text \"block\" (16, 26) 40x16",
+                "    Block (anonymous) (0, 48) 590x20",
+                "      Text \"after \" (0, 50) 40x16",
+                "      Inline <em> (48, 50) 16x16",
+                "        Text \"em\" (48, 50) 16x16",
+                "",
+            ]
+            .join("\n"),
+            layout_view.dump()
+        );
+    }
+
+    #[test]
+    fn test_inline_split() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, p { margin: 0; }
+                span { padding: 0 4px; border: 2px solid black; }
+              </style>
+            </head>
+            <body>
+              <p><span>a<div>b</div>c</span></p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        // <span> は <div> の前後で分割され、<div> は匿名ブロックの兄弟になる
+        assert_eq!(
+            [
+                "Block <body> (0, 0) 590x60",
+                "  Block <p> (0, 0) 590x60",
+                "    Block (anonymous) (0, 0) 590x20",
+                "      Inline <span> (split) (0, 0) 14x20",
+                "        Text \"a\" (6, 2) 8x16",
+                "    Block <div> (0, 20) 590x20",
+                "      Text \"b\" (0, 22) 8x16",
+                "    Block (anonymous) (0, 40) 590x20",
+                "      Inline <span> (split) (0, 40) 14x20",
+                "        Text \"c\" (0, 42) 8x16",
+                "",
+            ]
+            .join("\n"),
+            layout_view.dump()
+        );
+
+        // 左の border は最初の部分にだけ、右の border は最後の部分にだけ描画する
+        let borders = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Border { layout_rect, .. } => Some(layout_rect),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                LayoutRect::new(0, 0, 14, 2),
+                LayoutRect::new(0, 18, 14, 2),
+                LayoutRect::new(0, 0, 2, 20),
+                LayoutRect::new(0, 40, 14, 2),
+                LayoutRect::new(0, 58, 14, 2),
+                LayoutRect::new(12, 40, 2, 20),
+            ],
+            borders
+        );
+    }
+
+    #[test]
+    fn test_viewport_width() {
+        let html = format!(
+            r#"
+            <html>
+            <head>
+              <style>
+                body, p {{ margin: 0; }}
+                .half {{ width: 50%; }}
+                .center {{ max-width: 600px; margin: 0 auto; }}
+              </style>
+            </head>
+            <body>
+              <div class="half"><p>{}</p></div>
+              <div class="center">center</div>
+            </body>
+            </html>
+        "#,
+            ["word"; 30].join(" ")
+        );
+
+        // 同じ文書でも、ビューポートの横幅に応じて包含ブロックの横幅と折り返す位置が変わる
+        for (width, lines, center_x) in [(320, 8, 0), (1024, 3, 212), (1920, 2, 660)] {
+            let layout_view =
+                create_layout_view_with_viewport(html.clone(), &Viewport::new(width, 600));
+
+            let body = layout_view.root().expect("root should exist");
+            assert_eq!(width, body.borrow().size().width);
+
+            let half = body.borrow().first_child().expect("div node should exist");
+            let p = half.borrow().first_child().expect("p node should exist");
+            assert_eq!(
+                LayoutSize {
+                    width: width / 2,
+                    height: lines * 20,
+                },
+                p.borrow().size()
+            );
+
+            let center = half.borrow().next_sibling().expect("div node should exist");
+            assert_eq!(
+                LayoutRect::new(center_x, lines * 20, width.min(600), 20),
+                center.borrow().rect()
+            );
+        }
+    }
+
+    #[test]
+    fn test_font_metrics() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, p { margin: 0; }
+                p { width: 60px; }
+              </style>
+            </head>
+            <body>
+              <p>mill 日本語</p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let texts = |layout_view: &LayoutView| {
+            layout_view
+                .paint()
+                .into_iter()
+                .filter_map(|item| match item {
+                    DisplayItem::Text {
+                        text, layout_point, ..
+                    } => Some((text, layout_point)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // 等幅フォントでは、全角の文字は半角の文字の2倍の幅になる。日本語は文字の間で折り返す
+        let layout_view = create_layout_view(html.clone());
+        assert_eq!(
+            vec![
+                ("mill 日".to_string(), LayoutPoint { x: 0, y: 2 }),
+                ("本語".to_string(), LayoutPoint { x: 0, y: 22 }),
+            ],
+            texts(&layout_view)
+        );
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        let text = p.borrow().first_child().expect("text node should exist");
+        assert_eq!(
+            LayoutSize {
+                width: 56,
+                height: 36
+            },
+            text.borrow().size()
+        );
+
+        // 文字ごとの幅と行の高さは、組み込む側が提供するフォントの情報から求める。
+        // 行の高さは 16 + 8 + 8 = 32px で、ベースラインは行の上端から 20px の位置にある
+        let layout_view =
+            create_layout_view_with(html, &Viewport::default(), Rc::new(ProportionalFontMetrics));
+        assert_eq!(
+            vec![
+                ("mill 日".to_string(), LayoutPoint { x: 0, y: 4 }),
+                ("本語".to_string(), LayoutPoint { x: 0, y: 36 }),
+            ],
+            texts(&layout_view)
+        );
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        assert_eq!(64, p.borrow().size().height);
+        let text = p.borrow().first_child().expect("text node should exist");
+        assert_eq!(
+            LayoutSize {
+                width: 48,
+                height: 56
+            },
+            text.borrow().size()
+        );
+    }
+
+    #[test]
+    fn test_word_break() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, p { margin: 0; }
+                p { width: 64px; }
+                #keep { word-break: keep-all; }
+                #url { overflow-wrap: anywhere; }
+              </style>
+            </head>
+            <body>
+              <p>日本語の文章。</p>
+              <p id="keep">日本語 文章</p>
+              <p id="url">https://example.com/</p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+        let lines = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "日本語の",
+                "文章。",
+                "日本語",
+                "文章",
+                "https://",
+                "example.",
+                "com/",
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_bidi() {
+        // 1行に 20 文字まで入る
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, p { margin: 0; }
+                p { width: 160px; }
+              </style>
+            </head>
+            <body>
+              <p dir="rtl">שלום <a href="/abc">abc</a> עולם</p>
+              <p>ab <bdo dir="rtl">cd ef</bdo></p>
+              <p style="direction: rtl; unicode-bidi: bidi-override">ab cd</p>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+        let texts = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text, layout_point.x, layout_point.y)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // 右から左の段落は右に揃え、文字を表示する順に並べて描画する
+        assert_eq!(
+            vec![
+                ("םולש".to_string(), 128, 2),
+                ("abc".to_string(), 96, 2),
+                ("םלוע".to_string(), 56, 2),
+                ("ab".to_string(), 0, 22),
+                ("fe dc".to_string(), 24, 22),
+                ("dc ba".to_string(), 120, 42),
+            ],
+            texts
+        );
+
+        // 方向の混ざった行でも、クリックした位置の文字が見つかる
+        let link_text = layout_view
+            .find_node_by_position(LayoutPoint { x: 100, y: 5 })
+            .expect("text node should be hit");
+        assert_eq!(
+            NodeKind::Text("abc".to_string()),
+            link_text.borrow().node_kind()
+        );
+        let text = layout_view
+            .find_node_by_position(LayoutPoint { x: 60, y: 5 })
+            .expect("text node should be hit");
+        assert_eq!(
+            NodeKind::Text(" עולם".to_string()),
+            text.borrow().node_kind()
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        // 1行に 20 文字まで入る
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body { margin: 0; width: 160px; }
+                div, p { margin: 0; }
+                .l { float: left; width: 40px; height: 30px; }
+                .r { float: right; width: 24px; height: 50px; }
+                .c { clear: both; }
+                .bfc { overflow: hidden; }
+                .s { float: left; }
+                .root { display: flow-root; }
+              </style>
+            </head>
+            <body>
+              <div><div class="l"></div>aaa bbb ccc ddd eee fff ggg hhh iii jjj kkk lll</div>
+              <div><div class="r"></div>abc</div>
+              <p class="c">clear</p>
+              <div class="bfc"><div class="l"></div></div>
+              <span class="s">wide</span><div class="root">beside</div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+
+        // フロートの横の行は短くなり、フロートの下の行は元の幅に戻る。clear を指定したブロックは
+        // フロートの下に置き、overflow: hidden と display: flow-root のブロックはフロートを含むか避ける
+        assert_eq!(
+            [
+                "Block <body> (0, 0) 160x180",
+                "  Block <div> (0, 0) 160x60",
+                "    Block <div> (0, 0) 40x30",
+                "    Block (anonymous) (0, 0) 160x60",
+                "      Text \"aaa bbb ccc ddd eee fff ggg hhh iii jjj kkk lll\" (0, 2) 160x56",
+                "  Block <div> (0, 60) 160x20",
+                "    Block <div> (136, 60) 24x50",
+                "    Block (anonymous) (0, 60) 160x20",
+                "      Text \"abc\" (0, 62) 24x16",
+                "  Block <p> (0, 110) 160x20",
+                "    Text \"clear\" (0, 112) 40x16",
+                "  Block <div> (0, 130) 160x30",
+                "    Block <div> (0, 130) 40x30",
+                "  Block <span> (0, 160) 32x20",
+                "    Text \"wide\" (0, 162) 32x16",
+                "  Block <div> (32, 160) 128x20",
+                "    Text \"beside\" (32, 162) 48x16",
+                "",
+            ]
+            .join("\n"),
+            layout_view.dump()
+        );
+
+        let lines = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } if text.len() > 10 => Some((text, layout_point.x, layout_point.y)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("aaa bbb ccc ddd".to_string(), 40, 2),
+                ("eee fff ggg hhh".to_string(), 40, 22),
+                ("iii jjj kkk lll".to_string(), 0, 42),
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_flow_root() {
+        let html = r#"<html><body><div style="display: flow-root"></div></body></html>"#;
+        let layout_view = create_layout_view(html.to_string());
+        let div = layout_view
+            .root()
+            .and_then(|body| body.borrow().first_child())
+            .expect("div should exist");
+        assert_eq!(DisplayType::FlowRoot, div.borrow().style().display());
+        assert_eq!(LayoutObjectKind::Block, div.borrow().kind());
+    }
+
+    #[test]
+    fn test_flex() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, div { margin: 0; }
+                .row { display: flex; column-gap: 10px; height: 60px; align-items: center; }
+                .a { width: 50px; height: 20px; }
+                .b { flex: 1; height: 30px; }
+                .c { order: -1; align-self: stretch; width: 40px; }
+                .wrap {
+                  display: flex; flex-wrap: wrap; gap: 10px 20px; width: 200px;
+                  justify-content: space-between;
+                }
+                .w { width: 80px; height: 20px; }
+                .end { align-self: flex-end; width: 80px; height: 20px; }
+                .t { width: 80px; height: 30px; }
+                .column {
+                  display: flex; flex-direction: column-reverse; width: 100px; height: 100px;
+                  align-items: flex-start;
+                }
+                .x { height: 20px; }
+                .push { margin-bottom: auto; width: 30px; height: 10px; }
+                .narrow { display: flex; width: 60px; }
+              </style>
+            </head>
+            <body>
+              <div class="row">
+                <div class="a"></div>text<div class="b"></div>
+                <div class="c"></div>
+              </div>
+              <div class="wrap">
+                <div class="w"></div><div class="w"></div><div class="end"></div><div class="t"></div>
+              </div>
+              <div class="column"><div class="x">ab</div><div class="push"></div></div>
+              <div class="narrow"><div>aaaa bbbb</div><div>cc</div></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view_with_viewport(html, &Viewport::new(300, 200));
+
+        // order の小さいアイテムから並べ、余った横幅は flex-grow のアイテムに分ける。
+        // テキストは匿名のアイテムになる。折り返したラインはそれぞれ justify-content で揃える。
+        // column-reverse では下から並べ、auto のマージンが余った高さを受け取る。
+        // 収まらないアイテムは、中身の最小の幅までは flex-shrink の比で縮める
+        assert_eq!(
+            [
+                "Block <body> (0, 0) 300x260",
+                "  Flex <div> (0, 0) 300x60",
+                "    Block <div> (50, 20) 50x20",
+                "    Block (anonymous) (110, 20) 32x20",
+                "      Text \"text\" (110, 22) 32x16",
+                "    Block <div> (152, 15) 148x30",
+                "    Block <div> (0, 0) 40x60",
+                "  Flex <div> (0, 60) 200x60",
+                "    Block <div> (0, 60) 80x20",
+                "    Block <div> (120, 60) 80x20",
+                "    Block <div> (0, 100) 80x20",
+                "    Block <div> (120, 90) 80x30",
+                "  Flex <div> (0, 120) 100x100",
+                "    Block <div> (0, 200) 16x20",
+                "      Text \"ab\" (0, 202) 16x16",
+                "    Block <div> (0, 120) 30x10",
+                "  Flex <div> (0, 220) 60x40",
+                "    Block <div> (0, 220) 44x40",
+                "      Text \"aaaa bbbb\" (0, 222) 32x36",
+                "    Block <div> (44, 220) 16x40",
+                "      Text \"cc\" (44, 222) 16x16",
+                "",
+            ]
+            .join("\n"),
+            layout_view.dump()
+        );
+    }
+
+    #[test]
+    fn test_grid() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, div { margin: 0; }
+                .page {
+                  display: grid; width: 300px; height: 200px; gap: 10px;
+                  grid-template-columns: 100px 1fr;
+                  grid-template-rows: 40px 1fr 30px;
+                  grid-template-areas: "head head" "nav main" "foot foot";
+                }
+                .head { grid-area: head; }
+                .nav { grid-area: nav; }
+                .main { grid-area: main; align-self: center; height: 50px; }
+                .foot { grid-area: foot; justify-self: end; width: 60px; }
+                .cards {
+                  display: grid; width: 330px; column-gap: 15px; grid-auto-rows: 20px;
+                  grid-template-columns: repeat(auto-fill, minmax(90px, 1fr));
+                }
+                .wide { grid-column: span 2; }
+                .last { grid-column: -2 / -1; }
+                .fit { display: grid; width: 300px; grid-template-columns: repeat(auto-fit, 100px); }
+              </style>
+            </head>
+            <body>
+              <div class="page">
+                <div class="head"></div><div class="nav"></div>
+                <div class="main"></div><div class="foot"></div>
+              </div>
+              <div class="cards">
+                <div class="wide"></div><div></div><div class="last"></div><div>d</div>
+              </div>
+              <div class="fit">ab</div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view_with_viewport(html, &Viewport::new(400, 300));
+
+        // 領域の名前で置いたアイテムは、fr の行と列を含めて領域の大きさに引き伸ばす。
+        // 自動で置くアイテムは、前に置いたアイテムより後ろの空いたセルに置く。
+        // repeat(auto-fill) は収まるだけ列を作り、repeat(auto-fit) はアイテムのない列をなくす
+        assert_eq!(
+            [
+                "Block <body> (0, 0) 400x280",
+                "  Grid <div> (0, 0) 300x200",
+                "    Block <div> (0, 0) 300x40",
+                "    Block <div> (0, 50) 100x110",
+                "    Block <div> (110, 80) 190x50",
+                "    Block <div> (240, 170) 60x30",
+                "  Grid <div> (0, 200) 330x60",
+                "    Block <div> (0, 200) 215x20",
+                "    Block <div> (230, 200) 100x20",
+                "    Block <div> (230, 220) 100x20",
+                "    Block <div> (0, 240) 100x20",
+                "      Text \"d\" (0, 242) 8x16",
+                "  Grid <div> (0, 260) 300x20",
+                "    Block (anonymous) (0, 260) 100x20",
+                "      Text \"ab\" (0, 262) 16x16",
+                "",
+            ]
+            .join("\n"),
+            layout_view.dump()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_display() {
+        // サポートしていない display の値は無視するので、要素は消えない
+        let html = r#"<html><head><style>p { display: block; display: ruby; }</style></head><body><p>text</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let body = layout_view.root().expect("root should exist");
+        let p = body.borrow().first_child().expect("p node should exist");
+        assert_eq!(DisplayType::Block, p.borrow().style().display());
+        assert_eq!(LayoutObjectKind::Block, p.borrow().kind());
+    }
+
+    #[test]
+    fn test_positioned() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, div, p { margin: 0; }
+                .box {
+                  position: relative; left: 10px; top: 5px; height: 100px;
+                  padding: 4px; border-width: 2px; border-style: solid;
+                }
+                .corner { position: absolute; right: 0; bottom: 0; width: 20px; height: 10px; }
+                .stretch { position: absolute; left: 0; right: 0; top: 10px; bottom: 10px; }
+                .tip { position: absolute; }
+                .sticky { position: sticky; top: 0; }
+                .fixed { position: fixed; top: 0; left: 0; width: 200px; height: 10px; }
+                .spacer { height: 300px; }
+              </style>
+            </head>
+            <body>
+              <div class="box">
+                <div class="corner"></div><div class="stretch"></div>
+                <p>text</p><span class="tip">tip</span>
+              </div>
+              <p class="sticky">sticky</p>
+              <div class="spacer"></div>
+              <div class="fixed"></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let mut layout_view = create_layout_view_with_viewport(html, &Viewport::new(200, 100));
+
+        // 相対位置指定のブロックは中身と一緒にずれ、絶対位置指定のボックスの包含ブロックになる。
+        // left と right が auto のボックスは、内容に合わせた幅で通常のフローの位置に置く
+        assert_eq!(
+            [
+                "Block <body> (0, 0) 200x432",
+                "  Block <div> (10, 5) 200x112",
+                "    Block <div> (188, 105) 20x10",
+                "    Block <div> (12, 17) 196x88",
+                "    Block <p> (16, 11) 188x20",
+                "      Text \"text\" (16, 13) 32x16",
+                "    Block <span> (16, 31) 24x20",
+                "      Text \"tip\" (16, 33) 24x16",
+                "  Block <p> (0, 112) 200x20",
+                "    Text \"sticky\" (0, 114) 48x16",
+                "  Block <div> (0, 132) 200x300",
+                "  Block <div> (0, 0) 200x10",
+                "",
+            ]
+            .join("\n"),
+            layout_view.dump()
+        );
+
+        // スクロールしても、固定位置指定のボックスはビューポートの同じ位置にあり、
+        // sticky のボックスはビューポートの上端に留まる
+        let rect_of = |layout_view: &LayoutView, class: &str| {
+            let mut child = layout_view
+                .root()
+                .and_then(|body| body.borrow().first_child());
+            while let Some(c) = child {
+                if let NodeKind::Element(e) = c.borrow().node_kind()
+                    && e.get_attribute("class").as_deref() == Some(class)
+                {
+                    return c.borrow().rect();
+                }
+                child = c.borrow().next_sibling();
+            }
+            panic!("{class} should exist");
+        };
+        layout_view.set_scroll_offset(LayoutPoint { x: 0, y: 200 });
+        assert_eq!(
+            LayoutRect::new(0, 200, 200, 10),
+            rect_of(&layout_view, "fixed")
+        );
+        assert_eq!(
+            LayoutRect::new(0, 200, 200, 20),
+            rect_of(&layout_view, "sticky")
+        );
+        // sticky のボックスは、包含ブロックの外には出ない
+        layout_view.set_scroll_offset(LayoutPoint { x: 0, y: 500 });
+        assert_eq!(
+            LayoutRect::new(0, 412, 200, 20),
+            rect_of(&layout_view, "sticky")
+        );
+        assert_eq!(
+            LayoutRect::new(0, 500, 200, 10),
+            rect_of(&layout_view, "fixed")
+        );
+    }
+
+    #[test]
+    fn test_stacking_order() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                body, div { margin: 0; }
+                div { height: 10px; }
+                .above { position: relative; z-index: 2; background-color: red; }
+                .below { position: relative; z-index: -1; background-color: blue; }
+                .block { background-color: green; }
+                .auto { position: absolute; width: 10px; background-color: yellow; }
+                .float { float: left; width: 10px; background-color: gray; }
+              </style>
+            </head>
+            <body>
+              <div class="above"></div>
+              <div class="below"></div>
+              <div class="block"></div>
+              <div class="auto"></div>
+              <div class="float"></div>
+            </body>
+            </html>
+        "#
+        .to_string();
+        let layout_view = create_layout_view(html);
+        let backgrounds = layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Rect { style, .. } => Some(style.background_color()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // z-index が負のボックス、通常のフローのブロック、フロート、z-index が auto と正の
+        // 位置指定したボックスの順に描画する
+        let color = |name: &str| Color::from_name(name).expect("color should exist");
+        assert_eq!(
+            vec![
+                color("blue"),
+                color("green"),
+                color("gray"),
+                color("yellow"),
+                color("red"),
+            ],
+            backgrounds
+        );
+    }
 }
@@ -0,0 +1,443 @@
+use core::ops::{Add, Mul};
+
+use alloc::format;
+
+use crate::{
+    error::Error,
+    renderer::css::{cssom::ComponentValue, media::Viewport},
+};
+
+// 初期値のフォントサイズ（medium）
+pub static DEFAULT_FONT_SIZE: f64 = 16.0;
+
+// https://www.w3.org/TR/css-values-4/#numeric-ranges
+// サポートする長さの範囲。レイアウトは px を整数にして足し合わせるので、あふれないように範囲を決める
+pub static MAX_LENGTH: f64 = 33_554_432.0;
+
+// 範囲外の長さは、範囲の近い方の端に丸める
+pub fn clamp_length(px: f64) -> f64 {
+    px.clamp(-MAX_LENGTH, MAX_LENGTH)
+}
+
+// 指定値の長さ。calc() で単位を混ぜることができるので、単位ごとの係数の和として保持する
+// https://www.w3.org/TR/css-values-4/#lengths
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Length {
+    pub px: f64,
+    pub em: f64,
+    pub rem: f64,
+    pub vw: f64,
+    pub vh: f64,
+    pub ch: f64,
+}
+
+impl Length {
+    fn from_unit(value: f64, unit: &str) -> Result<Self, Error> {
+        let mut length = Self::default();
+        match unit.to_ascii_lowercase().as_str() {
+            "px" => length.px = value,
+            "em" => length.em = value,
+            "rem" => length.rem = value,
+            "vw" => length.vw = value,
+            "vh" => length.vh = value,
+            "ch" => length.ch = value,
+            // 絶対単位は px に換算する
+            "in" => length.px = value * 96.0,
+            "cm" => length.px = value * 96.0 / 2.54,
+            "mm" => length.px = value * 96.0 / 25.4,
+            "pt" => length.px = value * 96.0 / 72.0,
+            "pc" => length.px = value * 16.0,
+            _ => {
+                return Err(Error::UnexpectedInput(format!(
+                    "length unit {unit:?} is not supported"
+                )));
+            }
+        }
+        Ok(length)
+    }
+
+    // 相対単位を解決して px にする
+    pub fn to_px(&self, context: &LengthContext) -> f64 {
+        clamp_length(
+            self.px
+                + self.em * context.font_size
+                + self.rem * context.root_font_size
+                + self.vw * context.viewport_width / 100.0
+                + self.vh * context.viewport_height / 100.0
+                // 本書のブラウザは等幅フォントで、文字の幅はフォントサイズの半分
+                + self.ch * context.font_size / 2.0,
+        )
+    }
+}
+
+impl Add for Length {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            px: self.px + rhs.px,
+            em: self.em + rhs.em,
+            rem: self.rem + rhs.rem,
+            vw: self.vw + rhs.vw,
+            vh: self.vh + rhs.vh,
+            ch: self.ch + rhs.ch,
+        }
+    }
+}
+
+impl Mul<f64> for Length {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self {
+            px: self.px * rhs,
+            em: self.em * rhs,
+            rem: self.rem * rhs,
+            vw: self.vw * rhs,
+            vh: self.vh * rhs,
+            ch: self.ch * rhs,
+        }
+    }
+}
+
+// 指定値の <length-percentage>
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LengthPercentage {
+    pub length: Length,
+    pub percent: f64,
+}
+
+impl LengthPercentage {
+    pub fn parse(value: &[ComponentValue]) -> Result<Self, Error> {
+        let invalid = || Error::UnexpectedInput(format!("invalid length {value:?}"));
+
+        match value {
+            [ComponentValue::Number(n)] if *n == 0.0 => Ok(Self::default()),
+            [ComponentValue::Dimension(n, unit)] => Ok(Length::from_unit(*n, unit)?.into()),
+            [ComponentValue::Percentage(p)] => Ok(Self {
+                length: Length::default(),
+                percent: *p,
+            }),
+            [ComponentValue::Function(function), ..] if function.eq_ignore_ascii_case("calc") => {
+                let mut parser = CalcParser {
+                    tokens: value,
+                    pos: 1,
+                };
+                let result = parser.parse_sum().ok_or_else(invalid)?;
+                if parser.tokens.get(parser.pos) != Some(&ComponentValue::CloseParenthesis)
+                    || parser.pos + 1 != value.len()
+                {
+                    return Err(invalid());
+                }
+                match result {
+                    CalcValue::LengthPercentage(lp) => Ok(lp),
+                    CalcValue::Number(0.0) => Ok(Self::default()),
+                    CalcValue::Number(_) => Err(invalid()),
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    // width や font-size などの負の値をとらないプロパティの値をパースする。
+    // calc() の結果が負になる場合は無効にせず、使用値を求めるときに 0 に丸める
+    pub fn parse_non_negative(value: &[ComponentValue]) -> Result<Self, Error> {
+        let length = Self::parse(value)?;
+        let is_calc = matches!(value.first(), Some(ComponentValue::Function(_)));
+        let l = &length.length;
+        let negative = [l.px, l.em, l.rem, l.vw, l.vh, l.ch, length.percent]
+            .iter()
+            .any(|v| *v < 0.0);
+        if negative && !is_calc {
+            return Err(Error::UnexpectedInput(format!(
+                "negative length {value:?} is not allowed"
+            )));
+        }
+        Ok(length)
+    }
+
+    // 計算値を求める。パーセンテージは使用値を求めるときまで残す
+    pub fn compute(&self, context: &LengthContext) -> ComputedLength {
+        ComputedLength {
+            px: self.length.to_px(context),
+            percent: self.percent,
+        }
+    }
+}
+
+impl From<Length> for LengthPercentage {
+    fn from(length: Length) -> Self {
+        Self {
+            length,
+            percent: 0.0,
+        }
+    }
+}
+
+impl Add for LengthPercentage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            length: self.length + rhs.length,
+            percent: self.percent + rhs.percent,
+        }
+    }
+}
+
+impl Mul<f64> for LengthPercentage {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self {
+            length: self.length * rhs,
+            percent: self.percent * rhs,
+        }
+    }
+}
+
+// 相対単位を解決するための情報
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthContext {
+    // em の基準。font-size 自身を計算するときは親のフォントサイズになる
+    pub font_size: f64,
+    pub root_font_size: f64,
+    pub viewport_width: f64,
+    pub viewport_height: f64,
+}
+
+impl LengthContext {
+    pub fn new(font_size: f64, root_font_size: f64, viewport: &Viewport) -> Self {
+        Self {
+            font_size,
+            root_font_size,
+            viewport_width: viewport.width as f64,
+            viewport_height: viewport.height as f64,
+        }
+    }
+}
+
+// 計算値の <length-percentage>。px とパーセンテージの和で、パーセンテージは包含ブロックの大きさに対する割合
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ComputedLength {
+    pub px: f64,
+    pub percent: f64,
+}
+
+impl ComputedLength {
+    pub fn has_percentage(&self) -> bool {
+        self.percent != 0.0
+    }
+
+    // 使用値を求める。basis はパーセンテージの基準になる長さ
+    pub fn resolve(&self, basis: f64) -> f64 {
+        clamp_length(self.px + basis * self.percent / 100.0)
+    }
+}
+
+// width や height などの、auto を取りうる値
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Size {
+    #[default]
+    Auto,
+    Length(ComputedLength),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalcValue {
+    Number(f64),
+    LengthPercentage(LengthPercentage),
+}
+
+// calc() の中身をパースして、単位ごとの係数にまとめる
+// https://www.w3.org/TR/css-values-4/#calc-syntax
+struct CalcParser<'a> {
+    tokens: &'a [ComponentValue],
+    pos: usize,
+}
+
+impl CalcParser<'_> {
+    fn peek(&self) -> Option<&ComponentValue> {
+        self.tokens.get(self.pos)
+    }
+
+    // <calc-sum> = <calc-product> [ [ '+' | '-' ] <calc-product> ]*
+    fn parse_sum(&mut self) -> Option<CalcValue> {
+        let mut result = self.parse_product()?;
+        loop {
+            let sign = match self.peek() {
+                Some(ComponentValue::Delim('+')) => 1.0,
+                Some(ComponentValue::Delim('-')) => -1.0,
+                _ => return Some(result),
+            };
+            self.pos += 1;
+            let rhs = self.parse_product()?;
+            result = match (result, rhs) {
+                (CalcValue::Number(a), CalcValue::Number(b)) => CalcValue::Number(a + sign * b),
+                (CalcValue::LengthPercentage(a), CalcValue::LengthPercentage(b)) => {
+                    CalcValue::LengthPercentage(a + b * sign)
+                }
+                // 数値と長さは足し合わせられない
+                _ => return None,
+            };
+        }
+    }
+
+    // <calc-product> = <calc-value> [ [ '*' | '/' ] <calc-value> ]*
+    fn parse_product(&mut self) -> Option<CalcValue> {
+        let mut result = self.parse_value()?;
+        loop {
+            let divide = match self.peek() {
+                Some(ComponentValue::Delim('*')) => false,
+                Some(ComponentValue::Delim('/')) => true,
+                _ => return Some(result),
+            };
+            self.pos += 1;
+            let rhs = self.parse_value()?;
+            result = match (result, rhs, divide) {
+                (_, CalcValue::Number(0.0), true) => return None,
+                (CalcValue::Number(a), CalcValue::Number(b), false) => CalcValue::Number(a * b),
+                (CalcValue::Number(a), CalcValue::Number(b), true) => CalcValue::Number(a / b),
+                (CalcValue::LengthPercentage(a), CalcValue::Number(b), false)
+                | (CalcValue::Number(b), CalcValue::LengthPercentage(a), false) => {
+                    CalcValue::LengthPercentage(a * b)
+                }
+                (CalcValue::LengthPercentage(a), CalcValue::Number(b), true) => {
+                    CalcValue::LengthPercentage(a * (1.0 / b))
+                }
+                // 長さどうしの掛け算や、長さで割ることはできない
+                _ => return None,
+            };
+        }
+    }
+
+    // <calc-value> = <number> | <dimension> | <percentage> | ( <calc-sum> )
+    fn parse_value(&mut self) -> Option<CalcValue> {
+        let token = self.peek()?.clone();
+        self.pos += 1;
+
+        match token {
+            ComponentValue::Number(n) => Some(CalcValue::Number(n)),
+            ComponentValue::Dimension(n, unit) => Some(CalcValue::LengthPercentage(
+                Length::from_unit(n, &unit).ok()?.into(),
+            )),
+            ComponentValue::Percentage(p) => Some(CalcValue::LengthPercentage(LengthPercentage {
+                length: Length::default(),
+                percent: p,
+            })),
+            ComponentValue::OpenParenthesis => self.parse_nested(),
+            ComponentValue::Function(function) if function.eq_ignore_ascii_case("calc") => {
+                self.parse_nested()
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_nested(&mut self) -> Option<CalcValue> {
+        let value = self.parse_sum()?;
+        if self.peek() != Some(&ComponentValue::CloseParenthesis) {
+            return None;
+        }
+        self.pos += 1;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::renderer::css::{cssom::CssParser, token::CssTokenizer};
+
+    use super::*;
+
+    fn parse(value: &str) -> Result<LengthPercentage, Error> {
+        let declarations =
+            CssParser::new(CssTokenizer::new(format!("width: {value}"))).parse_declaration_list();
+        let value: Vec<ComponentValue> = declarations
+            .first()
+            .map(|d| d.value.clone())
+            .unwrap_or_default();
+        LengthPercentage::parse(&value)
+    }
+
+    fn context() -> LengthContext {
+        LengthContext::new(20.0, 16.0, &Viewport::new(800, 600))
+    }
+
+    #[test]
+    fn test_units() {
+        let context = context();
+        let px = |value: &str| parse(value).unwrap().compute(&context).px;
+
+        assert_eq!(10.0, px("10px"));
+        assert_eq!(30.0, px("1.5em"));
+        assert_eq!(32.0, px("2rem"));
+        assert_eq!(80.0, px("10vw"));
+        assert_eq!(300.0, px("50vh"));
+        assert_eq!(20.0, px("2ch"));
+        assert_eq!(96.0, px("1in"));
+        assert_eq!(0.0, px("0"));
+        assert_eq!(-4.0, px("-4px"));
+
+        let percent = parse("50%").unwrap().compute(&context);
+        assert!(percent.has_percentage());
+        assert_eq!(100.0, percent.resolve(200.0));
+
+        assert!(
+            LengthPercentage::parse_non_negative(&[ComponentValue::Dimension(-1.0, "px".into())])
+                .is_err()
+        );
+        // 範囲外の長さは丸める
+        assert_eq!(MAX_LENGTH, px("99999999999999999999px"));
+        assert_eq!(-MAX_LENGTH, px("-99999999999999999999em"));
+        assert_eq!(
+            MAX_LENGTH,
+            parse("99999999999999999999%")
+                .unwrap()
+                .compute(&context)
+                .resolve(100.0)
+        );
+
+        assert!(parse("10").is_err());
+        assert!(parse("10foo").is_err());
+        assert!(parse("red").is_err());
+    }
+
+    #[test]
+    fn test_calc() {
+        let context = context();
+
+        let value = parse("calc(100% - 2em + 10px)").unwrap();
+        assert_eq!(
+            LengthPercentage {
+                length: Length {
+                    px: 10.0,
+                    em: -2.0,
+                    ..Length::default()
+                },
+                percent: 100.0,
+            },
+            value
+        );
+        assert_eq!(170.0, value.compute(&context).resolve(200.0));
+
+        let value = parse("calc((10px + 1rem) * 2 / 4)").unwrap();
+        assert_eq!(13.0, value.compute(&context).px);
+
+        let value = parse("calc(2 * calc(50% - 5px))").unwrap();
+        assert_eq!(
+            ComputedLength {
+                px: -10.0,
+                percent: 100.0
+            },
+            value.compute(&context)
+        );
+
+        assert!(parse("calc(10px * 2px)").is_err());
+        assert!(parse("calc(10px + 2)").is_err());
+        assert!(parse("calc(10px / 0)").is_err());
+        assert!(parse("calc(10px").is_err());
+        assert!(parse("calc(10px) 1px").is_err());
+    }
+}
@@ -0,0 +1,451 @@
+use alloc::{vec, vec::Vec};
+
+use crate::renderer::layout::text::WordBreak;
+
+// https://www.unicode.org/reports/tr14/#Properties
+// 行分割のための文字の分類。本書のブラウザで使うものだけを扱い、
+// AI、SA、XX などの分類は LB1 に従って AL として扱う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineBreakClass {
+    // 必ず改行する文字
+    BK,
+    CR,
+    LF,
+    NL,
+    SP,
+    // ゼロ幅空白
+    ZW,
+    // 改行しない文字
+    WJ,
+    GL,
+    // 結合文字とゼロ幅接合子
+    CM,
+    ZWJ,
+    // 後ろで改行できる文字
+    BA,
+    HY,
+    B2,
+    // 前で改行できる文字
+    BB,
+    // 閉じ括弧や句読点など、行頭に置かない文字
+    EX,
+    QU,
+    CL,
+    CP,
+    IS,
+    NS,
+    SY,
+    // 開き括弧など、行末に置かない文字
+    OP,
+    // 数字とその前後に付く記号
+    PR,
+    PO,
+    NU,
+    // 英字などの文字と、漢字や仮名などの表意文字
+    AL,
+    ID,
+    IN,
+    // 行の中に置く画像などの置き換え要素
+    CB,
+    // 小書きの仮名。LB1 に従って NS として扱う
+    CJ,
+}
+
+use LineBreakClass::*;
+
+// 先頭の文字の順に並べた、分類の範囲の表。表にない文字は AL として扱う
+static LINE_BREAK_TABLE: &[(u32, u32, LineBreakClass)] = &[
+    (0x0009, 0x0009, BA),
+    (0x000A, 0x000A, LF),
+    (0x000B, 0x000C, BK),
+    (0x000D, 0x000D, CR),
+    (0x0020, 0x0020, SP),
+    (0x0021, 0x0021, EX),
+    (0x0022, 0x0022, QU),
+    (0x0024, 0x0024, PR),
+    (0x0025, 0x0025, PO),
+    (0x0027, 0x0027, QU),
+    (0x0028, 0x0028, OP),
+    (0x0029, 0x0029, CP),
+    (0x002B, 0x002B, PR),
+    (0x002C, 0x002C, IS),
+    (0x002D, 0x002D, HY),
+    (0x002E, 0x002E, IS),
+    (0x002F, 0x002F, SY),
+    (0x0030, 0x0039, NU),
+    (0x003A, 0x003B, IS),
+    (0x003F, 0x003F, EX),
+    (0x005B, 0x005B, OP),
+    (0x005C, 0x005C, PR),
+    (0x005D, 0x005D, CP),
+    (0x007B, 0x007B, OP),
+    (0x007C, 0x007C, BA),
+    (0x007D, 0x007D, CL),
+    (0x0085, 0x0085, NL),
+    (0x00A0, 0x00A0, GL),
+    (0x00A1, 0x00A1, OP),
+    (0x00A2, 0x00A2, PO),
+    (0x00A3, 0x00A5, PR),
+    (0x00AB, 0x00AB, QU),
+    (0x00AD, 0x00AD, BA),
+    (0x00B0, 0x00B0, PO),
+    (0x00B1, 0x00B1, PR),
+    (0x00B4, 0x00B4, BB),
+    (0x00BB, 0x00BB, QU),
+    (0x00BF, 0x00BF, OP),
+    (0x02C8, 0x02C8, BB),
+    (0x02CC, 0x02CC, BB),
+    (0x02DF, 0x02DF, BB),
+    (0x0300, 0x036F, CM),
+    (0x037E, 0x037E, IS),
+    (0x0483, 0x0489, CM),
+    (0x0591, 0x05BD, CM),
+    (0x0660, 0x0669, NU),
+    (0x06F0, 0x06F9, NU),
+    (0x0966, 0x096F, NU),
+    (0x0E50, 0x0E59, NU),
+    (0x1680, 0x1680, BA),
+    (0x1AB0, 0x1AFF, CM),
+    (0x1DC0, 0x1DFF, CM),
+    (0x2000, 0x2006, BA),
+    (0x2007, 0x2007, GL),
+    (0x2008, 0x200A, BA),
+    (0x200B, 0x200B, ZW),
+    (0x200C, 0x200C, CM),
+    (0x200D, 0x200D, ZWJ),
+    (0x2010, 0x2010, BA),
+    (0x2011, 0x2011, GL),
+    (0x2012, 0x2013, BA),
+    (0x2014, 0x2014, B2),
+    (0x2018, 0x2019, QU),
+    (0x201A, 0x201A, OP),
+    (0x201B, 0x201F, QU),
+    (0x2024, 0x2026, IN),
+    (0x2027, 0x2027, BA),
+    (0x2028, 0x2029, BK),
+    (0x202F, 0x202F, GL),
+    (0x2030, 0x2037, PO),
+    (0x2039, 0x203A, QU),
+    (0x203C, 0x203D, NS),
+    (0x2044, 0x2044, IS),
+    (0x2047, 0x2049, NS),
+    (0x2060, 0x2060, WJ),
+    (0x20A0, 0x20BF, PR),
+    (0x20D0, 0x20FF, CM),
+    (0x2103, 0x2103, PO),
+    (0x2109, 0x2109, PO),
+    (0x2116, 0x2116, PR),
+    (0x2E80, 0x2FFF, ID),
+    (0x3000, 0x3000, BA),
+    (0x3001, 0x3002, CL),
+    (0x3003, 0x3004, ID),
+    (0x3005, 0x3005, NS),
+    (0x3006, 0x3007, ID),
+    (0x3008, 0x3008, OP),
+    (0x3009, 0x3009, CL),
+    (0x300A, 0x300A, OP),
+    (0x300B, 0x300B, CL),
+    (0x300C, 0x300C, OP),
+    (0x300D, 0x300D, CL),
+    (0x300E, 0x300E, OP),
+    (0x300F, 0x300F, CL),
+    (0x3010, 0x3010, OP),
+    (0x3011, 0x3011, CL),
+    (0x3012, 0x3013, ID),
+    (0x3014, 0x3014, OP),
+    (0x3015, 0x3015, CL),
+    (0x3016, 0x3016, OP),
+    (0x3017, 0x3017, CL),
+    (0x3018, 0x3018, OP),
+    (0x3019, 0x3019, CL),
+    (0x301A, 0x301A, OP),
+    (0x301B, 0x301B, CL),
+    (0x301C, 0x301C, NS),
+    (0x301D, 0x301D, OP),
+    (0x301E, 0x301F, CL),
+    (0x3020, 0x3029, ID),
+    (0x302A, 0x302F, CM),
+    (0x3030, 0x303A, ID),
+    (0x303B, 0x303C, NS),
+    (0x303D, 0x303F, ID),
+    (0x3041, 0x3041, CJ),
+    (0x3042, 0x3042, ID),
+    (0x3043, 0x3043, CJ),
+    (0x3044, 0x3044, ID),
+    (0x3045, 0x3045, CJ),
+    (0x3046, 0x3046, ID),
+    (0x3047, 0x3047, CJ),
+    (0x3048, 0x3048, ID),
+    (0x3049, 0x3049, CJ),
+    (0x304A, 0x3062, ID),
+    (0x3063, 0x3063, CJ),
+    (0x3064, 0x3082, ID),
+    (0x3083, 0x3083, CJ),
+    (0x3084, 0x3084, ID),
+    (0x3085, 0x3085, CJ),
+    (0x3086, 0x3086, ID),
+    (0x3087, 0x3087, CJ),
+    (0x3088, 0x308D, ID),
+    (0x308E, 0x308E, CJ),
+    (0x308F, 0x3094, ID),
+    (0x3095, 0x3096, CJ),
+    (0x3099, 0x309A, CM),
+    (0x309B, 0x309E, NS),
+    (0x309F, 0x309F, ID),
+    (0x30A0, 0x30A0, NS),
+    (0x30A1, 0x30A1, CJ),
+    (0x30A2, 0x30A2, ID),
+    (0x30A3, 0x30A3, CJ),
+    (0x30A4, 0x30A4, ID),
+    (0x30A5, 0x30A5, CJ),
+    (0x30A6, 0x30A6, ID),
+    (0x30A7, 0x30A7, CJ),
+    (0x30A8, 0x30A8, ID),
+    (0x30A9, 0x30A9, CJ),
+    (0x30AA, 0x30C2, ID),
+    (0x30C3, 0x30C3, CJ),
+    (0x30C4, 0x30E2, ID),
+    (0x30E3, 0x30E3, CJ),
+    (0x30E4, 0x30E4, ID),
+    (0x30E5, 0x30E5, CJ),
+    (0x30E6, 0x30E6, ID),
+    (0x30E7, 0x30E7, CJ),
+    (0x30E8, 0x30ED, ID),
+    (0x30EE, 0x30EE, CJ),
+    (0x30EF, 0x30F4, ID),
+    (0x30F5, 0x30F6, CJ),
+    (0x30F7, 0x30FA, ID),
+    (0x30FB, 0x30FB, NS),
+    (0x30FC, 0x30FC, CJ),
+    (0x30FD, 0x30FE, NS),
+    (0x30FF, 0x31EF, ID),
+    (0x31F0, 0x31FF, CJ),
+    (0x3200, 0x4DBF, ID),
+    (0x4E00, 0x9FFF, ID),
+    (0xA000, 0xA4CF, ID),
+    (0xAC00, 0xD7A3, ID),
+    (0xF900, 0xFAFF, ID),
+    (0xFE00, 0xFE0F, CM),
+    (0xFE10, 0xFE10, IS),
+    (0xFE11, 0xFE12, CL),
+    (0xFE13, 0xFE14, IS),
+    (0xFE15, 0xFE16, EX),
+    (0xFE17, 0xFE17, OP),
+    (0xFE18, 0xFE18, CL),
+    (0xFE20, 0xFE2F, CM),
+    (0xFE30, 0xFE4F, ID),
+    (0xFEFF, 0xFEFF, WJ),
+    (0xFF01, 0xFF01, EX),
+    (0xFF02, 0xFF03, ID),
+    (0xFF04, 0xFF04, PR),
+    (0xFF05, 0xFF05, PO),
+    (0xFF06, 0xFF07, ID),
+    (0xFF08, 0xFF08, OP),
+    (0xFF09, 0xFF09, CP),
+    (0xFF0A, 0xFF0B, ID),
+    (0xFF0C, 0xFF0C, CL),
+    (0xFF0D, 0xFF0D, ID),
+    (0xFF0E, 0xFF0E, CL),
+    (0xFF0F, 0xFF19, ID),
+    (0xFF1A, 0xFF1B, NS),
+    (0xFF1C, 0xFF1E, ID),
+    (0xFF1F, 0xFF1F, EX),
+    (0xFF20, 0xFF3A, ID),
+    (0xFF3B, 0xFF3B, OP),
+    (0xFF3C, 0xFF3C, ID),
+    (0xFF3D, 0xFF3D, CP),
+    (0xFF3E, 0xFF5A, ID),
+    (0xFF5B, 0xFF5B, OP),
+    (0xFF5C, 0xFF5C, ID),
+    (0xFF5D, 0xFF5D, CL),
+    (0xFF5E, 0xFF5E, ID),
+    (0xFF5F, 0xFF5F, OP),
+    (0xFF60, 0xFF61, CL),
+    (0xFF62, 0xFF62, OP),
+    (0xFF63, 0xFF64, CL),
+    (0xFF65, 0xFF65, NS),
+    (0xFF67, 0xFF70, CJ),
+    (0xFF9E, 0xFF9F, NS),
+    (0xFFE0, 0xFFE0, PO),
+    (0xFFE1, 0xFFE1, PR),
+    (0xFFE2, 0xFFE4, ID),
+    (0xFFE5, 0xFFE6, PR),
+    (0xFFFC, 0xFFFC, CB),
+    (0x1F000, 0x1FAFF, ID),
+    (0x20000, 0x3FFFD, ID),
+];
+
+pub fn line_break_class(c: char) -> LineBreakClass {
+    let c = c as u32;
+    match LINE_BREAK_TABLE.binary_search_by(|&(start, end, _)| {
+        if end < c {
+            core::cmp::Ordering::Less
+        } else if start > c {
+            core::cmp::Ordering::Greater
+        } else {
+            core::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(index) => LINE_BREAK_TABLE[index].2,
+        Err(_) => AL,
+    }
+}
+
+// https://www.w3.org/TR/css-text-3/#word-break-property
+// LB1 で分類を決め、word-break に応じて分類を置き換える。
+// break-all は英字や数字を表意文字と同じように扱い、keep-all は表意文字を英字と同じように扱う
+fn resolve_class(c: char, word_break: WordBreak) -> LineBreakClass {
+    let class = match line_break_class(c) {
+        CJ => NS,
+        class => class,
+    };
+    match (word_break, class) {
+        (WordBreak::BreakAll, AL | NU) => ID,
+        (WordBreak::KeepAll, ID) => AL,
+        (_, class) => class,
+    }
+}
+
+// https://www.unicode.org/reports/tr14/#Algorithm
+// 隣り合う2文字の間で改行できるかどうか。空白をはさむ規則は、空白の後ろで改行できるものとして簡略化する
+fn breaks_between(before: LineBreakClass, after: LineBreakClass) -> bool {
+    match (before, after) {
+        // LB4、LB5
+        (BK | CR | LF | NL, _) => true,
+        // LB6、LB7
+        (_, BK | CR | LF | NL | SP | ZW) => false,
+        // LB8
+        (ZW, _) => true,
+        // LB8a
+        (ZWJ, _) => false,
+        // LB11
+        (_, WJ) | (WJ, _) => false,
+        // LB12、LB12a
+        (GL, _) => false,
+        (BA | HY, GL) => true,
+        (_, GL) => false,
+        // LB13
+        (_, CL | CP | EX | IS | SY) => false,
+        // LB14、LB15、LB16、LB17
+        (OP, _) => false,
+        (QU, OP) => false,
+        (CL | CP, NS) => false,
+        (B2, B2) => false,
+        // LB18
+        (SP, _) => true,
+        // LB19
+        (_, QU) | (QU, _) => false,
+        // LB20
+        (_, CB) | (CB, _) => true,
+        // LB21、LB22
+        (_, BA | HY | NS | IN) | (BB, _) => false,
+        // LB23、LB23a、LB24
+        (AL, NU) | (NU, AL) => false,
+        (PR, ID) | (ID, PO) => false,
+        (PR | PO, AL) | (AL, PR | PO) => false,
+        // LB25
+        (CL | CP | NU, PR | PO) | (PR | PO, OP | NU) | (HY | IS | NU | SY, NU) => false,
+        // LB28、LB29、LB30
+        (AL, AL) | (IS, AL) => false,
+        (AL | NU, OP) | (CP, AL | NU) => false,
+        // LB31
+        _ => true,
+    }
+}
+
+// https://www.unicode.org/reports/tr14/
+// 文字ごとに、その文字の前で改行できるかどうかを返す。先頭の文字の前では改行しない。
+// word_break は、その文字を含む要素の word-break
+pub fn break_opportunities(text: &[(char, WordBreak)]) -> Vec<bool> {
+    let mut opportunities = vec![false; text.len()];
+    // 結合文字をまとめたあとの、直前の文字の分類
+    let mut prev: Option<LineBreakClass> = None;
+    let mut after_zwj = false;
+
+    for (i, (c, word_break)) in text.iter().enumerate() {
+        let class = resolve_class(*c, *word_break);
+        let Some(before) = prev else {
+            // LB10
+            prev = Some(if matches!(class, CM | ZWJ) { AL } else { class });
+            continue;
+        };
+
+        // LB9: 結合文字は直前の文字と合わせて1文字として扱う
+        if matches!(class, CM | ZWJ) && !matches!(before, BK | CR | LF | NL | SP | ZW) {
+            after_zwj = class == ZWJ;
+            continue;
+        }
+
+        let class = if matches!(class, CM | ZWJ) { AL } else { class };
+        opportunities[i] = !after_zwj && breaks_between(before, class);
+        after_zwj = false;
+        prev = Some(class);
+    }
+
+    opportunities
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+
+    // 改行できる位置に | をはさんだ文字列
+    fn breaks(text: &str, word_break: WordBreak) -> String {
+        let chars = text.chars().map(|c| (c, word_break)).collect::<Vec<_>>();
+        let mut result = String::new();
+        for ((c, _), opportunity) in chars.iter().zip(break_opportunities(&chars)) {
+            if opportunity {
+                result.push('|');
+            }
+            result.push(*c);
+        }
+        result
+    }
+
+    #[test]
+    fn test_line_break_class() {
+        assert_eq!(AL, line_break_class('a'));
+        assert_eq!(NU, line_break_class('7'));
+        assert_eq!(ID, line_break_class('漢'));
+        assert_eq!(ID, line_break_class('あ'));
+        assert_eq!(CJ, line_break_class('ょ'));
+        assert_eq!(CL, line_break_class('。'));
+        assert_eq!(OP, line_break_class('「'));
+        assert_eq!(AL, line_break_class('ｱ'));
+        assert_eq!(ID, line_break_class('😀'));
+    }
+
+    #[test]
+    fn test_break_opportunities() {
+        // 英単語の中では改行しない。空白とハイフンの後ろでは改行できるが、負の数の符号の後ろでは改行しない
+        assert_eq!("hello |world", breaks("hello world", WordBreak::Normal));
+        assert_eq!(
+            "well-|known |-1",
+            breaks("well-known -1", WordBreak::Normal)
+        );
+        // URL はスラッシュの後ろで改行できる
+        assert_eq!(
+            "http://|example.com/|path",
+            breaks("http://example.com/path", WordBreak::Normal)
+        );
+
+        // 日本語は文字の間で改行できるが、閉じ括弧や句点、小書きの仮名の前と、開き括弧の後ろでは改行しない
+        assert_eq!(
+            "「日|本|語」|の|ちょっ|と|し|た|文|章。",
+            breaks("「日本語」のちょっとした文章。", WordBreak::Normal)
+        );
+        // 結合文字の前では改行しない
+        assert_eq!("か\u{3099}|な", breaks("か\u{3099}な", WordBreak::Normal));
+    }
+
+    #[test]
+    fn test_word_break() {
+        assert_eq!("w|o|r|d|s。", breaks("words。", WordBreak::BreakAll));
+        assert_eq!(
+            "日本語の |文章",
+            breaks("日本語の 文章", WordBreak::KeepAll)
+        );
+    }
+}
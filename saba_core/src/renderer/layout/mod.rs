@@ -1,3 +1,14 @@
+pub mod bidi;
 pub mod computed_style;
+pub mod east_asian_width;
+pub mod flex;
+pub mod float;
+pub mod font;
+pub mod grid;
+pub mod inline;
 pub mod layout_object;
 pub mod layout_view;
+pub mod length;
+pub mod line_break;
+pub mod position;
+pub mod text;
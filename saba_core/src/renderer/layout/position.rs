@@ -0,0 +1,193 @@
+use core::str::FromStr;
+
+use alloc::format;
+
+use crate::{
+    error::Error,
+    renderer::{
+        css::cssom::ComponentValue,
+        layout::{
+            computed_style::Sides,
+            layout_object::{LayoutPoint, LayoutRect},
+        },
+    },
+};
+
+// https://www.w3.org/TR/css-position-3/#position-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl Position {
+    pub fn is_positioned(&self) -> bool {
+        *self != Self::Static
+    }
+
+    // 通常のフローから外れて、包含ブロックの中の指定した位置に置くかどうか
+    pub fn is_absolutely_positioned(&self) -> bool {
+        matches!(self, Self::Absolute | Self::Fixed)
+    }
+}
+
+impl FromStr for Position {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "static" => Ok(Self::Static),
+            "relative" => Ok(Self::Relative),
+            "absolute" => Ok(Self::Absolute),
+            "fixed" => Ok(Self::Fixed),
+            "sticky" => Ok(Self::Sticky),
+            _ => Err(Error::UnexpectedInput(format!(
+                "position {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/CSS2/visuren.html#z-index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZIndex {
+    Auto,
+    Integer(i64),
+}
+
+impl ZIndex {
+    pub fn parse(value: &[ComponentValue]) -> Option<Self> {
+        match value {
+            [ComponentValue::Ident(keyword)] if keyword.eq_ignore_ascii_case("auto") => {
+                Some(Self::Auto)
+            }
+            [ComponentValue::Number(n)] if *n == (*n as i64) as f64 => {
+                Some(Self::Integer(*n as i64))
+            }
+            _ => None,
+        }
+    }
+}
+
+// 位置指定したボックスを置く基準になる位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionContext {
+    // 絶対位置指定のボックスの包含ブロック。最も近い位置指定された祖先のパディングボックスの左上
+    pub absolute: LayoutPoint,
+    // スクロールした位置にあるビューポート。固定位置指定のボックスの包含ブロックになる
+    pub viewport: LayoutRect,
+}
+
+// https://www.w3.org/TR/css-position-3/#stickypos-insets
+// 通常のフローでの位置が rect のボックスを、viewport の端から insets の距離より内側に
+// 収まるようにずらす量。containing は包含ブロックで、ずらしても包含ブロックからははみ出さない
+pub fn sticky_offset(
+    rect: LayoutRect,
+    insets: Sides<Option<i64>>,
+    viewport: LayoutRect,
+    containing: LayoutRect,
+) -> LayoutPoint {
+    LayoutPoint {
+        x: sticky_shift(
+            (rect.point.x, rect.size.width),
+            (viewport.point.x, viewport.size.width),
+            (containing.point.x, containing.size.width),
+            (insets.left, insets.right),
+        ),
+        y: sticky_shift(
+            (rect.point.y, rect.size.height),
+            (viewport.point.y, viewport.size.height),
+            (containing.point.y, containing.size.height),
+            (insets.top, insets.bottom),
+        ),
+    }
+}
+
+// 1つの軸で、始まりの位置と長さが span の範囲をずらす量。
+// start と end の両方の側に収まらないときは、start の側を優先する
+fn sticky_shift(
+    (start, size): (i64, i64),
+    (view_start, view_size): (i64, i64),
+    (containing_start, containing_size): (i64, i64),
+    (inset_start, inset_end): (Option<i64>, Option<i64>),
+) -> i64 {
+    let mut shift = 0;
+    if let Some(inset) = inset_end {
+        shift = shift.min(view_start + view_size - inset - (start + size));
+    }
+    if let Some(inset) = inset_start {
+        shift = shift.max(view_start + inset - start);
+    }
+    if shift > 0 {
+        shift.min((containing_start + containing_size - (start + size)).max(0))
+    } else {
+        shift.max((containing_start - start).min(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sticky_offset() {
+        let rect = LayoutRect::new(0, 100, 50, 20);
+        let containing = LayoutRect::new(0, 80, 200, 100);
+        let insets = Sides {
+            top: Some(10),
+            right: None,
+            bottom: None,
+            left: None,
+        };
+
+        // スクロールする前は、通常のフローの位置のまま
+        let viewport = LayoutRect::new(0, 0, 200, 300);
+        assert_eq!(
+            LayoutPoint { x: 0, y: 0 },
+            sticky_offset(rect, insets, viewport, containing)
+        );
+        // ビューポートの上端から 10px の位置に留まる
+        let viewport = LayoutRect::new(0, 120, 200, 300);
+        assert_eq!(
+            LayoutPoint { x: 0, y: 30 },
+            sticky_offset(rect, insets, viewport, containing)
+        );
+        // 包含ブロックの下端より下には動かない
+        let viewport = LayoutRect::new(0, 200, 200, 300);
+        assert_eq!(
+            LayoutPoint { x: 0, y: 60 },
+            sticky_offset(rect, insets, viewport, containing)
+        );
+
+        // 下端に留まるボックスは、上にずらす
+        let insets = Sides {
+            top: None,
+            right: None,
+            bottom: Some(0),
+            left: None,
+        };
+        let viewport = LayoutRect::new(0, 0, 200, 110);
+        assert_eq!(
+            LayoutPoint { x: 0, y: -10 },
+            sticky_offset(rect, insets, viewport, containing)
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(Position::Sticky), Position::from_str("sticky"));
+        assert!(Position::from_str("center").is_err());
+        assert_eq!(
+            Some(ZIndex::Integer(-2)),
+            ZIndex::parse(&[ComponentValue::Number(-2.0)])
+        );
+        assert_eq!(
+            Some(ZIndex::Auto),
+            ZIndex::parse(&[ComponentValue::Ident("auto".into())])
+        );
+        assert_eq!(None, ZIndex::parse(&[ComponentValue::Number(1.5)]));
+    }
+}
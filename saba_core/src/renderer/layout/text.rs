@@ -0,0 +1,478 @@
+use core::str::FromStr;
+
+use alloc::{format, rc::Rc, string::String};
+
+use crate::{
+    error::Error,
+    renderer::{
+        css::cssom::ComponentValue,
+        layout::{
+            computed_style::Color,
+            font::{Font, FontMetrics},
+        },
+    },
+};
+
+// https://www.w3.org/TR/css-text-3/#white-space-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhiteSpace {
+    Normal,
+    Pre,
+    Nowrap,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    // 連続する空白と、行頭と行末の空白をまとめるかどうか
+    pub fn collapses_spaces(&self) -> bool {
+        matches!(self, Self::Normal | Self::Nowrap | Self::PreLine)
+    }
+
+    pub fn preserves_newlines(&self) -> bool {
+        matches!(self, Self::Pre | Self::PreWrap | Self::PreLine)
+    }
+
+    // 行の幅に収まらないときに折り返すかどうか
+    pub fn wraps(&self) -> bool {
+        matches!(self, Self::Normal | Self::PreWrap | Self::PreLine)
+    }
+}
+
+impl FromStr for WhiteSpace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            "pre" => Ok(Self::Pre),
+            "nowrap" => Ok(Self::Nowrap),
+            "pre-wrap" => Ok(Self::PreWrap),
+            "pre-line" => Ok(Self::PreLine),
+            _ => Err(Error::UnexpectedInput(format!(
+                "white-space {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-text-3/#word-break-property
+// break-word は、overflow-wrap: anywhere と同じように単語の途中でも改行できるようにする古い値
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordBreak {
+    Normal,
+    BreakAll,
+    KeepAll,
+    BreakWord,
+}
+
+impl FromStr for WordBreak {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            "break-all" => Ok(Self::BreakAll),
+            "keep-all" => Ok(Self::KeepAll),
+            "break-word" => Ok(Self::BreakWord),
+            _ => Err(Error::UnexpectedInput(format!(
+                "word-break {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-text-3/#overflow-wrap-property
+// 本書のブラウザは最小内容幅を計算しないので、anywhere と break-word は同じように扱う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowWrap {
+    Normal,
+    Anywhere,
+    BreakWord,
+}
+
+impl OverflowWrap {
+    // 行の幅に収まらない単語を、途中で改行するかどうか
+    pub fn breaks_words(&self) -> bool {
+        !matches!(self, Self::Normal)
+    }
+}
+
+impl FromStr for OverflowWrap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            "anywhere" => Ok(Self::Anywhere),
+            "break-word" => Ok(Self::BreakWord),
+            _ => Err(Error::UnexpectedInput(format!(
+                "overflow-wrap {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-text-3/#text-align-property
+// start と end は、行の方向に応じて left か right になる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Start,
+    End,
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+impl FromStr for TextAlign {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "start" => Ok(Self::Start),
+            "end" => Ok(Self::End),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "center" => Ok(Self::Center),
+            "justify" => Ok(Self::Justify),
+            _ => Err(Error::UnexpectedInput(format!(
+                "text-align {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-writing-modes-4/#direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+impl Direction {
+    // 段落やインライン要素の内容の、埋め込みレベル
+    pub fn level(&self) -> u8 {
+        match self {
+            Self::Ltr => 0,
+            Self::Rtl => 1,
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ltr" => Ok(Self::Ltr),
+            "rtl" => Ok(Self::Rtl),
+            _ => Err(Error::UnexpectedInput(format!(
+                "direction {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-writing-modes-4/#unicode-bidi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeBidi {
+    Normal,
+    Embed,
+    Isolate,
+    BidiOverride,
+    IsolateOverride,
+    Plaintext,
+}
+
+impl FromStr for UnicodeBidi {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            "embed" => Ok(Self::Embed),
+            "isolate" => Ok(Self::Isolate),
+            "bidi-override" => Ok(Self::BidiOverride),
+            "isolate-override" => Ok(Self::IsolateOverride),
+            "plaintext" => Ok(Self::Plaintext),
+            _ => Err(Error::UnexpectedInput(format!(
+                "unicode-bidi {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-text-3/#text-transform-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextTransform {
+    None,
+    Uppercase,
+    Lowercase,
+    Capitalize,
+}
+
+impl FromStr for TextTransform {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "uppercase" => Ok(Self::Uppercase),
+            "lowercase" => Ok(Self::Lowercase),
+            "capitalize" => Ok(Self::Capitalize),
+            _ => Err(Error::UnexpectedInput(format!(
+                "text-transform {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-text-decor-3/#text-decoration-line-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextDecorationLine {
+    pub underline: bool,
+    pub overline: bool,
+    pub line_through: bool,
+}
+
+impl TextDecorationLine {
+    // none か、underline、overline、line-through をそれぞれ最大1つずつ並べたもの
+    pub fn parse(value: &[ComponentValue]) -> Option<Self> {
+        let mut line = Self::default();
+
+        for component in value {
+            let ComponentValue::Ident(keyword) = component else {
+                return None;
+            };
+            let flag = match keyword.to_ascii_lowercase().as_str() {
+                "none" if value.len() == 1 => return Some(line),
+                "underline" => &mut line.underline,
+                "overline" => &mut line.overline,
+                "line-through" => &mut line.line_through,
+                _ => return None,
+            };
+            if *flag {
+                return None;
+            }
+            *flag = true;
+        }
+
+        if value.is_empty() { None } else { Some(line) }
+    }
+
+    pub fn is_none(&self) -> bool {
+        !self.underline && !self.overline && !self.line_through
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            underline: self.underline || other.underline,
+            overline: self.overline || other.overline,
+            line_through: self.line_through || other.line_through,
+        }
+    }
+}
+
+// https://www.w3.org/TR/css-text-decor-3/#text-decoration-style-property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDecorationStyle {
+    Solid,
+    Double,
+    Dotted,
+    Dashed,
+    Wavy,
+}
+
+impl FromStr for TextDecorationStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "solid" => Ok(Self::Solid),
+            "double" => Ok(Self::Double),
+            "dotted" => Ok(Self::Dotted),
+            "dashed" => Ok(Self::Dashed),
+            "wavy" => Ok(Self::Wavy),
+            _ => Err(Error::UnexpectedInput(format!(
+                "text-decoration-style {s:?} is not supported"
+            ))),
+        }
+    }
+}
+
+// テキストに描画する装飾。色は currentColor を解決したもの
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextDecoration {
+    pub line: TextDecorationLine,
+    pub style: TextDecorationStyle,
+    pub color: Color,
+}
+
+// white-space と text-transform にしたがって、テキストノードの文字列を描画する文字列に変換する
+pub fn transform_text(
+    text: &str,
+    white_space: WhiteSpace,
+    text_transform: TextTransform,
+) -> String {
+    let mut result = String::new();
+
+    if white_space.collapses_spaces() {
+        // 連続する空白を1つの空白にする。行頭と行末の空白や、ほかのノードの空白と続く空白は、
+        // 行ボックスに並べるときに取り除く。改行を残す場合は、改行の前後の空白を取り除く
+        for c in text.chars() {
+            match c {
+                '\n' if white_space.preserves_newlines() => {
+                    while result.ends_with(' ') {
+                        result.pop();
+                    }
+                    result.push('\n');
+                }
+                ' ' | '\t' | '\n' | '\r' => {
+                    if !result.ends_with(' ') && !result.ends_with('\n') {
+                        result.push(' ');
+                    }
+                }
+                _ => result.push(c),
+            }
+        }
+    } else {
+        // タブは、8文字ごとのタブストップまでの空白にする
+        let mut column = 0;
+        for c in text.chars() {
+            match c {
+                '\t' => {
+                    let spaces = 8 - column % 8;
+                    result.extend(core::iter::repeat_n(' ', spaces));
+                    column += spaces;
+                }
+                '\r' => {}
+                '\n' => {
+                    result.push('\n');
+                    column = 0;
+                }
+                _ => {
+                    result.push(c);
+                    column += 1;
+                }
+            }
+        }
+    }
+
+    match text_transform {
+        TextTransform::None => result,
+        TextTransform::Uppercase => result.to_uppercase(),
+        TextTransform::Lowercase => result.to_lowercase(),
+        TextTransform::Capitalize => {
+            let mut capitalized = String::new();
+            let mut word_start = true;
+            for c in result.chars() {
+                if word_start && c.is_alphabetic() {
+                    capitalized.extend(c.to_uppercase());
+                } else {
+                    capitalized.push(c);
+                }
+                word_start = c.is_whitespace();
+            }
+            capitalized
+        }
+    }
+}
+
+// 文字の送り幅を求めるフォントと、letter-spacing と word-spacing の使用値
+#[derive(Debug, Clone)]
+pub struct TextMetrics {
+    pub font: Font,
+    pub font_metrics: Rc<dyn FontMetrics>,
+    pub letter_spacing: i64,
+    pub word_spacing: i64,
+}
+
+impl TextMetrics {
+    fn advance(&self, c: char) -> i64 {
+        let advance = self.font_metrics.advance(&self.font, c) + self.letter_spacing;
+        if c == ' ' {
+            advance + self.word_spacing
+        } else {
+            advance
+        }
+    }
+
+    pub fn measure(&self, text: &str) -> i64 {
+        text.chars().map(|c| self.advance(c)).sum()
+    }
+
+    // letter-spacing と word-spacing を含めない、フォントの送り幅だけの幅。
+    // バックエンドが文字列をまとめて描画するときの幅になる
+    pub fn measure_glyphs(&self, text: &str) -> i64 {
+        text.chars()
+            .map(|c| self.font_metrics.advance(&self.font, c))
+            .sum()
+    }
+}
+
+impl PartialEq for TextMetrics {
+    fn eq(&self, other: &Self) -> bool {
+        self.font == other.font
+            && Rc::ptr_eq(&self.font_metrics, &other.font_metrics)
+            && self.letter_spacing == other.letter_spacing
+            && self.word_spacing == other.word_spacing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_transform_text() {
+        let text = "  hello \n\t world  \n bye";
+        assert_eq!(
+            " hello world bye",
+            transform_text(text, WhiteSpace::Normal, TextTransform::None)
+        );
+        assert_eq!(
+            " hello\nworld\nbye",
+            transform_text(text, WhiteSpace::PreLine, TextTransform::None)
+        );
+        assert_eq!(
+            "  hello \n         world  \n bye",
+            transform_text(text, WhiteSpace::Pre, TextTransform::None)
+        );
+        assert_eq!(
+            " Hello World Bye",
+            transform_text(text, WhiteSpace::Nowrap, TextTransform::Capitalize)
+        );
+        assert_eq!(
+            " HELLO WORLD BYE",
+            transform_text(text, WhiteSpace::Normal, TextTransform::Uppercase)
+        );
+    }
+
+    #[test]
+    fn test_text_decoration_line() {
+        let ident = |s: &str| ComponentValue::Ident(s.to_string());
+        assert_eq!(
+            Some(TextDecorationLine {
+                underline: true,
+                overline: false,
+                line_through: true,
+            }),
+            TextDecorationLine::parse(&[ident("line-through"), ident("underline")])
+        );
+        assert_eq!(
+            Some(TextDecorationLine::default()),
+            TextDecorationLine::parse(&[ident("none")])
+        );
+        assert_eq!(
+            None,
+            TextDecorationLine::parse(&[ident("underline"), ident("underline")])
+        );
+        assert_eq!(
+            None,
+            TextDecorationLine::parse(&[ident("none"), ident("underline")])
+        );
+    }
+}
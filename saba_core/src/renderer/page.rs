@@ -2,45 +2,82 @@ use core::cell::RefCell;
 
 use alloc::{
     rc::{Rc, Weak},
-    string::String,
+    string::{String, ToString},
+    vec,
     vec::Vec,
 };
 
 use crate::{
     browser::Browser,
     display_item::DisplayItem,
+    error::Error,
     http::HttpResponse,
     renderer::{
         css::{
+            cascade::CascadeOrigin,
             cssom::{CssParser, StyleSheet},
+            invalidation::RuleFeatures,
+            media::Viewport,
             token::CssTokenizer,
+            user_agent::user_agent_stylesheet,
         },
         dom::{
-            api::get_style_content,
-            node::{ElementKind, NodeKind, Window},
+            api::{StyleSource, get_style_sources},
+            node::{ElementKind, Node, NodeKind, Window},
         },
         html::{parser::HtmlParser, token::HtmlTokenizer},
-        layout::{layout_object::LayoutPoint, layout_view::LayoutView},
+        layout::{
+            font::{FixedWidthFontMetrics, FontMetrics},
+            layout_object::LayoutPoint,
+            layout_view::LayoutView,
+        },
     },
+    url::Url,
 };
 
+// 外部のリソースを取得するために、ブラウザを組み込む側が提供する関数
+pub type Fetcher = fn(&str) -> Result<HttpResponse, Error>;
+
+// @import が循環していたり、深くネストしていたりする場合に打ち切るための上限
+static MAX_IMPORT_DEPTH: usize = 8;
+
 #[derive(Debug, Clone)]
 pub struct Page {
     browser: Weak<RefCell<Browser>>,
+    url: Option<Url>,
+    fetcher: Option<Fetcher>,
     frame: Option<Rc<RefCell<Window>>>,
-    style: Option<StyleSheet>,
+    styles: Vec<StyleSheet>,
+    user_agent_style: StyleSheet,
+    user_styles: Vec<StyleSheet>,
+    viewport: Viewport,
+    // テキストの幅と行の高さを求めるための、描画するバックエンドのフォントの情報
+    font_metrics: Rc<dyn FontMetrics>,
+    // スタイルシートのセレクタが参照している id とクラス。属性の変更がスタイルに影響するかを判定する
+    rule_features: RuleFeatures,
     layout_view: Option<LayoutView>,
     display_items: Vec<DisplayItem>,
+    // ビューポートの左上が、ページの左上からどれだけスクロールしているか
+    scroll_offset: LayoutPoint,
 }
 
 impl Default for Page {
     fn default() -> Self {
         Self {
             browser: Weak::new(),
+            url: None,
+            fetcher: None,
             frame: None,
-            style: None,
+            styles: Vec::new(),
+            // UA スタイルシートはページを作るときに一度だけパースして、以降のナビゲーションで使い回す
+            user_agent_style: user_agent_stylesheet(),
+            user_styles: Vec::new(),
+            viewport: Viewport::default(),
+            font_metrics: Rc::new(FixedWidthFontMetrics),
+            rule_features: RuleFeatures::default(),
             layout_view: None,
             display_items: Vec::new(),
+            scroll_offset: LayoutPoint { x: 0, y: 0 },
         }
     }
 }
@@ -50,7 +87,54 @@ impl Page {
         self.browser = browser;
     }
 
-    pub fn recieve_response(&mut self, response: HttpResponse) {
+    // ユーザーが設定したスタイルシートを追加する。作成者のスタイルより優先度は低いが、
+    // !important がついた宣言は作成者のものより優先される
+    pub fn add_user_style(&mut self, css: String) {
+        let mut sheet = CssParser::new(CssTokenizer::new(css)).parse_stylesheet();
+        sheet.set_origin(CascadeOrigin::User);
+        self.user_styles.push(sheet);
+    }
+
+    pub fn set_fetcher(&mut self, fetcher: Fetcher) {
+        self.fetcher = Some(fetcher);
+    }
+
+    // ブラウザを組み込む側が、テキストを描画するフォントの情報を設定する。
+    // 設定しない場合は、本書のブラウザの等幅フォントとしてレイアウトする
+    pub fn set_font_metrics(&mut self, font_metrics: Rc<dyn FontMetrics>) {
+        self.font_metrics = font_metrics;
+        self.set_layout_view();
+        self.paint_tree();
+    }
+
+    // ウィンドウのサイズやカラースキームが変わったときに呼ばれる。
+    // @media の評価結果が変わるので、スタイルの計算からやり直す
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        if self.viewport == viewport {
+            return;
+        }
+        self.viewport = viewport;
+        self.set_layout_view();
+        self.paint_tree();
+    }
+
+    // ページをスクロールしたときに呼ばれる。固定位置指定と sticky のボックスを置き直す。
+    // 表示項目はページの左上からの位置のままなので、描画する側がスクロールした分だけずらす
+    pub fn set_scroll_offset(&mut self, scroll_offset: LayoutPoint) {
+        if self.scroll_offset == scroll_offset {
+            return;
+        }
+        self.scroll_offset = scroll_offset;
+        if let Some(layout_view) = &mut self.layout_view {
+            layout_view.set_scroll_offset(scroll_offset);
+        }
+        self.paint_tree();
+    }
+
+    pub fn recieve_response(&mut self, url: &str, response: HttpResponse) {
+        self.url = Url::new(url.to_string()).parse().ok();
+        // 新しいページは、先頭から表示する
+        self.scroll_offset = LayoutPoint { x: 0, y: 0 };
         self.create_frame(response.body());
         self.set_layout_view();
         self.paint_tree();
@@ -61,24 +145,144 @@ impl Page {
         let frame = HtmlParser::new(html_tokenizer).construct_tree();
         let dom = frame.borrow().document();
 
-        let style = get_style_content(dom);
-        let css_tokenizer = CssTokenizer::new(style);
-        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
+        // <style> と <link rel="stylesheet"> を文書順に読み込む。後に読み込んだものほどカスケードで優先される
+        let mut styles = Vec::new();
+        for source in get_style_sources(dom) {
+            match source {
+                StyleSource::Inline(css) => {
+                    self.load_stylesheet(css, self.url.clone(), &mut Vec::new(), &mut styles);
+                }
+                StyleSource::External(href) => {
+                    let url = self.resolve_url(&href);
+                    if let Some(css) = self.fetch_stylesheet(&url) {
+                        let base = Url::new(url.clone()).parse().ok();
+                        self.load_stylesheet(css, base, &mut vec![url], &mut styles);
+                    }
+                }
+            }
+        }
 
         self.frame = Some(frame);
-        self.style = Some(cssom);
+        self.styles = styles;
+    }
+
+    fn load_stylesheet(
+        &self,
+        css: String,
+        base: Option<Url>,
+        import_chain: &mut Vec<String>,
+        styles: &mut Vec<StyleSheet>,
+    ) {
+        let sheet = CssParser::new(CssTokenizer::new(css)).parse_stylesheet();
+
+        // @import されたスタイルシートは、インポートしたスタイルシートのルールより前にあるものとして扱う
+        for import in sheet.import_rules() {
+            let url = match &base {
+                Some(base) => base.resolve(&import.href),
+                None => import.href.clone(),
+            };
+            if import_chain.len() >= MAX_IMPORT_DEPTH || import_chain.contains(&url) {
+                continue;
+            }
+            let Some(imported) = self.fetch_stylesheet(&url) else {
+                continue;
+            };
+
+            import_chain.push(url.clone());
+            let imported_base = Url::new(url).parse().ok();
+            self.load_stylesheet(imported, imported_base, import_chain, styles);
+            import_chain.pop();
+        }
+
+        styles.push(sheet);
+    }
+
+    fn resolve_url(&self, href: &str) -> String {
+        match &self.url {
+            Some(url) => url.resolve(href),
+            None => href.to_string(),
+        }
+    }
+
+    fn fetch_stylesheet(&self, url: &str) -> Option<String> {
+        let fetcher = self.fetcher?;
+        let response = fetcher(url).ok()?;
+        if !(200..300).contains(&response.status_code()) {
+            return None;
+        }
+        Some(response.body())
+    }
+
+    // カスケードの順に並べた、ページに適用するすべてのスタイルシート
+    fn stylesheets(&self) -> Vec<StyleSheet> {
+        let mut stylesheets = vec![self.user_agent_style.clone()];
+        stylesheets.extend(self.user_styles.clone());
+        stylesheets.extend(self.styles.clone());
+        stylesheets
     }
 
     fn set_layout_view(&mut self) {
         let Some(frame) = &self.frame else { return };
-        let Some(style) = self.style.clone() else {
-            return;
-        };
 
-        let layout_view = LayoutView::new(frame.borrow().document(), &style);
+        let stylesheets = self.stylesheets();
+        let mut layout_view = LayoutView::new(
+            frame.borrow().document(),
+            &stylesheets,
+            &self.viewport,
+            self.font_metrics.clone(),
+        );
+        layout_view.set_scroll_offset(self.scroll_offset);
+        self.rule_features = RuleFeatures::new(&stylesheets);
         self.layout_view = Some(layout_view);
     }
 
+    // 要素の属性を変更する。value が None のときは属性を取り除く。
+    // スタイルに影響する変更であれば、要素にスタイルの再計算が必要だと印をつける。
+    // 変更を画面に反映するには update_rendering を呼ぶ
+    pub fn set_attribute(&mut self, node: &Rc<RefCell<Node>>, name: &str, value: Option<&str>) {
+        let old = {
+            let mut node = node.borrow_mut();
+            let Some(element) = node.element_mut() else {
+                return;
+            };
+            let old = element.get_attribute(name);
+            match value {
+                Some(value) => element.set_attribute(name, value),
+                None => element.remove_attribute(name),
+            }
+            old
+        };
+
+        if self
+            .rule_features
+            .affects_style(name, old.as_deref(), value)
+        {
+            Node::mark_style_dirty(node);
+        }
+    }
+
+    // 印のついた要素のスタイルを計算し直し、レイアウトと描画をやり直す
+    pub fn update_rendering(&mut self) {
+        let Some(frame) = &self.frame else { return };
+        let document = frame.borrow().document();
+        let stylesheets = self.stylesheets();
+        let restyled = self
+            .layout_view
+            .as_mut()
+            .is_some_and(|view| view.restyle(document, &stylesheets, &self.viewport));
+        if !restyled {
+            self.set_layout_view();
+        }
+        self.paint_tree();
+    }
+
+    // 直前のスタイルの計算で、スタイルを計算した要素の数
+    pub fn restyled_element_count(&self) -> usize {
+        self.layout_view
+            .as_ref()
+            .map_or(0, |view| view.restyled_elements())
+    }
+
     fn paint_tree(&mut self) {
         let Some(layout_view) = &self.layout_view else {
             return;
@@ -109,3 +313,349 @@ impl Page {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use crate::renderer::{
+        css::media::ColorScheme,
+        layout::computed_style::{Color, ComputedStyle},
+    };
+
+    use super::*;
+
+    fn fetch(url: &str) -> Result<HttpResponse, Error> {
+        let body = match url {
+            "http://example.com/css/main.css" => {
+                r#"@import "base.css"; @import url(/loop.css); p { color: blue; }"#
+            }
+            "http://example.com/css/base.css" => "p { color: red; } span { color: yellow; }",
+            "http://example.com/loop.css" => r#"@import "loop.css"; h2 { color: green; }"#,
+            "http://example.com/css/selectors.css" => {
+                r#"
+                    div > p { color: red; }
+                    h1 + p, a ~ b { color: red; }
+                    . { color: red; }
+                    [ { color: red; }
+                    @font-face { font-family: x; }
+                    @-webkit-keyframes spin { from { color: red; } }
+                    em { color: green; }
+                    @a @
+                "#
+            }
+            _ => return Err(Error::Network(format!("{url} is not found"))),
+        };
+        HttpResponse::new(format!("HTTP/1.1 200 OK\nContent-Type: text/css\n\n{body}"))
+    }
+
+    fn element_by_id(node: Option<Rc<RefCell<Node>>>, id: &str) -> Option<Rc<RefCell<Node>>> {
+        let node = node?;
+        if node
+            .borrow()
+            .get_element()
+            .is_some_and(|e| e.get_attribute("id").as_deref() == Some(id))
+        {
+            return Some(node);
+        }
+        element_by_id(node.borrow().first_child(), id)
+            .or_else(|| element_by_id(node.borrow().next_sibling(), id))
+    }
+
+    fn text_style(page: &Page, text: &str) -> ComputedStyle {
+        page.display_items()
+            .into_iter()
+            .find_map(|item| match item {
+                DisplayItem::Text { text: t, style, .. } if t == text => Some(style),
+                _ => None,
+            })
+            .expect("text should be painted")
+    }
+
+    #[test]
+    fn test_multiple_stylesheets() {
+        let html = r#"
+            <html>
+            <head>
+              <style>p { color: orange; } h1 { color: orange; }</style>
+              <link rel="stylesheet" href="css/main.css">
+              <link rel="stylesheet" href="not_found.css">
+            </head>
+            <body>
+              <h1>title</h1>
+              <h2>loop</h2>
+              <p>text</p>
+              <div><span>imported</span></div>
+              <style>h1 { color: lime; }</style>
+            </body>
+            </html>
+        "#;
+        let response = HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/html\n\n{html}"
+        ))
+        .expect("response should be valid");
+
+        let mut page = Page::default();
+        page.set_fetcher(fetch);
+        page.recieve_response("http://example.com/index.html", response);
+
+        // main.css が <style> より後にあるので優先される
+        assert_eq!(
+            Color::from_name("blue").unwrap(),
+            text_style(&page, "text").color()
+        );
+        // base.css は main.css から @import されている
+        assert_eq!(
+            Color::from_name("yellow").unwrap(),
+            text_style(&page, "imported").color()
+        );
+        // loop.css は自分自身を @import しているが、一度だけ読み込まれる
+        assert_eq!(
+            Color::from_name("green").unwrap(),
+            text_style(&page, "loop").color()
+        );
+        // <body> の中の <style> がもっとも後ろにある
+        assert_eq!(
+            Color::from_name("lime").unwrap(),
+            text_style(&page, "title").color()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_selectors() {
+        let html = r#"
+            <html>
+            <head>
+              <link rel="stylesheet" href="css/selectors.css">
+            </head>
+            <body>
+              <div><p>child</p></div>
+              <h1>title</h1><p>sibling</p>
+              <em>emphasis</em>
+            </body>
+            </html>
+        "#;
+        let response = HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/html\n\n{html}"
+        ))
+        .expect("response should be valid");
+
+        let mut page = Page::default();
+        page.set_fetcher(fetch);
+        page.recieve_response("http://example.com/index.html", response);
+
+        // 結合子を含むセレクタや不正なセレクタはマッチせず、後ろに続くルールは読み込まれる
+        assert_eq!(
+            Color::from_name("black").unwrap(),
+            text_style(&page, "child").color()
+        );
+        assert_eq!(
+            Color::from_name("black").unwrap(),
+            text_style(&page, "sibling").color()
+        );
+        assert_eq!(
+            Color::from_name("green").unwrap(),
+            text_style(&page, "emphasis").color()
+        );
+    }
+
+    #[test]
+    fn test_set_viewport() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                p { color: red; }
+                @media (prefers-color-scheme: dark) { p { color: white; } }
+              </style>
+            </head>
+            <body><p>text</p></body>
+            </html>
+        "#;
+        let response = HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/html\n\n{html}"
+        ))
+        .expect("response should be valid");
+
+        let mut page = Page::default();
+        page.recieve_response("http://example.com/index.html", response);
+        assert_eq!(
+            Color::from_name("red").unwrap(),
+            text_style(&page, "text").color()
+        );
+
+        let mut viewport = Viewport::default();
+        viewport.set_color_scheme(ColorScheme::Dark);
+        page.set_viewport(viewport);
+        assert_eq!(
+            Color::from_name("white").unwrap(),
+            text_style(&page, "text").color()
+        );
+    }
+
+    #[test]
+    fn test_incremental_restyle() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                .highlight { color: red; }
+                .hidden { display: none; }
+              </style>
+            </head>
+            <body>
+              <p id="first">first</p>
+              <div id="container"><p>child1</p><p>child2</p></div>
+              <p id="hidden" class="hidden">hidden</p>
+            </body>
+            </html>
+        "#;
+        let response = HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/html\n\n{html}"
+        ))
+        .expect("response should be valid");
+
+        let mut page = Page::default();
+        page.recieve_response("http://example.com/index.html", response);
+        // 最初は <html> と、<body> とその中のすべての要素のスタイルを計算する
+        assert_eq!(7, page.restyled_element_count());
+
+        let document = page.frame.as_ref().unwrap().borrow().document();
+        let first = element_by_id(Some(document.clone()), "first").unwrap();
+        let container = element_by_id(Some(document.clone()), "container").unwrap();
+        let hidden = element_by_id(Some(document.clone()), "hidden").unwrap();
+
+        // マッチするセレクタが変わった要素だけを計算し直す
+        page.set_attribute(&first, "class", Some("highlight"));
+        page.update_rendering();
+        assert_eq!(1, page.restyled_element_count());
+        assert_eq!(
+            Color::from_name("red").unwrap(),
+            text_style(&page, "first").color()
+        );
+
+        // どのセレクタからも参照されていない属性やクラスの変更は、スタイルに影響しない
+        page.set_attribute(&first, "title", Some("title"));
+        page.set_attribute(&container, "class", Some("unused"));
+        page.update_rendering();
+        assert_eq!(0, page.restyled_element_count());
+
+        // スタイルが変わった要素の子孫は、継承した値が変わるので計算し直す
+        page.set_attribute(&container, "style", Some("color: blue"));
+        page.update_rendering();
+        assert_eq!(3, page.restyled_element_count());
+        assert_eq!(
+            Color::from_name("blue").unwrap(),
+            text_style(&page, "child2").color()
+        );
+        assert_eq!(
+            Color::from_name("red").unwrap(),
+            text_style(&page, "first").color()
+        );
+
+        // display: none でなくなった要素はレイアウトツリーにないので、すべて作り直す
+        page.set_attribute(&hidden, "class", None);
+        page.update_rendering();
+        assert_eq!(7, page.restyled_element_count());
+        assert_eq!(
+            Color::from_name("black").unwrap(),
+            text_style(&page, "hidden").color()
+        );
+    }
+
+    #[test]
+    fn test_restyle_class_list() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                .x { color: red; }
+                .y { color: blue; }
+              </style>
+            </head>
+            <body>
+              <p id="target" class="x">text</p>
+            </body>
+            </html>
+        "#;
+        let response = HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/html\n\n{html}"
+        ))
+        .expect("response should be valid");
+
+        let mut page = Page::default();
+        page.recieve_response("http://example.com/index.html", response);
+
+        let document = page.frame.as_ref().unwrap().borrow().document();
+        let target = element_by_id(Some(document.clone()), "target").unwrap();
+
+        // 計算し直した結果は、レイアウトツリーをすべて作り直した結果と同じになる
+        let mut assert_class = |class: &str, color: &str| {
+            page.set_attribute(&target, "class", Some(class));
+            page.update_rendering();
+            let incremental = page.display_items();
+            assert_eq!(
+                Color::from_name(color).unwrap(),
+                text_style(&page, "text").color()
+            );
+
+            page.set_layout_view();
+            page.paint_tree();
+            assert_eq!(incremental, page.display_items());
+        };
+
+        // クラスを足しても、すでにマッチしていたセレクタはマッチし続ける
+        assert_class("x z", "red");
+        assert_class("z  x", "red");
+        assert_class("x y", "blue");
+        assert_class("y", "blue");
+        assert_class("xy", "black");
+    }
+
+    #[test]
+    fn test_restyle_anonymous_block() {
+        let html = r#"
+            <html>
+            <head>
+              <style>
+                .highlight { color: red; }
+              </style>
+            </head>
+            <body>
+              <div id="mixed">text<p id="block">block</p></div>
+              <p><span id="split">before<em>inner</em><div>inside</div></span></p>
+            </body>
+            </html>
+        "#;
+        let response = HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/html\n\n{html}"
+        ))
+        .expect("response should be valid");
+
+        let mut page = Page::default();
+        page.recieve_response("http://example.com/index.html", response);
+        assert_eq!(8, page.restyled_element_count());
+
+        let document = page.frame.as_ref().unwrap().borrow().document();
+        let mixed = element_by_id(Some(document.clone()), "mixed").unwrap();
+        let split = element_by_id(Some(document.clone()), "split").unwrap();
+
+        // 匿名ブロックの中のテキストにも、親の要素から継承した値が伝わる
+        page.set_attribute(&mixed, "class", Some("highlight"));
+        page.update_rendering();
+        assert_eq!(2, page.restyled_element_count());
+        assert_eq!(
+            Color::from_name("red").unwrap(),
+            text_style(&page, "text").color()
+        );
+
+        // 分割したインライン要素は DOM と対応しないので、すべて作り直す
+        page.set_attribute(&split, "class", Some("highlight"));
+        page.update_rendering();
+        assert_eq!(8, page.restyled_element_count());
+        assert_eq!(
+            Color::from_name("red").unwrap(),
+            text_style(&page, "inside").color()
+        );
+    }
+}
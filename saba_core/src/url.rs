@@ -1,4 +1,5 @@
 use alloc::{
+    format,
     string::{String, ToString},
     vec::Vec,
 };
@@ -52,6 +53,55 @@ impl Url {
         &self.searchpart
     }
 
+    // このURLを基準に、相対URLを絶対URLに変換する
+    pub fn resolve(&self, reference: &str) -> String {
+        if reference.contains("://") {
+            return reference.to_string();
+        }
+        if let Some(rest) = reference.strip_prefix("//") {
+            return format!("http://{rest}");
+        }
+
+        let origin = if self.port == 80 {
+            format!("http://{}", self.host)
+        } else {
+            format!("http://{}:{}", self.host, self.port)
+        };
+
+        let (reference, searchpart) = match reference.split_once('?') {
+            Some((r, s)) => (r, Some(s)),
+            None => (reference, None),
+        };
+
+        let mut segments: Vec<&str> = Vec::new();
+        if !reference.starts_with('/') {
+            // 基準URLのパスの最後のセグメント（ファイル名）を取り除いたものがディレクトリになる
+            segments = self.path.split('/').collect();
+            segments.pop();
+        }
+        for segment in reference.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop();
+                }
+                _ => segments.push(segment),
+            }
+        }
+        // 空のセグメントは結合すると余分な / になるので取り除く
+        segments.retain(|s| !s.is_empty());
+
+        let mut url = format!("{origin}/{}", segments.join("/"));
+        if reference.ends_with('/') && !segments.is_empty() {
+            url.push('/');
+        }
+        if let Some(searchpart) = searchpart {
+            url.push('?');
+            url.push_str(searchpart);
+        }
+        url
+    }
+
     fn is_http(&self) -> bool {
         self.url.contains("http://")
     }
@@ -188,4 +238,38 @@ mod tests {
         let expected = Err("Only HTTP scheme is supported.".to_string());
         assert_eq!(expected, Url::new(url).parse());
     }
+
+    #[test]
+    fn test_resolve() {
+        let base = Url::new("http://example.com:8888/docs/index.html".to_string())
+            .parse()
+            .unwrap();
+        assert_eq!(
+            "http://example.com:8888/docs/style.css",
+            base.resolve("style.css")
+        );
+        assert_eq!(
+            "http://example.com:8888/docs/css/style.css",
+            base.resolve("./css/style.css")
+        );
+        assert_eq!(
+            "http://example.com:8888/style.css?v=1",
+            base.resolve("../style.css?v=1")
+        );
+        assert_eq!(
+            "http://example.com:8888/assets/style.css",
+            base.resolve("/assets/style.css")
+        );
+        assert_eq!(
+            "http://cdn.example.com/style.css",
+            base.resolve("//cdn.example.com/style.css")
+        );
+        assert_eq!(
+            "http://other.example.com/style.css",
+            base.resolve("http://other.example.com/style.css")
+        );
+
+        let base = Url::new("http://example.com".to_string()).parse().unwrap();
+        assert_eq!("http://example.com/style.css", base.resolve("style.css"));
+    }
 }
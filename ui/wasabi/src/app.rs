@@ -19,12 +19,12 @@ use saba_core::{
         LIGHTGREY, TITLE_BAR_HEIGHT, TOOLBAR_HEIGHT, WHITE, WINDOW_HEIGHT, WINDOW_INIT_X_POS,
         WINDOW_INIT_Y_POS, WINDOW_PADDING, WINDOW_WIDTH,
     },
-    display_item::DisplayItem,
+    display_item::{DisplayItem, border_segments, decoration_segments},
     error::Error,
     http::HttpResponse,
-    renderer::layout::{
-        computed_style::{FontSize, TextDecoration},
-        layout_object::LayoutPoint,
+    renderer::{
+        css::media::Viewport,
+        layout::layout_object::{LayoutPoint, font_size_ratio},
     },
 };
 
@@ -62,6 +62,13 @@ impl WasabiUI {
     pub fn start(&mut self, handle_url: UrlHandler) -> Result<(), Error> {
         self.setup()?;
 
+        // ページがスタイルシートなどのサブリソースを取得できるようにする
+        let page = self.browser.borrow().current_page();
+        page.borrow_mut().set_fetcher(handle_url);
+        // ページはコンテンツエリアの大きさでレイアウトする。大きさが変わったときも set_viewport で伝える
+        page.borrow_mut()
+            .set_viewport(Viewport::new(CONTENT_AREA_WIDTH, CONTENT_AREA_HRIGHT));
+
         self.run_app(handle_url)?;
 
         Ok(())
@@ -262,7 +269,7 @@ impl WasabiUI {
 
         let response = handle_url(destination)?;
         let page = self.browser.borrow().current_page();
-        page.borrow_mut().recieve_response(response);
+        page.borrow_mut().recieve_response(destination, response);
 
         self.update_ui()?;
 
@@ -300,18 +307,23 @@ impl WasabiUI {
                 DisplayItem::Text {
                     text,
                     style,
+                    font,
                     layout_point,
-                } => self
-                    .window
-                    .draw_string(
-                        style.color().code_u32(),
-                        layout_point.x + WINDOW_PADDING,
-                        layout_point.y + WINDOW_PADDING + TOOLBAR_HEIGHT,
-                        &text,
-                        convert_font_size(style.font_size()),
-                        style.text_decoration() == TextDecoration::Underline,
-                    )
-                    .map_err(|_| Error::InvalidUI("failed to draw a string".to_string()))?,
+                } => {
+                    let x = layout_point.x + WINDOW_PADDING;
+                    let y = layout_point.y + WINDOW_PADDING + TOOLBAR_HEIGHT;
+                    let size = convert_font_size(font.size);
+                    // 下線などの装飾は DisplayItem::Decoration として別に描画する
+                    self.window
+                        .draw_string(style.color().code_u32(), x, y, &text, size, false)
+                        .map_err(|_| Error::InvalidUI("failed to draw a string".to_string()))?;
+                    // ビットマップフォントには太字がないので、1px ずらして重ね書きする
+                    if font.weight >= 600 {
+                        self.window
+                            .draw_string(style.color().code_u32(), x + 1, y, &text, size, false)
+                            .map_err(|_| Error::InvalidUI("failed to draw a string".to_string()))?;
+                    }
+                }
                 DisplayItem::Rect { style, layout_rect } => self
                     .window
                     .fill_rect(
@@ -322,6 +334,42 @@ impl WasabiUI {
                         layout_rect.size.height,
                     )
                     .map_err(|_| Error::InvalidUI("failed to draw a string".to_string()))?,
+                DisplayItem::Border {
+                    border_style,
+                    color,
+                    layout_rect,
+                } => {
+                    for segment in border_segments(border_style, layout_rect) {
+                        self.window
+                            .fill_rect(
+                                color.code_u32(),
+                                segment.point.x + WINDOW_PADDING,
+                                segment.point.y + WINDOW_PADDING + TOOLBAR_HEIGHT,
+                                segment.size.width,
+                                segment.size.height,
+                            )
+                            .map_err(|_| Error::InvalidUI("failed to draw a border".to_string()))?;
+                    }
+                }
+                DisplayItem::Decoration {
+                    decoration_style,
+                    color,
+                    layout_rect,
+                } => {
+                    for segment in decoration_segments(decoration_style, layout_rect) {
+                        self.window
+                            .fill_rect(
+                                color.code_u32(),
+                                segment.point.x + WINDOW_PADDING,
+                                segment.point.y + WINDOW_PADDING + TOOLBAR_HEIGHT,
+                                segment.size.width,
+                                segment.size.height,
+                            )
+                            .map_err(|_| {
+                                Error::InvalidUI("failed to draw a text decoration".to_string())
+                            })?;
+                    }
+                }
             }
         }
 
@@ -331,11 +379,11 @@ impl WasabiUI {
     }
 }
 
-fn convert_font_size(size: FontSize) -> StringSize {
-    match size {
-        FontSize::Medium => StringSize::Medium,
-        FontSize::XLarge => StringSize::Large,
-        FontSize::XXLarge => StringSize::XLarge,
+fn convert_font_size(size: f64) -> StringSize {
+    match font_size_ratio(size) {
+        1 => StringSize::Medium,
+        2 => StringSize::Large,
+        _ => StringSize::XLarge,
     }
 }
 